@@ -9,7 +9,7 @@ module.exports = {
   parserOptions: {
     ecmaVersion: 'latest',
   },
-  ignorePatterns: ['dist/**', 'node_modules/**', 'src-tauri/**'],
+  ignorePatterns: ['dist/**', 'node_modules/**', 'src-tauri/**', 'src/types/bindings/**'],
   rules: {
     'vue/multi-word-component-names': 'off',
     '@typescript-eslint/no-unused-vars': ['warn', { argsIgnorePattern: '^_' }],
@@ -0,0 +1,313 @@
+//! 截图标注文字渲染模块
+//!
+//! 在后端将标注文字直接绘制到截图上，保证发送给 AI 的图片与用户看到的一致。
+//! 标注文字经常是中英文混排，需要支持 CJK 的字体：
+//! 1. 配置中指定的字体文件
+//! 2. 系统自带的 CJK 字体（PingFang / 微软雅黑 / Noto Sans CJK 等）
+//!
+//! 找不到可用字体时返回错误，而不是绘制出一排方块。
+
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// 用于检测字体是否覆盖中文的字符
+const CJK_PROBE_CHAR: char = '中';
+
+/// 系统 CJK 字体候选路径（按优先级）
+#[cfg(target_os = "macos")]
+const SYSTEM_FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/Library/Fonts/Arial Unicode.ttf",
+];
+
+#[cfg(target_os = "windows")]
+const SYSTEM_FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttf",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+];
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const SYSTEM_FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+/// 标注渲染错误类型
+#[derive(Debug, Error)]
+pub enum AnnotationError {
+    #[error("No CJK-capable font found; set annotation.fontPath in settings")]
+    FontNotFound,
+
+    #[error("Failed to load font {0}: {1}")]
+    FontLoadError(String, String),
+
+    #[error("Invalid color: {0}")]
+    InvalidColor(String),
+}
+
+/// 标注文字配置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationConfig {
+    /// 自定义字体文件路径（为空时自动查找系统 CJK 字体）
+    #[serde(default)]
+    pub font_path: Option<String>,
+    /// 默认字号（像素）
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    /// 默认文字颜色（#RRGGBB 或 #RRGGBBAA）
+    #[serde(default = "default_color")]
+    pub color: String,
+}
+
+fn default_font_size() -> f32 {
+    18.0
+}
+
+fn default_color() -> String {
+    "#FF3B30".to_string()
+}
+
+impl Default for AnnotationConfig {
+    fn default() -> Self {
+        Self {
+            font_path: None,
+            font_size: default_font_size(),
+            color: default_color(),
+        }
+    }
+}
+
+/// 文字标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAnnotation {
+    /// 文字左上角 X 坐标（图片像素）
+    pub x: i32,
+    /// 文字左上角 Y 坐标（图片像素）
+    pub y: i32,
+    /// 文字内容，支持换行
+    pub text: String,
+    /// 字号，未指定时使用配置默认值
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    /// 颜色，未指定时使用配置默认值
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// 解析 #RRGGBB / #RRGGBBAA 颜色
+pub fn parse_color(value: &str) -> Result<Rgba<u8>, AnnotationError> {
+    let hex = value.trim().trim_start_matches('#');
+    let invalid = || AnnotationError::InvalidColor(value.to_string());
+
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// 加载字体文件，要求字体包含中文字形
+///
+/// 字体集合（.ttc）会依次尝试其中的字体
+fn load_cjk_font(path: &str) -> Result<FontVec, AnnotationError> {
+    let data = std::fs::read(path)
+        .map_err(|e| AnnotationError::FontLoadError(path.to_string(), e.to_string()))?;
+
+    let mut index = 0;
+    loop {
+        let font = FontVec::try_from_vec_and_index(data.clone(), index)
+            .map_err(|e| AnnotationError::FontLoadError(path.to_string(), e.to_string()))?;
+        if font.glyph_id(CJK_PROBE_CHAR).0 != 0 {
+            return Ok(font);
+        }
+        index += 1;
+    }
+}
+
+/// 查找系统 CJK 字体（结果缓存）
+fn system_cjk_font() -> Option<&'static FontVec> {
+    static FONT: OnceLock<Option<FontVec>> = OnceLock::new();
+
+    FONT.get_or_init(|| {
+        let font = SYSTEM_FONT_CANDIDATES
+            .iter()
+            .find_map(|path| load_cjk_font(path).ok().inspect(|_| {
+                log::info!("[annotation] 使用系统字体: {}", path);
+            }));
+        if font.is_none() {
+            log::warn!("[annotation] 未找到可用的 CJK 系统字体");
+        }
+        font
+    })
+    .as_ref()
+}
+
+/// 在图片上绘制文字标注
+pub fn render_annotations(
+    image: &mut RgbaImage,
+    annotations: &[TextAnnotation],
+    config: &AnnotationConfig,
+) -> Result<(), AnnotationError> {
+    if annotations.is_empty() {
+        return Ok(());
+    }
+
+    let custom_font = match config.font_path.as_deref().filter(|p| !p.is_empty()) {
+        Some(path) => Some(load_cjk_font(path)?),
+        None => None,
+    };
+    let font = match custom_font.as_ref() {
+        Some(font) => font,
+        None => system_cjk_font().ok_or(AnnotationError::FontNotFound)?,
+    };
+
+    for annotation in annotations {
+        let color = parse_color(annotation.color.as_deref().unwrap_or(&config.color))?;
+        let size = annotation.font_size.unwrap_or(config.font_size);
+        draw_text(image, font, size, color, annotation.x, annotation.y, &annotation.text);
+    }
+
+    Ok(())
+}
+
+/// 使用指定字体绘制一段文字，(x, y) 为首行左上角
+fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontVec,
+    size: f32,
+    color: Rgba<u8>,
+    x: i32,
+    y: i32,
+    text: &str,
+) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let line_height = scaled.height() + scaled.line_gap();
+    let (width, height) = image.dimensions();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let baseline = y as f32 + scaled.ascent() + line_index as f32 * line_height;
+        let mut caret = x as f32;
+        let mut previous = None;
+
+        for ch in line.chars() {
+            let glyph_id = scaled.glyph_id(ch);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, glyph_id);
+            }
+            let glyph = glyph_id.with_scale_and_position(size, ab_glyph::point(caret, baseline));
+            caret += scaled.h_advance(glyph_id);
+            previous = Some(glyph_id);
+
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px as u32 >= width || py as u32 >= height {
+                    return;
+                }
+                let alpha = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+                blend_pixel(image.get_pixel_mut(px as u32, py as u32), color, alpha);
+            });
+        }
+    }
+}
+
+/// 按覆盖率将颜色混合到像素上
+fn blend_pixel(pixel: &mut Rgba<u8>, color: Rgba<u8>, alpha: f32) {
+    for (channel, source) in pixel.0.iter_mut().zip(color.0).take(3) {
+        *channel = (source as f32 * alpha + *channel as f32 * (1.0 - alpha)).round() as u8;
+    }
+    pixel[3] = pixel[3].max((alpha * 255.0).round() as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#FF3B30").unwrap(), Rgba([255, 59, 48, 255]));
+        assert_eq!(parse_color("00ff0080").unwrap(), Rgba([0, 255, 0, 128]));
+        assert!(parse_color("#FFF").is_err());
+        assert!(parse_color("#GG0000").is_err());
+        assert!(parse_color("#中文中").is_err());
+    }
+
+    #[test]
+    fn test_empty_annotations_keep_image() {
+        let mut image = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        let original = image.clone();
+
+        render_annotations(&mut image, &[], &AnnotationConfig::default()).unwrap();
+
+        assert_eq!(image, original);
+    }
+
+    #[test]
+    fn test_mixed_cjk_text_renders_every_glyph() {
+        // 没有 CJK 字体的环境（如精简 CI 镜像）跳过
+        let Some(font) = system_cjk_font() else {
+            return;
+        };
+
+        let background = Rgba([255, 255, 255, 255]);
+        let render = |text: &str| {
+            let mut image = RgbaImage::from_pixel(240, 40, background);
+            draw_text(&mut image, font, 24.0, Rgba([0, 0, 0, 255]), 4, 4, text);
+            image
+        };
+        let inked_columns = |image: &RgbaImage| {
+            (0..image.width())
+                .filter(|&x| (0..image.height()).any(|y| *image.get_pixel(x, y) != background))
+                .count()
+        };
+
+        let english = render("Bug");
+        let mixed = render("Bug 在这里");
+
+        assert!(inked_columns(&english) > 0);
+        // 中文字形应被实际绘制，而不是缺字后什么都不画
+        assert!(inked_columns(&mixed) > inked_columns(&english));
+        // 相同输入渲染结果稳定
+        assert_eq!(mixed, render("Bug 在这里"));
+    }
+
+    #[test]
+    fn test_missing_custom_font_is_error() {
+        let mut image = RgbaImage::new(8, 8);
+        let config = AnnotationConfig {
+            font_path: Some("/nonexistent/font.ttf".to_string()),
+            ..AnnotationConfig::default()
+        };
+        let annotations = vec![TextAnnotation {
+            x: 0,
+            y: 0,
+            text: "中文".to_string(),
+            font_size: None,
+            color: None,
+        }];
+
+        assert!(matches!(
+            render_annotations(&mut image, &annotations, &config),
+            Err(AnnotationError::FontLoadError(..))
+        ));
+    }
+}
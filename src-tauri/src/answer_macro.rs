@@ -0,0 +1,185 @@
+//! 回答宏模块
+//!
+//! 用户选中某个预定义选项时自动执行配置好的宏：追加常用文本、附加日志文件末尾若干行、
+//! 截取全屏截图。例如选择“还是失败”时自动附上最新截图和日志。
+//!
+//! 宏只能执行固定的动作类型，读取的日志文件必须在允许列表中。
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 读取日志时最多读取的末尾字节数
+const MAX_TAIL_BYTES: u64 = 1024 * 1024;
+
+fn default_tail_lines() -> usize {
+    50
+}
+
+/// 宏动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MacroAction {
+    /// 截取全屏截图并附加
+    Screenshot,
+    /// 附加日志文件末尾若干行（文件必须在允许列表中）
+    #[serde(rename_all = "camelCase")]
+    TailLog {
+        path: String,
+        #[serde(default = "default_tail_lines")]
+        lines: usize,
+    },
+}
+
+/// 单个回答宏
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerMacro {
+    /// 触发宏的选项文本
+    pub option: String,
+    /// 追加到反馈中的文本
+    #[serde(default)]
+    pub append_text: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<MacroAction>,
+}
+
+/// 回答宏配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerMacroConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 允许宏读取的日志文件
+    #[serde(default)]
+    pub allowed_log_files: Vec<String>,
+    #[serde(default)]
+    pub macros: Vec<AnswerMacro>,
+}
+
+/// 宏展开结果（截图由调用方执行，需要隐藏窗口）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MacroPlan {
+    /// 需要追加的文本块
+    pub text_blocks: Vec<String>,
+    /// 是否需要截图
+    pub capture_screenshot: bool,
+}
+
+impl AnswerMacroConfig {
+    /// 查找选项对应的宏
+    pub fn find(&self, option: &str) -> Option<&AnswerMacro> {
+        if !self.enabled {
+            return None;
+        }
+        self.macros.iter().find(|m| m.option.trim() == option.trim())
+    }
+
+    /// 日志文件是否在允许列表中（按规范化后的路径比较，防止 `..` 绕过）
+    fn is_allowed(&self, path: &Path) -> bool {
+        let Ok(path) = path.canonicalize() else {
+            return false;
+        };
+        self.allowed_log_files
+            .iter()
+            .filter_map(|allowed| PathBuf::from(allowed).canonicalize().ok())
+            .any(|allowed| allowed == path)
+    }
+
+    /// 展开选项对应的宏，选项没有配置宏时返回 None
+    pub fn expand(&self, option: &str) -> Option<MacroPlan> {
+        let answer_macro = self.find(option)?;
+        let mut plan = MacroPlan::default();
+
+        if let Some(text) = answer_macro.append_text.as_deref().filter(|t| !t.trim().is_empty()) {
+            plan.text_blocks.push(text.to_string());
+        }
+        for action in &answer_macro.actions {
+            match action {
+                MacroAction::Screenshot => plan.capture_screenshot = true,
+                MacroAction::TailLog { path, lines } => {
+                    let path = Path::new(path);
+                    if !self.is_allowed(path) {
+                        log::warn!("[answer_macro] 日志文件不在允许列表中，已跳过: {:?}", path);
+                        continue;
+                    }
+                    match tail_lines(path, *lines) {
+                        Ok(tail) => plan.text_blocks.push(format!(
+                            "{} (last {} lines):\n```\n{}\n```",
+                            path.display(),
+                            lines,
+                            tail.trim_end()
+                        )),
+                        Err(e) => log::warn!("[answer_macro] 读取日志失败 {:?}: {}", path, e),
+                    }
+                }
+            }
+        }
+        Some(plan)
+    }
+}
+
+/// 读取文件末尾 `lines` 行
+fn tail_lines(path: &Path, lines: usize) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let content = String::from_utf8_lossy(&bytes);
+    let all: Vec<&str> = content.lines().collect();
+    // 从文件中间开始读取时，第一行可能不完整
+    let skip_partial = usize::from(start > 0 && all.len() > lines);
+    let from = all.len().saturating_sub(lines).max(skip_partial);
+    Ok(all[from..].join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_macro_with_allowlisted_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("app.log");
+        let content: Vec<String> = (1..=100).map(|i| format!("line {}", i)).collect();
+        std::fs::write(&log_path, content.join("\n")).unwrap();
+        let secret = dir.path().join("secret.txt");
+        std::fs::write(&secret, "token").unwrap();
+
+        let config = AnswerMacroConfig {
+            enabled: true,
+            allowed_log_files: vec![log_path.to_string_lossy().to_string()],
+            macros: vec![AnswerMacro {
+                option: "It still fails".to_string(),
+                append_text: Some("Still failing after the change.".to_string()),
+                actions: vec![
+                    MacroAction::Screenshot,
+                    MacroAction::TailLog { path: log_path.to_string_lossy().to_string(), lines: 3 },
+                    MacroAction::TailLog { path: secret.to_string_lossy().to_string(), lines: 3 },
+                ],
+            }],
+        };
+
+        let plan = config.expand("It still fails").unwrap();
+        assert!(plan.capture_screenshot);
+        assert_eq!(plan.text_blocks.len(), 2);
+        assert_eq!(plan.text_blocks[0], "Still failing after the change.");
+        assert!(plan.text_blocks[1].contains("line 98\nline 99\nline 100\n```"));
+        assert!(!plan.text_blocks[1].contains("line 97"));
+
+        assert!(config.expand("Looks good").is_none());
+        let disabled = AnswerMacroConfig { enabled: false, ..config };
+        assert!(disabled.expand("It still fails").is_none());
+    }
+
+    #[test]
+    fn test_action_deserialization() {
+        let actions: Vec<MacroAction> =
+            serde_json::from_str(r#"[{"type":"screenshot"},{"type":"tailLog","path":"/tmp/a.log"}]"#).unwrap();
+        assert_eq!(actions[0], MacroAction::Screenshot);
+        assert_eq!(actions[1], MacroAction::TailLog { path: "/tmp/a.log".to_string(), lines: 50 });
+    }
+}
@@ -22,6 +22,9 @@ pub enum ApiProvider {
     Gemini,
     DeepSeek,
     Volcengine,
+    Anthropic,
+    Azure,
+    Ollama,
 }
 
 impl ApiProvider {
@@ -32,6 +35,9 @@ impl ApiProvider {
             "gemini" => Ok(Self::Gemini),
             "deepseek" => Ok(Self::DeepSeek),
             "volcengine" => Ok(Self::Volcengine),
+            "anthropic" => Ok(Self::Anthropic),
+            "azure" => Ok(Self::Azure),
+            "ollama" => Ok(Self::Ollama),
             _ => Err(ApiKeyError::InvalidProvider(s.to_string())),
         }
     }
@@ -43,6 +49,9 @@ impl ApiProvider {
             Self::Gemini,
             Self::DeepSeek,
             Self::Volcengine,
+            Self::Anthropic,
+            Self::Azure,
+            Self::Ollama,
         ]
     }
     
@@ -53,6 +62,9 @@ impl ApiProvider {
             Self::Gemini => "gemini",
             Self::DeepSeek => "deepseek",
             Self::Volcengine => "volcengine",
+            Self::Anthropic => "anthropic",
+            Self::Azure => "azure",
+            Self::Ollama => "ollama",
         }
     }
 }
@@ -0,0 +1,221 @@
+//! 会话自动归档模块
+//!
+//! 请求完成后，将请求/响应（JSON）和图片附件复制到用户选择的归档目录，
+//! 按项目和日期组织，避免临时目录被清理后丢失记录：
+//!
+//! ```text
+//! <archive>/<project>/<YYYY-MM-DD>/<HHMMSS>_<session_id>/
+//!     session.json
+//!     attachments/image_1.png
+//! ```
+//!
+//! 超过保留天数的日期目录会在每次归档后删除。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::popup::PopupRequest;
+use crate::session::SessionRecord;
+
+/// 项目名称无法确定时使用的目录名
+const DEFAULT_PROJECT_NAME: &str = "default";
+
+/// 日期目录格式
+const DATE_DIR_FORMAT: &str = "%Y-%m-%d";
+
+/// 归档配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveConfig {
+    /// 是否启用自动归档
+    #[serde(default)]
+    pub enabled: bool,
+    /// 归档目录
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// 保留天数（0 表示永久保留）
+    #[serde(default)]
+    pub retention_days: u32,
+}
+
+impl ArchiveConfig {
+    /// 启用且配置了目录时返回归档目录
+    pub fn archive_dir(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.directory
+            .as_deref()
+            .filter(|d| !d.trim().is_empty())
+            .map(PathBuf::from)
+    }
+}
+
+/// 当前项目名称（MCP 服务器由客户端在项目目录中启动，取工作目录名）
+pub fn current_project_name() -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().to_string()))
+        .map(|name| sanitize_dir_name(&name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROJECT_NAME.to_string())
+}
+
+/// 请求所属项目的归档目录名：优先使用请求中的项目名称或工作目录，否则取当前工作目录名
+pub fn request_project_name(request: &PopupRequest) -> String {
+    request
+        .project_label()
+        .map(|name| sanitize_dir_name(&name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(current_project_name)
+}
+
+/// 替换文件名中不允许的字符
+fn sanitize_dir_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_control() || r#"<>:"/\|?*"#.contains(c) { '_' } else { c })
+        .collect::<String>()
+        .trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string()
+}
+
+/// 图片 MIME 类型对应的扩展名
+fn image_extension(mime_type: &str) -> &str {
+    match mime_type {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "png",
+    }
+}
+
+/// 归档一次会话，并按保留策略清理过期记录
+///
+/// # Returns
+/// * 本次会话的归档目录
+pub async fn archive_session(record: &SessionRecord, config: &ArchiveConfig, project: &str) -> Result<PathBuf> {
+    let root = config.archive_dir().ok_or_else(|| anyhow!("Archive is not enabled"))?;
+
+    let responded_at = DateTime::parse_from_rfc3339(&record.responded_at)
+        .map(|t| t.with_timezone(&Local))
+        .unwrap_or_else(|_| Local::now());
+    let session_dir = root
+        .join(project)
+        .join(responded_at.format(DATE_DIR_FORMAT).to_string())
+        .join(format!("{}_{}", responded_at.format("%H%M%S"), record.id));
+    let attachments_dir = session_dir.join("attachments");
+    tokio::fs::create_dir_all(&attachments_dir).await?;
+
+    // 图片单独保存为文件，JSON 中以相对路径引用
+    let mut archived = record.clone();
+    for (i, image) in archived.response.images.iter_mut().enumerate() {
+        let file_name = format!("image_{}.{}", i + 1, image_extension(&image.mime_type));
+        match crate::image_processor::ImageProcessor::decode_base64(&image.data) {
+            Ok(bytes) => {
+                tokio::fs::write(attachments_dir.join(&file_name), bytes).await?;
+                image.data = format!("attachments/{}", file_name);
+            }
+            Err(e) => log::warn!("[archive] 图片解码失败，保留原始数据: {}", e),
+        }
+    }
+
+    let content = serde_json::to_string_pretty(&archived)?;
+    tokio::fs::write(session_dir.join("session.json"), content).await?;
+    log::info!("[archive] 已归档会话: {:?}", session_dir);
+
+    if config.retention_days > 0 {
+        let cutoff = Local::now().date_naive() - chrono::Duration::days(config.retention_days as i64);
+        if let Err(e) = purge_expired(&root, cutoff).await {
+            log::warn!("[archive] 清理过期归档失败: {}", e);
+        }
+    }
+
+    Ok(session_dir)
+}
+
+/// 删除早于 `cutoff` 的日期目录
+async fn purge_expired(root: &Path, cutoff: NaiveDate) -> Result<()> {
+    let mut projects = tokio::fs::read_dir(root).await?;
+    while let Some(project) = projects.next_entry().await? {
+        if !project.file_type().await?.is_dir() {
+            continue;
+        }
+        let mut dates = tokio::fs::read_dir(project.path()).await?;
+        while let Some(date_dir) = dates.next_entry().await? {
+            let name = date_dir.file_name();
+            let Ok(date) = NaiveDate::parse_from_str(&name.to_string_lossy(), DATE_DIR_FORMAT) else {
+                continue;
+            };
+            if date < cutoff {
+                log::info!("[archive] 删除过期归档: {:?}", date_dir.path());
+                tokio::fs::remove_dir_all(date_dir.path()).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::{ImageData, PopupResponse};
+
+    #[test]
+    fn test_sanitize_dir_name() {
+        assert_eq!(sanitize_dir_name("my:project?"), "my_project_");
+        assert_eq!(sanitize_dir_name(" .hidden. "), "hidden");
+    }
+
+    #[test]
+    fn test_request_project_name() {
+        let mut request = PopupRequest::new(Some("review".to_string()), None, None);
+        request.working_dir = Some("/home/me/work/api-server".to_string());
+        assert_eq!(request_project_name(&request), "api-server");
+
+        request.project_name = Some("web:app".to_string());
+        assert_eq!(request_project_name(&request), "web_app");
+        assert_eq!(request.window_title("Interactive Feedback"), "Interactive Feedback — web:app");
+    }
+
+    #[tokio::test]
+    async fn test_archive_session_and_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ArchiveConfig {
+            enabled: true,
+            directory: Some(dir.path().to_string_lossy().to_string()),
+            retention_days: 30,
+        };
+
+        // 过期和未过期的日期目录
+        let expired = dir.path().join("demo").join("2000-01-01");
+        std::fs::create_dir_all(&expired).unwrap();
+
+        let request = PopupRequest::new(Some("review".to_string()), None, None);
+        let mut response = PopupResponse::cancelled(&request.id);
+        response.cancelled = false;
+        response.images = vec![ImageData {
+            data: "iVBORw0KGgo=".to_string(),
+            mime_type: "image/png".to_string(),
+            attachment_id: None,
+        }];
+        let record = SessionRecord::new(&request, &response);
+
+        let session_dir = archive_session(&record, &config, "demo").await.unwrap();
+
+        assert!(session_dir.starts_with(dir.path().join("demo")));
+        assert!(session_dir.join("attachments/image_1.png").exists());
+        let json = std::fs::read_to_string(session_dir.join("session.json")).unwrap();
+        assert!(json.contains("attachments/image_1.png"));
+        assert!(!expired.exists());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_archive_is_error() {
+        let request = PopupRequest::new(None, None, None);
+        let record = SessionRecord::new(&request, &PopupResponse::cancelled(&request.id));
+
+        assert!(archive_session(&record, &ArchiveConfig::default(), "demo").await.is_err());
+    }
+}
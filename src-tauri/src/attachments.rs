@@ -0,0 +1,354 @@
+//! 附件存储模块
+//!
+//! 处理后的图片按内容的 SHA-256 保存在应用数据目录的 `attachments` 目录下，附件 ID 即内容哈希，
+//! 相同内容只保存一份。反馈内容、MCP 响应和会话历史只保存附件 ID，
+//! 需要图片数据时（提交反馈、格式化 MCP 结果、导出报告、归档）再按 ID 读取，避免重复传递 Base64 数据。
+//!
+//! GUI 进程和 MCP Server 进程共享同一个目录，因此 GUI 写入的附件可以直接被 MCP Server 解析。
+
+use crate::config;
+use crate::image_processor::{ImageMetrics, ImageProcessor};
+use crate::popup::{self, ImageData};
+use crate::types::ProcessedImage;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// 附件事件名称
+pub const ATTACHMENT_CAPTURED_EVENT: &str = "attachment-captured";
+
+/// 应用数据目录下的附件目录名
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// 内存缓存的最大附件数（超出后清空，之后按需从磁盘读取）
+const MAX_CACHED: usize = 32;
+
+/// 附件捕获事件负载
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentCapturedPayload {
+    pub attachment_id: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub size: usize,
+}
+
+/// 附件元数据，与内容文件 `<id>` 一起保存为 `<id>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentMeta {
+    mime_type: String,
+    width: u32,
+    height: u32,
+    size: usize,
+    #[serde(default)]
+    metrics: Option<ImageMetrics>,
+}
+
+/// 内容寻址的附件存储
+pub struct AttachmentStore {
+    /// 附件目录，无法确定应用数据目录时为空（只保存在内存中）
+    dir: Option<PathBuf>,
+    cache: Mutex<HashMap<String, ProcessedImage>>,
+}
+
+impl Default for AttachmentStore {
+    fn default() -> Self {
+        match config::get_default_data_dir() {
+            Ok(dir) => Self::open(dir.join(ATTACHMENTS_DIR)),
+            Err(e) => {
+                log::warn!("[attachments] 无法确定应用数据目录，附件只保存在内存中: {}", e);
+                Self { dir: None, cache: Mutex::new(HashMap::new()) }
+            }
+        }
+    }
+}
+
+/// 进程内共享的附件存储
+pub fn store() -> &'static AttachmentStore {
+    static STORE: OnceLock<AttachmentStore> = OnceLock::new();
+    STORE.get_or_init(AttachmentStore::default)
+}
+
+/// 附件 ID 必须是 64 位小写十六进制（防止通过 ID 访问附件目录以外的文件）
+pub fn is_valid_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+impl AttachmentStore {
+    /// 使用指定目录（目录在第一次写入时创建）
+    pub fn open(dir: PathBuf) -> Self {
+        Self { dir: Some(dir), cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// 存储图片并返回附件 ID（内容的 SHA-256），内容已存在时不重复写入
+    pub fn insert(&self, image: &ProcessedImage) -> Result<String> {
+        let bytes = ImageProcessor::decode_base64(&image.data)?;
+        let id = popup::sha256_hex(&bytes);
+
+        if let Some(dir) = &self.dir {
+            let blob_path = dir.join(&id);
+            if !blob_path.exists() {
+                std::fs::create_dir_all(dir)?;
+                let meta = AttachmentMeta {
+                    mime_type: image.mime_type.clone(),
+                    width: image.width,
+                    height: image.height,
+                    size: bytes.len(),
+                    metrics: image.metrics.clone(),
+                };
+                // 先写元数据再写内容：内容文件存在即表示附件完整
+                std::fs::write(dir.join(format!("{}.json", id)), serde_json::to_vec(&meta)?)?;
+                let tmp_path = dir.join(format!("{}.tmp", id));
+                std::fs::write(&tmp_path, &bytes)?;
+                std::fs::rename(&tmp_path, &blob_path)?;
+                log::debug!("[attachments] 已保存附件 {} ({} bytes)", id, bytes.len());
+            }
+        }
+
+        let mut stored = image.clone();
+        stored.attachment_id = Some(id.clone());
+        self.cache_insert(id.clone(), stored);
+        Ok(id)
+    }
+
+    /// 根据 ID 获取附件
+    pub fn get(&self, id: &str) -> Option<ProcessedImage> {
+        if let Some(image) = self.cache.lock().unwrap().get(id) {
+            return Some(image.clone());
+        }
+        let image = self.load(id)
+            .inspect_err(|e| log::warn!("[attachments] 读取附件 {} 失败: {}", id, e))
+            .ok()?;
+        self.cache_insert(id.to_string(), image.clone());
+        Some(image)
+    }
+
+    /// 附件内容大小（字节），只读取元数据
+    pub fn size_of(&self, id: &str) -> Option<usize> {
+        if let Some(image) = self.cache.lock().unwrap().get(id) {
+            return Some(image.size);
+        }
+        self.load_meta(id).ok().map(|meta| meta.size)
+    }
+
+    /// 从内存缓存中移除附件
+    ///
+    /// 磁盘上的内容可能仍被会话历史引用，不在这里删除
+    pub fn remove(&self, id: &str) -> Option<ProcessedImage> {
+        self.cache.lock().unwrap().remove(id)
+    }
+
+    /// 将只有附件 ID 的图片填充为完整数据（ID 无法解析时保持不变）
+    pub fn resolve_images(&self, images: &mut [ImageData]) {
+        for image in images {
+            if !image.data.is_empty() {
+                continue;
+            }
+            let Some(id) = image.attachment_id.as_deref() else { continue };
+            match self.get(id) {
+                Some(stored) => {
+                    image.data = stored.data;
+                    image.mime_type = stored.mime_type;
+                }
+                None => log::warn!("[attachments] 附件不存在: {}", id),
+            }
+        }
+    }
+
+    /// 将图片数据存入附件存储，只保留附件 ID（保存失败时保留原始数据）
+    pub fn intern_images(&self, images: &mut [ImageData]) {
+        for image in images {
+            if image.data.is_empty() {
+                continue;
+            }
+            let processed = ProcessedImage {
+                data: std::mem::take(&mut image.data),
+                mime_type: image.mime_type.clone(),
+                width: 0,
+                height: 0,
+                size: 0,
+                metrics: None,
+                attachment_id: None,
+            };
+            match self.insert(&processed) {
+                Ok(id) => image.attachment_id = Some(id),
+                Err(e) => {
+                    log::warn!("[attachments] 保存图片附件失败，保留原始数据: {}", e);
+                    image.data = processed.data;
+                }
+            }
+        }
+    }
+
+    /// 删除没有被引用的附件（只删除超过 `min_age` 的文件，避免删除尚未提交的附件）
+    ///
+    /// # Returns
+    /// * 删除的附件数和字节数
+    pub fn sweep_orphans(&self, referenced: &HashSet<String>, min_age: Duration) -> (usize, u64) {
+        let Some(dir) = &self.dir else {
+            return (0, 0);
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (0, 0);
+        };
+        let now = SystemTime::now();
+        let mut removed = 0;
+        let mut freed = 0;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // 元数据文件随内容文件一起删除；写入中断残留的临时文件和元数据按同样规则处理
+            let id = name.strip_suffix(".json")
+                .or_else(|| name.strip_suffix(".tmp"))
+                .unwrap_or(&name);
+            if !is_valid_id(id) || referenced.contains(id) || (id != name && dir.join(id).exists()) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let old_enough = metadata.modified()
+                .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age >= min_age));
+            if !old_enough || std::fs::remove_file(entry.path()).is_err() {
+                continue;
+            }
+            freed += metadata.len();
+            if id == name {
+                removed += 1;
+                self.remove(id);
+                if let Ok(meta) = std::fs::metadata(dir.join(format!("{}.json", id))) {
+                    if std::fs::remove_file(dir.join(format!("{}.json", id))).is_ok() {
+                        freed += meta.len();
+                    }
+                }
+            }
+        }
+        if removed > 0 {
+            log::info!("[attachments] 已清理 {} 个未被引用的附件", removed);
+        }
+        (removed, freed)
+    }
+
+    fn cache_insert(&self, id: String, image: ProcessedImage) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED && !cache.contains_key(&id) {
+            cache.clear();
+        }
+        cache.insert(id, image);
+    }
+
+    fn load_meta(&self, id: &str) -> Result<AttachmentMeta> {
+        if !is_valid_id(id) {
+            return Err(anyhow!("无效的附件 ID: {}", id));
+        }
+        let dir = self.dir.as_ref().ok_or_else(|| anyhow!("附件目录不可用"))?;
+        Ok(serde_json::from_slice(&std::fs::read(dir.join(format!("{}.json", id)))?)?)
+    }
+
+    fn load(&self, id: &str) -> Result<ProcessedImage> {
+        let meta = self.load_meta(id)?;
+        let dir = self.dir.as_ref().ok_or_else(|| anyhow!("附件目录不可用"))?;
+        let bytes = std::fs::read(dir.join(id))?;
+        if popup::sha256_hex(&bytes) != id {
+            return Err(anyhow!("附件内容与 ID 不一致"));
+        }
+        Ok(ProcessedImage {
+            data: ImageProcessor::encode_base64(&bytes),
+            mime_type: meta.mime_type,
+            width: meta.width,
+            height: meta.height,
+            size: meta.size,
+            metrics: meta.metrics,
+            attachment_id: Some(id.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_image() -> ProcessedImage {
+        ProcessedImage {
+            data: "AAAA".to_string(),
+            mime_type: "image/jpeg".to_string(),
+            width: 1,
+            height: 1,
+            size: 3,
+            metrics: None,
+            attachment_id: None,
+        }
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::open(dir.path().to_path_buf());
+        let id = store.insert(&sample_image()).unwrap();
+
+        assert!(is_valid_id(&id));
+        assert_eq!(store.get(&id).unwrap().mime_type, "image/jpeg");
+        assert!(store.remove(&id).is_some());
+        // 移除只影响缓存，仍可从磁盘读取
+        let loaded = store.get(&id).unwrap();
+        assert_eq!(loaded.data, "AAAA");
+        assert_eq!(loaded.attachment_id.as_deref(), Some(id.as_str()));
+        assert!(store.get("../config").is_none());
+    }
+
+    #[test]
+    fn test_content_addressed_dedup() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::open(dir.path().to_path_buf());
+        let first = store.insert(&sample_image()).unwrap();
+        let second = store.insert(&sample_image()).unwrap();
+        assert_eq!(first, second);
+
+        // 另一个进程（新的存储实例）可以通过 ID 读取
+        let other = AttachmentStore::open(dir.path().to_path_buf());
+        assert_eq!(other.size_of(&first), Some(3));
+        assert_eq!(other.get(&first).unwrap().data, "AAAA");
+    }
+
+    #[test]
+    fn test_sweep_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::open(dir.path().to_path_buf());
+        let kept = store.insert(&sample_image()).unwrap();
+        let orphan = store.insert(&ProcessedImage { data: "BBBB".to_string(), ..sample_image() }).unwrap();
+
+        // 新附件可能尚未提交，不清理
+        let referenced = HashSet::from([kept.clone()]);
+        assert_eq!(store.sweep_orphans(&referenced, Duration::from_secs(3600)).0, 0);
+
+        let (removed, freed) = store.sweep_orphans(&referenced, Duration::ZERO);
+        assert_eq!(removed, 1);
+        assert!(freed >= 3);
+        assert!(store.get(&orphan).is_none());
+        assert!(!dir.path().join(format!("{}.json", orphan)).exists());
+        assert!(store.get(&kept).is_some());
+    }
+
+    #[test]
+    fn test_intern_and_resolve_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::open(dir.path().to_path_buf());
+        let mut images = vec![ImageData {
+            data: "AAAA".to_string(),
+            mime_type: "image/png".to_string(),
+            attachment_id: None,
+        }];
+
+        store.intern_images(&mut images);
+        assert!(images[0].data.is_empty());
+        assert!(images[0].attachment_id.is_some());
+        assert_eq!(store.size_of(images[0].attachment_id.as_deref().unwrap()), Some(3));
+
+        store.resolve_images(&mut images);
+        assert_eq!(images[0].data, "AAAA");
+        assert_eq!(images[0].mime_type, "image/png");
+    }
+}
@@ -5,24 +5,67 @@
 //! 
 //! - 12.1: 反馈窗口打开时播放通知音
 //! - 12.3: 支持选择自定义音频文件
+//! 
+//! 内置音频作为 Tauri 资源随 GUI 打包（`sounds/` 目录），运行时按需读取，不编译进二进制。
+//! GUI 启动时通过 [`set_sound_dir`] 设置资源目录，其他进程（MCP Server）按 Tauri 的资源目录规则
+//! 查找：可执行文件旁的 `sounds/`，macOS 应用包中为 `Contents/Resources/sounds/`。
+//! 找不到音频文件时播放代码生成的提示音。
 
+use rodio::source::{SineWave, Source};
 use rodio::{Decoder, OutputStream, Sink};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufReader, Cursor};
-use std::path::Path;
-use std::sync::mpsc;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, OnceLock};
 use std::thread;
 use std::time::Duration;
 use thiserror::Error;
 
-/// 内置音频文件
-const SOUND_NOTIFICATION: &[u8] = include_bytes!("../assets/sounds/notification.wav");
-const SOUND_100W: &[u8] = include_bytes!("../assets/sounds/100w.mp3");
-const SOUND_GANMA: &[u8] = include_bytes!("../assets/sounds/ganma.mp3");
-const SOUND_GAOWAN: &[u8] = include_bytes!("../assets/sounds/gaowan.mp3");
-const SOUND_JI: &[u8] = include_bytes!("../assets/sounds/ji.mp3");
-const SOUND_DENG: &[u8] = include_bytes!("../assets/sounds/deng.mp3");
+/// 内置音频 ID 和资源目录中的文件名
+const BUILTIN_SOUND_FILES: [(&str, &str); 6] = [
+    ("notification", "notification.wav"),
+    ("100w", "100w.mp3"),
+    ("ganma", "ganma.mp3"),
+    ("gaowan", "gaowan.mp3"),
+    ("ji", "ji.mp3"),
+    ("deng", "deng.mp3"),
+];
+
+/// 内置音频资源目录（GUI 启动时设置）
+static SOUND_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 设置内置音频资源目录，只有第一次调用生效
+pub fn set_sound_dir(dir: PathBuf) {
+    log::info!("内置音频目录: {}", dir.display());
+    let _ = SOUND_DIR.set(dir);
+}
+
+/// 内置音频资源目录：已设置的目录，否则按可执行文件位置查找第一个存在的候选目录
+fn sound_dir() -> Option<PathBuf> {
+    if let Some(dir) = SOUND_DIR.get() {
+        return Some(dir.clone());
+    }
+    let exe = std::env::current_exe().ok()?;
+    let candidates = sound_dir_candidates(exe.parent()?);
+    candidates.iter().find(|dir| dir.is_dir()).or(candidates.first()).cloned()
+}
+
+/// 可执行文件所在目录对应的资源目录候选（与 Tauri 的资源目录规则一致）
+fn sound_dir_candidates(exe_dir: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    // macOS 应用包：Contents/MacOS/<exe> 的资源在 Contents/Resources
+    if cfg!(target_os = "macos") {
+        candidates.push(exe_dir.join("../Resources/sounds"));
+    }
+    // Linux 安装包（deb/rpm/AppImage）：<prefix>/bin/<exe> 的资源在 <prefix>/lib/<产品名>
+    if cfg!(target_os = "linux") {
+        candidates.push(exe_dir.join("../lib/WhaleInteractiveFeedback/sounds"));
+        candidates.push(PathBuf::from("/usr/lib/WhaleInteractiveFeedback/sounds"));
+    }
+    candidates.push(exe_dir.join("sounds"));
+    candidates
+}
 
 /// 内置音频信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,19 +112,46 @@ pub fn get_builtin_sounds() -> Vec<BuiltinSound> {
     ]
 }
 
-/// 根据 ID 获取内置音频数据
-fn get_builtin_sound_data(id: &str) -> Option<&'static [u8]> {
-    match id {
-        "notification" => Some(SOUND_NOTIFICATION),
-        "100w" => Some(SOUND_100W),
-        "ganma" => Some(SOUND_GANMA),
-        "gaowan" => Some(SOUND_GAOWAN),
-        "ji" => Some(SOUND_JI),
-        "deng" => Some(SOUND_DENG),
-        _ => None,
+/// 是否是已知的内置音频 ID
+fn is_builtin_sound(id: &str) -> bool {
+    BUILTIN_SOUND_FILES.iter().any(|(sound_id, _)| *sound_id == id)
+}
+
+/// 内置音频在指定目录中的路径，未知的 ID 返回 None
+fn builtin_sound_path(dir: &Path, id: &str) -> Option<PathBuf> {
+    BUILTIN_SOUND_FILES
+        .iter()
+        .find(|(sound_id, _)| *sound_id == id)
+        .map(|(_, file)| dir.join(file))
+}
+
+/// 读取内置音频数据，资源缺失时返回 None
+fn load_builtin_sound(id: &str) -> Option<Vec<u8>> {
+    let path = builtin_sound_path(&sound_dir()?, id)?;
+    match std::fs::read(&path) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            log::warn!("读取内置音频失败，改用提示音: {}: {}", path.display(), e);
+            None
+        }
     }
 }
 
+/// 代码生成的提示音（内置音频资源缺失时使用）：两声短促的正弦波
+fn append_fallback_beep(sink: &Sink) {
+    let tone = |freq: f32| SineWave::new(freq).take_duration(Duration::from_millis(120)).amplify(0.25);
+    sink.append(tone(880.0));
+    sink.append(tone(1320.0).delay(Duration::from_millis(60)));
+}
+
+/// 紧急提示音重复次数
+const ALERT_REPEAT: u32 = 3;
+/// 紧急提示音音量（1.0 为原始音量）
+const ALERT_VOLUME: f32 = 1.5;
+
+/// 音频输出设备检测结果（进程内只检测一次）
+static AUDIO_AVAILABILITY: OnceLock<Result<(), String>> = OnceLock::new();
+
 /// 音频错误类型
 #[derive(Error, Debug)]
 pub enum AudioError {
@@ -100,6 +170,9 @@ pub enum AudioError {
     #[error("音频播放失败: {0}")]
     PlaybackError(String),
     
+    #[error("音频输出不可用，已跳过播放: {0}")]
+    Unavailable(String),
+    
     #[error("不支持的音频格式: {0}")]
     UnsupportedFormat(String),
 }
@@ -110,6 +183,26 @@ pub enum AudioError {
 pub struct AudioNotifier;
 
 impl AudioNotifier {
+    /// 检测音频输出设备是否可用
+    /// 
+    /// 首次调用时尝试打开默认输出设备并缓存结果，之后直接返回缓存。
+    /// 无头 Linux 等没有音频设备的环境下只在检测时记录一次日志，之后的播放请求直接跳过。
+    /// 
+    /// # Returns
+    /// * `Ok(())` - 音频输出可用
+    /// * `Err(String)` - 不可用的原因
+    pub fn availability() -> Result<(), String> {
+        AUDIO_AVAILABILITY
+            .get_or_init(|| match OutputStream::try_default() {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    log::info!("未检测到可用的音频输出设备，将跳过通知音播放: {}", e);
+                    Err(e.to_string())
+                }
+            })
+            .clone()
+    }
+    
     /// 播放通知音
     /// 
     /// # Arguments
@@ -163,6 +256,9 @@ impl AudioNotifier {
     /// # Requirements
     /// - 12.4: IF audio playback fails THEN the Audio_Notifier SHALL silently continue without interrupting the workflow
     pub fn play_notification_async(sound_path: Option<&str>) {
+        if Self::availability().is_err() {
+            return;
+        }
         let path_owned = sound_path.map(|s| s.to_string());
         
         thread::spawn(move || {
@@ -172,8 +268,29 @@ impl AudioNotifier {
         });
     }
     
+    /// 异步播放紧急提示音：提高音量并重复播放
+    pub fn play_alert_async(sound_path: Option<&str>) {
+        if Self::availability().is_err() {
+            return;
+        }
+        let path_owned = sound_path.map(|s| s.to_string());
+        
+        thread::spawn(move || {
+            if let Err(e) = Self::play_sound_repeated(path_owned.as_deref(), ALERT_REPEAT, ALERT_VOLUME) {
+                log::warn!("音频播放失败（静默继续）: {}", e);
+            }
+        });
+    }
+    
     /// 阻塞式播放音频
     fn play_sound_blocking(sound_path: Option<&str>) -> Result<(), AudioError> {
+        Self::play_sound_repeated(sound_path, 1, 1.0)
+    }
+    
+    /// 阻塞式按指定次数和音量播放音频
+    fn play_sound_repeated(sound_path: Option<&str>, repeat: u32, volume: f32) -> Result<(), AudioError> {
+        Self::availability().map_err(AudioError::Unavailable)?;
+        
         // 获取音频输出流
         let (_stream, stream_handle) = OutputStream::try_default()
             .map_err(|e| AudioError::OutputDeviceError(e.to_string()))?;
@@ -181,21 +298,24 @@ impl AudioNotifier {
         // 创建 Sink
         let sink = Sink::try_new(&stream_handle)
             .map_err(|e| AudioError::PlaybackError(e.to_string()))?;
+        sink.set_volume(volume);
         
-        // 根据是否有自定义路径选择音频源
-        match sound_path {
-            Some(path) if !path.is_empty() => {
-                // 检查是否是内置音频 ID（以 "builtin:" 开头）
-                if let Some(builtin_id) = path.strip_prefix("builtin:") {
-                    Self::play_builtin_sound(&sink, builtin_id)?;
-                } else {
-                    // 使用自定义音频文件
-                    Self::play_custom_sound(&sink, path)?;
+        for _ in 0..repeat.max(1) {
+            // 根据是否有自定义路径选择音频源
+            match sound_path {
+                Some(path) if !path.is_empty() => {
+                    // 检查是否是内置音频 ID（以 "builtin:" 开头）
+                    if let Some(builtin_id) = path.strip_prefix("builtin:") {
+                        Self::play_builtin_sound(&sink, builtin_id)?;
+                    } else {
+                        // 使用自定义音频文件
+                        Self::play_custom_sound(&sink, path)?;
+                    }
+                }
+                _ => {
+                    // 使用默认音频
+                    Self::play_builtin_sound(&sink, "notification")?;
                 }
-            }
-            _ => {
-                // 使用默认音频
-                Self::play_builtin_sound(&sink, "notification")?;
             }
         }
         
@@ -219,10 +339,16 @@ impl AudioNotifier {
         Ok(())
     }
     
-    /// 播放内置音频
+    /// 播放内置音频，资源缺失时播放生成的提示音
     fn play_builtin_sound(sink: &Sink, id: &str) -> Result<(), AudioError> {
-        let sound_data = get_builtin_sound_data(id)
-            .ok_or_else(|| AudioError::FileNotFound(format!("内置音频不存在: {}", id)))?;
+        if !is_builtin_sound(id) {
+            return Err(AudioError::FileNotFound(format!("内置音频不存在: {}", id)));
+        }
+        let Some(sound_data) = load_builtin_sound(id) else {
+            append_fallback_beep(sink);
+            log::info!("播放生成的提示音（内置音频 {} 不可用）", id);
+            return Ok(());
+        };
         
         let cursor = Cursor::new(sound_data);
         
@@ -248,8 +374,9 @@ impl AudioNotifier {
     /// * `Err(AudioError)` - 文件无效
     pub fn validate_audio_file(path: &str) -> Result<(), AudioError> {
         // 检查是否是内置音频
+        // 内置音频资源缺失时会播放生成的提示音，只检查 ID
         if let Some(builtin_id) = path.strip_prefix("builtin:") {
-            if get_builtin_sound_data(builtin_id).is_some() {
+            if is_builtin_sound(builtin_id) {
                 return Ok(());
             } else {
                 return Err(AudioError::FileNotFound(format!("内置音频不存在: {}", builtin_id)));
@@ -305,6 +432,49 @@ mod tests {
         assert!(formats.contains(&"flac"));
     }
     
+    #[test]
+    fn test_availability_cached() {
+        let first = AudioNotifier::availability();
+        assert_eq!(first, AudioNotifier::availability());
+        if first.is_err() {
+            let result = AudioNotifier::play_sound_blocking(None);
+            assert!(matches!(result, Err(AudioError::Unavailable(_))));
+        }
+    }
+    
+    #[test]
+    fn test_builtin_sound_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/sounds");
+        for sound in get_builtin_sounds() {
+            let path = builtin_sound_path(&dir, &sound.id).unwrap();
+            assert!(path.exists(), "缺少内置音频资源: {}", path.display());
+        }
+        assert!(builtin_sound_path(&dir, "unknown").is_none());
+        
+        assert!(AudioNotifier::validate_audio_file("builtin:deng").is_ok());
+        assert!(matches!(
+            AudioNotifier::validate_audio_file("builtin:unknown"),
+            Err(AudioError::FileNotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn test_sound_dir_candidates() {
+        let exe_dir = Path::new("/Applications/WhaleInteractiveFeedback.app/Contents/MacOS");
+        let candidates = sound_dir_candidates(exe_dir);
+        assert_eq!(candidates.last(), Some(&exe_dir.join("sounds")));
+        if cfg!(target_os = "macos") {
+            assert_eq!(candidates[0], exe_dir.join("../Resources/sounds"));
+        }
+
+        let exe_dir = Path::new("/usr/bin");
+        let candidates = sound_dir_candidates(exe_dir);
+        if cfg!(target_os = "linux") {
+            assert!(candidates.contains(&PathBuf::from("/usr/lib/WhaleInteractiveFeedback/sounds")));
+            assert!(candidates.contains(&exe_dir.join("../lib/WhaleInteractiveFeedback/sounds")));
+        }
+    }
+    
     #[test]
     fn test_validate_nonexistent_file() {
         let result = AudioNotifier::validate_audio_file("/nonexistent/path/audio.wav");
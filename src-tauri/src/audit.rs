@@ -0,0 +1,195 @@
+//! 审计日志模块
+//!
+//! 可选地将每次 MCP 工具调用以 JSON Lines 格式追加到审计日志文件，
+//! 记录工具名、参数、结果摘要、耗时和结果，便于团队追溯 Agent 提出了什么问题、用户批准了什么。
+//!
+//! 写入前会脱敏参数：API Key 等凭据字段和图片数据不会写入日志。
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::text_metrics::TextMetrics;
+
+/// 默认审计日志文件名（位于应用数据目录）
+const DEFAULT_FILE_NAME: &str = "audit.jsonl";
+
+/// 结果摘要最大字符数
+const MAX_SUMMARY_CHARS: usize = 500;
+
+/// 脱敏后的占位文本
+const REDACTED: &str = "[REDACTED]";
+
+/// 名称包含这些片段的字段视为凭据
+const SECRET_KEY_PARTS: &[&str] = &["api_key", "apikey", "secret", "token", "password", "authorization"];
+
+/// 超过此长度且看起来像 base64 的字符串视为二进制数据（图片等）
+const MAX_INLINE_DATA_CHARS: usize = 1024;
+
+/// 审计日志配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogConfig {
+    /// 是否启用审计日志
+    #[serde(default)]
+    pub enabled: bool,
+    /// 日志文件路径（为空时使用应用数据目录下的 audit.jsonl）
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl AuditLogConfig {
+    /// 启用时返回日志文件路径
+    pub fn log_path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        match self.path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => crate::config::get_default_data_dir()
+                .ok()
+                .map(|dir| dir.join(DEFAULT_FILE_NAME)),
+        }
+    }
+}
+
+/// 单条审计记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// 调用时间（RFC 3339）
+    pub timestamp: String,
+    pub tool: String,
+    /// MCP 客户端名称
+    #[serde(default)]
+    pub client: Option<String>,
+    /// 脱敏后的调用参数
+    pub params: Value,
+    /// 结果摘要（截断）
+    pub summary: String,
+    /// ok / error
+    pub outcome: String,
+    pub latency_ms: u64,
+    /// 用户最终输入的文本统计（有文本输入时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_metrics: Option<TextMetrics>,
+}
+
+/// 写入锁，避免并发调用的记录交错
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 追加一条记录到审计日志
+pub fn append(path: &std::path::Path, record: &AuditRecord) -> Result<()> {
+    let line = serde_json::to_string(record)?;
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// 脱敏参数：凭据字段替换为占位文本，图片等大段二进制数据只保留长度
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let lower = key.to_lowercase();
+                let redacted = if SECRET_KEY_PARTS.iter().any(|part| lower.contains(part)) {
+                    Value::String(REDACTED.to_string())
+                } else {
+                    redact(value)
+                };
+                (key.clone(), redacted)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::String(s) if is_binary_data(s) => Value::String(format!("[{} bytes of data redacted]", s.len())),
+        _ => value.clone(),
+    }
+}
+
+/// data URL 或大段 base64 文本
+fn is_binary_data(s: &str) -> bool {
+    if s.starts_with("data:") && s.contains(";base64,") {
+        return true;
+    }
+    s.len() > MAX_INLINE_DATA_CHARS
+        && !s.contains(char::is_whitespace)
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_'))
+}
+
+/// 截断结果文本作为摘要
+pub fn summarize(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_secrets_and_images() {
+        let image = "iVBORw0KGgo".repeat(200);
+        let params = json!({
+            "message": "Deploy?",
+            "apiKey": "sk-123",
+            "headers": { "Authorization": "Bearer abc" },
+            "images": [{ "data": image, "mime_type": "image/png" }],
+            "preview": "data:image/png;base64,AAAA",
+        });
+
+        let redacted = redact(&params);
+        assert_eq!(redacted["message"], "Deploy?");
+        assert_eq!(redacted["apiKey"], REDACTED);
+        assert_eq!(redacted["headers"]["Authorization"], REDACTED);
+        assert_eq!(redacted["images"][0]["data"], "[2200 bytes of data redacted]");
+        assert_eq!(redacted["images"][0]["mime_type"], "image/png");
+        assert_eq!(redacted["preview"], "[26 bytes of data redacted]");
+    }
+
+    #[test]
+    fn test_append_writes_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("audit.jsonl");
+        let record = AuditRecord {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            tool: "whale_confirm".to_string(),
+            client: Some("cursor".to_string()),
+            params: json!({ "message": "Deploy?" }),
+            summary: summarize("{\"confirmed\":true}"),
+            outcome: "ok".to_string(),
+            latency_ms: 1200,
+            input_metrics: Some(crate::text_metrics::text_metrics("ship it")),
+        };
+
+        append(&path, &record).unwrap();
+        append(&path, &record).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: AuditRecord = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(parsed.tool, "whale_confirm");
+        assert_eq!(parsed.latency_ms, 1200);
+        assert_eq!(parsed.input_metrics.unwrap().words, 2);
+    }
+
+    #[test]
+    fn test_log_path_requires_enabled() {
+        let config = AuditLogConfig { enabled: false, path: Some("/tmp/audit.jsonl".to_string()) };
+        assert!(config.log_path().is_none());
+        let config = AuditLogConfig { enabled: true, ..config };
+        assert_eq!(config.log_path(), Some(PathBuf::from("/tmp/audit.jsonl")));
+    }
+}
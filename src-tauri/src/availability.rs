@@ -0,0 +1,612 @@
+//! 用户可用时间
+//!
+//! 根据配置的工作时间和 ICS 日历判断用户当前是否方便回答：
+//! 会议期间或工作时间以外收到反馈请求时，可以照常弹窗、等到用户有空再弹窗，
+//! 或者发送到 Webhook 并立即告知 Agent。紧急请求总是照常弹窗。
+//!
+//! 日历按 `calendarPollMinutes` 轮询并缓存。只解析 VEVENT 的开始/结束时间、标题和状态；
+//! 全天事件、透明（空闲）和已取消的事件不视为会议，重复事件只支持 `FREQ=DAILY/WEEKLY`。
+//! 带 `TZID` 的时间按本地时区处理。
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::popup::{PopupRequest, Priority};
+use crate::result_language::{fill, ResultLanguage};
+use crate::types::{AppConfig, AvailabilityConfig, UnavailableAction, WorkingHours};
+
+/// 下载日历和调用 Webhook 的超时时间
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// 缓存多长时间范围内的会议（重复事件只展开这段时间）
+const CALENDAR_WINDOW_DAYS: i64 = 8;
+
+/// 当前状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AvailabilityStatus {
+    Available,
+    InMeeting,
+    OutsideWorkingHours,
+}
+
+/// 用户当前是否方便回答
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Availability {
+    pub status: AvailabilityStatus,
+    /// 预计有空的时间（会议结束或下一个工作时间开始）
+    pub until: Option<DateTime<Utc>>,
+    /// 当前会议的标题
+    pub meeting: Option<String>,
+}
+
+impl Availability {
+    /// 按配置决定如何处理反馈请求
+    pub fn action(&self, config: &AvailabilityConfig, priority: Priority) -> UnavailableAction {
+        if priority == Priority::Critical {
+            return UnavailableAction::Popup;
+        }
+        let action = match self.status {
+            AvailabilityStatus::Available => UnavailableAction::Popup,
+            AvailabilityStatus::InMeeting => config.during_meeting,
+            AvailabilityStatus::OutsideWorkingHours => config.outside_working_hours,
+        };
+        match action {
+            UnavailableAction::Webhook if webhook_url(config).is_none() => {
+                log::warn!("[availability] 未设置 Webhook 地址，照常弹窗");
+                UnavailableAction::Popup
+            }
+            UnavailableAction::Defer if self.until.is_none() => UnavailableAction::Popup,
+            action => action,
+        }
+    }
+
+    /// 距离有空还要等多久
+    pub fn wait_time(&self) -> Duration {
+        self.until
+            .and_then(|until| (until - Utc::now()).to_std().ok())
+            .unwrap_or_default()
+    }
+
+    /// 说明用户为什么不方便以及何时有空
+    pub fn describe(&self, language: ResultLanguage) -> String {
+        let labels = language.text();
+        let until = self
+            .until
+            .map(|until| until.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        match self.status {
+            AvailabilityStatus::InMeeting => fill(labels.in_meeting_until, &[&until]),
+            AvailabilityStatus::OutsideWorkingHours => fill(labels.outside_working_hours_until, &[&until]),
+            AvailabilityStatus::Available => labels.busy.to_string(),
+        }
+    }
+}
+
+/// 日历中的一段会议
+#[derive(Debug, Clone, PartialEq)]
+struct BusyPeriod {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    summary: Option<String>,
+}
+
+/// 按工作时间和会议判断当前状态
+fn evaluate<Tz: TimeZone>(config: &AvailabilityConfig, busy: &[BusyPeriod], now: DateTime<Tz>) -> Availability {
+    let now_utc = now.with_timezone(&Utc);
+    if let Some(meeting) = busy.iter().find(|b| b.start <= now_utc && now_utc < b.end) {
+        // 连续的会议合并计算结束时间
+        let mut until = meeting.end;
+        while let Some(next) = busy.iter().filter(|b| b.start <= until && b.end > until).map(|b| b.end).max() {
+            until = next;
+        }
+        return Availability {
+            status: AvailabilityStatus::InMeeting,
+            until: Some(until),
+            meeting: meeting.summary.clone(),
+        };
+    }
+
+    let rules: Vec<Rule> = config.working_hours.iter().filter_map(Rule::parse).collect();
+    let local = now.naive_local();
+    if !rules.is_empty() && !rules.iter().any(|rule| rule.contains(local)) {
+        let until = next_working_start(&rules, local)
+            .and_then(|start| now.timezone().from_local_datetime(&start).earliest())
+            .map(|start| start.with_timezone(&Utc));
+        return Availability { status: AvailabilityStatus::OutsideWorkingHours, until, meeting: None };
+    }
+
+    Availability { status: AvailabilityStatus::Available, until: None, meeting: None }
+}
+
+/// 解析后的工作时间段
+struct Rule {
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Rule {
+    fn parse(hours: &WorkingHours) -> Option<Self> {
+        let days: Vec<Weekday> = hours.days.iter().filter_map(|d| d.trim().parse().ok()).collect();
+        let start = NaiveTime::parse_from_str(hours.start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(hours.end.trim(), "%H:%M").ok()?;
+        if days.is_empty() {
+            log::warn!("[availability] 工作时间 {}-{} 没有有效的星期，已忽略", hours.start, hours.end);
+            return None;
+        }
+        Some(Self { days, start, end })
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let (day, time) = (now.weekday(), now.time());
+        if self.start < self.end {
+            self.days.contains(&day) && self.start <= time && time < self.end
+        } else {
+            // 跨过午夜：从开始日的开始时间到次日的结束时间
+            (self.days.contains(&day) && time >= self.start) || (self.days.contains(&day.pred()) && time < self.end)
+        }
+    }
+}
+
+/// 下一个工作时间的开始
+fn next_working_start(rules: &[Rule], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    (0..=7)
+        .filter_map(|offset| now.date().checked_add_signed(ChronoDuration::days(offset)))
+        .flat_map(|date| {
+            rules.iter()
+                .filter(move |rule| rule.days.contains(&date.weekday()))
+                .map(move |rule| date.and_time(rule.start))
+        })
+        .filter(|start| *start > now)
+        .min()
+}
+
+/// ICS 中的时间：UTC（以 `Z` 结尾）或本地时间
+#[derive(Debug, Clone, Copy)]
+struct IcsTime {
+    naive: NaiveDateTime,
+    utc: bool,
+}
+
+impl IcsTime {
+    /// 解析 `YYYYMMDDTHHMMSS[Z]`，全天日期（`YYYYMMDD`）返回 None
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Some(Self { naive, utc })
+    }
+
+    fn with_naive(self, naive: NaiveDateTime) -> Self {
+        Self { naive, ..self }
+    }
+
+    fn to_utc(self) -> Option<DateTime<Utc>> {
+        if self.utc {
+            Some(Utc.from_utc_datetime(&self.naive))
+        } else {
+            Local.from_local_datetime(&self.naive).earliest().map(|t| t.with_timezone(&Utc))
+        }
+    }
+}
+
+/// 解析 `RRULE` 中支持的部分
+#[derive(Debug, Default)]
+struct Recurrence {
+    weekly: bool,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    by_day: Vec<Weekday>,
+}
+
+impl Recurrence {
+    fn parse(value: &str) -> Option<Self> {
+        let mut rule = Recurrence { interval: 1, ..Default::default() };
+        for part in value.split(';') {
+            let (key, value) = part.split_once('=')?;
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => match value.trim().to_ascii_uppercase().as_str() {
+                    "DAILY" => rule.weekly = false,
+                    "WEEKLY" => rule.weekly = true,
+                    _ => return None,
+                },
+                "INTERVAL" => rule.interval = value.trim().parse().ok().filter(|i| *i > 0)?,
+                "COUNT" => rule.count = value.trim().parse().ok(),
+                "UNTIL" => {
+                    rule.until = IcsTime::parse(value)
+                        .and_then(IcsTime::to_utc)
+                        .or_else(|| {
+                            let date = NaiveDate::parse_from_str(value.trim(), "%Y%m%d").ok()?;
+                            Some(Utc.from_utc_datetime(&date.and_hms_opt(23, 59, 59)?))
+                        })
+                }
+                "BYDAY" => {
+                    // 忽略 `1MO` 之类的序号
+                    rule.by_day = value.split(',')
+                        .filter_map(|d| ics_weekday(d.trim().trim_start_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit())))
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+        Some(rule)
+    }
+
+    /// 从 `start` 开始的各次发生时间（开始时间早于 `window_end`）
+    fn occurrences(&self, start: IcsTime, window_end: DateTime<Utc>) -> Vec<IcsTime> {
+        let first = start.naive.date();
+        let by_day = if self.by_day.is_empty() { vec![first.weekday()] } else { self.by_day.clone() };
+        let first_week = first.week(Weekday::Mon).first_day();
+        let mut occurrences = Vec::new();
+        let mut date = first;
+        loop {
+            let matches = if self.weekly {
+                let weeks = (date.week(Weekday::Mon).first_day() - first_week).num_weeks();
+                weeks % self.interval == 0 && by_day.contains(&date.weekday())
+            } else {
+                (date - first).num_days() % self.interval == 0
+            };
+            if matches {
+                let occurrence = start.with_naive(date.and_time(start.naive.time()));
+                let Some(utc) = occurrence.to_utc() else { break };
+                if utc >= window_end || self.until.is_some_and(|until| utc > until) {
+                    break;
+                }
+                occurrences.push(occurrence);
+                if self.count.is_some_and(|count| occurrences.len() >= count) {
+                    break;
+                }
+            }
+            match date.succ_opt() {
+                Some(next) => date = next,
+                None => break,
+            }
+        }
+        occurrences
+    }
+}
+
+/// ICS 的星期缩写（`MO`、`TU` ...）
+fn ics_weekday(value: &str) -> Option<Weekday> {
+    match value.to_ascii_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// 解析 `DURATION`（如 `PT1H30M`、`P1D`、`P1W`）
+fn parse_duration(value: &str) -> Option<ChronoDuration> {
+    let value = value.trim().strip_prefix('P')?;
+    let mut total = ChronoDuration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match c {
+                    'W' => ChronoDuration::weeks(n),
+                    'D' => ChronoDuration::days(n),
+                    'H' => ChronoDuration::hours(n),
+                    'M' => ChronoDuration::minutes(n),
+                    _ => ChronoDuration::seconds(n),
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(total)
+}
+
+/// 单个 VEVENT 中用到的属性
+#[derive(Debug, Default)]
+struct Event {
+    start: Option<IcsTime>,
+    end: Option<IcsTime>,
+    duration: Option<ChronoDuration>,
+    summary: Option<String>,
+    recurrence: Option<Recurrence>,
+    all_day: bool,
+    free: bool,
+}
+
+/// 解析日历内容，返回 `[window_start, window_end)` 内的会议
+fn parse_ics(content: &str, window_start: DateTime<Utc>, window_end: DateTime<Utc>) -> Vec<BusyPeriod> {
+    // 展开折行（以空格或制表符开头的行接在上一行后面）
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut periods = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        let is_date = params.to_ascii_uppercase().contains("VALUE=DATE") && !value.contains('T');
+        match (name.to_ascii_uppercase().as_str(), event.as_mut()) {
+            ("BEGIN", _) if value.trim().eq_ignore_ascii_case("VEVENT") => event = Some(Event::default()),
+            ("END", Some(_)) if value.trim().eq_ignore_ascii_case("VEVENT") => {
+                if let Some(event) = event.take() {
+                    expand_event(event, window_start, window_end, &mut periods);
+                }
+            }
+            ("DTSTART", Some(event)) => {
+                event.all_day |= is_date;
+                event.start = IcsTime::parse(value);
+            }
+            ("DTEND", Some(event)) => event.end = IcsTime::parse(value),
+            ("DURATION", Some(event)) => event.duration = parse_duration(value),
+            ("SUMMARY", Some(event)) => {
+                event.summary = Some(value.replace("\\,", ",").replace("\\;", ";").replace("\\n", " ").trim().to_string())
+            }
+            ("RRULE", Some(event)) => event.recurrence = Recurrence::parse(value),
+            ("TRANSP", Some(event)) => event.free |= value.trim().eq_ignore_ascii_case("TRANSPARENT"),
+            ("STATUS", Some(event)) => event.free |= value.trim().eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+    periods.sort_by_key(|p| p.start);
+    periods
+}
+
+fn expand_event(event: Event, window_start: DateTime<Utc>, window_end: DateTime<Utc>, periods: &mut Vec<BusyPeriod>) {
+    if event.all_day || event.free {
+        return;
+    }
+    let Some(start) = event.start else { return };
+    let length = match (event.end, event.duration) {
+        (Some(end), _) => end.naive - start.naive,
+        (None, Some(duration)) => duration,
+        (None, None) => return,
+    };
+    let occurrences = match &event.recurrence {
+        Some(recurrence) => recurrence.occurrences(start, window_end),
+        None => vec![start],
+    };
+    for occurrence in occurrences {
+        let Some(begin) = occurrence.to_utc() else { continue };
+        let Some(end) = occurrence.with_naive(occurrence.naive + length).to_utc() else { continue };
+        if end > window_start && begin < window_end {
+            periods.push(BusyPeriod { start: begin, end, summary: event.summary.clone() });
+        }
+    }
+}
+
+/// 已下载的日历
+struct CalendarCache {
+    url: String,
+    fetched_at: Instant,
+    events: Vec<BusyPeriod>,
+}
+
+static CALENDAR: Mutex<Option<CalendarCache>> = Mutex::new(None);
+
+fn calendar_url(config: &AvailabilityConfig) -> Option<&str> {
+    config.calendar_url.as_deref().map(str::trim).filter(|url| !url.is_empty())
+}
+
+fn webhook_url(config: &AvailabilityConfig) -> Option<&str> {
+    config.webhook_url.as_deref().map(str::trim).filter(|url| !url.is_empty())
+}
+
+fn poll_interval(config: &AvailabilityConfig) -> Duration {
+    Duration::from_secs(config.calendar_poll_minutes.max(1) * 60)
+}
+
+/// 下载或读取日历内容
+async fn fetch_calendar(url: &str) -> Result<String> {
+    let http_url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    if http_url.starts_with("http://") || http_url.starts_with("https://") {
+        let client = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+        Ok(client.get(&http_url).send().await?.error_for_status()?.text().await?)
+    } else {
+        Ok(tokio::fs::read_to_string(url.strip_prefix("file://").unwrap_or(url)).await?)
+    }
+}
+
+/// 日历中的会议，按轮询间隔缓存；下载失败时继续使用上次的结果
+async fn busy_periods(config: &AvailabilityConfig) -> Vec<BusyPeriod> {
+    let Some(url) = calendar_url(config) else { return Vec::new() };
+    if let Some(cache) = CALENDAR.lock().unwrap().as_ref() {
+        if cache.url == url && cache.fetched_at.elapsed() < poll_interval(config) {
+            return cache.events.clone();
+        }
+    }
+
+    let now = Utc::now();
+    let events = match fetch_calendar(url).await {
+        Ok(content) => {
+            let events = parse_ics(&content, now - ChronoDuration::days(1), now + ChronoDuration::days(CALENDAR_WINDOW_DAYS));
+            log::info!("[availability] 日历已更新，{} 个会议", events.len());
+            events
+        }
+        Err(e) => {
+            log::warn!("[availability] 下载日历失败: {}", e);
+            CALENDAR.lock().unwrap().as_ref()
+                .filter(|cache| cache.url == url)
+                .map(|cache| cache.events.clone())
+                .unwrap_or_default()
+        }
+    };
+    *CALENDAR.lock().unwrap() = Some(CalendarCache {
+        url: url.to_string(),
+        fetched_at: Instant::now(),
+        events: events.clone(),
+    });
+    events
+}
+
+/// 用户当前是否方便回答
+pub async fn current(config: &AvailabilityConfig) -> Availability {
+    let busy = busy_periods(config).await;
+    evaluate(config, &busy, Local::now())
+}
+
+/// 按轮询间隔刷新日历（每次重新读取配置），请求到来时不需要等待下载
+pub async fn poll_calendar<F, Fut>(load_config: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = AppConfig>,
+{
+    loop {
+        let config = load_config().await.availability;
+        busy_periods(&config).await;
+        tokio::time::sleep(poll_interval(&config)).await;
+    }
+}
+
+/// 发送到 Webhook 的内容
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Escalation<'a> {
+    pub request_id: &'a str,
+    pub title: Option<&'a str>,
+    pub message: Option<&'a str>,
+    pub project_name: Option<String>,
+    pub priority: Priority,
+    pub availability: &'a Availability,
+}
+
+impl<'a> Escalation<'a> {
+    pub fn new(request: &'a PopupRequest, availability: &'a Availability) -> Self {
+        Self {
+            request_id: &request.id,
+            title: request.title.as_deref(),
+            message: request.message.as_deref(),
+            project_name: request.project_label(),
+            priority: request.priority,
+            availability,
+        }
+    }
+}
+
+/// 将请求发送到配置的 Webhook
+pub async fn escalate(config: &AvailabilityConfig, escalation: &Escalation<'_>) -> Result<()> {
+    let url = webhook_url(config).ok_or_else(|| anyhow::anyhow!("未设置 Webhook 地址"))?;
+    let client = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+    client.post(url).json(escalation).send().await?.error_for_status()?;
+    log::info!("[availability] 请求 {} 已发送到 Webhook", escalation.request_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    fn office_hours() -> AvailabilityConfig {
+        AvailabilityConfig {
+            working_hours: vec![WorkingHours {
+                days: ["mon", "tue", "wed", "thu", "fri"].map(String::from).to_vec(),
+                start: "09:00".to_string(),
+                end: "18:00".to_string(),
+            }],
+            ..AvailabilityConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_working_hours() {
+        let config = office_hours();
+        // 2024-01-05 是周五
+        let availability = evaluate(&config, &[], utc("2024-01-05T10:00:00Z"));
+        assert_eq!(availability.status, AvailabilityStatus::Available);
+
+        let availability = evaluate(&config, &[], utc("2024-01-05T19:00:00Z"));
+        assert_eq!(availability.status, AvailabilityStatus::OutsideWorkingHours);
+        assert_eq!(availability.until, Some(utc("2024-01-08T09:00:00Z")));
+
+        let availability = evaluate(&AvailabilityConfig::default(), &[], utc("2024-01-06T03:00:00Z"));
+        assert_eq!(availability.status, AvailabilityStatus::Available);
+    }
+
+    #[test]
+    fn test_overnight_working_hours() {
+        let rules = [Rule::parse(&WorkingHours {
+            days: vec!["fri".to_string()],
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+        })
+        .unwrap()];
+        let at = |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        assert!(rules[0].contains(at("2024-01-05 23:00")));
+        assert!(rules[0].contains(at("2024-01-06 05:00")));
+        assert!(!rules[0].contains(at("2024-01-06 07:00")));
+        assert_eq!(next_working_start(&rules, at("2024-01-06 07:00")), Some(at("2024-01-12 22:00")));
+    }
+
+    #[test]
+    fn test_parse_ics_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\nDTSTART:20240105T100000Z\r\nDTEND:20240105T110000Z\r\nSUMMARY:Design\r\n  review\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nDTSTART:20240105T110000Z\r\nDURATION:PT30M\r\nSUMMARY:1:1\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240105\r\nDTEND;VALUE=DATE:20240106\r\nSUMMARY:Holiday\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nDTSTART:20240105T150000Z\r\nDTEND:20240105T160000Z\r\nTRANSP:TRANSPARENT\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+        let busy = parse_ics(ics, utc("2024-01-01T00:00:00Z"), utc("2024-01-10T00:00:00Z"));
+        assert_eq!(busy.len(), 2);
+        assert_eq!(busy[0].summary.as_deref(), Some("Design review"));
+
+        // 连续的会议合并计算结束时间
+        let availability = evaluate(&office_hours(), &busy, utc("2024-01-05T10:30:00Z"));
+        assert_eq!(availability.status, AvailabilityStatus::InMeeting);
+        assert_eq!(availability.until, Some(utc("2024-01-05T11:30:00Z")));
+        assert_eq!(availability.meeting.as_deref(), Some("Design review"));
+    }
+
+    #[test]
+    fn test_parse_ics_recurring_events() {
+        let ics = "BEGIN:VEVENT\nDTSTART:20240101T090000Z\nDTEND:20240101T091500Z\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=5\nSUMMARY:Standup\nEND:VEVENT\n\
+BEGIN:VEVENT\nDTSTART:20240101T120000Z\nDTEND:20240101T130000Z\nRRULE:FREQ=DAILY;INTERVAL=2;UNTIL=20240105T235959Z\nEND:VEVENT\n";
+        let busy = parse_ics(ics, utc("2024-01-03T00:00:00Z"), utc("2024-01-31T00:00:00Z"));
+        let starts: Vec<String> = busy.iter().map(|b| b.start.format("%m-%d %H:%M").to_string()).collect();
+        assert_eq!(starts, vec!["01-03 09:00", "01-03 12:00", "01-05 12:00", "01-08 09:00", "01-10 09:00", "01-15 09:00"]);
+    }
+
+    #[test]
+    fn test_action() {
+        let mut config = office_hours();
+        config.during_meeting = UnavailableAction::Webhook;
+        config.outside_working_hours = UnavailableAction::Defer;
+        let meeting = Availability {
+            status: AvailabilityStatus::InMeeting,
+            until: Some(utc("2024-01-05T11:00:00Z")),
+            meeting: None,
+        };
+        // 未设置 Webhook 地址时照常弹窗
+        assert_eq!(meeting.action(&config, Priority::Normal), UnavailableAction::Popup);
+        config.webhook_url = Some("https://example.com/hook".to_string());
+        assert_eq!(meeting.action(&config, Priority::Normal), UnavailableAction::Webhook);
+        assert_eq!(meeting.action(&config, Priority::Critical), UnavailableAction::Popup);
+
+        let outside = Availability { status: AvailabilityStatus::OutsideWorkingHours, ..meeting };
+        assert_eq!(outside.action(&config, Priority::Low), UnavailableAction::Defer);
+    }
+}
@@ -1,20 +1,29 @@
 //! MCP Server binary entry point
 //!
 //! This binary runs the MCP server that communicates with AI assistants.
+//!
+//! Transport selection:
+//! - `--transport stdio` (default) or `--transport http [--bind 127.0.0.1:8765]`
+//! - or the `WHALE_MCP_TRANSPORT` / `WHALE_MCP_HTTP_BIND` environment variables
 
-use whale_interactive_feedback_lib::mcp_server::run_mcp_server;
+use whale_interactive_feedback_lib::mcp_logging;
+use whale_interactive_feedback_lib::mcp_server::{run_mcp_server_with, McpTransport};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger (to stderr so it doesn't interfere with MCP protocol)
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .target(env_logger::Target::Stderr)
-        .init();
+    // Initialize logger (to stderr so it doesn't interfere with MCP protocol);
+    // records are also forwarded to clients that enable MCP logging
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    builder.target(env_logger::Target::Stderr);
+    mcp_logging::init(builder);
 
     log::info!("Starting Whale Interactive Feedback MCP Server...");
 
+    let args: Vec<String> = std::env::args().collect();
+    let transport = McpTransport::from_args_and_env(&args)?;
+
     // Run the MCP server
-    run_mcp_server().await?;
+    run_mcp_server_with(transport).await?;
 
     Ok(())
 }
@@ -0,0 +1,143 @@
+//! 截图色彩管理模块
+//! 
+//! 广色域（P3 等）显示器的截图按 sRGB 直接编码会发灰，
+//! 这里按显示器 ICC 配置文件将像素转换到 sRGB，使附件截图与屏幕显示一致。
+//! 
+//! 配置文件来源（按优先级）：
+//! 1. 配置中按显示器名称指定的 ICC 文件
+//! 2. 操作系统提供的显示器配置文件（目前支持 macOS）
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 色彩管理配置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorManagementConfig {
+    /// 是否启用 ICC 转换
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 显示器名称 -> ICC 文件路径
+    #[serde(default)]
+    pub profile_overrides: HashMap<String, String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ColorManagementConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profile_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl ColorManagementConfig {
+    /// 查找显示器的 ICC 配置文件数据
+    pub fn resolve_profile(&self, monitor_name: &str, display_id: u32) -> Option<Vec<u8>> {
+        if !self.enabled {
+            return None;
+        }
+        
+        if let Some(path) = self.profile_overrides.get(monitor_name) {
+            match std::fs::read(path) {
+                Ok(data) => return Some(data),
+                Err(e) => log::warn!("[color_profile] 读取 ICC 文件失败 {}: {}", path, e),
+            }
+        }
+        
+        system_display_profile(display_id)
+    }
+}
+
+/// 将图片从指定 ICC 配置文件转换到 sRGB
+/// 
+/// 配置文件无效时保持原图不变
+pub fn convert_to_srgb(image: &mut RgbaImage, icc_profile: &[u8]) {
+    let Some(source) = qcms::Profile::new_from_slice(icc_profile, false) else {
+        log::warn!("[color_profile] 无法解析 ICC 配置文件，跳过色彩转换");
+        return;
+    };
+    let target = qcms::Profile::new_sRGB();
+    
+    match qcms::Transform::new(&source, &target, qcms::DataType::RGBA8, qcms::Intent::Perceptual) {
+        Some(transform) => transform.apply(image),
+        None => log::warn!("[color_profile] 创建色彩转换失败，跳过色彩转换"),
+    }
+}
+
+/// 获取操作系统为显示器配置的 ICC 文件（macOS）
+#[cfg(target_os = "macos")]
+fn system_display_profile(display_id: u32) -> Option<Vec<u8>> {
+    use std::ffi::c_void;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGDisplayCopyColorSpace(display: u32) -> *const c_void;
+        fn CGColorSpaceCopyICCData(space: *const c_void) -> *const c_void;
+        fn CGColorSpaceRelease(space: *const c_void);
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFDataGetLength(data: *const c_void) -> isize;
+        fn CFDataGetBytePtr(data: *const c_void) -> *const u8;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    unsafe {
+        let space = CGDisplayCopyColorSpace(display_id);
+        if space.is_null() {
+            return None;
+        }
+        let data = CGColorSpaceCopyICCData(space);
+        CGColorSpaceRelease(space);
+        if data.is_null() {
+            return None;
+        }
+        
+        let len = CFDataGetLength(data);
+        let ptr = CFDataGetBytePtr(data);
+        let bytes = if len > 0 && !ptr.is_null() {
+            Some(std::slice::from_raw_parts(ptr, len as usize).to_vec())
+        } else {
+            None
+        };
+        CFRelease(data);
+        bytes
+    }
+}
+
+/// 获取操作系统为显示器配置的 ICC 文件（其他平台暂不支持，请使用配置覆盖）
+#[cfg(not(target_os = "macos"))]
+fn system_display_profile(_display_id: u32) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_profile_keeps_image() {
+        let mut image = RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 255]));
+        let original = image.clone();
+        
+        convert_to_srgb(&mut image, b"not an icc profile");
+        
+        assert_eq!(image, original);
+    }
+
+    #[test]
+    fn test_disabled_resolves_no_profile() {
+        let config = ColorManagementConfig {
+            enabled: false,
+            profile_overrides: HashMap::new(),
+        };
+        assert!(config.resolve_profile("Built-in Retina Display", 1).is_none());
+    }
+}
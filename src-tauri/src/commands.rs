@@ -1,6 +1,9 @@
-use crate::api_keys::{ApiKeyManager, ApiProvider};
+use crate::api_keys::{ApiKeyError, ApiKeyManager, ApiProvider};
 use crate::config;
-use crate::types::{AppConfig, CannedResponse, FeedbackData, ProcessedImage};
+use crate::types::{
+    AppConfig, AzureOpenAiConfig, CannedResponse, CustomProvider, FeedbackData, OllamaConfig,
+    OptimizationTypeConfig, OptimizationTypeValidation, ProcessedImage, ScalePreset,
+};
 use tauri::{AppHandle, Manager};
 
 /// 获取配置
@@ -23,6 +26,7 @@ pub async fn save_config(app_handle: AppHandle, config: AppConfig) -> Result<(),
 /// 
 /// 接收前端提交的反馈数据，进行序列化处理并返回结构化的 JSON 响应。
 /// 该命令用于将用户反馈数据传递给 MCP 服务器。
+/// 图片只带附件 ID 时在这里从附件存储读取数据。
 /// 
 /// # Arguments
 /// * `feedback` - 反馈数据结构，包含文本、图片和文件引用
@@ -31,7 +35,7 @@ pub async fn save_config(app_handle: AppHandle, config: AppConfig) -> Result<(),
 /// * `Ok(String)` - 序列化后的 JSON 字符串
 /// * `Err(String)` - 错误信息
 #[tauri::command]
-pub async fn submit_feedback(feedback: FeedbackData) -> Result<String, String> {
+pub async fn submit_feedback(mut feedback: FeedbackData) -> Result<String, String> {
     log::info!("Received feedback with {} content items", feedback.content.len());
     
     // 验证反馈数据
@@ -47,13 +51,26 @@ pub async fn submit_feedback(feedback: FeedbackData) -> Result<String, String> {
             .map_err(|e| format!("Failed to serialize empty feedback: {}", e));
     }
     
+    // 解析附件引用
+    let store = attachments::store();
+    for content in feedback.content.iter_mut() {
+        if let crate::types::FeedbackContent::Image { data, mime_type, attachment_id: Some(id) } = content {
+            if data.is_empty() {
+                let attachment = store.get(id)
+                    .ok_or_else(|| format!("附件不存在: {}", id))?;
+                *data = attachment.data;
+                *mime_type = attachment.mime_type;
+            }
+        }
+    }
+    
     // 记录反馈内容摘要
     for (i, content) in feedback.content.iter().enumerate() {
         match content {
             crate::types::FeedbackContent::Text { text } => {
                 log::debug!("Content[{}]: Text ({} chars)", i, text.len());
             }
-            crate::types::FeedbackContent::Image { mime_type, data } => {
+            crate::types::FeedbackContent::Image { mime_type, data, .. } => {
                 log::debug!("Content[{}]: Image ({}, {} bytes base64)", i, mime_type, data.len());
             }
             crate::types::FeedbackContent::FileReference { display_name, path } => {
@@ -73,23 +90,37 @@ pub async fn submit_feedback(feedback: FeedbackData) -> Result<String, String> {
 
 /// 处理图片
 #[tauri::command]
-pub async fn process_image(image_data: Vec<u8>) -> Result<ProcessedImage, String> {
+pub async fn process_image(app_handle: AppHandle, image_data: Vec<u8>) -> Result<ProcessedImage, String> {
     use crate::image_processor::ImageProcessor;
 
-    // 使用 ImageProcessor 处理图片
-    let result = ImageProcessor::process_with_defaults(&image_data)
+    // 使用 ImageProcessor 处理图片（JPEG 编码器由配置选择）
+    let backend = config::load_config(&app_handle).await.unwrap_or_default().image_encoder.jpeg;
+    let result = ImageProcessor::process_with_defaults(&image_data, backend)
         .map_err(|e| e.to_string())?;
 
     // Base64 编码
     let base64_data = ImageProcessor::encode_base64(&result.data);
 
-    Ok(ProcessedImage {
+    log::debug!(
+        "[process_image] {} -> {} 字节（质量 {:?}，{} ms）",
+        result.metrics.original_bytes, result.data.len(), result.metrics.quality, result.metrics.elapsed_ms
+    );
+
+    let mut processed = ProcessedImage {
         data: base64_data,
         mime_type: "image/jpeg".to_string(),
         width: result.width,
         height: result.height,
         size: result.data.len(),
-    })
+        metrics: Some(result.metrics),
+        attachment_id: None,
+    };
+    // 存入附件存储，提交时只需传递附件 ID
+    match attachments::store().insert(&processed) {
+        Ok(id) => processed.attachment_id = Some(id),
+        Err(e) => log::warn!("[process_image] 保存附件失败: {}", e),
+    }
+    Ok(processed)
 }
 
 /// 播放通知音
@@ -101,19 +132,24 @@ pub async fn process_image(image_data: Vec<u8>) -> Result<ProcessedImage, String
 /// 
 /// # Arguments
 /// * `sound_path` - 可选的自定义音频文件路径，如果为 None 则使用默认音频
+/// * `priority` - MCP 请求优先级，紧急请求会提高音量并重复播放
 /// 
 /// # Returns
 /// * `Ok(())` - 播放成功（异步播放，立即返回）
 /// * `Err(String)` - 播放失败（仅在严重错误时返回）
 #[tauri::command]
-pub async fn play_notification_sound(sound_path: Option<String>) -> Result<(), String> {
+pub async fn play_notification_sound(sound_path: Option<String>, priority: Option<Priority>) -> Result<(), String> {
     use crate::audio::AudioNotifier;
     
-    log::info!("播放通知音: {:?}", sound_path);
+    log::info!("播放通知音: {:?} (priority: {:?})", sound_path, priority);
     
     // 使用异步播放，不阻塞主线程
     // 如果播放失败，会静默继续（Requirement 12.4）
-    AudioNotifier::play_notification_async(sound_path.as_deref());
+    if priority == Some(Priority::Critical) {
+        AudioNotifier::play_alert_async(sound_path.as_deref());
+    } else {
+        AudioNotifier::play_notification_async(sound_path.as_deref());
+    }
     
     Ok(())
 }
@@ -209,17 +245,189 @@ pub async fn save_canned_responses(
 }
 
 
+// ============================================================================
+// 优化类型管理命令
+// ============================================================================
+
+/// 获取下拉列表中可见的优化类型（已按置顶和排序处理）
+#[tauri::command]
+pub async fn get_visible_optimization_types(app_handle: AppHandle) -> Result<Vec<OptimizationTypeConfig>, String> {
+    let current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(current_config.visible_optimization_types())
+}
+
+/// 设置是否显示系统预设优化类型
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `visible` - 是否显示系统预设类型
+#[tauri::command]
+pub async fn set_system_optimization_types_visible(app_handle: AppHandle, visible: bool) -> Result<(), String> {
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    current_config.show_system_optimization_types = visible;
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Set system optimization types visible: {}", visible);
+    Ok(())
+}
+
+/// 置顶或取消置顶优化类型
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `id` - 优化类型 ID
+/// * `pinned` - 是否置顶
+#[tauri::command]
+pub async fn set_optimization_type_pinned(app_handle: AppHandle, id: String, pinned: bool) -> Result<(), String> {
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    let opt_type = current_config.optimization_types.iter_mut()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("未找到优化类型: {}", id))?;
+    opt_type.pinned = pinned;
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Set optimization type {} pinned: {}", id, pinned);
+    Ok(())
+}
+
+/// 设置优化类型排序
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `ids` - 优化类型 ID 列表，按显示顺序排列；未列出的类型排在其后
+#[tauri::command]
+pub async fn set_optimization_type_order(app_handle: AppHandle, ids: Vec<String>) -> Result<(), String> {
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    let listed = ids.len() as i32;
+    for (index, opt_type) in current_config.optimization_types.iter_mut().enumerate() {
+        opt_type.order = match ids.iter().position(|id| *id == opt_type.id) {
+            Some(pos) => pos as i32,
+            None => listed + index as i32,
+        };
+    }
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Set optimization type order: {:?}", ids);
+    Ok(())
+}
+
+/// 设置弹窗的首选显示器
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `monitor` - 显示器名称，为空时使用窗口当前所在的显示器
+/// 
+/// # Returns
+/// * 设置的显示器名称
+#[tauri::command]
+pub async fn set_preferred_monitor(app_handle: AppHandle, monitor: Option<String>) -> Result<String, String> {
+    let window = app_handle.get_webview_window("main")
+        .ok_or_else(|| "未找到主窗口".to_string())?;
+    let monitor = match monitor {
+        Some(name) => {
+            let names = window_geometry::monitor_names(&window);
+            if !names.contains(&name) {
+                return Err(format!("未找到显示器: {}（可用: {}）", name, names.join(", ")));
+            }
+            name
+        }
+        None => window_geometry::current_monitor_name(&window)
+            .ok_or_else(|| "无法确定窗口所在的显示器".to_string())?,
+    };
+    
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    current_config.window_geometry.preferred_monitor = Some(monitor.clone());
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Set preferred monitor: {}", monitor);
+    Ok(monitor)
+}
+
+/// 校验优化类型提示词的 token 预算（不保存）
+#[tauri::command]
+pub async fn validate_optimization_type(
+    app_handle: AppHandle,
+    optimization_type: OptimizationTypeConfig,
+) -> Result<OptimizationTypeValidation, String> {
+    let current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(OptimizationTypeValidation::check(&optimization_type.prompt, current_config.prompt_token_budget))
+}
+
+/// 保存优化类型（存在则更新，不存在则新增）
+/// 
+/// 超出 token 预算时仍会保存，但返回结果中包含警告信息
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `optimization_type` - 优化类型配置
+/// 
+/// # Returns
+/// * 校验结果（估算 token 数、预算、警告）
+#[tauri::command]
+pub async fn save_optimization_type(
+    app_handle: AppHandle,
+    optimization_type: OptimizationTypeConfig,
+) -> Result<OptimizationTypeValidation, String> {
+    if optimization_type.id.trim().is_empty() {
+        return Err("优化类型 ID 不能为空".to_string());
+    }
+    
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    let validation = OptimizationTypeValidation::check(&optimization_type.prompt, current_config.prompt_token_budget);
+    if validation.exceeds_budget {
+        log::warn!("Optimization type {} exceeds token budget: {} > {}",
+            optimization_type.id, validation.estimated_tokens, validation.token_budget);
+    }
+    
+    match current_config.optimization_types.iter_mut().find(|t| t.id == optimization_type.id) {
+        Some(existing) => *existing = optimization_type,
+        None => current_config.optimization_types.push(optimization_type),
+    }
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(validation)
+}
+
 // ============================================================================
 // API 密钥管理命令
 // Requirements: 7.5, 14.5
 // 使用配置文件存储 + 混淆加密
 // ============================================================================
 
+/// 提供商 ID：内置提供商不区分大小写，其他名称视为自定义提供商
+fn provider_id(provider: &str) -> String {
+    ApiProvider::from_str(provider)
+        .map(|p| p.name().to_string())
+        .unwrap_or_else(|_| provider.to_string())
+}
+
 /// 保存 API 密钥到配置文件
 /// 
 /// # Arguments
 /// * `app_handle` - Tauri 应用句柄
-/// * `provider` - AI 提供商名称 (openai, gemini, deepseek, volcengine)
+/// * `provider` - AI 提供商名称 (openai, gemini, deepseek, volcengine, anthropic, azure 或自定义提供商名称)
 /// * `api_key` - API 密钥
 /// 
 /// # Returns
@@ -227,9 +435,6 @@ pub async fn save_canned_responses(
 /// * `Err(String)` - 错误信息
 #[tauri::command]
 pub async fn save_api_key(app_handle: AppHandle, provider: String, api_key: String) -> Result<(), String> {
-    let provider_enum = ApiProvider::from_str(&provider)
-        .map_err(|e| e.to_string())?;
-    
     // 混淆 API 密钥
     let obfuscated = ApiKeyManager::obfuscate(&api_key);
     
@@ -238,12 +443,9 @@ pub async fn save_api_key(app_handle: AppHandle, provider: String, api_key: Stri
         .map_err(|e| e.to_string())?;
     
     // 更新对应的 API 密钥
-    match provider_enum {
-        ApiProvider::OpenAI => current_config.api_keys.openai = Some(obfuscated),
-        ApiProvider::Gemini => current_config.api_keys.gemini = Some(obfuscated),
-        ApiProvider::DeepSeek => current_config.api_keys.deepseek = Some(obfuscated),
-        ApiProvider::Volcengine => current_config.api_keys.volcengine = Some(obfuscated),
-    }
+    let slot = current_config.api_keys.get_mut(&provider_id(&provider))
+        .ok_or_else(|| ApiKeyError::InvalidProvider(provider.clone()).to_string())?;
+    *slot = Some(obfuscated);
     
     // 保存配置
     config::save_config(&app_handle, &current_config).await
@@ -265,26 +467,20 @@ pub async fn save_api_key(app_handle: AppHandle, provider: String, api_key: Stri
 /// * `Err(String)` - 错误信息
 #[tauri::command]
 pub async fn get_api_key(app_handle: AppHandle, provider: String) -> Result<Option<String>, String> {
-    let provider_enum = ApiProvider::from_str(&provider)
-        .map_err(|e| e.to_string())?;
-    
+    let provider = provider_id(&provider);
     let current_config = config::load_config(&app_handle).await
         .map_err(|e| e.to_string())?;
+    if ApiProvider::from_str(&provider).is_err() && current_config.api_keys.custom_provider(&provider).is_none() {
+        return Err(ApiKeyError::InvalidProvider(provider).to_string());
+    }
     
-    let obfuscated = match provider_enum {
-        ApiProvider::OpenAI => current_config.api_keys.openai,
-        ApiProvider::Gemini => current_config.api_keys.gemini,
-        ApiProvider::DeepSeek => current_config.api_keys.deepseek,
-        ApiProvider::Volcengine => current_config.api_keys.volcengine,
-    };
-    
-    match obfuscated {
-        Some(ref s) if !s.is_empty() => {
+    match current_config.api_keys.get(&provider) {
+        Some(s) => {
             let key = ApiKeyManager::deobfuscate(s)
                 .map_err(|e| e.to_string())?;
             Ok(Some(key))
         }
-        _ => Ok(None),
+        None => Ok(None),
     }
 }
 
@@ -299,18 +495,12 @@ pub async fn get_api_key(app_handle: AppHandle, provider: String) -> Result<Opti
 /// * `Err(String)` - 错误信息
 #[tauri::command]
 pub async fn delete_api_key(app_handle: AppHandle, provider: String) -> Result<(), String> {
-    let provider_enum = ApiProvider::from_str(&provider)
-        .map_err(|e| e.to_string())?;
-    
     let mut current_config = config::load_config(&app_handle).await
         .map_err(|e| e.to_string())?;
     
-    match provider_enum {
-        ApiProvider::OpenAI => current_config.api_keys.openai = None,
-        ApiProvider::Gemini => current_config.api_keys.gemini = None,
-        ApiProvider::DeepSeek => current_config.api_keys.deepseek = None,
-        ApiProvider::Volcengine => current_config.api_keys.volcengine = None,
-    }
+    let slot = current_config.api_keys.get_mut(&provider_id(&provider))
+        .ok_or_else(|| ApiKeyError::InvalidProvider(provider.clone()).to_string())?;
+    *slot = None;
     
     config::save_config(&app_handle, &current_config).await
         .map_err(|e| e.to_string())?;
@@ -340,7 +530,7 @@ pub async fn has_api_key(app_handle: AppHandle, provider: String) -> Result<bool
 /// * `app_handle` - Tauri 应用句柄
 /// 
 /// # Returns
-/// * 已配置 API 密钥的提供商名称列表
+/// * 已配置 API 密钥的内置提供商和所有自定义提供商的名称列表
 #[tauri::command]
 pub async fn get_configured_providers(app_handle: AppHandle) -> Vec<String> {
     let config = match config::load_config(&app_handle).await {
@@ -348,22 +538,7 @@ pub async fn get_configured_providers(app_handle: AppHandle) -> Vec<String> {
         Err(_) => return Vec::new(),
     };
     
-    let mut providers = Vec::new();
-    
-    if config.api_keys.openai.as_ref().map(|s| !s.is_empty()).unwrap_or(false) {
-        providers.push("openai".to_string());
-    }
-    if config.api_keys.gemini.as_ref().map(|s| !s.is_empty()).unwrap_or(false) {
-        providers.push("gemini".to_string());
-    }
-    if config.api_keys.deepseek.as_ref().map(|s| !s.is_empty()).unwrap_or(false) {
-        providers.push("deepseek".to_string());
-    }
-    if config.api_keys.volcengine.as_ref().map(|s| !s.is_empty()).unwrap_or(false) {
-        providers.push("volcengine".to_string());
-    }
-    
-    providers
+    config.api_keys.configured_providers().into_iter().map(str::to_string).collect()
 }
 
 /// 获取 API 密钥的掩码版本（用于 UI 显示）
@@ -422,19 +597,15 @@ pub async fn get_provider_order(app_handle: AppHandle) -> Result<Vec<String>, St
 /// * `tested` - 是否测试通过
 #[tauri::command]
 pub async fn set_api_test_status(app_handle: AppHandle, provider: String, tested: bool) -> Result<(), String> {
-    let provider_enum = ApiProvider::from_str(&provider)
-        .map_err(|e| e.to_string())?;
-    
+    let provider_id = provider_id(&provider);
     let mut current_config = config::load_config(&app_handle).await
         .map_err(|e| e.to_string())?;
-    
-    match provider_enum {
-        ApiProvider::OpenAI => current_config.api_test_status.openai = tested,
-        ApiProvider::Gemini => current_config.api_test_status.gemini = tested,
-        ApiProvider::DeepSeek => current_config.api_test_status.deepseek = tested,
-        ApiProvider::Volcengine => current_config.api_test_status.volcengine = tested,
+    if ApiProvider::from_str(&provider_id).is_err() && current_config.api_keys.custom_provider(&provider_id).is_none() {
+        return Err(ApiKeyError::InvalidProvider(provider).to_string());
     }
     
+    current_config.api_test_status.set(&provider_id, tested);
+    
     config::save_config(&app_handle, &current_config).await
         .map_err(|e| e.to_string())?;
     
@@ -449,20 +620,203 @@ pub async fn set_api_test_status(app_handle: AppHandle, provider: String, tested
 /// * `provider` - AI 提供商名称
 #[tauri::command]
 pub async fn get_api_test_status(app_handle: AppHandle, provider: String) -> Result<bool, String> {
-    let provider_enum = ApiProvider::from_str(&provider)
+    let current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(current_config.api_test_status.get(&provider_id(&provider)))
+}
+
+/// 添加或更新自定义的 OpenAI 兼容提供商
+/// 
+/// # Arguments
+/// * `app_handle` - Tauri 应用句柄
+/// * `provider` - 提供商配置；`apiKey` 为明文密钥，为空时保留已保存的密钥
+#[tauri::command]
+pub async fn save_custom_provider(app_handle: AppHandle, provider: CustomProvider) -> Result<(), String> {
+    let name = provider.name.trim().to_string();
+    if name.is_empty() {
+        return Err("提供商名称不能为空".to_string());
+    }
+    if ApiProvider::from_str(&name).is_ok() {
+        return Err(format!("{} 是内置提供商，请使用其他名称", name));
+    }
+    let base_url = provider.base_url.trim().to_string();
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(format!("无效的 API 地址: {}", base_url));
+    }
+    if provider.model.trim().is_empty() {
+        return Err("模型名称不能为空".to_string());
+    }
+    
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    let custom = &mut current_config.api_keys.custom;
+    let api_key = match provider.api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(key) => Some(ApiKeyManager::obfuscate(key)),
+        None => custom.iter().find(|c| c.name == name).and_then(|c| c.api_key.clone()),
+    };
+    let updated = CustomProvider {
+        name: name.clone(),
+        base_url,
+        model: provider.model.trim().to_string(),
+        headers: provider.headers,
+        api_key,
+    };
+    match custom.iter_mut().find(|c| c.name == name) {
+        Some(existing) => *existing = updated,
+        None => custom.push(updated),
+    }
+    // 配置变化后需要重新测试
+    current_config.api_test_status.set(&name, false);
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Saved custom provider: {}", name);
+    Ok(())
+}
+
+/// 删除自定义提供商（同时从优先级顺序中移除）
+#[tauri::command]
+pub async fn delete_custom_provider(app_handle: AppHandle, name: String) -> Result<(), String> {
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    current_config.api_keys.custom.retain(|c| c.name != name);
+    current_config.provider_order.retain(|p| *p != name);
+    current_config.api_test_status.set(&name, false);
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Deleted custom provider: {}", name);
+    Ok(())
+}
+
+/// 获取自定义提供商列表（API 密钥为掩码版本）
+#[tauri::command]
+pub async fn get_custom_providers(app_handle: AppHandle) -> Result<Vec<CustomProvider>, String> {
+    let current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(current_config.api_keys.custom.into_iter()
+        .map(|mut provider| {
+            provider.api_key = provider.api_key
+                .and_then(|k| ApiKeyManager::deobfuscate(&k).ok())
+                .and_then(|k| ApiKeyManager::mask_key(&k));
+            provider
+        })
+        .collect())
+}
+
+/// 保存 Azure OpenAI 设置
+/// 
+/// # Arguments
+/// * `azure` - 终结点、部署名称和 API 版本，apiKey 为空时保留已保存的密钥
+#[tauri::command]
+pub async fn save_azure_openai_config(app_handle: AppHandle, azure: AzureOpenAiConfig) -> Result<(), String> {
+    let endpoint = azure.endpoint.trim().trim_end_matches('/').to_string();
+    if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+        return Err(format!("无效的终结点: {}", endpoint));
+    }
+    let deployment = azure.deployment.trim().to_string();
+    if deployment.is_empty() {
+        return Err("部署名称不能为空".to_string());
+    }
+    let api_version = match azure.api_version.trim() {
+        "" => AzureOpenAiConfig::DEFAULT_API_VERSION.to_string(),
+        version => version.to_string(),
+    };
+    
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    let api_key = match azure.api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(key) => Some(ApiKeyManager::obfuscate(key)),
+        None => current_config.api_keys.azure.api_key.clone(),
+    };
+    current_config.api_keys.azure = AzureOpenAiConfig {
+        endpoint,
+        deployment,
+        api_version,
+        api_key,
+    };
+    // 配置变化后需要重新测试
+    current_config.api_test_status.set("azure", false);
+    
+    config::save_config(&app_handle, &current_config).await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Saved Azure OpenAI config: deployment={}", current_config.api_keys.azure.deployment);
+    Ok(())
+}
+
+/// 获取 Azure OpenAI 设置（API 密钥为掩码版本）
+#[tauri::command]
+pub async fn get_azure_openai_config(app_handle: AppHandle) -> Result<AzureOpenAiConfig, String> {
+    let current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    
+    let mut azure = current_config.api_keys.azure;
+    azure.api_key = azure.api_key
+        .and_then(|k| ApiKeyManager::deobfuscate(&k).ok())
+        .and_then(|k| ApiKeyManager::mask_key(&k));
+    Ok(azure)
+}
+
+/// 保存本地模型（Ollama）设置
+#[tauri::command]
+pub async fn save_ollama_config(app_handle: AppHandle, ollama: OllamaConfig) -> Result<(), String> {
+    let base_url = match ollama.base_url.trim() {
+        "" => OllamaConfig::DEFAULT_BASE_URL.to_string(),
+        url if url.starts_with("http://") || url.starts_with("https://") => crate::llm::ollama::openai_base_url(url),
+        url => return Err(format!("无效的 API 地址: {}", url)),
+    };
+    if ollama.enabled && ollama.model.trim().is_empty() {
+        return Err("请先选择模型".to_string());
+    }
+    
+    let mut current_config = config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    if current_config.api_keys.ollama.base_url != base_url || current_config.api_keys.ollama.model != ollama.model.trim() {
+        // 地址或模型变化后需要重新测试
+        current_config.api_test_status.set("ollama", false);
+    }
+    current_config.api_keys.ollama = OllamaConfig {
+        enabled: ollama.enabled,
+        base_url,
+        model: ollama.model.trim().to_string(),
+    };
+    
+    config::save_config(&app_handle, &current_config).await
         .map_err(|e| e.to_string())?;
     
+    log::info!("Saved local model config: enabled={}, model={}", ollama.enabled, ollama.model.trim());
+    Ok(())
+}
+
+/// 获取本地模型（Ollama）设置
+#[tauri::command]
+pub async fn get_ollama_config(app_handle: AppHandle) -> Result<OllamaConfig, String> {
     let current_config = config::load_config(&app_handle).await
         .map_err(|e| e.to_string())?;
     
-    let tested = match provider_enum {
-        ApiProvider::OpenAI => current_config.api_test_status.openai,
-        ApiProvider::Gemini => current_config.api_test_status.gemini,
-        ApiProvider::DeepSeek => current_config.api_test_status.deepseek,
-        ApiProvider::Volcengine => current_config.api_test_status.volcengine,
+    Ok(current_config.api_keys.ollama)
+}
+
+/// 获取本地已下载的模型列表
+/// 
+/// # Arguments
+/// * `base_url` - API 地址，为空时使用已保存的地址
+#[tauri::command]
+pub async fn list_ollama_models(app_handle: AppHandle, base_url: Option<String>) -> Result<Vec<String>, String> {
+    let base_url = match base_url.filter(|url| !url.trim().is_empty()) {
+        Some(url) => url.trim().to_string(),
+        None => config::load_config(&app_handle).await
+            .map_err(|e| e.to_string())?
+            .api_keys.ollama.base_url,
     };
     
-    Ok(tested)
+    crate::llm::ollama::list_models(&base_url).await
 }
 
 
@@ -471,7 +825,11 @@ pub async fn get_api_test_status(app_handle: AppHandle, provider: String) -> Res
 // Requirements: 8.1, 8.2, 8.3, 8.4, 8.5
 // ============================================================================
 
-use crate::screenshot::{ScreenshotManager, ScreenshotRegion, ScreenshotResult, MonitorInfo};
+use crate::annotation::{self, TextAnnotation};
+use crate::attachments::{self, AttachmentCapturedPayload, ATTACHMENT_CAPTURED_EVENT};
+use crate::screenshot::{CaptureOptions, ScreenshotManager, ScreenshotRegion, ScreenshotResult, MonitorInfo, SnapTarget};
+use crate::window_geometry;
+use crate::window_guard::HiddenWindowWatchdog;
 
 /// 获取所有显示器信息
 /// 
@@ -485,18 +843,51 @@ pub async fn get_monitors() -> Result<Vec<MonitorInfo>, String> {
         .map_err(|e| e.to_string())
 }
 
+/// 获取鼠标光标位置
+/// 
+/// # Returns
+/// * 全局屏幕坐标 (x, y)，平台不支持时返回 None
+#[tauri::command]
+pub fn get_cursor_position() -> Option<(i32, i32)> {
+    ScreenshotManager::get_cursor_position()
+}
+
 /// 捕获整个屏幕
 /// 
 /// Requirement 8.4: 实现屏幕捕获
 /// 
 /// # Arguments
-/// * `monitor_id` - 可选的显示器 ID，默认使用主显示器
+/// * `monitor_id` - 可选的显示器 ID，默认使用鼠标所在的显示器
+/// * `scale` - 可选的缩放预设，默认使用配置中的 `screenshot_scale`
 /// 
 /// # Returns
 /// * 截图结果，包含 Base64 编码的图片数据
 #[tauri::command]
-pub async fn capture_full_screen(monitor_id: Option<u32>) -> Result<ScreenshotResult, String> {
-    ScreenshotManager::capture_full_screen(monitor_id)
+pub async fn capture_full_screen(
+    app_handle: AppHandle,
+    monitor_id: Option<u32>,
+    scale: Option<ScalePreset>,
+) -> Result<ScreenshotResult, String> {
+    let options = resolve_capture_options(&app_handle, scale).await;
+    ScreenshotManager::capture_full_screen(monitor_id, &options)
+        .map_err(|e| e.to_string())
+}
+
+/// 并发捕获所有显示器
+///
+/// # Arguments
+/// * `scale` - 可选的缩放预设，默认使用配置中的 `screenshot_scale`
+///
+/// # Returns
+/// * 按显示器 ID 排列的截图结果
+#[tauri::command]
+pub async fn capture_all_screens(
+    app_handle: AppHandle,
+    scale: Option<ScalePreset>,
+) -> Result<Vec<ScreenshotResult>, String> {
+    let options = resolve_capture_options(&app_handle, scale).await;
+    ScreenshotManager::capture_all_screens(&options)
+        .await
         .map_err(|e| e.to_string())
 }
 
@@ -506,12 +897,42 @@ pub async fn capture_full_screen(monitor_id: Option<u32>) -> Result<ScreenshotRe
 /// 
 /// # Arguments
 /// * `region` - 截图区域（x, y, width, height）
+/// * `scale` - 可选的缩放预设，默认使用配置中的 `screenshot_scale`
 /// 
 /// # Returns
 /// * 截图结果，包含 Base64 编码的图片数据
 #[tauri::command]
-pub async fn capture_region(region: ScreenshotRegion) -> Result<ScreenshotResult, String> {
-    ScreenshotManager::capture_region(region)
+pub async fn capture_region(
+    app_handle: AppHandle,
+    region: ScreenshotRegion,
+    scale: Option<ScalePreset>,
+) -> Result<ScreenshotResult, String> {
+    let options = resolve_capture_options(&app_handle, scale).await;
+    ScreenshotManager::capture_region(region, &options)
+        .map_err(|e| e.to_string())
+}
+
+/// 解析截图处理选项：缩放预设优先使用单次截图指定的值，否则使用配置默认值；隐私模糊始终使用配置
+async fn resolve_capture_options(app_handle: &AppHandle, scale: Option<ScalePreset>) -> CaptureOptions {
+    let config = config::load_config(app_handle).await.unwrap_or_default();
+    CaptureOptions {
+        scale: scale.unwrap_or(config.screenshot_scale),
+        privacy: config.privacy_blur,
+        color: config.color_management,
+        png: config.image_encoder.png,
+    }
+}
+
+/// 获取区域选择的吸附目标（窗口矩形）
+/// 
+/// # Arguments
+/// * `region` - 可选的当前选区，只返回与选区相交的窗口
+/// 
+/// # Returns
+/// * 窗口矩形列表
+#[tauri::command]
+pub async fn get_snap_targets(region: Option<ScreenshotRegion>) -> Result<Vec<SnapTarget>, String> {
+    ScreenshotManager::get_snap_targets(region.as_ref())
         .map_err(|e| e.to_string())
 }
 
@@ -523,17 +944,26 @@ pub async fn capture_region(region: ScreenshotRegion) -> Result<ScreenshotResult
 /// 3. 截取全屏
 /// 4. 返回截图数据（窗口保持隐藏，由前端控制恢复）
 /// 
+/// 截图失败时立即恢复窗口；截图成功后启动看门狗，
+/// 若前端在超时时间内未调用 `show_window`，后端自动恢复窗口
+/// 
 /// # Arguments
 /// * `window` - Tauri 窗口句柄
 /// * `monitor_id` - 可选的显示器 ID
+/// * `scale` - 可选的缩放预设，默认使用配置中的 `screenshot_scale`
 /// 
 /// # Returns
 /// * 截图结果，包含 Base64 编码的图片数据
 #[tauri::command]
 pub async fn capture_screen_hidden(
+    app_handle: AppHandle,
     window: tauri::Window,
+    watchdog: tauri::State<'_, HiddenWindowWatchdog>,
     monitor_id: Option<u32>,
+    scale: Option<ScalePreset>,
 ) -> Result<ScreenshotResult, String> {
+    let options = resolve_capture_options(&app_handle, scale).await;
+    
     // 隐藏窗口
     window.hide().map_err(|e| format!("Failed to hide window: {}", e))?;
     
@@ -541,21 +971,45 @@ pub async fn capture_screen_hidden(
     tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
     
     // 截取全屏
-    let result = ScreenshotManager::capture_full_screen(monitor_id)
+    let result = ScreenshotManager::capture_full_screen(monitor_id, &options)
         .map_err(|e| e.to_string());
     
-    // 注意：窗口保持隐藏状态，由前端在选区完成后调用 show_window 恢复
-    result
-}
-
-/// 显示窗口
+    match result {
+        Ok(screenshot) => {
+            // 窗口保持隐藏状态，由前端在选区完成后调用 show_window 恢复
+            let watchdog = watchdog.inner().clone();
+            let token = watchdog.arm();
+            let window = window.clone();
+            tauri::async_runtime::spawn(async move {
+                if watchdog.expire(token).await {
+                    log::warn!("[capture_screen_hidden] 前端未在 {:?} 内恢复窗口，自动恢复", watchdog.timeout());
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            });
+            Ok(screenshot)
+        }
+        Err(e) => {
+            log::error!("[capture_screen_hidden] 截图失败，恢复窗口: {}", e);
+            let _ = window.show();
+            let _ = window.set_focus();
+            Err(e)
+        }
+    }
+}
+
+/// 显示窗口
 /// 
 /// 用于截图选区完成后恢复窗口显示
 /// 
 /// # Arguments
 /// * `window` - Tauri 窗口句柄
 #[tauri::command]
-pub async fn show_window(window: tauri::Window) -> Result<(), String> {
+pub async fn show_window(
+    window: tauri::Window,
+    watchdog: tauri::State<'_, HiddenWindowWatchdog>,
+) -> Result<(), String> {
+    watchdog.disarm();
     window.show().map_err(|e| format!("Failed to show window: {}", e))?;
     window.set_focus().map_err(|e| format!("Failed to focus window: {}", e))?;
     Ok(())
@@ -579,6 +1033,8 @@ pub async fn crop_screenshot(
     use base64::{Engine as _, engine::general_purpose::STANDARD};
     use image::{DynamicImage, ImageEncoder};
     
+    let started = std::time::Instant::now();
+    
     // 解码 Base64
     let bytes = STANDARD.decode(&image_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
@@ -614,9 +1070,189 @@ pub async fn crop_screenshot(
         width: cropped.width(),
         height: cropped.height(),
         size: buffer.len(),
+        metrics: Some(ImageMetrics::new((img.width(), img.height()), bytes.len(), buffer.len(), None, started)),
     })
 }
 
+/// 隐藏窗口后截图，无论截图是否成功都恢复窗口，避免窗口一直处于隐藏状态
+async fn capture_with_window_hidden(
+    window: &tauri::Window,
+    region: Option<ScreenshotRegion>,
+    monitor_id: Option<u32>,
+    options: &CaptureOptions,
+) -> Result<ScreenshotResult, String> {
+    // 隐藏窗口
+    window.hide().map_err(|e| format!("Failed to hide window: {}", e))?;
+    
+    // 等待窗口完全隐藏
+    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+    
+    let captured = match region {
+        Some(region) => ScreenshotManager::capture_region(region, options),
+        None => ScreenshotManager::capture_full_screen(monitor_id, options),
+    };
+    
+    if let Err(e) = window.show() {
+        log::error!("[capture] 恢复窗口失败: {}", e);
+    }
+    let _ = window.set_focus();
+    
+    captured.map_err(|e| e.to_string())
+}
+
+/// 截取当前显示器作为请求上下文
+/// 
+/// Agent 请求 `capture_context` 且用户已开启自动截图时，由前端在倒计时结束后调用。
+/// 截取鼠标所在的显示器，返回截图供前端预填到附件中
+#[tauri::command]
+pub async fn capture_context(
+    app_handle: AppHandle,
+    window: tauri::Window,
+) -> Result<ScreenshotResult, String> {
+    let config = config::load_config(&app_handle).await.unwrap_or_default();
+    if !config.context_capture.enabled {
+        return Err("Automatic context capture is disabled".to_string());
+    }
+    let options = resolve_capture_options(&app_handle, None).await;
+    let screenshot = capture_with_window_hidden(&window, None, None, &options).await?;
+    log::info!("[capture_context] 已截取上下文截图 {}x{}", screenshot.width, screenshot.height);
+    Ok(screenshot)
+}
+
+/// 一键截图并存入附件
+/// 
+/// 将 隐藏窗口 → 截图 → 恢复窗口 → 处理图片 合并为一次后端调用：
+/// 1. 隐藏应用窗口并等待隐藏完成
+/// 2. 截取全屏或指定区域
+/// 3. 无论截图成功与否都恢复窗口显示
+/// 4. 按缩放预设处理图片并存入附件存储，发送 `attachment-captured` 事件
+/// 
+/// # Arguments
+/// * `window` - Tauri 窗口句柄
+/// * `region` - 可选的截图区域，为空时截取整个屏幕
+/// * `monitor_id` - 可选的显示器 ID（仅全屏截图时使用）
+/// * `scale` - 可选的缩放预设，默认使用配置中的 `screenshot_scale`
+/// 
+/// # Returns
+/// * 附件 ID
+#[tauri::command]
+pub async fn capture_and_attach(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    region: Option<ScreenshotRegion>,
+    monitor_id: Option<u32>,
+    scale: Option<ScalePreset>,
+) -> Result<String, String> {
+    use tauri::Emitter;
+    
+    let options = resolve_capture_options(&app_handle, scale).await;
+    let captured = capture_with_window_hidden(&window, region, monitor_id, &options).await?;
+    let processed = ProcessedImage {
+        data: captured.data,
+        mime_type: captured.mime_type,
+        width: captured.width,
+        height: captured.height,
+        size: captured.size,
+        metrics: captured.metrics,
+        attachment_id: None,
+    };
+    
+    let (mime_type, width, height, size) =
+        (processed.mime_type.clone(), processed.width, processed.height, processed.size);
+    let attachment_id = attachments::store().insert(&processed)
+        .map_err(|e| format!("Failed to store attachment: {}", e))?;
+    let payload = AttachmentCapturedPayload {
+        attachment_id: attachment_id.clone(),
+        mime_type,
+        width,
+        height,
+        size,
+    };
+    
+    if let Err(e) = app_handle.emit(ATTACHMENT_CAPTURED_EVENT, &payload) {
+        log::warn!("[capture_and_attach] 发送附件事件失败: {}", e);
+    }
+    
+    log::info!("[capture_and_attach] 截图已存入附件: {}", attachment_id);
+    Ok(attachment_id)
+}
+
+/// 获取附件
+/// 
+/// # Arguments
+/// * `attachment_id` - 附件 ID
+#[tauri::command]
+pub async fn get_attachment(
+    attachment_id: String,
+) -> Result<ProcessedImage, String> {
+    attachments::store().get(&attachment_id)
+        .ok_or_else(|| format!("附件不存在: {}", attachment_id))
+}
+
+/// 在附件截图上绘制文字标注（支持中英文混排）
+/// 
+/// # Arguments
+/// * `attachment_id` - 附件 ID
+/// * `annotations` - 文字标注列表，未指定字号/颜色时使用配置默认值
+/// 
+/// # Returns
+/// * 绘制标注后的图片（PNG），附件 ID 为标注后内容的新 ID，原附件保持不变
+#[tauri::command]
+pub async fn annotate_attachment(
+    app_handle: AppHandle,
+    attachment_id: String,
+    annotations: Vec<TextAnnotation>,
+) -> Result<ProcessedImage, String> {
+    use crate::image_processor::ImageProcessor;
+    use image::ImageEncoder;
+    
+    let started = std::time::Instant::now();
+    let store = attachments::store();
+    let attachment = store.get(&attachment_id)
+        .ok_or_else(|| format!("附件不存在: {}", attachment_id))?;
+    let config = config::load_config(&app_handle).await.unwrap_or_default();
+    
+    let bytes = ImageProcessor::decode_base64(&attachment.data).map_err(|e| e.to_string())?;
+    let mut image = ImageProcessor::load_from_bytes(&bytes)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    
+    annotation::render_annotations(&mut image, &annotations, &config.annotation)
+        .map_err(|e| e.to_string())?;
+    
+    let (width, height) = image.dimensions();
+    let mut buffer = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut buffer)
+        .write_image(image.as_raw(), width, height, image::ExtendedColorType::Rgba8)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    
+    let annotated = ProcessedImage {
+        data: ImageProcessor::encode_base64(&buffer),
+        mime_type: "image/png".to_string(),
+        width,
+        height,
+        size: buffer.len(),
+        metrics: Some(ImageMetrics::new((width, height), bytes.len(), buffer.len(), None, started)),
+        attachment_id: None,
+    };
+    let id = store.insert(&annotated)
+        .map_err(|e| format!("Failed to store attachment: {}", e))?;
+    
+    Ok(ProcessedImage { attachment_id: Some(id), ..annotated })
+}
+
+/// 删除附件（只释放内存缓存，磁盘内容可能仍被会话历史引用）
+/// 
+/// # Arguments
+/// * `attachment_id` - 附件 ID
+#[tauri::command]
+pub async fn remove_attachment(
+    attachment_id: String,
+) -> Result<(), String> {
+    attachments::store().remove(&attachment_id);
+    Ok(())
+}
+
 // ============================================================================
 // 窗口控制命令
 // ============================================================================
@@ -640,7 +1276,19 @@ pub async fn set_window_always_on_top(window: tauri::WebviewWindow, on_top: bool
 // MCP 相关命令
 // ============================================================================
 
-use crate::popup::{PopupRequest, PopupResponse};
+use crate::daemon;
+use crate::image_processor::{AttachmentFormat, ImageMetrics};
+use crate::ipc_crypto::FileKey;
+use crate::janitor::{self, CleanupReport};
+use crate::popup::{self, PopupOutcome, PopupRequest, PopupResponse, PopupUpdate, Priority};
+use crate::report::{self, ReportFormat};
+use crate::session::SessionSummary;
+use crate::retention::{self, PurgeGuard, RetentionReport};
+use crate::result_template;
+use crate::rate_limit;
+use crate::storage::{SearchFilters, SearchResult, Storage, ToolUsageStats, UserNote};
+use crate::response_lint::{self, LintWarning};
+use crate::text_metrics::{self, TextMetrics};
 
 /// CLI 参数结构
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
@@ -649,6 +1297,12 @@ pub struct CliArgs {
     pub mcp_request_file: Option<String>,
     /// 是否为 MCP 模式
     pub mcp_mode: bool,
+    /// 是否在 stdin 关闭（启动弹窗的 MCP 服务端进程退出）时退出
+    #[serde(default)]
+    pub watch_parent: bool,
+    /// 常驻模式：窗口隐藏在后台，通过本地 socket 接收 MCP Server 的请求
+    #[serde(default)]
+    pub daemon: bool,
 }
 
 impl CliArgs {
@@ -670,6 +1324,13 @@ impl CliArgs {
                 "--mcp" | "-m" => {
                     cli_args.mcp_mode = true;
                 }
+                "--watch-parent" => {
+                    cli_args.watch_parent = true;
+                }
+                "--daemon" => {
+                    cli_args.daemon = true;
+                    cli_args.mcp_mode = true;
+                }
                 _ => {}
             }
             i += 1;
@@ -692,133 +1353,429 @@ pub async fn read_mcp_request(file_path: String) -> Result<PopupRequest, String>
         .await
         .map_err(|e| format!("Failed to read MCP request file: {}", e))?;
     
-    let request: PopupRequest = serde_json::from_str(&content)
+    let request = popup::parse_request_file(&content)
         .map_err(|e| format!("Failed to parse MCP request: {}", e))?;
     
     log::info!("Loaded MCP request: id={}, message={:?}", request.id, request.message);
     Ok(request)
 }
 
-/// 写入 MCP 响应文件
+/// 写入 MCP 响应
+///
+/// 请求带有响应通道（`endpoint`）时优先通过本地 socket 发送，失败时写入响应文件（使用请求的 `file_key` 加密）。
+/// 图片存入附件存储，响应中只传递附件 ID
 #[tauri::command]
 pub async fn write_response_file(
     file_path: String,
-    response: PopupResponse,
+    mut response: PopupResponse,
+    endpoint: Option<String>,
+    attachment_format: Option<AttachmentFormat>,
+    file_key: Option<String>,
 ) -> Result<(), String> {
-    let content = serde_json::to_string_pretty(&response)
-        .map_err(|e| format!("Failed to serialize response: {}", e))?;
-    
-    tokio::fs::write(&file_path, content)
+    let key = file_key.as_deref()
+        .map(FileKey::decode)
+        .transpose()
+        .map_err(|e| format!("Invalid file key: {}", e))?;
+    let format = attachment_format.unwrap_or_default();
+    response.schema_version = popup::POPUP_SCHEMA_VERSION;
+    response.min_schema_version = popup::POPUP_MIN_SCHEMA_VERSION;
+    response.app_version = Some(popup::APP_VERSION.to_string());
+    response.images = tokio::task::spawn_blocking(move || {
+        let mut images = match format {
+            AttachmentFormat::Png => response.images,
+            format => transcode_screenshots(response.images, format),
+        };
+        attachments::store().intern_images(&mut images);
+        images
+    })
+    .await
+    .map_err(|e| format!("Failed to store attachments: {}", e))?;
+    popup::deliver_response(endpoint.as_deref(), std::path::Path::new(&file_path), &response, key.as_ref())
         .await
-        .map_err(|e| format!("Failed to write response file: {}", e))?;
+        .map_err(|e| format!("Failed to write response: {}", e))?;
     
-    log::info!("Wrote MCP response to: {}", file_path);
+    log::info!("Delivered MCP response for: {}", response.request_id);
+    Ok(())
+}
+
+/// 将 PNG 截图转换为请求指定的附件格式
+///
+/// 只转换 PNG（JPEG 等有损图片转为无损格式反而更大），转换失败或没有变小时保留原图
+fn transcode_screenshots(images: Vec<popup::ImageData>, format: AttachmentFormat) -> Vec<popup::ImageData> {
+    use crate::image_processor::ImageProcessor;
+
+    let store = attachments::store();
+    images.into_iter()
+        .map(|mut image| {
+            if image.mime_type != "image/png" {
+                return image;
+            }
+            store.resolve_images(std::slice::from_mut(&mut image));
+            let converted = ImageProcessor::decode_base64(&image.data)
+                .and_then(|png| ImageProcessor::transcode_png(&png, format));
+            match converted {
+                Ok(Some((data, format))) => popup::ImageData {
+                    data: ImageProcessor::encode_base64(&data),
+                    mime_type: format.mime_type().to_string(),
+                    attachment_id: None,
+                },
+                Ok(None) => image,
+                Err(e) => {
+                    log::warn!("[write_response_file] 截图转换为 {:?} 失败，保留 PNG: {}", format, e);
+                    image
+                }
+            }
+        })
+        .collect()
+}
+
+/// 获取已收到的请求更新
+///
+/// 前端注册 `mcp-request-update` 监听之前到达的更新只能通过这里补取
+#[tauri::command]
+pub fn get_request_updates(request_id: String) -> Vec<PopupUpdate> {
+    popup::received_updates(&request_id)
+}
+
+/// 通知 MCP Server 多轮会话中的响应已写入
+///
+/// 弹窗保持打开，等待服务端通过 stdin 发送的下一个问题
+#[tauri::command]
+pub fn finish_session_turn(request_id: String) -> Result<(), String> {
+    use std::io::Write;
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}{}", popup::SESSION_RESPONSE_READY_PREFIX, request_id)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("Failed to notify MCP server: {}", e))?;
+    log::info!("[finish_session_turn] 已通知服务端响应就绪: {}", request_id);
     Ok(())
 }
 
 /// 退出应用
 /// 确保在 MCP 模式下正确退出进程
+/// 
+/// # Arguments
+/// * `outcome` - 本次请求的结果，决定进程退出码（见 [`PopupOutcome`]），默认为已提交
 #[tauri::command]
-pub fn exit_app(app_handle: tauri::AppHandle) -> Result<(), String> {
-    log::info!("[exit_app] 正在退出应用...");
+pub async fn exit_app(app_handle: tauri::AppHandle, outcome: Option<PopupOutcome>) -> Result<(), String> {
+    let outcome = outcome.unwrap_or_default();
+    
+    // 常驻模式下只隐藏窗口，等待下一个请求；普通窗口显示转发的请求时只结束该请求
+    if CliArgs::parse().daemon || daemon::has_active() {
+        log::info!("[exit_app] 结束通过 socket 收到的请求（{:?}）", outcome);
+        daemon::finish_active(&app_handle, outcome);
+        return Ok(());
+    }
+    
+    log::info!("[exit_app] 正在退出应用（{:?}）...", outcome);
+    
+    // 记录窗口位置，下次弹窗时恢复
+    window_geometry::save_main_window(&app_handle).await;
     
     // 使用 app_handle.exit() 确保进程完全退出
-    app_handle.exit(0);
+    app_handle.exit(outcome.exit_code());
     
     Ok(())
 }
 
+/// 导出反馈会话报告
+/// 
+/// # Arguments
+/// * `session_id` - 会话 ID（即 MCP 请求 ID）
+/// * `format` - 报告格式（markdown / html）
+/// * `output_path` - 可选的输出文件路径，默认保存到应用数据目录的 reports 目录
+/// 
+/// # Returns
+/// * 报告文件路径
+#[tauri::command]
+pub async fn export_session_report(
+    session_id: String,
+    format: ReportFormat,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let mut record = open_storage()?
+        .load_session(&session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+    attachments::store().resolve_images(&mut record.response.images);
+    let report = report::render_report(&record, format);
+    
+    let path = match output_path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => config::get_default_data_dir()
+            .map_err(|e| e.to_string())?
+            .join("reports")
+            .join(format!("{}.{}", record.id, format.extension())),
+    };
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create report directory: {}", e))?;
+    }
+    tokio::fs::write(&path, report).await
+        .map_err(|e| format!("Failed to write report: {}", e))?;
+    
+    log::info!("Exported session report to: {:?}", path);
+    Ok(path.to_string_lossy().to_string())
+}
 
-// ============================================================================
-// LLM 文本优化命令
-// ============================================================================
+/// 打开本地存储
+fn open_storage() -> Result<Storage, String> {
+    Storage::open_default().map_err(|e| format!("Failed to open storage: {}", e))
+}
 
-use crate::llm::{LlmProvider, LlmConfig, get_optimization_prompt, OptimizationType};
+/// 搜索反馈历史
+/// 
+/// # Arguments
+/// * `query` - 搜索关键词（匹配请求消息和用户输入），为空时返回全部
+/// * `limit` - 返回条数（默认 50）
+/// * `offset` - 偏移量（分页）
+#[tauri::command]
+pub async fn search_history(
+    query: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<SessionSummary>, String> {
+    open_storage()?
+        .search_sessions(query.as_deref().unwrap_or(""), limit.unwrap_or(50), offset.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
 
-/// 从配置中获取指定提供商的 API 密钥
-async fn get_api_key_from_config(app_handle: &AppHandle, provider: &str) -> Result<String, String> {
-    let current_config = config::load_config(app_handle).await
-        .map_err(|e| e.to_string())?;
-    
-    let provider_enum = ApiProvider::from_str(provider)
+/// 全文搜索历史反馈、AI 请求消息和常用语
+/// 
+/// # Arguments
+/// * `query` - 搜索关键词
+/// * `filters` - 来源、时间范围和条数限制
+/// 
+/// # Returns
+/// * 按相关度排序的结果，片段中的匹配部分以 STX/ETX 控制字符标记
+#[tauri::command]
+pub async fn search_all(
+    app_handle: AppHandle,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<SearchResult>, String> {
+    let storage = open_storage()?;
+    
+    // 常用语保存在 JSON 文件中，搜索前同步到索引
+    let canned = get_canned_responses(app_handle).await?;
+    storage.index_canned_responses(&canned)
         .map_err(|e| e.to_string())?;
     
-    let obfuscated = match provider_enum {
-        ApiProvider::OpenAI => current_config.api_keys.openai,
-        ApiProvider::Gemini => current_config.api_keys.gemini,
-        ApiProvider::DeepSeek => current_config.api_keys.deepseek,
-        ApiProvider::Volcengine => current_config.api_keys.volcengine,
-    };
+    storage.search_all(&query, &filters.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// 立即按保留策略清理本地数据
+#[tauri::command]
+pub async fn apply_retention_now(app_handle: AppHandle) -> Result<RetentionReport, String> {
+    let config = config::load_config(&app_handle).await.map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || retention::apply_retention(&config))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 立即运行一次后台清理（临时文件、过期数据、孤立附件）
+/// 
+/// # Returns
+/// * 清理结果，包含释放的字节数
+#[tauri::command]
+pub async fn run_cleanup_now(app_handle: AppHandle) -> Result<CleanupReport, String> {
+    let config = config::load_config(&app_handle).await.map_err(|e| e.to_string())?;
+    Ok(janitor::run_cleanup(&config).await)
+}
+
+/// 获取清除全部用户数据的确认令牌（60 秒内有效，只能使用一次）
+#[tauri::command]
+pub fn request_purge_token(guard: tauri::State<'_, PurgeGuard>) -> String {
+    guard.issue()
+}
+
+/// 清除全部用户数据（配置、API 密钥、历史记录、报告、归档、临时文件）
+/// 
+/// # Arguments
+/// * `confirm_token` - 通过 `request_purge_token` 获取的确认令牌
+/// 
+/// # Returns
+/// * 未能删除的路径列表
+#[tauri::command]
+pub async fn purge_all_user_data(
+    app_handle: AppHandle,
+    guard: tauri::State<'_, PurgeGuard>,
+    confirm_token: String,
+) -> Result<Vec<String>, String> {
+    if !guard.consume(&confirm_token) {
+        return Err("确认令牌无效或已过期".to_string());
+    }
     
-    match obfuscated {
-        Some(ref s) if !s.is_empty() => {
-            ApiKeyManager::deobfuscate(s).map_err(|e| e.to_string())
-        }
-        _ => Err(format!("未配置 {} 的 API 密钥", provider)),
+    let config = config::load_config(&app_handle).await.unwrap_or_default();
+    tokio::task::spawn_blocking(move || retention::purge_all_user_data(&config))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取 MCP 工具使用统计
+#[tauri::command]
+pub async fn get_usage_stats() -> Result<Vec<ToolUsageStats>, String> {
+    open_storage()?
+        .usage_stats()
+        .map_err(|e| e.to_string())
+}
+
+/// 留言给 Agent：消息会附加到下一次反馈结果中，
+/// Agent 也可以通过 `whale_get_pending_user_notes` 工具主动读取
+#[tauri::command]
+pub async fn push_user_note(message: String) -> Result<UserNote, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("消息不能为空".to_string());
     }
+    open_storage()?
+        .add_user_note(message)
+        .map_err(|e| e.to_string())
 }
 
-/// 从配置中获取第一个已配置的提供商（按优先级顺序）
-async fn get_first_configured_provider(app_handle: &AppHandle) -> Result<(String, String), String> {
-    log::info!("[优化] 获取已配置的提供商...");
-    let current_config = config::load_config(app_handle).await
+/// 获取尚未交给 Agent 的留言
+#[tauri::command]
+pub async fn list_pending_user_notes() -> Result<Vec<UserNote>, String> {
+    open_storage()?
+        .pending_user_notes()
+        .map_err(|e| e.to_string())
+}
+
+/// 撤回尚未交给 Agent 的留言
+#[tauri::command]
+pub async fn delete_user_note(id: i64) -> Result<bool, String> {
+    open_storage()?
+        .delete_user_note(id)
+        .map_err(|e| e.to_string())
+}
+
+/// 熔断状态（连续多个请求未得到回答后，MCP Server 拒绝新的弹窗请求）
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerStatus {
+    pub tripped: bool,
+    pub tripped_at: Option<String>,
+    pub reset_at: Option<String>,
+}
+
+/// 获取熔断状态
+#[tauri::command]
+pub async fn get_circuit_breaker_status() -> Result<CircuitBreakerStatus, String> {
+    let storage = open_storage()?;
+    let tripped_at = storage.get_state(rate_limit::BREAKER_TRIPPED_AT_KEY).map_err(|e| e.to_string())?;
+    let reset_at = storage.get_state(rate_limit::BREAKER_RESET_AT_KEY).map_err(|e| e.to_string())?;
+    let parse = |value: &Option<String>| value.as_deref().and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok());
+    let tripped = match (parse(&tripped_at), parse(&reset_at)) {
+        (Some(tripped), Some(reset)) => tripped > reset,
+        (Some(_), None) => true,
+        _ => false,
+    };
+    Ok(CircuitBreakerStatus { tripped, tripped_at, reset_at })
+}
+
+/// 重置熔断，MCP Server 在下一次调用时恢复接受弹窗请求
+#[tauri::command]
+pub async fn reset_circuit_breaker() -> Result<(), String> {
+    open_storage()?
+        .set_state(rate_limit::BREAKER_RESET_AT_KEY, &chrono::Utc::now().to_rfc3339())
         .map_err(|e| e.to_string())?;
+    log::info!("[reset_circuit_breaker] 熔断已重置");
+    Ok(())
+}
+
+/// 用示例数据预览反馈结果模板
+/// 
+/// # Returns
+/// * 渲染结果；模板语法错误时返回错误信息
+#[tauri::command]
+pub fn preview_result_template(template: String) -> Result<String, String> {
+    result_template::render(&template, &result_template::sample_data())
+}
+
+/// 回答宏执行结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerMacroResult {
+    /// 需要追加到反馈中的文本（多个文本块以空行分隔）
+    pub text: Option<String>,
+    /// 宏截取的全屏截图
+    pub screenshot: Option<ScreenshotResult>,
+}
+
+/// 执行选项对应的回答宏
+/// 
+/// # Arguments
+/// * `option` - 用户选中的选项文本
+/// 
+/// # Returns
+/// * 选项没有配置宏时返回 None
+#[tauri::command]
+pub async fn run_answer_macro(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    option: String,
+) -> Result<Option<AnswerMacroResult>, String> {
+    let config = config::load_config(&app_handle).await.map_err(|e| e.to_string())?;
+    let macros = config.answer_macros.clone();
+    let Some(plan) = tokio::task::spawn_blocking(move || macros.expand(&option))
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
     
-    log::info!("[优化] provider_order: {:?}", current_config.provider_order);
-    log::info!("[优化] api_keys - openai: {}, gemini: {}, deepseek: {}, volcengine: {}", 
-        current_config.api_keys.openai.is_some(),
-        current_config.api_keys.gemini.is_some(),
-        current_config.api_keys.deepseek.is_some(),
-        current_config.api_keys.volcengine.is_some()
-    );
-    
-    // 获取 API 密钥的辅助函数
-    let get_key = |provider: &str| -> Option<String> {
-        let obfuscated = match provider {
-            "openai" => current_config.api_keys.openai.as_ref(),
-            "gemini" => current_config.api_keys.gemini.as_ref(),
-            "deepseek" => current_config.api_keys.deepseek.as_ref(),
-            "volcengine" => current_config.api_keys.volcengine.as_ref(),
-            _ => None,
-        };
-        obfuscated.and_then(|s| {
-            if s.is_empty() {
-                log::info!("[优化] {} 密钥为空", provider);
+    let screenshot = if plan.capture_screenshot {
+        let options = resolve_capture_options(&app_handle, None).await;
+        window.hide().map_err(|e| format!("Failed to hide window: {}", e))?;
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+        let captured = ScreenshotManager::capture_full_screen(None, &options);
+        let _ = window.show();
+        let _ = window.set_focus();
+        match captured {
+            Ok(screenshot) => Some(screenshot),
+            Err(e) => {
+                log::warn!("[run_answer_macro] 截图失败: {}", e);
                 None
-            } else {
-                match ApiKeyManager::deobfuscate(s) {
-                    Ok(key) => {
-                        log::info!("[优化] {} 密钥解密成功", provider);
-                        Some(key)
-                    }
-                    Err(e) => {
-                        log::error!("[优化] {} 密钥解密失败: {}", provider, e);
-                        None
-                    }
-                }
             }
-        })
-    };
-    
-    // 优先按 provider_order 顺序查找
-    for provider in &current_config.provider_order {
-        if let Some(api_key) = get_key(provider) {
-            log::info!("[优化] 使用提供商: {}", provider);
-            return Ok((provider.clone(), api_key));
         }
-    }
+    } else {
+        None
+    };
     
-    // 如果 provider_order 为空或没有找到，使用默认顺序
-    let default_order = ["openai", "gemini", "deepseek", "volcengine"];
-    for provider in default_order {
-        if let Some(api_key) = get_key(provider) {
-            log::info!("[优化] 使用提供商: {}", provider);
-            return Ok((provider.to_string(), api_key));
-        }
-    }
+    let text = (!plan.text_blocks.is_empty()).then(|| plan.text_blocks.join("\n\n"));
+    Ok(Some(AnswerMacroResult { text, screenshot }))
+}
+
+/// 统计输入文本（字符数、词数、估算 token 数、主要语言），供编辑时实时显示
+#[tauri::command]
+pub fn get_text_metrics(text: String) -> TextMetrics {
+    text_metrics::text_metrics(&text)
+}
+
+/// 提交前检查回答：多部分问题回答为空、有问题没有回应、残留待办标记
+#[tauri::command]
+pub fn lint_response(message: String, user_input: String, selected_options: Vec<String>) -> Vec<LintWarning> {
+    response_lint::lint(&message, &user_input, &selected_options)
+}
+
+
+// ============================================================================
+// LLM 文本优化命令
+// ============================================================================
+
+use crate::llm::{LlmProvider, LlmConfig, get_optimization_prompt, OptimizationType};
+use crate::llm::resolver;
+use crate::llm::selection::{self, SelectionReplacement, TextRange};
+use crate::pii::{self, PiiAction, PiiScanReport};
+use tauri::ipc::Channel;
+
+/// 从配置中获取指定提供商（内置或自定义）的 LLM 配置
+async fn get_llm_config(app_handle: &AppHandle, provider: &str) -> Result<LlmConfig, String> {
+    let current_config = config::load_config(app_handle).await
+        .map_err(|e| e.to_string())?;
     
-    log::error!("[优化] 未找到任何已配置的 API 密钥");
-    Err("未配置任何 API 密钥，请先在设置中配置".to_string())
+    resolver::llm_config(&current_config, &provider_id(provider))
 }
 
 /// 优化文本
@@ -829,6 +1786,7 @@ async fn get_first_configured_provider(app_handle: &AppHandle) -> Result<(String
 /// * `app_handle` - Tauri 应用句柄
 /// * `text` - 要优化的文本
 /// * `optimization_type` - 优化类型 ID（从配置的 optimization_types 中匹配）
+/// * `allow_pii` - 用户已确认发送包含敏感信息的文本
 /// 
 /// # Returns
 /// * 优化后的文本
@@ -837,13 +1795,136 @@ pub async fn optimize_text(
     app_handle: AppHandle,
     text: String,
     optimization_type: String,
+    allow_pii: Option<bool>,
 ) -> Result<String, String> {
+    run_optimization(&app_handle, &text, &optimization_type, allow_pii.unwrap_or(false)).await
+}
+
+/// 只优化选中的部分文本
+/// 
+/// # Arguments
+/// * `text` - 完整文本
+/// * `range` - 选区（UTF-16 偏移，与 textarea 的 selectionStart/selectionEnd 一致）
+/// * `optimization_type` - 优化类型 ID
+/// * `allow_pii` - 用户已确认发送包含敏感信息的文本
+/// 
+/// # Returns
+/// * 可直接替换选区的优化结果（包含原始偏移）
+#[tauri::command]
+pub async fn optimize_selection(
+    app_handle: AppHandle,
+    text: String,
+    range: TextRange,
+    optimization_type: String,
+    allow_pii: Option<bool>,
+) -> Result<SelectionReplacement, String> {
+    let selected = selection::selected_text(&text, range)?;
+    if selected.trim().is_empty() {
+        return Err("选中的文本不能为空".to_string());
+    }
+    
+    log::info!("[优化] 优化选中文本: {}..{}", range.start, range.end);
+    let optimized = run_optimization(&app_handle, selected, &optimization_type, allow_pii.unwrap_or(false)).await?;
+    Ok(SelectionReplacement {
+        range,
+        original: selected.to_string(),
+        replacement: selection::fit_replacement(selected, &optimized),
+    })
+}
+
+/// 扫描文本中的敏感信息（邮箱、手机号、证件号、银行卡号），供发送给 LLM 前提示用户
+#[tauri::command]
+pub async fn scan_pii(app_handle: AppHandle, text: String) -> Result<PiiScanReport, String> {
+    let config = config::load_config(&app_handle).await.map_err(|e| e.to_string())?;
+    let matches = match config.pii_scan.action {
+        PiiAction::Off => Vec::new(),
+        _ => pii::scan(&text),
+    };
+    Ok(PiiScanReport { action: config.pii_scan.action, matches })
+}
+
+/// 流式优化文本的进度
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeStreamChunk {
+    /// 到目前为止生成的完整文本（已还原屏蔽的敏感信息）
+    pub text: String,
+}
+
+/// 流式优化文本
+/// 
+/// 与 [`optimize_text`] 相同，但生成过程中通过 `on_chunk` 逐段发送已生成的文本，
+/// 长文本优化时前端可以边生成边显示
+/// 
+/// # Returns
+/// * 完整的优化结果
+#[tauri::command]
+pub async fn optimize_text_stream(
+    app_handle: AppHandle,
+    text: String,
+    optimization_type: String,
+    allow_pii: Option<bool>,
+    on_chunk: Channel<OptimizeStreamChunk>,
+) -> Result<String, String> {
+    log::info!("[优化] 开始流式文本优化，类型: {}", optimization_type);
+    
+    let config = crate::config::load_config(&app_handle).await
+        .map_err(|e| e.to_string())?;
+    let (prompt_template, masked) = optimization_prompt(&config, &text, &optimization_type, allow_pii.unwrap_or(false))?;
+    
+    let mut generated = String::new();
+    let send_chunk = |chunk: &str| {
+        generated.push_str(chunk);
+        // 占位符可能被切断，每次还原完整的已生成文本
+        let text = masked.unmask(&generated);
+        if let Err(e) = on_chunk.send(OptimizeStreamChunk { text }) {
+            log::warn!("[优化] 发送流式结果失败: {}", e);
+        }
+    };
+    match resolver::optimize_stream_with_fallback(&config, &masked.text, &prompt_template, send_chunk).await {
+        Ok((provider, result)) => {
+            log::info!("[优化] 流式调用完成（{}），结果长度: {} 字符", provider, result.len());
+            Ok(masked.unmask(&result))
+        }
+        Err(e) => {
+            log::error!("[优化] 流式调用失败: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// 按配置的优化类型调用 LLM 优化文本（发送前按配置检查敏感信息）
+async fn run_optimization(app_handle: &AppHandle, text: &str, optimization_type: &str, allow_pii: bool) -> Result<String, String> {
     log::info!("[优化] 开始文本优化，类型: {}", optimization_type);
     
+    let config = crate::config::load_config(app_handle).await
+        .map_err(|e| e.to_string())?;
+    let (prompt_template, masked) = optimization_prompt(&config, text, optimization_type, allow_pii)?;
+    
+    // 按 provider_order 依次尝试已配置的提供商
+    log::info!("[优化] provider_order: {:?}", config.provider_order);
+    log::info!("[优化] 系统提示词长度: {} 字符, 用户输入长度: {} 字符", prompt_template.len(), text.len());
+    
+    match resolver::optimize_with_fallback(&config, &masked.text, &prompt_template).await {
+        Ok((provider, result)) => {
+            log::info!("[优化] API 调用成功（{}），结果长度: {} 字符", provider, result.len());
+            Ok(masked.unmask(&result))
+        }
+        Err(e) => {
+            log::error!("[优化] API 调用失败: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// 查找优化类型的提示词，并按配置检查和屏蔽敏感信息
+fn optimization_prompt(config: &AppConfig, text: &str, optimization_type: &str, allow_pii: bool) -> Result<(String, pii::MaskedText), String> {
     // 从配置中查找优化类型
-    let prompt_template = {
-        let config = crate::config::load_config(&app_handle).await
-            .map_err(|e| e.to_string())?;
+    let (prompt_template, masked) = {
+        let masked = pii::guard(&config.pii_scan, text, allow_pii)?;
+        if !masked.replacements.is_empty() {
+            log::info!("[优化] 已屏蔽 {} 处敏感信息", masked.replacements.len());
+        }
         
         // 在配置的优化类型中查找匹配的 ID
         let found = config.optimization_types.iter()
@@ -852,7 +1933,7 @@ pub async fn optimize_text(
         match found {
             Some(t) => {
                 log::info!("[优化] 找到优化类型: id={}, label={}", t.id, t.label);
-                t.prompt.clone()
+                (t.prompt.clone(), masked)
             }
             None => {
                 log::error!("[优化] 未找到优化类型: {}", optimization_type);
@@ -867,33 +1948,7 @@ pub async fn optimize_text(
     };
     
     log::debug!("[优化] 提示词模板前100字符: {}", &prompt_template.chars().take(100).collect::<String>());
-    
-    // 获取第一个已配置的提供商
-    let (provider_name, api_key) = get_first_configured_provider(&app_handle).await?;
-    log::info!("[优化] 创建 LLM 配置...");
-    
-    // 创建 LLM 配置
-    let config = LlmConfig::from_provider(&provider_name, api_key)
-        .ok_or_else(|| format!("不支持的提供商: {}", provider_name))?;
-    log::info!("[优化] LLM 配置创建成功: model={}, base_url={}", config.model, config.base_url);
-    
-    // 创建 Provider
-    let llm = LlmProvider::new(config)?;
-    log::info!("[优化] LLM Provider 创建成功，开始调用 API...");
-    
-    // 系统提示词作为 system 角色，用户输入作为 user 角色
-    log::info!("[优化] 系统提示词长度: {} 字符, 用户输入长度: {} 字符", prompt_template.len(), text.len());
-    
-    match llm.optimize_text(&text, &prompt_template).await {
-        Ok(result) => {
-            log::info!("[优化] API 调用成功，结果长度: {} 字符", result.len());
-            Ok(result)
-        }
-        Err(e) => {
-            log::error!("[优化] API 调用失败: {}", e);
-            Err(e)
-        }
-    }
+    Ok((prompt_template, masked))
 }
 
 /// 使用指定提供商优化文本
@@ -904,6 +1959,7 @@ pub async fn optimize_text(
 /// * `provider` - AI 提供商名称
 /// * `mode` - 优化模式 (optimize, reinforce)
 /// * `custom_prompt` - 自定义提示词（reinforce 模式使用）
+/// * `allow_pii` - 用户已确认发送包含敏感信息的文本
 /// 
 /// # Returns
 /// * 优化后的文本
@@ -914,26 +1970,25 @@ pub async fn optimize_text_with_provider(
     provider: String,
     mode: String,
     custom_prompt: Option<String>,
+    allow_pii: Option<bool>,
 ) -> Result<String, String> {
     // 解析优化类型
     let opt_type = OptimizationType::from_str(&mode)
         .ok_or_else(|| format!("无效的优化模式: {}", mode))?;
     
-    // 获取 API 密钥
-    let api_key = get_api_key_from_config(&app_handle, &provider).await?;
-    
-    // 创建 LLM 配置
-    let config = LlmConfig::from_provider(&provider, api_key)
-        .ok_or_else(|| format!("不支持的提供商: {}", provider))?;
+    // 发送前检查敏感信息
+    let app_config = config::load_config(&app_handle).await.map_err(|e| e.to_string())?;
+    let masked = pii::guard(&app_config.pii_scan, &text, allow_pii.unwrap_or(false))?;
     
-    // 创建 Provider
-    let llm = LlmProvider::new(config)?;
+    // 获取 LLM 配置并创建 Provider
+    let llm = LlmProvider::new(get_llm_config(&app_handle, &provider).await?)?;
     
     // 获取提示词
     let system_prompt = get_optimization_prompt(opt_type, custom_prompt.as_deref());
     
     // 调用 LLM
-    llm.optimize_text(&text, &system_prompt).await
+    let result = llm.optimize_text(&masked.text, &system_prompt).await?;
+    Ok(masked.unmask(&result))
 }
 
 /// 测试 API 连接
@@ -946,15 +2001,8 @@ pub async fn optimize_text_with_provider(
 /// * 测试结果消息
 #[tauri::command]
 pub async fn test_api_connection(app_handle: AppHandle, provider: String) -> Result<String, String> {
-    // 获取 API 密钥
-    let api_key = get_api_key_from_config(&app_handle, &provider).await?;
-    
-    // 创建 LLM 配置
-    let config = LlmConfig::from_provider(&provider, api_key)
-        .ok_or_else(|| format!("不支持的提供商: {}", provider))?;
-    
-    // 创建 Provider
-    let llm = LlmProvider::new(config)?;
+    // 获取 LLM 配置并创建 Provider
+    let llm = LlmProvider::new(get_llm_config(&app_handle, &provider).await?)?;
     
     // 测试连接
     llm.test_connection().await?;
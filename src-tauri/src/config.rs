@@ -202,13 +202,16 @@ pub async fn save_config(app_handle: &AppHandle, config: &AppConfig) -> Result<(
     Ok(())
 }
 
+/// 获取默认应用数据目录（不依赖 AppHandle，用于 MCP server）
+pub fn get_default_data_dir() -> Result<PathBuf, ConfigError> {
+    Ok(dirs::data_dir()
+        .ok_or(ConfigError::NoAppDataDir)?
+        .join("com.whale-interactive-feedback.app"))
+}
+
 /// 获取默认配置文件路径（不依赖 AppHandle，用于 MCP server）
 pub fn get_default_config_path() -> Result<PathBuf, ConfigError> {
-    let app_data_dir = dirs::data_dir()
-        .ok_or(ConfigError::NoAppDataDir)?
-        .join("com.whale-interactive-feedback.app");
-    
-    Ok(app_data_dir.join("config.json"))
+    Ok(get_default_data_dir()?.join("config.json"))
 }
 
 /// 直接从文件加载配置（不依赖 AppHandle，用于 MCP server）
@@ -230,6 +233,21 @@ pub async fn load_config_direct() -> Result<AppConfig, ConfigError> {
     }
 }
 
+/// 同步加载配置（用于同步上下文，如打开本地存储）
+///
+/// 配置文件无法解析时返回错误而不是默认配置：打开本地存储时不能把默认的“未加密”当作用户的设置
+pub fn load_config_direct_blocking() -> Result<AppConfig, ConfigError> {
+    let config_path = get_default_config_path()?;
+    if !config_path.exists() {
+        return Ok(AppConfig::default());
+    }
+    let content = std::fs::read_to_string(&config_path)?;
+    serde_json::from_str(&content).map_err(|e| {
+        log::warn!("Failed to parse config: {}", e);
+        ConfigError::Json(e)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,6 +334,39 @@ mod tests {
         assert_eq!(config.theme, Theme::Light);
         assert!(!config.audio_enabled);
     }
+
+    #[test]
+    fn test_custom_options_legacy_migration() {
+        use crate::types::{CustomOption, InsertPosition};
+
+        let json = serde_json::json!([
+            "请继续",
+            { "text": "返回上一步", "icon": "i-carbon-undo", "color": "#ff0000", "insertPosition": "replace" }
+        ]);
+        let options: Vec<CustomOption> = serde_json::from_value(json).unwrap();
+
+        assert_eq!(options[0], CustomOption::from_text("请继续"));
+        assert_eq!(options[1].icon.as_deref(), Some("i-carbon-undo"));
+        assert_eq!(options[1].color.as_deref(), Some("#ff0000"));
+        assert_eq!(options[1].insert_position, InsertPosition::Replace);
+    }
+
+    #[test]
+    fn test_visible_optimization_types_pinned_and_hidden() {
+        let mut config = AppConfig::default();
+        config.show_system_optimization_types = false;
+        config.optimization_types[3].pinned = true;
+        let pinned_id = config.optimization_types[3].id.clone();
+        
+        let mut custom = config.optimization_types[0].clone();
+        custom.id = "my-custom".to_string();
+        custom.is_system = false;
+        config.optimization_types.push(custom);
+        
+        let visible = config.visible_optimization_types();
+        let ids: Vec<&str> = visible.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec![pinned_id.as_str(), "my-custom"]);
+    }
 }
 
 #[cfg(test)]
@@ -341,12 +392,17 @@ mod property_tests {
             arbitrary_optional_string(),
             arbitrary_optional_string(),
             arbitrary_optional_string(),
-        ).prop_map(|(openai, gemini, deepseek, volcengine)| {
+            arbitrary_optional_string(),
+        ).prop_map(|(openai, gemini, deepseek, volcengine, anthropic)| {
             ApiKeys {
                 openai,
                 gemini,
                 deepseek,
                 volcengine,
+                anthropic,
+                azure: Default::default(),
+                ollama: Default::default(),
+                custom: Vec::new(),
             }
         })
     }
@@ -426,6 +482,7 @@ mod property_tests {
             prop_assert_eq!(config.api_keys.gemini, deserialized.api_keys.gemini);
             prop_assert_eq!(config.api_keys.deepseek, deserialized.api_keys.deepseek);
             prop_assert_eq!(config.api_keys.volcengine, deserialized.api_keys.volcengine);
+            prop_assert_eq!(config.api_keys.anthropic, deserialized.api_keys.anthropic);
             prop_assert_eq!(config.selected_provider, deserialized.selected_provider);
             prop_assert_eq!(config.optimize_prompt, deserialized.optimize_prompt);
             prop_assert_eq!(config.enhance_prompt, deserialized.enhance_prompt);
@@ -461,6 +518,7 @@ mod property_tests {
                 assert_eq!(config.api_keys.gemini, loaded.api_keys.gemini);
                 assert_eq!(config.api_keys.deepseek, loaded.api_keys.deepseek);
                 assert_eq!(config.api_keys.volcengine, loaded.api_keys.volcengine);
+                assert_eq!(config.api_keys.anthropic, loaded.api_keys.anthropic);
                 assert_eq!(config.selected_provider, loaded.selected_provider);
                 assert_eq!(config.optimize_prompt, loaded.optimize_prompt);
                 assert_eq!(config.enhance_prompt, loaded.enhance_prompt);
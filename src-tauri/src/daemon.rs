@@ -0,0 +1,1052 @@
+//! 常驻 GUI 模式
+//!
+//! 启用后 GUI 进程在后台常驻（窗口隐藏），MCP Server 通过本地 socket 把请求交给它显示，
+//! 而不是每次调用都启动新的 `app` 进程，省去 webview 冷启动的数秒等待。
+//!
+//! 每个请求使用一个连接：服务端第一行发送 [`ParentMessage::Request`]，常驻进程回复 `ok`（已显示）
+//! 或 `busy`（正在显示其他请求，服务端改为启动独立弹窗）。请求期间连接保持打开，之后的行是请求更新；
+//! 任一方关闭连接即结束请求：服务端关闭（超时、取消、退出）时常驻进程隐藏窗口，
+//! 常驻进程关闭（用户作答或关闭窗口）时服务端读取响应。响应本身仍通过响应通道或响应文件返回；
+//! 常驻进程关闭连接前发送一行 `exit <退出码>`，即请求的结果对应的退出码（见 [`PopupOutcome`]）。
+//!
+//! ## 单实例
+//! 普通打开的应用窗口同样监听该 socket。以 `--mcp-request` 启动的弹窗进程发现已有实例空闲时，
+//! 把请求转发给它显示（前端收到 `mcp-session-request` 事件），自己不创建窗口，
+//! 只在后台转发服务端的请求更新并等待请求结束后，以对方发来的退出码退出，服务端照常读取响应和退出码。
+//!
+//! ## 进程监督
+//! 启动常驻进程的 MCP Server 负责监督它：异常退出（非零退出码、被信号终止）后按退避时间重启，
+//! 正常退出（用户退出应用）后不再重启。监督任务在常驻进程每次启动和退出时更新它的代数，
+//! 请求显示期间连接断开且没有响应时，服务端区分两种情况：常驻进程退出了时
+//! 把同一请求重新交给新的常驻进程显示（未运行时由该服务端启动并监督），仍在运行（用户关闭了窗口）时当作用户取消。
+//! 不是本进程启动的常驻进程没有退出信号，服务端改为重新连接它的 socket：无法连接或 socket
+//! 已被新的常驻进程重新创建时，说明接受请求的常驻进程已退出。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tauri::{AppHandle, Emitter, Manager, UserAttentionType};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{oneshot, watch};
+
+use crate::popup::{
+    self, InFlightGuard, ParentMessage, PopupKind, PopupOutcome, PopupRequest, PopupResponse, PopupUpdate,
+    Priority, ResponseChannel,
+};
+use crate::gui_limit;
+use crate::sla;
+use crate::types::FocusPolicy;
+use crate::window_geometry;
+
+/// 常驻进程接受请求时的回复
+const REPLY_OK: &str = "ok";
+/// 常驻进程正在显示其他请求时的回复
+const REPLY_BUSY: &str = "busy";
+/// 请求结束时发送的退出码行的前缀
+const EXIT_PREFIX: &str = "exit ";
+/// 等待常驻进程回复的最长时间
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// 启动常驻进程后尝试连接的间隔
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// 常驻进程异常退出后重启的最长退避时间
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+/// 常驻进程运行超过这个时间后才退出时，视为已稳定运行，重新计算退避时间和重启次数
+const STABLE_RUN: Duration = Duration::from_secs(60);
+/// 连接断开后等待监督任务报告常驻进程退出的最长时间
+const EXIT_SIGNAL_GRACE: Duration = Duration::from_secs(1);
+
+#[cfg(unix)]
+pub type DaemonStream = tokio::net::UnixStream;
+#[cfg(windows)]
+pub type DaemonStream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+/// 常驻进程当前显示的请求
+struct ActiveRequest {
+    id: String,
+    /// 用户作答或关闭窗口时通知连接结束，并交给它请求的结果
+    done: oneshot::Sender<PopupOutcome>,
+    /// 显示请求前的窗口标题（普通窗口在请求结束后恢复）
+    idle_title: Option<String>,
+}
+
+static ACTIVE: Mutex<Option<ActiveRequest>> = Mutex::new(None);
+
+/// 常驻模式：请求结束后隐藏窗口（普通窗口保持显示）
+static HIDE_WHEN_IDLE: AtomicBool = AtomicBool::new(false);
+
+/// 本进程监督的常驻进程（MCP Server 使用）
+static SUPERVISED: OnceLock<Generations> = OnceLock::new();
+
+fn supervised() -> &'static Generations {
+    SUPERVISED.get_or_init(Generations::new)
+}
+
+/// 常驻进程的一代：每启动一次代数加一
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Generation {
+    number: u64,
+    running: bool,
+}
+
+/// 由监督任务维护的常驻进程代数，等待请求的一方据此得知常驻进程是否已退出
+struct Generations(watch::Sender<Generation>);
+
+impl Generations {
+    fn new() -> Self {
+        Self(watch::channel(Generation::default()).0)
+    }
+
+    /// 记录新启动的常驻进程，返回它的代数
+    fn started(&self) -> u64 {
+        let mut number = 0;
+        self.0.send_modify(|generation| {
+            generation.number += 1;
+            generation.running = true;
+            number = generation.number;
+        });
+        number
+    }
+
+    /// 记录第 `number` 代常驻进程已退出
+    fn exited(&self, number: u64) {
+        self.0.send_if_modified(|generation| {
+            let current = generation.number == number && generation.running;
+            if current {
+                generation.running = false;
+            }
+            current
+        });
+    }
+
+    /// 监视正在运行的常驻进程，没有时返回 None
+    fn watch_running(&self) -> Option<ExitWatch> {
+        let receiver = self.0.subscribe();
+        let current = *receiver.borrow();
+        current.running.then_some(ExitWatch { receiver, number: current.number })
+    }
+}
+
+/// 监视某一代常驻进程是否已退出
+struct ExitWatch {
+    receiver: watch::Receiver<Generation>,
+    number: u64,
+}
+
+impl ExitWatch {
+    /// 在 `grace` 内得知该代常驻进程已退出（或已被新的一代取代）时返回 true
+    async fn exited_within(&mut self, grace: Duration) -> bool {
+        let number = self.number;
+        let exited = self.receiver.wait_for(|g| g.number != number || !g.running);
+        matches!(tokio::time::timeout(grace, exited).await, Ok(Ok(_)))
+    }
+}
+
+/// 常驻进程的 socket 地址（每个用户一个）
+pub fn daemon_endpoint() -> Result<String> {
+    #[cfg(unix)]
+    {
+        let dir = crate::config::get_default_data_dir()?;
+        Ok(dir.join("daemon.sock").to_string_lossy().into_owned())
+    }
+    #[cfg(windows)]
+    {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        Ok(format!(r"\\.\pipe\whale_daemon_{}", user))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MCP Server 端
+// ---------------------------------------------------------------------------
+
+/// 显示请求并等待响应
+///
+/// 启用常驻模式时交给常驻 GUI（未运行时先启动），常驻 GUI 不可用、正在显示其他请求
+/// 或请求不能由其他实例显示（见 [`can_forward`]）时与 [`popup::launch_popup_and_wait`] 相同，启动独立的弹窗进程
+pub async fn launch_and_wait(
+    request: &PopupRequest,
+    timeout: Option<Duration>,
+    cancelled: impl Future<Output = ()>,
+    mut updates: Option<UnboundedReceiver<PopupUpdate>>,
+) -> Result<PopupResponse> {
+    let app_config = crate::config::load_config_direct().await.unwrap_or_default();
+    let config = &app_config.daemon_mode;
+    let started = Instant::now();
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+    tokio::pin!(cancelled);
+
+    // 原生文件选择器和多轮会话不经过常驻 GUI（与转发给其他实例的条件相同）
+    if config.enabled && can_forward(request) {
+        // 常驻 GUI 在显示请求期间退出时，把请求重新交给重启后的常驻 GUI
+        let retry = &app_config.launch_retry;
+        let mut backoff = Duration::from_millis(retry.initial_backoff_ms);
+        let mut attempt = 1;
+        loop {
+            match dispatch(request, Duration::from_secs(config.start_timeout_secs)).await {
+                Ok(Some(dispatched)) => {
+                    match dispatched.wait(deadline.as_mut(), cancelled.as_mut(), &mut updates, timeout).await? {
+                        DaemonExit::Finished(response) => return Ok(response),
+                        DaemonExit::Lost if attempt < retry.max_attempts.max(1) => {
+                            log::warn!("[daemon] 常驻 GUI 在显示请求 {} 时退出（第 {} 次），{:?} 后重新显示", request.id, attempt, backoff);
+                        }
+                        DaemonExit::Lost => {
+                            log::error!("[daemon] 常驻 GUI 多次在显示请求时退出，启动独立弹窗");
+                            break;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    log::info!("[daemon] 常驻 GUI 正在显示其他请求，启动独立弹窗");
+                    break;
+                }
+                Err(e) => {
+                    log::warn!("[daemon] 常驻 GUI 不可用（{}），启动独立弹窗", e);
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = &mut deadline => return Ok(PopupResponse::timed_out(&request.id)),
+                _ = &mut cancelled => return Ok(PopupResponse::cancelled(&request.id)),
+            }
+            attempt += 1;
+            backoff *= 2;
+        }
+    }
+
+    // 独立弹窗数量达到上限时等待其他弹窗关闭，等待时间计入超时
+    let _slot = tokio::select! {
+        slot = gui_limit::acquire(&app_config.gui_limit) => slot,
+        _ = &mut cancelled => return Ok(PopupResponse::cancelled(&request.id)),
+        _ = &mut deadline => return Ok(PopupResponse::timed_out(&request.id)),
+    };
+    let timeout = timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+    popup::launch_popup_and_wait(request, timeout, cancelled, updates, &app_config.launch_retry).await
+}
+
+/// 交给常驻 GUI 的请求的结果
+enum DaemonExit {
+    /// 得到了响应（包括超时、取消和用户关闭窗口）
+    Finished(PopupResponse),
+    /// 常驻 GUI 在显示请求期间退出且没有响应
+    Lost,
+}
+
+/// 已交给常驻 GUI 的请求
+struct Dispatched<S = DaemonStream> {
+    request_id: String,
+    stream: BufReader<S>,
+    channel: Option<ResponseChannel>,
+    /// 本进程监督的常驻 GUI 的退出信号（连接的不是本进程启动的常驻 GUI 时为 None）
+    exit: Option<ExitWatch>,
+    /// 接受请求的常驻 GUI 实例，没有退出信号时据此判断它是否已退出
+    instance: Option<DaemonInstance>,
+    _in_flight: InFlightGuard,
+}
+
+/// 将请求发送给常驻 GUI，常驻 GUI 正在显示其他请求时返回 None
+async fn dispatch(request: &PopupRequest, start_timeout: Duration) -> Result<Option<Dispatched>> {
+    let channel = ResponseChannel::bind(&request.id).ok_or_else(|| anyhow!("无法创建响应通道"))?;
+    let mut request = request.clone();
+    request.response_endpoint = Some(channel.endpoint().to_string());
+    let in_flight = InFlightGuard::new(&request.id);
+    // 响应通道不可用时 GUI 根据请求文件路径写入响应文件
+    popup::create_request_file(&request).await?;
+
+    let stream = connect_or_start(start_timeout).await?;
+    // 连接后立即开始监视，之后常驻 GUI 任何时候退出都能得知
+    let mut dispatched = Dispatched {
+        request_id: request.id.clone(),
+        stream: BufReader::new(stream),
+        channel: Some(channel),
+        exit: supervised().watch_running(),
+        instance: None,
+        _in_flight: in_flight,
+    };
+    if !dispatched.send_request(&request).await? {
+        return Ok(None);
+    }
+    // 常驻 GUI 回复时已在监听，socket 文件不会再变化
+    dispatched.instance = Some(DaemonInstance::current(daemon_endpoint()?));
+    log::info!("[daemon] 请求 {} 已交给常驻 GUI", request.id);
+    Ok(Some(dispatched))
+}
+
+impl<S> Dispatched<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// 发送请求，常驻 GUI 接受时返回 true，正在显示其他请求时返回 false
+    async fn send_request(&mut self, request: &PopupRequest) -> Result<bool> {
+        popup::send_message(self.stream.get_mut(), &ParentMessage::Request(popup::with_file_key(request))).await?;
+        let mut reply = String::new();
+        tokio::time::timeout(REPLY_TIMEOUT, self.stream.read_line(&mut reply))
+            .await
+            .map_err(|_| anyhow!("等待常驻 GUI 回复超时"))??;
+
+        match reply.trim() {
+            REPLY_OK => Ok(true),
+            REPLY_BUSY => Ok(false),
+            other => Err(anyhow!("常驻 GUI 回复无效: {:?}", other)),
+        }
+    }
+
+    /// 等待用户作答；超时或取消时关闭连接，常驻 GUI 随之隐藏窗口
+    async fn wait<D, C>(
+        mut self,
+        mut deadline: std::pin::Pin<&mut D>,
+        mut cancelled: std::pin::Pin<&mut C>,
+        updates: &mut Option<UnboundedReceiver<PopupUpdate>>,
+        timeout: Option<Duration>,
+    ) -> Result<DaemonExit>
+    where
+        D: Future<Output = ()>,
+        C: Future<Output = ()>,
+    {
+        let response_path = popup::get_response_file_path(&self.request_id);
+        let mut line = String::new();
+
+        loop {
+            tokio::select! {
+                response = popup::next_channel_response(&mut self.channel) => return Ok(DaemonExit::Finished(response)),
+                read = self.stream.read_line(&mut line) => match read {
+                    // 常驻 GUI 结束了请求（用户作答或关闭窗口）或已退出
+                    Ok(0) | Err(_) => {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        if let Some(response) = popup::take_channel_response(&mut self.channel) {
+                            return Ok(DaemonExit::Finished(response));
+                        }
+                        if response_path.exists() {
+                            return popup::read_response_file(&self.request_id).await.map(DaemonExit::Finished);
+                        }
+                        // 监督任务报告常驻 GUI 已退出，说明它崩溃了而不是用户关闭了窗口
+                        if let Some(exit) = &mut self.exit {
+                            if exit.exited_within(EXIT_SIGNAL_GRACE).await {
+                                return Ok(DaemonExit::Lost);
+                            }
+                        }
+                        // 其他服务端启动的常驻 GUI：接受请求的实例已不再监听时同样视为崩溃
+                        if let Some(instance) = &self.instance {
+                            if !instance.is_running().await {
+                                log::warn!("[daemon] 常驻 GUI 在显示请求 {} 时已退出", self.request_id);
+                                return Ok(DaemonExit::Lost);
+                            }
+                        }
+                        log::warn!("[daemon] 常驻 GUI 结束了请求但没有响应，返回取消状态");
+                        return Ok(DaemonExit::Finished(PopupResponse::cancelled(&self.request_id)));
+                    }
+                    // 常驻 GUI 不会在请求期间发送其他内容
+                    Ok(_) => line.clear(),
+                },
+                _ = &mut deadline => {
+                    log::warn!("[daemon] 等待用户响应超时 ({:?})，关闭常驻 GUI 中的请求", timeout);
+                    let _ = tokio::fs::remove_file(&response_path).await;
+                    return Ok(DaemonExit::Finished(PopupResponse::timed_out(&self.request_id)));
+                }
+                _ = &mut cancelled => {
+                    log::warn!("[daemon] 请求已被取消，关闭常驻 GUI 中的请求");
+                    let _ = tokio::fs::remove_file(&response_path).await;
+                    return Ok(DaemonExit::Finished(PopupResponse::cancelled(&self.request_id)));
+                }
+                update = popup::next_update(updates) => match update {
+                    Some(update) => {
+                        if let Err(e) = popup::send_message(self.stream.get_mut(), &ParentMessage::Update(update)).await {
+                            log::warn!("[daemon] 发送更新失败: {}", e);
+                        }
+                    }
+                    None => *updates = None,
+                },
+            }
+        }
+    }
+}
+
+/// 连接常驻 GUI
+async fn connect() -> Result<DaemonStream> {
+    Ok(connect_to(&daemon_endpoint()?).await?)
+}
+
+async fn connect_to(endpoint: &str) -> std::io::Result<DaemonStream> {
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(endpoint).await?;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(endpoint)?;
+    Ok(stream)
+}
+
+/// 接受请求的常驻 GUI 实例
+///
+/// 常驻进程每次启动都会重新创建 socket，socket 文件的标识变化说明已换成新的常驻进程
+struct DaemonInstance {
+    endpoint: String,
+    socket_id: Option<(u64, i64, i64)>,
+}
+
+impl DaemonInstance {
+    /// 当前在 `endpoint` 监听的实例
+    fn current(endpoint: String) -> Self {
+        let socket_id = socket_id(&endpoint);
+        Self { endpoint, socket_id }
+    }
+
+    /// 该实例是否仍在运行：无法连接或 socket 已被新的常驻进程重新创建时视为已退出
+    async fn is_running(&self) -> bool {
+        match connect_to(&self.endpoint).await {
+            Ok(_) => socket_id(&self.endpoint) == self.socket_id,
+            // 命名管道的所有实例都在使用中，说明常驻进程仍在运行
+            #[cfg(windows)]
+            Err(e) if e.raw_os_error() == Some(231) => true,
+            Err(_) => false,
+        }
+    }
+}
+
+/// socket 文件的 inode 和创建时写入的修改时间（inode 可能被复用；Windows 命名管道没有对应的文件，返回 None）
+fn socket_id(endpoint: &str) -> Option<(u64, i64, i64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(endpoint).ok().map(|m| (m.ino(), m.mtime(), m.mtime_nsec()))
+    }
+    #[cfg(windows)]
+    {
+        let _ = endpoint;
+        None
+    }
+}
+
+/// 连接常驻 GUI，未运行时启动并等待其就绪
+async fn connect_or_start(start_timeout: Duration) -> Result<DaemonStream> {
+    if let Ok(stream) = connect().await {
+        return Ok(stream);
+    }
+
+    supervise(spawn_daemon()?);
+
+    let deadline = Instant::now() + start_timeout;
+    loop {
+        tokio::time::sleep(CONNECT_POLL_INTERVAL).await;
+        match connect().await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if Instant::now() >= deadline => {
+                return Err(anyhow!("常驻 GUI 未在 {:?} 内就绪: {}", start_timeout, e));
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// 启动常驻 GUI 进程
+fn spawn_daemon() -> Result<tokio::process::Child> {
+    let ui_exe = popup::find_ui_executable()?;
+    log::info!("[daemon] 启动常驻 GUI: {:?} --daemon", ui_exe);
+    tokio::process::Command::new(&ui_exe)
+        .arg("--daemon")
+        .env(popup::IPC_DIR_ENV, popup::ipc_dir())
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("启动常驻 GUI 失败: {}", e))
+}
+
+/// 监督本进程启动的常驻 GUI，异常退出后按退避时间重启
+///
+/// 常驻进程不随 MCP Server 退出；服务端运行期间由这里回收，避免退出后成为僵尸进程。
+/// 正常退出、已关闭常驻模式、已有其他常驻进程在运行或连续重启次数用完时停止监督
+fn supervise(child: tokio::process::Child) {
+    let generations = supervised();
+    // 在返回前登记，连接到新进程的请求一定能监视到它
+    let mut generation = generations.started();
+    tokio::spawn(async move {
+        let mut child = child;
+        let mut restarts = 0;
+        let mut backoff = Duration::ZERO;
+        loop {
+            let started = Instant::now();
+            let exit = child.wait().await;
+            generations.exited(generation);
+            let status = match exit {
+                Ok(status) if status.success() => {
+                    log::info!("[daemon] 常驻 GUI 已退出");
+                    return;
+                }
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+
+            let config = crate::config::load_config_direct().await.unwrap_or_default();
+            let retry = &config.launch_retry;
+            if started.elapsed() >= STABLE_RUN || restarts == 0 {
+                restarts = 0;
+                backoff = Duration::from_millis(retry.initial_backoff_ms);
+            }
+            if restarts + 1 >= retry.max_attempts.max(1) {
+                log::error!("[daemon] 常驻 GUI 连续异常退出（{}），不再重启", status);
+                return;
+            }
+            log::warn!("[daemon] 常驻 GUI 异常退出（{}），{:?} 后重启", status, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+            restarts += 1;
+
+            if !config.daemon_mode.enabled {
+                return;
+            }
+            // 等待期间其他 MCP Server 已经启动了新的常驻进程，由它负责监督
+            if connect().await.is_ok() {
+                log::info!("[daemon] 已有其他常驻 GUI 在运行，停止监督");
+                return;
+            }
+            child = match spawn_daemon() {
+                Ok(child) => child,
+                Err(e) => {
+                    log::error!("[daemon] 重启常驻 GUI 失败: {}", e);
+                    return;
+                }
+            };
+            generation = generations.started();
+        }
+    });
+}
+
+// ---------------------------------------------------------------------------
+// GUI 端
+// ---------------------------------------------------------------------------
+
+/// 以常驻模式运行：监听 MCP Server 的连接（GUI 使用）
+///
+/// 已有常驻进程在运行或无法监听时退出应用
+pub fn run_daemon(app_handle: AppHandle) {
+    HIDE_WHEN_IDLE.store(true, Ordering::Relaxed);
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(app_handle.clone()).await {
+            log::error!("[daemon] 常驻模式无法运行: {}", e);
+            app_handle.exit(1);
+        }
+    });
+}
+
+/// 普通窗口接受其他实例转发的请求（GUI 使用）
+///
+/// 已有其他实例在监听时不接受，之后启动的弹窗转发给那个实例
+pub fn accept_forwarded_requests(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(app_handle).await {
+            log::info!("[daemon] 不接受转发的请求: {}", e);
+        }
+    });
+}
+
+/// 当前是否正在显示通过 socket 收到的请求
+pub fn has_active() -> bool {
+    ACTIVE.lock().unwrap().is_some()
+}
+
+/// 是否可以转发给其他实例（多轮会话和原生文件选择器仍使用独立进程）
+pub fn can_forward(request: &PopupRequest) -> bool {
+    request.kind == PopupKind::Feedback && request.session_id.is_none()
+}
+
+/// 把请求转发给正在运行的实例（弹窗进程使用），对方接受时返回连接
+pub async fn forward_to_running_instance(request: &PopupRequest) -> Option<DaemonStream> {
+    let mut stream = BufReader::new(connect().await.ok()?);
+    if let Err(e) = popup::send_message(stream.get_mut(), &ParentMessage::Request(request.clone())).await {
+        log::warn!("[daemon] 转发请求失败: {}", e);
+        return None;
+    }
+    let mut reply = String::new();
+    match tokio::time::timeout(REPLY_TIMEOUT, stream.read_line(&mut reply)).await {
+        Ok(Ok(_)) if reply.trim() == REPLY_OK => {
+            log::info!("[daemon] 请求 {} 已转发给正在运行的实例", request.id);
+            Some(stream.into_inner())
+        }
+        _ => {
+            log::info!("[daemon] 正在运行的实例无法显示请求 {}，打开新窗口", request.id);
+            None
+        }
+    }
+}
+
+/// 等待转发的请求结束后退出（弹窗进程使用）
+///
+/// 以对方发来的请求结果的退出码退出，服务端据此得知超时、自动选择等结果；没有收到时以 0 退出。
+/// `relay_stdin` 时把服务端通过 stdin 发送的请求更新转发给正在显示请求的实例；
+/// stdin 关闭（服务端退出）时关闭连接，对方随之结束请求
+pub fn proxy_forwarded(app_handle: AppHandle, stream: DaemonStream, relay_stdin: bool) {
+    let (sender, mut messages) = tokio::sync::mpsc::unbounded_channel::<String>();
+    if relay_stdin {
+        std::thread::spawn(move || {
+            use std::io::BufRead;
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if !line.trim().is_empty() && sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    tauri::async_runtime::spawn(async move {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        let mut outcome = PopupOutcome::Submitted;
+        loop {
+            tokio::select! {
+                read = reader.read_line(&mut line) => match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if let Some(received) = parse_exit_line(&line) {
+                            outcome = received;
+                        }
+                        line.clear();
+                    }
+                },
+                message = messages.recv(), if relay_stdin => match message {
+                    Some(message) => {
+                        if let Err(e) = writer.write_all(format!("{}\n", message).as_bytes()).await {
+                            log::warn!("[daemon] 转发请求更新失败: {}", e);
+                        }
+                    }
+                    None => {
+                        log::warn!("[daemon] MCP 服务端已退出，结束转发的请求");
+                        break;
+                    }
+                },
+            }
+        }
+        log::info!("[daemon] 转发的请求已结束（{:?}）", outcome);
+        app_handle.exit(outcome.exit_code());
+    });
+}
+
+#[cfg(unix)]
+async fn serve(app_handle: AppHandle) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let endpoint = daemon_endpoint()?;
+    if tokio::net::UnixStream::connect(&endpoint).await.is_ok() {
+        return Err(anyhow!("已有常驻 GUI 在运行"));
+    }
+    // 上次异常退出残留的 socket 文件
+    let _ = std::fs::remove_file(&endpoint);
+    if let Some(dir) = std::path::Path::new(&endpoint).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let listener = tokio::net::UnixListener::bind(&endpoint)?;
+    std::fs::set_permissions(&endpoint, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("[daemon] 常驻模式已启动，监听 {}", endpoint);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tauri::async_runtime::spawn(handle_connection(app_handle.clone(), stream));
+    }
+}
+
+#[cfg(windows)]
+async fn serve(app_handle: AppHandle) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+    let endpoint = daemon_endpoint()?;
+    // 已有常驻进程时 first_pipe_instance 创建失败
+    let mut server = ServerOptions::new().first_pipe_instance(true).create(&endpoint)
+        .map_err(|e| anyhow!("已有常驻 GUI 在运行或无法创建命名管道: {}", e))?;
+    log::info!("[daemon] 常驻模式已启动，监听 {}", endpoint);
+
+    loop {
+        server.connect().await?;
+        // 为下一次连接创建新的管道实例，当前实例交给连接处理
+        let connected = std::mem::replace(&mut server, ServerOptions::new().create(&endpoint)?);
+        tauri::async_runtime::spawn(handle_connection(app_handle.clone(), connected));
+    }
+}
+
+/// 处理一个请求连接
+async fn handle_connection<S>(app_handle: AppHandle, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+        return;
+    }
+    let request = match popup::decode_parent_message(&line) {
+        Ok(ParentMessage::Request(request)) => request,
+        Ok(_) => {
+            log::warn!("[daemon] 连接的第一条消息不是请求");
+            return;
+        }
+        Err(e) => {
+            log::warn!("[daemon] 无法解析服务端消息: {}", e);
+            return;
+        }
+    };
+
+    let (done, mut finished) = oneshot::channel();
+    let accepted = {
+        let mut active = ACTIVE.lock().unwrap();
+        // 文件选择和多轮会话需要独立进程，回复 busy 让服务端启动独立弹窗
+        if active.is_some() || !can_forward(&request) {
+            false
+        } else {
+            *active = Some(ActiveRequest {
+                id: request.id.clone(),
+                done,
+                idle_title: app_handle.get_webview_window("main").and_then(|w| w.title().ok()),
+            });
+            true
+        }
+    };
+    let request_id = request.id.clone();
+    let reply = if accepted { REPLY_OK } else { REPLY_BUSY };
+    if let Err(e) = write_reply(stream.get_mut(), reply).await {
+        log::warn!("[daemon] 回复服务端失败: {}", e);
+        if accepted {
+            end_request(&app_handle, Some(&request_id), PopupOutcome::Cancelled);
+        }
+        return;
+    }
+    if !accepted {
+        log::info!("[daemon] 正在显示其他请求，拒绝 {}", request_id);
+        return;
+    }
+
+    show_window(&app_handle, &request);
+    // 声明了响应截止时间的请求，临近和超过截止时间时逐级提醒，请求结束时停止
+    let escalation = request
+        .respond_by
+        .is_some()
+        .then(|| tauri::async_runtime::spawn(sla::run_escalation(app_handle.clone(), request.clone())));
+    popup::forward_parent_message(&app_handle, ParentMessage::Request(request));
+
+    loop {
+        line.clear();
+        tokio::select! {
+            read = stream.read_line(&mut line) => match read {
+                Ok(0) | Err(_) => {
+                    log::info!("[daemon] 服务端结束了请求 {}", request_id);
+                    end_request(&app_handle, Some(&request_id), PopupOutcome::Cancelled);
+                    break;
+                }
+                Ok(_) => match popup::decode_parent_message(&line) {
+                    Ok(message @ ParentMessage::Update(_)) => popup::forward_parent_message(&app_handle, message),
+                    Ok(ParentMessage::Request(_)) => log::warn!("[daemon] 忽略同一连接上的新请求"),
+                    Err(e) => log::warn!("[daemon] 无法解析服务端消息: {}", e),
+                },
+            },
+            // 发送结果对应的退出码后关闭连接，服务端随后读取响应
+            outcome = &mut finished => {
+                let outcome = outcome.unwrap_or(PopupOutcome::Cancelled);
+                log::info!("[daemon] 请求 {} 已结束（{:?}）", request_id, outcome);
+                if let Err(e) = write_reply(stream.get_mut(), &exit_line(outcome)).await {
+                    log::warn!("[daemon] 发送请求结果失败: {}", e);
+                }
+                break;
+            }
+        }
+    }
+    if let Some(escalation) = escalation {
+        escalation.abort();
+    }
+}
+
+/// 请求结束时发送给对方的退出码行
+fn exit_line(outcome: PopupOutcome) -> String {
+    format!("{}{}", EXIT_PREFIX, outcome.exit_code())
+}
+
+/// 解析退出码行，其他内容返回 None
+fn parse_exit_line(line: &str) -> Option<PopupOutcome> {
+    let code = line.trim().strip_prefix(EXIT_PREFIX)?.parse().ok()?;
+    PopupOutcome::from_exit_code(code)
+}
+
+async fn write_reply<W: AsyncWrite + Unpin>(writer: &mut W, reply: &str) -> std::io::Result<()> {
+    writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+    writer.flush().await
+}
+
+/// 显示窗口并更新标题，按焦点策略激活窗口或只闪烁任务栏图标
+fn show_window(app_handle: &AppHandle, request: &PopupRequest) {
+    let Some(window) = app_handle.get_webview_window("main") else { return };
+    let _ = window.set_title(&request.window_title("Interactive Feedback"));
+    let _ = window.unminimize();
+    let _ = window.show();
+    let focus = crate::config::load_config_direct_blocking().unwrap_or_default().mcp_window_behavior.focus;
+    if focus == FocusPolicy::Steal && request.priority != Priority::Low {
+        let _ = window.set_focus();
+    } else {
+        let attention = if request.priority == Priority::Critical {
+            UserAttentionType::Critical
+        } else {
+            UserAttentionType::Informational
+        };
+        let _ = window.request_user_attention(Some(attention));
+    }
+}
+
+/// 结束当前请求：隐藏窗口并通知前端重置（常驻模式下代替退出应用）
+///
+/// 用户作答或关闭窗口时调用，连接随之关闭，服务端读取响应；`outcome` 是请求的结果
+pub fn finish_active(app_handle: &AppHandle, outcome: PopupOutcome) {
+    end_request(app_handle, None, outcome);
+}
+
+/// 结束当前请求；指定 `request_id` 时只在当前请求匹配时结束
+fn end_request(app_handle: &AppHandle, request_id: Option<&str>, outcome: PopupOutcome) {
+    let ended = {
+        let mut active = ACTIVE.lock().unwrap();
+        if active.as_ref().is_some_and(|a| request_id.is_none_or(|id| a.id == id)) {
+            active.take()
+        } else {
+            None
+        }
+    };
+    if ended.is_none() && request_id.is_some() {
+        return;
+    }
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if HIDE_WHEN_IDLE.load(Ordering::Relaxed) {
+            if let Some(geometry) = window_geometry::capture(&window) {
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move { window_geometry::save(&app_handle, geometry).await });
+            }
+            let _ = window.hide();
+        } else if let Some(title) = ended.as_ref().and_then(|a| a.idle_title.as_deref()) {
+            let _ = window.set_title(title);
+        }
+    }
+    if let Some(ended) = ended {
+        let _ = ended.done.send(outcome);
+        if let Err(e) = app_handle.emit("mcp-request-dismissed", &ended.id) {
+            log::error!("[daemon] 发送请求结束事件失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    fn new_dispatched(stream: DuplexStream, exit: Option<ExitWatch>) -> (PopupRequest, Dispatched<DuplexStream>) {
+        let request = PopupRequest::new(Some("继续吗？".to_string()), None, None);
+        let dispatched = Dispatched {
+            request_id: request.id.clone(),
+            stream: BufReader::new(stream),
+            channel: None,
+            exit,
+            instance: None,
+            _in_flight: InFlightGuard::new(&request.id),
+        };
+        (request, dispatched)
+    }
+
+    /// 模拟常驻 GUI：读取请求并回复，返回连接供测试继续使用
+    async fn fake_daemon(stream: DuplexStream, reply: &str) -> (String, BufReader<DuplexStream>) {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let request_id = match popup::decode_parent_message(&line).unwrap() {
+            ParentMessage::Request(request) => request.id,
+            _ => panic!("第一条消息应该是请求"),
+        };
+        write_reply(stream.get_mut(), reply).await.unwrap();
+        (request_id, stream)
+    }
+
+    async fn wait_for_exit(dispatched: Dispatched<DuplexStream>) -> DaemonExit {
+        let deadline = std::future::pending::<()>();
+        let cancelled = std::future::pending::<()>();
+        tokio::pin!(deadline);
+        tokio::pin!(cancelled);
+        dispatched.wait(deadline.as_mut(), cancelled.as_mut(), &mut None, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_replies() {
+        for (reply, accepted) in [(REPLY_OK, true), (REPLY_BUSY, false)] {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            let (request, mut dispatched) = new_dispatched(client, None);
+            let daemon = tokio::spawn(async move { fake_daemon(server, reply).await.0 });
+            assert_eq!(dispatched.send_request(&request).await.unwrap(), accepted);
+            assert_eq!(daemon.await.unwrap(), request.id);
+        }
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, None);
+        tokio::spawn(async move { fake_daemon(server, "what").await });
+        assert!(dispatched.send_request(&request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generations() {
+        let generations = Generations::new();
+        assert!(generations.watch_running().is_none());
+
+        let first = generations.started();
+        let mut watch = generations.watch_running().unwrap();
+        // 常驻进程仍在运行
+        assert!(!watch.exited_within(Duration::from_millis(20)).await);
+        // 旧的一代重复报告退出不影响当前一代
+        generations.exited(first - 1);
+        assert!(generations.watch_running().is_some());
+
+        generations.exited(first);
+        assert!(watch.exited_within(Duration::from_millis(20)).await);
+        assert!(generations.watch_running().is_none());
+
+        // 监视期间被重启后的新一代取代同样视为已退出
+        generations.started();
+        let mut watch = generations.watch_running().unwrap();
+        generations.started();
+        assert!(watch.exited_within(Duration::from_millis(20)).await);
+    }
+
+    #[tokio::test]
+    async fn test_wait_replays_after_daemon_crash() {
+        let generations = Generations::new();
+        let number = generations.started();
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, generations.watch_running());
+        let daemon = tokio::spawn(async move { fake_daemon(server, REPLY_OK).await });
+        assert!(dispatched.send_request(&request).await.unwrap());
+
+        // 常驻 GUI 崩溃：连接断开，随后监督任务报告退出
+        drop(daemon.await.unwrap());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            generations.exited(number);
+        });
+        assert!(matches!(wait_for_exit(dispatched).await, DaemonExit::Lost));
+    }
+
+    #[tokio::test]
+    async fn test_wait_replays_when_unsupervised_daemon_gone() {
+        // 其他服务端启动的常驻 GUI：没有退出信号，断开后已无法连接
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, None);
+        let endpoint = std::env::temp_dir().join(format!("whale_daemon_{}.sock", uuid::Uuid::new_v4()));
+        dispatched.instance = Some(DaemonInstance::current(endpoint.to_string_lossy().into_owned()));
+        let daemon = tokio::spawn(async move { fake_daemon(server, REPLY_OK).await });
+        assert!(dispatched.send_request(&request).await.unwrap());
+
+        drop(daemon.await.unwrap());
+        assert!(matches!(wait_for_exit(dispatched).await, DaemonExit::Lost));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_daemon_instance_is_running() {
+        let endpoint = std::env::temp_dir().join(format!("whale_daemon_{}.sock", uuid::Uuid::new_v4()));
+        let listener = tokio::net::UnixListener::bind(&endpoint).unwrap();
+        let instance = DaemonInstance::current(endpoint.to_string_lossy().into_owned());
+        assert!(instance.is_running().await);
+
+        // 崩溃后残留的 socket 文件无法连接
+        drop(listener);
+        assert!(!instance.is_running().await);
+
+        // 重启后的常驻进程重新创建了 socket
+        std::fs::remove_file(&endpoint).unwrap();
+        let _listener = tokio::net::UnixListener::bind(&endpoint).unwrap();
+        assert!(!instance.is_running().await);
+        assert!(DaemonInstance::current(endpoint.to_string_lossy().into_owned()).is_running().await);
+        let _ = std::fs::remove_file(&endpoint);
+    }
+
+    #[tokio::test]
+    async fn test_wait_without_response_while_daemon_running() {
+        let generations = Generations::new();
+        generations.started();
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, generations.watch_running());
+        let daemon = tokio::spawn(async move { fake_daemon(server, REPLY_OK).await });
+        assert!(dispatched.send_request(&request).await.unwrap());
+
+        // 用户关闭了窗口：常驻 GUI 结束请求但仍在运行，即使它已经可以接受新连接也不会重新显示
+        drop(daemon.await.unwrap());
+        match wait_for_exit(dispatched).await {
+            DaemonExit::Finished(response) => {
+                assert!(response.cancelled);
+                assert_eq!(response.request_id, request.id);
+            }
+            DaemonExit::Lost => panic!("常驻 GUI 没有退出，不应重新显示请求"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_cancel_closes_connection() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, None);
+        let daemon = tokio::spawn(async move { fake_daemon(server, REPLY_OK).await });
+        assert!(dispatched.send_request(&request).await.unwrap());
+        let (_, mut daemon_stream) = daemon.await.unwrap();
+
+        let deadline = std::future::pending::<()>();
+        let cancelled = std::future::ready(());
+        tokio::pin!(deadline);
+        tokio::pin!(cancelled);
+        match dispatched.wait(deadline.as_mut(), cancelled.as_mut(), &mut None, None).await.unwrap() {
+            DaemonExit::Finished(response) => assert!(response.cancelled && !response.timed_out),
+            DaemonExit::Lost => panic!("取消的请求不应重新显示"),
+        }
+        // 服务端关闭连接，常驻 GUI 随之结束请求
+        let mut line = String::new();
+        assert_eq!(daemon_stream.read_line(&mut line).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wait_timeout_closes_connection() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (request, mut dispatched) = new_dispatched(client, None);
+        let daemon = tokio::spawn(async move { fake_daemon(server, REPLY_OK).await });
+        assert!(dispatched.send_request(&request).await.unwrap());
+        let (_, mut daemon_stream) = daemon.await.unwrap();
+
+        let deadline = tokio::time::sleep(Duration::from_millis(50));
+        let cancelled = std::future::pending::<()>();
+        tokio::pin!(deadline);
+        tokio::pin!(cancelled);
+        let exit = dispatched.wait(deadline.as_mut(), cancelled.as_mut(), &mut None, Some(Duration::from_millis(50))).await;
+        assert!(matches!(exit.unwrap(), DaemonExit::Finished(response) if response.timed_out));
+        let mut line = String::new();
+        assert_eq!(daemon_stream.read_line(&mut line).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_can_forward() {
+        let feedback = PopupRequest::new(Some("继续吗？".to_string()), None, None);
+        assert!(can_forward(&feedback));
+
+        // 多轮会话复用自己的弹窗进程
+        let mut session = feedback.clone();
+        session.session_id = Some("session-1".to_string());
+        assert!(!can_forward(&session));
+
+        // 原生文件选择器和其他类型的弹窗使用独立进程
+        let select_file = PopupRequest::select_file(None, None, popup::FilePickerOptions::default());
+        assert!(!can_forward(&select_file));
+        assert!(!can_forward(&PopupRequest::confirm(None, "删除吗？".to_string(), false)));
+        assert!(!can_forward(&PopupRequest::form(None, None, Vec::new())));
+    }
+
+    #[test]
+    fn test_exit_line() {
+        for outcome in [PopupOutcome::Submitted, PopupOutcome::Cancelled, PopupOutcome::TimedOut, PopupOutcome::AutoSelected] {
+            assert_eq!(parse_exit_line(&format!("{}\n", exit_line(outcome))), Some(outcome));
+        }
+        assert_eq!(parse_exit_line(REPLY_OK), None);
+        assert_eq!(parse_exit_line("exit 99"), None);
+        assert_eq!(parse_exit_line("exit timed_out"), None);
+    }
+}
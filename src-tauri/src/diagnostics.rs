@@ -0,0 +1,176 @@
+//! 环境自检模块
+//!
+//! `whale_diagnostics` 工具汇总 UI 可执行文件查找结果、配置文件、已配置的 LLM 提供商、
+//! 临时目录、音频设备和截图权限，排查“弹窗不出现”等问题时无需翻看 stderr 日志。
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioNotifier;
+use crate::popup::find_ui_executable;
+use crate::screenshot::ScreenshotManager;
+use crate::types::AppConfig;
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        Self { name: name.to_string(), ok, detail }
+    }
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub version: String,
+    pub platform: String,
+    /// 所有检查均通过
+    pub ok: bool,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+/// 运行全部检查
+pub async fn run_diagnostics() -> DiagnosticsReport {
+    let (config_check, config) = check_config().await;
+    let checks = vec![
+        DiagnosticCheck::new("ui_executable", check_ui_executable()),
+        config_check,
+        DiagnosticCheck::new("llm_providers", check_providers(&config)),
+        DiagnosticCheck::new("temp_dir", check_temp_dir().await),
+        DiagnosticCheck::new("audio_device", blocking(check_audio_device).await),
+        DiagnosticCheck::new("screen_capture", check_screen_capture().await),
+    ];
+
+    DiagnosticsReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        ok: checks.iter().all(|c| c.ok),
+        checks,
+    }
+}
+
+/// 在阻塞线程中运行检查
+async fn blocking(check: fn() -> Result<String, String>) -> Result<String, String> {
+    tokio::task::spawn_blocking(check)
+        .await
+        .unwrap_or_else(|e| Err(format!("检查失败: {}", e)))
+}
+
+fn check_ui_executable() -> Result<String, String> {
+    let env_hint = std::env::var("WHALE_UI_PATH")
+        .map(|path| format!("（WHALE_UI_PATH={}）", path))
+        .unwrap_or_default();
+    find_ui_executable()
+        .map(|path| format!("{}{}", path.display(), env_hint))
+        .map_err(|e| format!("{}{}", e, env_hint))
+}
+
+/// 检查配置文件，同时返回加载到的配置（无效时为默认配置）
+async fn check_config() -> (DiagnosticCheck, AppConfig) {
+    let path = match crate::config::get_default_config_path() {
+        Ok(path) => path,
+        Err(e) => return (DiagnosticCheck::new("config", Err(e.to_string())), AppConfig::default()),
+    };
+    let (result, config) = match tokio::fs::read_to_string(&path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            (Ok(format!("{}（不存在，使用默认配置）", path.display())), AppConfig::default())
+        }
+        Err(e) => (Err(format!("{}: 读取失败: {}", path.display(), e)), AppConfig::default()),
+        Ok(content) => match serde_json::from_str::<AppConfig>(&content) {
+            Ok(config) => (Ok(path.display().to_string()), config),
+            Err(e) => (Err(format!("{}: 解析失败，使用默认配置: {}", path.display(), e)), AppConfig::default()),
+        },
+    };
+    (DiagnosticCheck::new("config", result), config)
+}
+
+fn check_providers(config: &AppConfig) -> Result<String, String> {
+    let providers = config.api_keys.configured_providers();
+    if providers.is_empty() {
+        Err(format!("未配置 API 密钥（sampling 模式: {:?}）", config.optimize_sampling))
+    } else {
+        Ok(providers.join(", "))
+    }
+}
+
+async fn check_temp_dir() -> Result<String, String> {
+    let dir = crate::popup::ipc_dir();
+    let probe = dir.join(format!("whale-diagnostics-{}.tmp", uuid::Uuid::new_v4()));
+    tokio::fs::write(&probe, b"ok")
+        .await
+        .map_err(|e| format!("{}: 不可写: {}", dir.display(), e))?;
+    let _ = tokio::fs::remove_file(&probe).await;
+    Ok(dir.display().to_string())
+}
+
+fn check_audio_device() -> Result<String, String> {
+    // 使用启动时缓存的检测结果，不可用时通知音会被跳过
+    AudioNotifier::availability()
+        .map(|_| "默认输出设备可用".to_string())
+        .map_err(|e| format!("没有可用的音频输出设备，通知音已禁用: {}", e))
+}
+
+/// 检查录屏权限后并发试截每个显示器
+async fn check_screen_capture() -> Result<String, String> {
+    blocking(check_screen_access).await?;
+    let probes = ScreenshotManager::probe_all_monitors().await.map_err(|e| e.to_string())?;
+    let failed: Vec<String> = probes.iter()
+        .enumerate()
+        .filter_map(|(id, probe)| probe.as_ref().err().map(|e| format!("显示器 {}: {}", id, e)))
+        .collect();
+    if failed.is_empty() {
+        Ok(format!("{} 个显示器均可截图", probes.len()))
+    } else {
+        Err(format!("{}/{} 个显示器截图失败（{}）", failed.len(), probes.len(), failed.join("; ")))
+    }
+}
+
+fn check_screen_access() -> Result<String, String> {
+    let monitors = ScreenshotManager::get_monitors().map_err(|e| e.to_string())?;
+    if monitors.is_empty() {
+        return Err("未检测到显示器".to_string());
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        #[link(name = "CoreGraphics", kind = "framework")]
+        extern "C" {
+            fn CGPreflightScreenCaptureAccess() -> bool;
+        }
+        // SAFETY: 无参数的系统 API，只查询当前进程的录屏权限
+        if !unsafe { CGPreflightScreenCaptureAccess() } {
+            return Err(format!("{} 个显示器，未授予屏幕录制权限（系统设置 → 隐私与安全性 → 屏幕录制）", monitors.len()));
+        }
+    }
+
+    Ok(format!("{} 个显示器", monitors.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_providers() {
+        let mut config = AppConfig::default();
+        assert!(check_providers(&config).is_err());
+
+        config.api_keys.deepseek = Some("key".to_string());
+        config.api_keys.openai = Some(String::new());
+        assert_eq!(check_providers(&config), Ok("deepseek".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_temp_dir_writable() {
+        assert!(check_temp_dir().await.is_ok());
+    }
+}
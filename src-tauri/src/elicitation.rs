@@ -0,0 +1,169 @@
+//! MCP elicitation 支持
+//!
+//! 客户端声明支持 `elicitation/create` 且用户在设置中启用后，反馈、确认和表单请求
+//! 改为由客户端在自己的界面中向用户收集结构化输入，不再启动 GUI 弹窗；
+//! 客户端不支持、请求无法用 elicitation 表达（如文件选择）或调用失败时仍使用弹窗。
+//!
+//! 本模块负责弹窗请求与 elicitation schema / 结果之间的转换。
+
+use serde_json::{json, Map, Value};
+
+use crate::popup::{FormFieldKind, PopupKind, PopupRequest, PopupResponse};
+
+/// 反馈文本字段名
+const FEEDBACK_FIELD: &str = "feedback";
+/// 选项字段名
+const OPTION_FIELD: &str = "selected_option";
+/// 确认字段名
+const CONFIRMED_FIELD: &str = "confirmed";
+
+/// elicitation 请求显示给用户的文本
+pub fn message(request: &PopupRequest) -> String {
+    request
+        .message
+        .as_deref()
+        .or(request.title.as_deref())
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or("Please provide your feedback")
+        .to_string()
+}
+
+/// 请求对应的 elicitation schema（只包含基本类型字段），无法表达时返回 None
+pub fn requested_schema(request: &PopupRequest) -> Option<Value> {
+    let (properties, required) = match request.kind {
+        PopupKind::Feedback => {
+            let mut properties = Map::new();
+            if let Some(options) = request.predefined_options.as_ref().filter(|o| !o.is_empty()) {
+                properties.insert(OPTION_FIELD.to_string(), json!({
+                    "type": "string",
+                    "title": "Option",
+                    "enum": options,
+                }));
+            }
+            properties.insert(FEEDBACK_FIELD.to_string(), json!({
+                "type": "string",
+                "title": "Feedback",
+            }));
+            (properties, Vec::new())
+        }
+        PopupKind::Confirm => {
+            let mut properties = Map::new();
+            properties.insert(CONFIRMED_FIELD.to_string(), json!({
+                "type": "boolean",
+                "title": "Confirm",
+                "default": false,
+            }));
+            (properties, vec![CONFIRMED_FIELD.to_string()])
+        }
+        PopupKind::Form => {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for field in &request.form_fields {
+                let mut schema = match field.kind {
+                    FormFieldKind::Text | FormFieldKind::Multiline => json!({ "type": "string" }),
+                    FormFieldKind::Number => json!({ "type": "number" }),
+                    FormFieldKind::Select => json!({ "type": "string", "enum": field.options }),
+                    FormFieldKind::Checkbox => json!({ "type": "boolean" }),
+                };
+                schema["title"] = json!(field.label.as_deref().unwrap_or(&field.name));
+                if let Some(description) = &field.description {
+                    schema["description"] = json!(description);
+                }
+                if let Some(default) = &field.default {
+                    schema["default"] = default.clone();
+                }
+                if field.required && field.kind != FormFieldKind::Checkbox {
+                    required.push(field.name.clone());
+                }
+                properties.insert(field.name.clone(), schema);
+            }
+            (properties, required)
+        }
+        // 文件选择需要本地文件系统，只能使用 GUI
+        PopupKind::SelectFile => return None,
+    };
+
+    Some(json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    }))
+}
+
+/// 将 elicitation 结果转换为弹窗响应
+///
+/// # Arguments
+/// * `content` - 用户接受时提交的内容；拒绝或取消时为 None
+pub fn to_response(request: &PopupRequest, content: Option<&Value>) -> PopupResponse {
+    let Some(content) = content else {
+        return PopupResponse::cancelled(&request.id);
+    };
+    let mut response = PopupResponse { cancelled: false, ..PopupResponse::cancelled(&request.id) };
+    let text = |field: &str| {
+        content
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    match request.kind {
+        PopupKind::Feedback => {
+            response.user_input = text(FEEDBACK_FIELD);
+            response.selected_options = text(OPTION_FIELD).into_iter().collect();
+        }
+        PopupKind::Confirm => {
+            response.confirmed = Some(content.get(CONFIRMED_FIELD).and_then(Value::as_bool).unwrap_or(false));
+        }
+        PopupKind::Form => {
+            response.form_values = content.as_object().cloned();
+        }
+        PopupKind::SelectFile => return PopupResponse::cancelled(&request.id),
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::FormField;
+
+    #[test]
+    fn test_feedback_round_trip() {
+        let request = PopupRequest::new(
+            Some("Which database?".to_string()),
+            None,
+            Some(vec!["Postgres".to_string(), "SQLite".to_string()]),
+        );
+        let schema = requested_schema(&request).unwrap();
+        assert_eq!(schema["properties"]["selected_option"]["enum"], json!(["Postgres", "SQLite"]));
+        assert_eq!(message(&request), "Which database?");
+
+        let response = to_response(&request, Some(&json!({ "selected_option": "SQLite", "feedback": " keep it small " })));
+        assert!(!response.cancelled);
+        assert_eq!(response.selected_options, vec!["SQLite"]);
+        assert_eq!(response.user_input.as_deref(), Some("keep it small"));
+
+        assert!(to_response(&request, None).cancelled);
+    }
+
+    #[test]
+    fn test_form_schema() {
+        let fields: Vec<FormField> = serde_json::from_value(json!([
+            { "name": "port", "kind": "number", "required": true },
+            { "name": "env", "label": "Environment", "kind": "select", "options": ["dev", "prod"] },
+            { "name": "overwrite", "kind": "checkbox", "required": true },
+        ]))
+        .unwrap();
+        let request = PopupRequest::form(None, Some("Deploy settings".to_string()), fields);
+
+        let schema = requested_schema(&request).unwrap();
+        assert_eq!(schema["properties"]["port"]["type"], "number");
+        assert_eq!(schema["properties"]["env"]["title"], "Environment");
+        assert_eq!(schema["required"], json!(["port"]));
+
+        let select_file = PopupRequest::select_file(None, None, Default::default());
+        assert!(requested_schema(&select_file).is_none());
+    }
+}
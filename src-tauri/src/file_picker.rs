@@ -0,0 +1,71 @@
+//! 文件选择模式
+//! 
+//! MCP 请求类型为 `select_file` 时，GUI 进程不显示反馈窗口，
+//! 直接弹出原生文件/目录选择器，将结果发送给 MCP Server 后退出。
+
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, FilePath};
+
+use crate::ipc_crypto::FileKey;
+use crate::popup::{deliver_response, get_response_file_path, FileReferenceData, PopupRequest, PopupResponse};
+
+/// 弹出原生文件选择器，用户完成选择后写入响应并退出应用
+pub fn run_file_picker(app_handle: &AppHandle, request: PopupRequest) {
+    let options = request.file_picker.clone().unwrap_or_default();
+    log::info!("[file_picker] 打开文件选择器: {:?}", options);
+    
+    let mut dialog = app_handle.dialog().file();
+    if let Some(ref title) = request.title {
+        dialog = dialog.set_title(title);
+    }
+    if let Some(ref default_path) = options.default_path {
+        dialog = dialog.set_directory(default_path);
+    }
+    if !options.directory && !options.extensions.is_empty() {
+        let extensions: Vec<&str> = options.extensions.iter().map(|e| e.as_str()).collect();
+        dialog = dialog.add_filter("Files", &extensions);
+    }
+    
+    let app_handle = app_handle.clone();
+    let finish = move |paths: Vec<FilePath>| {
+        let paths: Vec<PathBuf> = paths.into_iter()
+            .filter_map(|p| p.into_path().ok())
+            .collect();
+        tauri::async_runtime::spawn(async move {
+            write_picker_response(&request, paths).await;
+            app_handle.exit(0);
+        });
+    };
+    
+    match (options.directory, options.multiple) {
+        (true, true) => dialog.pick_folders(move |paths| finish(paths.unwrap_or_default())),
+        (true, false) => dialog.pick_folder(move |path| finish(path.into_iter().collect())),
+        (false, true) => dialog.pick_files(move |paths| finish(paths.unwrap_or_default())),
+        (false, false) => dialog.pick_file(move |path| finish(path.into_iter().collect())),
+    }
+}
+
+/// 发送文件选择结果
+async fn write_picker_response(request: &PopupRequest, paths: Vec<PathBuf>) {
+    let mut response = PopupResponse::cancelled(&request.id);
+    if !paths.is_empty() {
+        response.cancelled = false;
+        response.file_references = paths.iter()
+            .map(|path| FileReferenceData {
+                display_name: path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string()),
+                path: path.display().to_string(),
+                is_directory: path.is_dir(),
+            })
+            .collect();
+    }
+    
+    let response_path = get_response_file_path(&request.id);
+    let key = request.file_key.as_deref().and_then(|key| FileKey::decode(key).ok());
+    match deliver_response(request.response_endpoint.as_deref(), &response_path, &response, key.as_ref()).await {
+        Ok(()) => log::info!("[file_picker] 已选择 {} 个路径", response.file_references.len()),
+        Err(e) => log::error!("[file_picker] 发送响应失败: {}", e),
+    }
+}
@@ -0,0 +1,197 @@
+//! 同时运行的 GUI 进程数量上限
+//!
+//! 每个 Agent 会话各自运行一个 MCP Server 进程，同时提问时会各自启动 GUI，屏幕上堆满弹窗。
+//! 这里用本机范围的槽位限制同时存在的独立弹窗进程数：每个槽位是数据目录下的一个本地 socket
+//! （Windows 为命名管道），启动 GUI 的服务端进程在 GUI 运行期间监听它，进程退出后自动释放。
+//! Unix 上占用槽位前先取得槽位锁文件的独占锁（flock），避免两个进程同时占用同一槽位。
+//!
+//! 槽位已满时按配置排队等待空闲槽位，或在调用开始时返回“忙，稍后重试”的结构化错误
+//! （检查和启动之间被其他进程占用时仍会排队）。常驻模式只有一个窗口，不受限制。
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::types::GuiLimitConfig;
+
+/// 等待空闲槽位时的检查间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 占用的槽位，释放时其他进程可以使用
+pub struct GuiSlot {
+    _holder: imp::Holder,
+}
+
+/// 槽位是否已全部占用（未设置上限时为 false）
+pub fn is_full(config: &GuiLimitConfig) -> bool {
+    config.max_concurrent > 0 && running(config) >= config.max_concurrent
+}
+
+/// 正在运行的独立 GUI 进程数（只统计到上限为止）
+pub fn running(config: &GuiLimitConfig) -> u32 {
+    let Some(dir) = slot_dir() else { return 0 };
+    (0..config.max_concurrent).filter(|&index| imp::occupied(&dir, index)).count() as u32
+}
+
+/// 占用一个空闲槽位，已满时等待；未设置上限时返回 None
+pub async fn acquire(config: &GuiLimitConfig) -> Option<GuiSlot> {
+    if config.max_concurrent == 0 {
+        return None;
+    }
+    let Some(dir) = slot_dir() else {
+        log::warn!("[gui_limit] 无法确定数据目录，不限制 GUI 数量");
+        return None;
+    };
+    let mut logged = false;
+    loop {
+        if let Some(slot) = try_acquire_in(&dir, config.max_concurrent) {
+            return Some(slot);
+        }
+        if !logged {
+            log::info!("[gui_limit] 已有 {} 个弹窗在运行，等待其中一个关闭", config.max_concurrent);
+            logged = true;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn try_acquire_in(dir: &std::path::Path, max_concurrent: u32) -> Option<GuiSlot> {
+    (0..max_concurrent).find_map(|index| imp::claim(dir, index)).map(|holder| GuiSlot { _holder: holder })
+}
+
+fn slot_dir() -> Option<PathBuf> {
+    let dir = crate::config::get_default_data_dir().ok()?.join("gui_slots");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::path::{Path, PathBuf};
+
+    pub struct Holder {
+        path: PathBuf,
+        task: tokio::task::JoinHandle<()>,
+        /// 槽位锁文件上的独占锁，在 socket 文件删除后才随 Holder 释放
+        _lock: std::fs::File,
+    }
+
+    fn slot_path(dir: &Path, index: u32) -> PathBuf {
+        dir.join(format!("slot_{}.sock", index))
+    }
+
+    fn lock_path(dir: &Path, index: u32) -> PathBuf {
+        dir.join(format!("slot_{}.lock", index))
+    }
+
+    pub fn occupied(dir: &Path, index: u32) -> bool {
+        std::os::unix::net::UnixStream::connect(slot_path(dir, index)).is_ok()
+    }
+
+    pub fn claim(dir: &Path, index: u32) -> Option<Holder> {
+        // 先取得槽位的独占锁，多个进程同时占用同一槽位时只有一个成功；
+        // 锁随进程退出自动释放，持有锁后残留的 socket 文件一定属于已退出的进程
+        let lock = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(lock_path(dir, index))
+            .ok()?;
+        lock.try_lock().ok()?;
+        let path = slot_path(dir, index);
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).ok()?;
+        // 及时接受并关闭检查连接，避免积压队列写满后误判为空闲
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                drop(stream);
+            }
+        });
+        Some(Holder { path, task, _lock: lock })
+    }
+
+    impl Drop for Holder {
+        fn drop(&mut self) {
+            self.task.abort();
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::path::Path;
+    use tokio::net::windows::named_pipe::{ClientOptions, ServerOptions};
+
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    pub struct Holder {
+        task: tokio::task::JoinHandle<()>,
+    }
+
+    fn pipe_name(index: u32) -> String {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        format!(r"\\.\pipe\whale_gui_slot_{}_{}", user, index)
+    }
+
+    pub fn occupied(_dir: &Path, index: u32) -> bool {
+        match ClientOptions::new().open(pipe_name(index)) {
+            Ok(_) => true,
+            Err(e) => e.raw_os_error() == Some(ERROR_PIPE_BUSY),
+        }
+    }
+
+    pub fn claim(_dir: &Path, index: u32) -> Option<Holder> {
+        let name = pipe_name(index);
+        // 槽位已被占用时 first_pipe_instance 创建失败
+        let mut server = ServerOptions::new().first_pipe_instance(true).create(&name).ok()?;
+        let task = tokio::spawn(async move {
+            // 检查连接占用管道实例后创建新实例，保持槽位可被检测到
+            while server.connect().await.is_ok() {
+                match ServerOptions::new().create(&name) {
+                    Ok(next) => server = next,
+                    Err(_) => break,
+                }
+            }
+        });
+        Some(Holder { task })
+    }
+
+    impl Drop for Holder {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slots_are_limited_and_released() {
+        let dir = std::env::temp_dir().join(format!("whale_gui_slots_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let first = try_acquire_in(&dir, 2).unwrap();
+        let second = try_acquire_in(&dir, 2).unwrap();
+        assert!(try_acquire_in(&dir, 2).is_none());
+        assert!(imp::occupied(&dir, 0) && imp::occupied(&dir, 1));
+
+        drop(first);
+        assert!(!imp::occupied(&dir, 0));
+        let third = try_acquire_in(&dir, 2).unwrap();
+        drop((second, third));
+
+        // 持有槽位锁时，即使 socket 文件被删除也不能再占用该槽位
+        let held = try_acquire_in(&dir, 1).unwrap();
+        std::fs::remove_file(dir.join("slot_0.sock")).unwrap();
+        assert!(try_acquire_in(&dir, 1).is_none());
+        drop(held);
+
+        // 残留的 socket 文件不占用槽位
+        std::fs::write(dir.join("slot_0.sock"), b"").unwrap();
+        assert!(!imp::occupied(&dir, 0));
+        assert!(try_acquire_in(&dir, 1).is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,13 @@
 //! 图片处理模块
 //!
 //! 提供图片缩放、压缩、Base64 编解码等功能。
+//!
+//! JPEG 和 PNG 编码器可在配置中选择：纯 Rust 实现之外，可通过 cargo feature
+//! `mozjpeg` / `turbojpeg` 启用 SIMD 加速的 JPEG 编码器；PNG 的快速压缩使用 fdeflate，
+//! 适合 4K 截图等编码耗时明显的场景。未编译所选后端时回退到纯 Rust 实现。
+//!
+//! 截图附件可转换为 WebP（无损）或 AVIF（需要 `avif` feature）以减小响应体积，
+//! 不支持这些格式的客户端仍收到 PNG。
 //! 
 //! # Requirements
 //! - 3.6: 图片缩放保持宽高比
@@ -8,10 +15,123 @@
 //! - 3.8: Base64 编解码
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use image::{DynamicImage, GenericImageView};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, GenericImageView, ImageEncoder, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
 use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use thiserror::Error;
 
+/// 是否已提示过所选 JPEG 编码器未编译（只提示一次）
+static JPEG_FALLBACK_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// JPEG 编码后端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum JpegBackend {
+    /// image crate 的纯 Rust 编码器
+    #[default]
+    Image,
+    /// mozjpeg（需要 `mozjpeg` feature），同等质量下文件更小
+    Mozjpeg,
+    /// libjpeg-turbo（需要 `turbojpeg` feature），编码速度最快
+    Turbojpeg,
+}
+
+impl JpegBackend {
+    /// 当前构建是否包含该编码器
+    pub fn is_available(self) -> bool {
+        match self {
+            JpegBackend::Image => true,
+            JpegBackend::Mozjpeg => cfg!(feature = "mozjpeg"),
+            JpegBackend::Turbojpeg => cfg!(feature = "turbojpeg"),
+        }
+    }
+
+    /// 实际使用的编码器：未编译所选编码器时回退到纯 Rust 实现
+    fn resolve(self) -> Self {
+        if self.is_available() {
+            return self;
+        }
+        if !JPEG_FALLBACK_LOGGED.swap(true, Ordering::Relaxed) {
+            log::warn!("[image_processor] 当前构建未包含 {:?} 编码器，使用纯 Rust 实现", self);
+        }
+        JpegBackend::Image
+    }
+}
+
+/// PNG 压缩级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum PngCompression {
+    /// 使用 fdeflate 快速压缩，文件稍大但编码快数倍
+    Fast,
+    /// 默认压缩
+    #[default]
+    Default,
+    /// 最高压缩率，编码最慢
+    Best,
+}
+
+/// 是否已提示过 AVIF 编码器未编译（只提示一次）
+static AVIF_FALLBACK_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// 截图附件的编码格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum AttachmentFormat {
+    /// PNG，所有客户端都支持
+    #[default]
+    Png,
+    /// 无损 WebP，UI 截图通常比 PNG 小 30%~50%
+    Webp,
+    /// AVIF（需要 `avif` feature），体积最小但编码较慢
+    Avif,
+}
+
+impl AttachmentFormat {
+    /// 当前构建是否包含该编码器
+    pub fn is_available(self) -> bool {
+        match self {
+            AttachmentFormat::Png | AttachmentFormat::Webp => true,
+            AttachmentFormat::Avif => cfg!(feature = "avif"),
+        }
+    }
+
+    /// 实际使用的格式：未编译 AVIF 编码器时回退到 WebP
+    pub fn resolve(self) -> Self {
+        if self.is_available() {
+            return self;
+        }
+        if !AVIF_FALLBACK_LOGGED.swap(true, Ordering::Relaxed) {
+            log::warn!("[image_processor] 当前构建未包含 AVIF 编码器，使用 WebP");
+        }
+        AttachmentFormat::Webp
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            AttachmentFormat::Png => "image/png",
+            AttachmentFormat::Webp => "image/webp",
+            AttachmentFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// 图片编码器设置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageEncoderConfig {
+    #[serde(default)]
+    pub jpeg: JpegBackend,
+    #[serde(default)]
+    pub png: PngCompression,
+    /// 截图附件格式（可在工作区设置中为不支持的客户端改回 PNG）
+    #[serde(default)]
+    pub attachment: AttachmentFormat,
+}
+
 /// 图片处理错误类型
 #[derive(Debug, Error)]
 pub enum ImageError {
@@ -28,6 +148,51 @@ pub enum ImageError {
     CompressionFailed,
 }
 
+/// 图片处理的度量信息
+///
+/// 界面据此显示压缩效果（如 “8.2 MB → 240 KB”），也便于发现处理流程的性能回退
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub struct ImageMetrics {
+    /// 原始宽度
+    pub original_width: u32,
+    /// 原始高度
+    pub original_height: u32,
+    /// 原始字节数（截图为未压缩的 RGBA 像素数据）
+    pub original_bytes: usize,
+    /// 输出大小与原始大小之比
+    pub compression_ratio: f64,
+    /// 使用的 JPEG 质量（无损编码时为 None）
+    pub quality: Option<u8>,
+    /// 处理耗时（毫秒）
+    #[ts(type = "number")]
+    pub elapsed_ms: u64,
+}
+
+impl ImageMetrics {
+    /// 根据原图信息和输出大小计算度量，耗时从 `started` 算起
+    pub fn new(
+        original_dimensions: (u32, u32),
+        original_bytes: usize,
+        output_bytes: usize,
+        quality: Option<u8>,
+        started: Instant,
+    ) -> Self {
+        let compression_ratio = if original_bytes == 0 {
+            1.0
+        } else {
+            output_bytes as f64 / original_bytes as f64
+        };
+        Self {
+            original_width: original_dimensions.0,
+            original_height: original_dimensions.1,
+            original_bytes,
+            compression_ratio,
+            quality,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
 /// 处理后的图片结果
 #[derive(Debug, Clone)]
 pub struct ProcessedImageResult {
@@ -37,6 +202,8 @@ pub struct ProcessedImageResult {
     pub width: u32,
     /// 高度
     pub height: u32,
+    /// 处理度量
+    pub metrics: ImageMetrics,
 }
 
 /// 图片处理器
@@ -105,9 +272,10 @@ impl ImageProcessor {
     /// * `img` - 要压缩的图片
     /// * `max_size_bytes` - 最大文件大小（字节）
     /// * `initial_quality` - 初始 JPEG 质量 (1-100)
+    /// * `backend` - JPEG 编码器
     ///
     /// # Returns
-    /// * `Ok(Vec<u8>)` - 压缩后的 JPEG 数据
+    /// * `Ok((Vec<u8>, u8))` - 压缩后的 JPEG 数据和最终使用的质量
     /// * `Err(ImageError)` - 压缩失败
     ///
     /// # Property 5: Image Size Constraint
@@ -116,36 +284,48 @@ impl ImageProcessor {
         img: &DynamicImage,
         max_size_bytes: usize,
         initial_quality: u8,
-    ) -> Result<Vec<u8>, ImageError> {
+        backend: JpegBackend,
+    ) -> Result<(Vec<u8>, u8), ImageError> {
         let mut quality = initial_quality.min(100);
+        let backend = backend.resolve();
+        // 转换为 RGB8 格式以确保 JPEG 编码兼容性（只转换一次，多次尝试不同质量）
+        let rgb_img = img.to_rgb8();
         
         loop {
-            let jpeg_data = Self::encode_jpeg(img, quality)?;
+            let jpeg_data = Self::encode_jpeg_rgb(&rgb_img, quality, backend)?;
             
             if jpeg_data.len() <= max_size_bytes || quality <= Self::MIN_QUALITY {
-                return Ok(jpeg_data);
+                return Ok((jpeg_data, quality));
             }
             
             quality = quality.saturating_sub(10);
         }
     }
 
-    /// 将图片编码为 JPEG 格式
+    /// 使用指定编码器将 RGB 图片编码为 JPEG
     ///
     /// # Arguments
-    /// * `img` - 要编码的图片
+    /// * `rgb_img` - 要编码的图片
     /// * `quality` - JPEG 质量 (1-100)
+    /// * `backend` - 编码器（需已确认当前构建包含）
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - JPEG 数据
     /// * `Err(ImageError)` - 编码失败
-    fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, ImageError> {
+    fn encode_jpeg_rgb(rgb_img: &RgbImage, quality: u8, backend: JpegBackend) -> Result<Vec<u8>, ImageError> {
+        // 未启用加速编码器的构建中只有默认分支
+        #[allow(clippy::match_single_binding)]
+        match backend {
+            #[cfg(feature = "mozjpeg")]
+            JpegBackend::Mozjpeg => return encode_mozjpeg(rgb_img, quality),
+            #[cfg(feature = "turbojpeg")]
+            JpegBackend::Turbojpeg => return encode_turbojpeg(rgb_img, quality),
+            _ => {}
+        }
+        
         let mut buffer = Vec::new();
         let mut cursor = Cursor::new(&mut buffer);
         
-        // 转换为 RGB8 格式以确保 JPEG 编码兼容性
-        let rgb_img = img.to_rgb8();
-        
         let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, quality);
         rgb_img
             .write_with_encoder(encoder)
@@ -154,6 +334,75 @@ impl ImageProcessor {
         Ok(buffer)
     }
 
+    /// 将 RGBA 图片编码为 PNG
+    ///
+    /// # Arguments
+    /// * `img` - 要编码的图片
+    /// * `compression` - 压缩级别
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - PNG 数据
+    /// * `Err(ImageError)` - 编码失败
+    pub fn encode_png(img: &RgbaImage, compression: PngCompression) -> Result<Vec<u8>, ImageError> {
+        let (compression, filter) = match compression {
+            // fdeflate 的快速模式配合 Up 滤波速度最好
+            PngCompression::Fast => (CompressionType::Fast, FilterType::Up),
+            PngCompression::Default => (CompressionType::Default, FilterType::Adaptive),
+            PngCompression::Best => (CompressionType::Best, FilterType::Adaptive),
+        };
+        
+        let mut buffer = Vec::new();
+        PngEncoder::new_with_quality(&mut buffer, compression, filter)
+            .write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgba8)
+            .map_err(|e| ImageError::EncodeError(e.to_string()))?;
+        
+        Ok(buffer)
+    }
+
+    /// 按附件格式编码图片
+    ///
+    /// # Arguments
+    /// * `img` - 要编码的图片
+    /// * `format` - 附件格式（未编译的格式按 [`AttachmentFormat::resolve`] 回退）
+    /// * `png` - 编码为 PNG 时的压缩级别
+    ///
+    /// # Returns
+    /// * `Ok((Vec<u8>, AttachmentFormat))` - 编码后的数据和实际使用的格式
+    /// * `Err(ImageError)` - 编码失败
+    pub fn encode_attachment(
+        img: &RgbaImage,
+        format: AttachmentFormat,
+        png: PngCompression,
+    ) -> Result<(Vec<u8>, AttachmentFormat), ImageError> {
+        let format = format.resolve();
+        let data = match format {
+            AttachmentFormat::Png => Self::encode_png(img, png)?,
+            #[cfg(feature = "avif")]
+            AttachmentFormat::Avif => encode_avif(img)?,
+            // WebP（未编译 AVIF 时 resolve 已回退到 WebP）
+            _ => {
+                let mut buffer = Vec::new();
+                image::codecs::webp::WebPEncoder::new_lossless(&mut buffer)
+                    .write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgba8)
+                    .map_err(|e| ImageError::EncodeError(e.to_string()))?;
+                buffer
+            }
+        };
+        Ok((data, format))
+    }
+
+    /// 将 PNG 截图转换为指定的附件格式
+    ///
+    /// 结果不比原图小时返回 `None`，调用方保留原来的 PNG
+    pub fn transcode_png(data: &[u8], format: AttachmentFormat) -> Result<Option<(Vec<u8>, AttachmentFormat)>, ImageError> {
+        if format == AttachmentFormat::Png {
+            return Ok(None);
+        }
+        let img = Self::load_from_bytes(data)?.to_rgba8();
+        let (encoded, format) = Self::encode_attachment(&img, format, PngCompression::Default)?;
+        Ok((encoded.len() < data.len()).then_some((encoded, format)))
+    }
+
     /// 将字节数据编码为 Base64 字符串
     ///
     /// # Arguments
@@ -192,6 +441,7 @@ impl ImageProcessor {
     /// * `data` - 原始图片字节数据
     /// * `max_dimension` - 最大边长
     /// * `max_file_size` - 最大文件大小（字节）
+    /// * `backend` - JPEG 编码器
     ///
     /// # Returns
     /// * `Ok(ProcessedImageResult)` - 处理结果
@@ -200,21 +450,27 @@ impl ImageProcessor {
         data: &[u8],
         max_dimension: u32,
         max_file_size: usize,
+        backend: JpegBackend,
     ) -> Result<ProcessedImageResult, ImageError> {
+        let started = Instant::now();
+        
         // 1. 加载图片
         let img = Self::load_from_bytes(data)?;
+        let original_dimensions = img.dimensions();
         
         // 2. 缩放
         let resized = Self::resize(img, max_dimension);
         let (width, height) = resized.dimensions();
         
         // 3. 压缩为 JPEG
-        let jpeg_data = Self::compress_to_jpeg(&resized, max_file_size, Self::DEFAULT_INITIAL_QUALITY)?;
+        let (jpeg_data, quality) = Self::compress_to_jpeg(&resized, max_file_size, Self::DEFAULT_INITIAL_QUALITY, backend)?;
+        let metrics = ImageMetrics::new(original_dimensions, data.len(), jpeg_data.len(), Some(quality), started);
         
         Ok(ProcessedImageResult {
             data: jpeg_data,
             width,
             height,
+            metrics,
         })
     }
 
@@ -222,15 +478,58 @@ impl ImageProcessor {
     ///
     /// # Arguments
     /// * `data` - 原始图片字节数据
+    /// * `backend` - JPEG 编码器
     ///
     /// # Returns
     /// * `Ok(ProcessedImageResult)` - 处理结果
     /// * `Err(ImageError)` - 处理失败
-    pub fn process_with_defaults(data: &[u8]) -> Result<ProcessedImageResult, ImageError> {
-        Self::process(data, Self::DEFAULT_MAX_SIZE, Self::DEFAULT_MAX_FILE_SIZE)
+    pub fn process_with_defaults(data: &[u8], backend: JpegBackend) -> Result<ProcessedImageResult, ImageError> {
+        Self::process(data, Self::DEFAULT_MAX_SIZE, Self::DEFAULT_MAX_FILE_SIZE, backend)
     }
 }
 
+/// 使用 ravif 编码 AVIF
+#[cfg(feature = "avif")]
+fn encode_avif(img: &RgbaImage) -> Result<Vec<u8>, ImageError> {
+    let mut buffer = Vec::new();
+    image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut buffer, 8, 80)
+        .write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgba8)
+        .map_err(|e| ImageError::EncodeError(e.to_string()))?;
+    Ok(buffer)
+}
+
+/// 使用 mozjpeg 编码
+#[cfg(feature = "mozjpeg")]
+fn encode_mozjpeg(rgb_img: &RgbImage, quality: u8) -> Result<Vec<u8>, ImageError> {
+    // mozjpeg 通过 panic 报告 libjpeg 错误
+    std::panic::catch_unwind(|| -> std::io::Result<Vec<u8>> {
+        let mut compress = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+        compress.set_size(rgb_img.width() as usize, rgb_img.height() as usize);
+        compress.set_quality(f32::from(quality));
+        let mut started = compress.start_compress(Vec::new())?;
+        started.write_scanlines(rgb_img.as_raw())?;
+        started.finish()
+    })
+    .map_err(|_| ImageError::EncodeError("mozjpeg 编码失败".to_string()))?
+    .map_err(|e| ImageError::EncodeError(e.to_string()))
+}
+
+/// 使用 libjpeg-turbo 编码
+#[cfg(feature = "turbojpeg")]
+fn encode_turbojpeg(rgb_img: &RgbImage, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let width = rgb_img.width() as usize;
+    let image = turbojpeg::Image {
+        pixels: rgb_img.as_raw().as_slice(),
+        width,
+        pitch: width * 3,
+        height: rgb_img.height() as usize,
+        format: turbojpeg::PixelFormat::RGB,
+    };
+    turbojpeg::compress(image, i32::from(quality), turbojpeg::Subsamp::Sub2x2)
+        .map(|buf| buf.to_vec())
+        .map_err(|e| ImageError::EncodeError(e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,12 +565,60 @@ mod tests {
     #[test]
     fn test_encode_jpeg() {
         let img = create_test_image(100, 100);
-        let jpeg_data = ImageProcessor::encode_jpeg(&img, 85).unwrap();
+        let jpeg_data = ImageProcessor::encode_jpeg_rgb(&img.to_rgb8(), 85, JpegBackend::Image).unwrap();
         assert!(!jpeg_data.is_empty());
         // JPEG 文件以 0xFF 0xD8 开头
         assert_eq!(jpeg_data[0], 0xFF);
         assert_eq!(jpeg_data[1], 0xD8);
     }
+
+    #[test]
+    fn test_process_metrics() {
+        let png_data = ImageProcessor::encode_png(&create_test_image(1024, 512).to_rgba8(), PngCompression::Fast).unwrap();
+        let result = ImageProcessor::process_with_defaults(&png_data, JpegBackend::Image).unwrap();
+        let metrics = &result.metrics;
+        assert_eq!((metrics.original_width, metrics.original_height), (1024, 512));
+        assert_eq!(metrics.original_bytes, png_data.len());
+        assert_eq!(metrics.quality, Some(ImageProcessor::DEFAULT_INITIAL_QUALITY));
+        let expected_ratio = result.data.len() as f64 / png_data.len() as f64;
+        assert!((metrics.compression_ratio - expected_ratio).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_encode_png_compression_levels() {
+        let img = create_test_image(64, 64).to_rgba8();
+        for compression in [PngCompression::Fast, PngCompression::Default, PngCompression::Best] {
+            let png_data = ImageProcessor::encode_png(&img, compression).unwrap();
+            // PNG 文件签名
+            assert_eq!(&png_data[..4], b"\x89PNG");
+            let decoded = ImageProcessor::load_from_bytes(&png_data).unwrap();
+            assert_eq!(decoded.dimensions(), (64, 64));
+        }
+    }
+
+    #[test]
+    fn test_attachment_format_encoding() {
+        let img = create_test_image(256, 256).to_rgba8();
+        let png_data = ImageProcessor::encode_png(&img, PngCompression::Default).unwrap();
+        assert_eq!(ImageProcessor::transcode_png(&png_data, AttachmentFormat::Png).unwrap(), None);
+
+        let (webp, format) = ImageProcessor::encode_attachment(&img, AttachmentFormat::Webp, PngCompression::Default).unwrap();
+        assert_eq!(format, AttachmentFormat::Webp);
+        assert_eq!(&webp[8..12], b"WEBP");
+        assert_eq!(ImageProcessor::load_from_bytes(&webp).unwrap().dimensions(), (256, 256));
+
+        let expected = if AttachmentFormat::Avif.is_available() { AttachmentFormat::Avif } else { AttachmentFormat::Webp };
+        assert_eq!(AttachmentFormat::Avif.resolve(), expected);
+    }
+
+    #[test]
+    fn test_jpeg_backend_fallback() {
+        assert_eq!(JpegBackend::Image.resolve(), JpegBackend::Image);
+        for backend in [JpegBackend::Mozjpeg, JpegBackend::Turbojpeg] {
+            let expected = if backend.is_available() { backend } else { JpegBackend::Image };
+            assert_eq!(backend.resolve(), expected);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -354,11 +701,11 @@ mod property_tests {
         ) {
             let img = create_test_image(width, height);
             
-            let result = ImageProcessor::compress_to_jpeg(&img, max_size_bytes, 85);
+            let result = ImageProcessor::compress_to_jpeg(&img, max_size_bytes, 85, JpegBackend::Image);
             
             prop_assert!(result.is_ok(), "Compression should succeed");
             
-            let jpeg_data = result.unwrap();
+            let (jpeg_data, _quality) = result.unwrap();
             prop_assert!(
                 jpeg_data.len() <= max_size_bytes,
                 "JPEG size {} exceeds max_size_bytes {}",
@@ -0,0 +1,144 @@
+//! MCP 请求/响应临时文件加密
+//!
+//! 请求文件和响应文件位于系统临时目录，包含反馈文本和 Base64 截图。
+//! MCP Server 为每个请求生成一个密钥：独立弹窗通过环境变量 [`FILE_KEY_ENV`] 获得，
+//! 多轮会话和常驻 GUI 通过 stdin 管道/本地 socket 中的请求获得。
+//! 文件内容使用 ChaCha20-Poly1305 加密，文件本身只允许当前用户读写。
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// 传递给 GUI 子进程的密钥环境变量（Base64）
+pub const FILE_KEY_ENV: &str = "WHALE_IPC_KEY";
+
+/// 加密内容的前缀，后接 Base64 编码的 nonce 和密文
+const SEALED_PREFIX: &str = "whale-sealed:v1:";
+
+/// nonce 长度（字节）
+const NONCE_LEN: usize = 12;
+
+/// 单个请求的临时文件密钥
+#[derive(Clone)]
+pub struct FileKey(Key);
+
+impl std::fmt::Debug for FileKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FileKey(..)")
+    }
+}
+
+impl FileKey {
+    /// 生成随机密钥
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    /// 读取 MCP Server 通过环境变量传入的密钥（GUI 使用）
+    pub fn from_env() -> Option<Self> {
+        let encoded = std::env::var(FILE_KEY_ENV).ok()?;
+        Self::decode(&encoded)
+            .inspect_err(|e| log::warn!("[ipc_crypto] 环境变量中的密钥无效: {}", e))
+            .ok()
+    }
+
+    /// Base64 编码
+    pub fn encode(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// 从 Base64 解码
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD.decode(encoded.trim())?;
+        if bytes.len() != 32 {
+            return Err(anyhow!("密钥长度无效: {} 字节", bytes.len()));
+        }
+        Ok(Self(*Key::from_slice(&bytes)))
+    }
+
+    /// 加密文本
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.0)
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("加密失败"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", SEALED_PREFIX, STANDARD.encode(sealed)))
+    }
+}
+
+/// 按需加密：没有密钥时原样返回
+pub fn seal(key: Option<&FileKey>, plaintext: &str) -> Result<String> {
+    match key {
+        Some(key) => key.seal(plaintext),
+        None => Ok(plaintext.to_string()),
+    }
+}
+
+/// 解密文件内容；未加密的内容原样返回
+pub fn open(key: Option<&FileKey>, content: &str) -> Result<String> {
+    let Some(encoded) = content.strip_prefix(SEALED_PREFIX) else {
+        return Ok(content.to_string());
+    };
+    let key = key.ok_or_else(|| anyhow!("内容已加密但没有密钥"))?;
+    let sealed = STANDARD.decode(encoded.trim())?;
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("加密内容不完整"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(&key.0)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("解密失败：密钥不匹配或内容被修改"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// 创建只有当前用户可读写的文件（已存在时截断）
+pub async fn create_private(path: &Path) -> std::io::Result<tokio::fs::File> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let key = FileKey::generate();
+        let sealed = key.seal("{\"user_input\":\"你好\"}").unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("user_input"));
+        assert_eq!(open(Some(&key), &sealed).unwrap(), "{\"user_input\":\"你好\"}");
+
+        let decoded = FileKey::decode(&key.encode()).unwrap();
+        assert_eq!(open(Some(&decoded), &sealed).unwrap(), "{\"user_input\":\"你好\"}");
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_key() {
+        let sealed = FileKey::generate().seal("secret").unwrap();
+        assert!(open(Some(&FileKey::generate()), &sealed).is_err());
+        assert!(open(None, &sealed).is_err());
+        // 未加密的内容原样返回
+        assert_eq!(open(None, "{}").unwrap(), "{}");
+        assert_eq!(seal(None, "{}").unwrap(), "{}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_create_private_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("request.json");
+        drop(create_private(&path).await.unwrap());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,88 @@
+//! 后台清理任务
+//!
+//! 启动时和之后每天运行一次：
+//! - 崩溃残留的 MCP 请求/响应临时文件（按 `temp_file_max_age_hours`）
+//! - 按保留策略清理历史记录、报告、截图、日志和归档（见 [`crate::retention`]）
+//! - 不再被历史记录引用的附件
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::attachments;
+use crate::popup;
+use crate::retention::{self, RetentionReport};
+use crate::storage::Storage;
+use crate::types::AppConfig;
+
+/// 清理间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 未被引用的附件至少保留多久（刚截取、尚未提交的附件还没有出现在历史记录中）
+const ORPHAN_ATTACHMENT_MIN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 清理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    pub temp_files_removed: usize,
+    pub attachments_removed: usize,
+    pub retention: RetentionReport,
+    /// 释放的磁盘空间（字节）
+    pub bytes_freed: u64,
+}
+
+/// 执行一次清理
+pub async fn run_cleanup(app_config: &AppConfig) -> CleanupReport {
+    let mut report = CleanupReport::default();
+
+    if app_config.temp_file_max_age_hours > 0 {
+        let max_age = Duration::from_secs(app_config.temp_file_max_age_hours * 3600);
+        let (files, bytes) = popup::sweep_orphaned_temp_files(max_age).await;
+        report.temp_files_removed = files;
+        report.bytes_freed += bytes;
+    }
+
+    // 先按保留策略删除历史记录，再清理因此不再被引用的附件
+    let config = app_config.clone();
+    let (retention, attachments) = tokio::task::spawn_blocking(move || {
+        let retention = retention::apply_retention(&config);
+        let attachments = match Storage::open_default().and_then(|storage| storage.referenced_attachment_ids()) {
+            Ok(referenced) => attachments::store().sweep_orphans(&referenced, ORPHAN_ATTACHMENT_MIN_AGE),
+            Err(e) => {
+                // 无法确定哪些附件仍被引用时不删除任何附件
+                log::warn!("[janitor] 读取历史记录失败，跳过附件清理: {}", e);
+                (0, 0)
+            }
+        };
+        (retention, attachments)
+    })
+    .await
+    .unwrap_or_default();
+
+    report.bytes_freed += retention.bytes_freed + attachments.1;
+    report.attachments_removed = attachments.0;
+    report.retention = retention;
+    log::info!(
+        "[janitor] 清理完成：临时文件 {} 个，附件 {} 个，历史记录 {} 条，其他文件 {} 个，共释放 {} 字节",
+        report.temp_files_removed,
+        report.attachments_removed,
+        report.retention.sessions_removed,
+        report.retention.files_removed,
+        report.bytes_freed
+    );
+    report
+}
+
+/// 立即清理一次，之后每天清理一次（每次重新读取配置）
+pub async fn run_daily<F, Fut>(load_config: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = AppConfig>,
+{
+    let mut ticker = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        ticker.tick().await;
+        run_cleanup(&load_config().await).await;
+    }
+}
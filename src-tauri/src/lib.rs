@@ -1,28 +1,69 @@
+mod annotation;
+mod answer_macro;
 mod api_keys;
+mod archive;
+mod attachments;
+mod availability;
 mod audio;
+mod audit;
+mod color_profile;
 mod config;
 mod commands;
+mod daemon;
+mod diagnostics;
+mod elicitation;
+mod file_picker;
+mod gui_limit;
 mod image_processor;
+mod ipc_crypto;
+mod janitor;
 pub mod llm;
 pub mod mcp_server;
+pub mod mcp_logging;
+mod mcp_tools;
+mod notification;
+mod option_packs;
+mod pii;
 pub mod popup;
+mod popup_queue;
+mod popup_session;
+mod popup_updates;
+mod rate_limit;
+mod repeat_answer;
+mod response_lint;
+mod result_language;
+mod result_template;
+mod report;
+mod retention;
+mod sampling;
 mod screenshot;
+mod session;
+mod sla;
+mod storage;
+mod text_metrics;
 mod types;
+mod window_geometry;
+mod window_guard;
+mod workspace;
 
-use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri::{Manager, UserAttentionType, WebviewUrl, WebviewWindowBuilder};
+
+use popup::{PopupKind, Priority};
+use types::FocusPolicy;
 
 pub use api_keys::{ApiKeyManager, ApiKeyError, ApiProvider};
 pub use audio::{AudioNotifier, AudioError};
 pub use config::load_config_direct;
 pub use image_processor::ImageProcessor;
 pub use mcp_server::{
-    McpServer, InteractiveFeedbackParams, OptimizeUserInputParams,
-    OptimizeResult, PopupResponse,
-    run_mcp_server,
+    McpServer, InteractiveFeedbackParams, PredefinedOptionParam, RespondBy, OptimizeUserInputParams, ConfirmParams,
+    ConfirmResult, CollectFormParams, CollectFormResult, NotifyParams, SelectFileParams, SelectFileResult, SelectedFile, OptimizeResult, PopupResponse,
+    FeedbackResult, CancelReason, ImageRef, FileRef,
+    run_mcp_server, run_mcp_server_with, McpTransport,
     validate_interactive_feedback_params, validate_optimize_user_input_params,
 };
-pub use popup::PopupRequest;
-pub use screenshot::{ScreenshotManager, ScreenshotRegion, ScreenshotResult, MonitorInfo};
+pub use popup::{PopupRequest, PopupKind, OptionDetail};
+pub use screenshot::{ScreenshotManager, ScreenshotRegion, ScreenshotResult, MonitorInfo, SnapTarget};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -32,6 +73,8 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_screenshots::init())
+        .manage(window_guard::HiddenWindowWatchdog::default())
+        .manage(retention::PurgeGuard::default())
         .invoke_handler(tauri::generate_handler![
             commands::get_config,
             commands::save_config,
@@ -43,12 +86,27 @@ pub fn run() {
             commands::get_builtin_sounds,
             commands::get_canned_responses,
             commands::save_canned_responses,
+            // 优化类型管理命令
+            commands::get_visible_optimization_types,
+            commands::set_system_optimization_types_visible,
+            commands::set_optimization_type_pinned,
+            commands::set_optimization_type_order,
+            commands::validate_optimization_type,
+            commands::save_optimization_type,
             // API 密钥管理命令
             commands::save_api_key,
             commands::get_api_key,
             commands::delete_api_key,
             commands::has_api_key,
             commands::get_configured_providers,
+            commands::save_custom_provider,
+            commands::delete_custom_provider,
+            commands::get_custom_providers,
+            commands::save_azure_openai_config,
+            commands::get_azure_openai_config,
+            commands::save_ollama_config,
+            commands::get_ollama_config,
+            commands::list_ollama_models,
             commands::get_masked_api_key,
             commands::set_api_test_status,
             commands::get_api_test_status,
@@ -56,21 +114,52 @@ pub fn run() {
             commands::get_provider_order,
             // 截图功能命令
             commands::get_monitors,
+            commands::set_preferred_monitor,
+            commands::get_cursor_position,
             commands::capture_full_screen,
+            commands::capture_all_screens,
             commands::capture_region,
+            commands::get_snap_targets,
             commands::capture_screen_hidden,
             commands::show_window,
             commands::crop_screenshot,
+            commands::capture_and_attach,
+            commands::capture_context,
+            commands::get_attachment,
+            commands::annotate_attachment,
+            commands::remove_attachment,
             // 窗口控制命令
             commands::set_window_always_on_top,
             // MCP 相关命令
             commands::get_cli_args,
             commands::read_mcp_request,
             commands::write_response_file,
+            commands::finish_session_turn,
+            commands::get_request_updates,
             commands::exit_app,
+            commands::export_session_report,
+            commands::search_history,
+            commands::search_all,
+            commands::get_usage_stats,
+            commands::push_user_note,
+            commands::list_pending_user_notes,
+            commands::delete_user_note,
+            commands::get_circuit_breaker_status,
+            commands::reset_circuit_breaker,
+            commands::preview_result_template,
+            commands::apply_retention_now,
+            commands::run_cleanup_now,
+            commands::request_purge_token,
+            commands::purge_all_user_data,
+            commands::run_answer_macro,
+            commands::get_text_metrics,
+            commands::lint_response,
             // LLM 文本优化命令
             commands::optimize_text,
+            commands::optimize_selection,
+            commands::scan_pii,
             commands::optimize_text_with_provider,
+            commands::optimize_text_stream,
             commands::test_api_connection,
         ])
         // 注意：不要添加自定义 on_webview_event 处理器
@@ -81,48 +170,170 @@ pub fn run() {
             env_logger::init();
             log::info!("Interactive Feedback MCP started");
             
+            // 临时文件目录按配置设置（环境变量优先），清理、诊断和数据清除扫描与服务端相同的目录
+            let app_config = config::load_config_direct_blocking().unwrap_or_default();
+            popup::set_configured_ipc_dir(app_config.ipc_dir.as_deref());
+            
+            // 内置音频作为资源打包，从资源目录读取
+            match app.path().resource_dir() {
+                Ok(dir) => audio::set_sound_dir(dir.join("sounds")),
+                Err(e) => log::warn!("无法获取资源目录，内置音频将使用生成的提示音: {}", e),
+            }
+            // 启动时检测一次音频设备，没有设备时之后的通知音直接跳过
+            tauri::async_runtime::spawn_blocking(audio::AudioNotifier::availability);
+            
             // 动态获取版本号
             let version = app.config().version.clone().unwrap_or_else(|| "0.0.0".to_string());
-            let title = format!("Interactive Feedback (v{})", version);
+            
+            // MCP 请求的优先级决定窗口是否抢占焦点、是否保持置顶
+            let cli_args = commands::CliArgs::parse();
+            let mcp_request = cli_args.mcp_request_file.as_deref().and_then(popup::load_request_file);
+            let priority = mcp_request.as_ref().map(|r| r.priority).unwrap_or_default();
+            
+            // 单实例：已有实例空闲时把请求转发给它显示，不再叠加一个新窗口
+            if let Some(request) = mcp_request.as_ref().filter(|r| !cli_args.daemon && daemon::can_forward(r)) {
+                if let Some(stream) = tauri::async_runtime::block_on(daemon::forward_to_running_instance(request)) {
+                    daemon::proxy_forwarded(app.handle().clone(), stream, cli_args.watch_parent);
+                    return Ok(());
+                }
+            }
+            
+            // 接收 MCP 服务端发来的请求更新；服务端退出后回答已无人接收，关闭孤立的弹窗
+            if cli_args.watch_parent {
+                popup::watch_parent_channel(app.handle().clone());
+            }
+            
+            // 弹窗按配置决定是否抢占焦点，低优先级请求始终不抢占
+            let popup_config = (mcp_request.is_some() || cli_args.daemon).then_some(app_config);
+            let steal_focus = priority != Priority::Low
+                && popup_config.as_ref().map_or(FocusPolicy::Steal, |c| c.mcp_window_behavior.focus) == FocusPolicy::Steal;
+            
+            // 窗口标题显示请求的标题和项目，便于区分多个会话的弹窗
+            let title = match &mcp_request {
+                Some(request) => format!("{} (v{})", request.window_title("Interactive Feedback"), version),
+                None => format!("Interactive Feedback (v{})", version),
+            };
             
             // 手动创建窗口，使用 Tauri 原生拖拽以获取完整文件路径
-            let _window = WebviewWindowBuilder::new(
+            let window = WebviewWindowBuilder::new(
                 app,
                 "main",
                 WebviewUrl::App("index.html".into())
             )
             .title(&title)
-            .inner_size(1024.0, 800.0)
-            .min_inner_size(400.0, 300.0)
+            .inner_size(window_geometry::DEFAULT_WIDTH, window_geometry::DEFAULT_HEIGHT)
+            .min_inner_size(window_geometry::MIN_WIDTH, window_geometry::MIN_HEIGHT)
             .resizable(true)
             .center()
-            .focused(true)
-            .visible(true)
+            .focused(steal_focus)
+            // 常驻模式下窗口隐藏，收到请求时再显示
+            .visible(!cli_args.daemon)
             // 不禁用拖拽处理器，使用 Tauri 原生拖拽以获取完整文件路径
             // .disable_drag_drop_handler()
             .build()?;
             
             log::info!("[Setup] 窗口已创建 ({}), 使用 Tauri 原生拖拽", title);
             
+            // 弹窗出现在上次的位置或首选显示器上，而不是总在主显示器居中
+            if let Some(config) = &popup_config {
+                window_geometry::restore(&window, &config.window_geometry);
+            }
+            
+            // 常驻模式：监听 MCP Server 的请求，关闭窗口时只隐藏并结束当前请求
+            if cli_args.daemon {
+                daemon::run_daemon(app.handle().clone());
+                let app_handle = app.handle().clone();
+                window.on_window_event(move |event| {
+                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                        api.prevent_close();
+                        daemon::finish_active(&app_handle, popup::PopupOutcome::Cancelled);
+                    }
+                });
+            } else if mcp_request.is_none() && !cli_args.mcp_mode {
+                // 普通窗口：空闲时显示其他实例转发的 MCP 请求
+                daemon::accept_forwarded_requests(app.handle().clone());
+            } else if mcp_request.is_some() {
+                // 关闭弹窗前记录窗口位置（进程随后退出，需要等待写入完成）
+                let app_handle = app.handle().clone();
+                window.on_window_event(move |event| {
+                    if let tauri::WindowEvent::CloseRequested { .. } = event {
+                        tauri::async_runtime::block_on(window_geometry::save_main_window(&app_handle));
+                    }
+                });
+            }
+            
+            // 文件选择模式：不显示反馈窗口，直接弹出原生文件选择器
+            if let Some(request) = mcp_request.as_ref().filter(|r| r.kind == PopupKind::SelectFile) {
+                let _ = window.hide();
+                file_picker::run_file_picker(app.handle(), request.clone());
+                return Ok(());
+            }
+            
+            // 声明了响应截止时间的请求，临近和超过截止时间时逐级提醒
+            if let Some(request) = mcp_request.filter(|r| r.respond_by.is_some()) {
+                tauri::async_runtime::spawn(sla::run_escalation(app.handle().clone(), request));
+            }
+            
             // 初始化配置
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = config::init_config(&app_handle).await {
                     log::error!("Failed to initialize config: {}", e);
                 }
+                
+                // 启动时和之后每天清理临时文件、过期数据和孤立附件
+                janitor::run_daily(|| {
+                    let app_handle = app_handle.clone();
+                    async move { config::load_config(&app_handle).await.unwrap_or_default() }
+                })
+                .await;
             });
             
             // MCP 模式下强制激活窗口
             let app_handle_window = app.handle().clone();
             tauri::async_runtime::spawn(async move {
+                let behavior = config::load_config(&app_handle_window).await
+                    .map(|c| c.mcp_window_behavior)
+                    .unwrap_or_default();
+                
                 // 短暂延迟确保窗口初始化完成
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                tokio::time::sleep(std::time::Duration::from_millis(behavior.activation_delay_ms)).await;
                 
                 // 检测 MCP 模式
                 let args: Vec<String> = std::env::args().collect();
                 let is_mcp_mode = args.iter().any(|arg| arg == "--mcp-request" || arg == "-r");
                 
-                if is_mcp_mode {
+                if is_mcp_mode && priority == Priority::Low {
+                    // 低优先级：仅显示窗口，不抢占焦点
+                    if let Some(window) = app_handle_window.get_webview_window("main") {
+                        let _ = window.unminimize();
+                        let _ = window.show();
+                        log::info!("[MCP] 低优先级请求，窗口已显示（未激活）");
+                    }
+                } else if is_mcp_mode && !steal_focus {
+                    // 不抢占焦点：显示窗口并闪烁任务栏/Dock 图标，紧急请求持续闪烁直到用户切换过来
+                    if let Some(window) = app_handle_window.get_webview_window("main") {
+                        let _ = window.unminimize();
+                        let _ = window.show();
+                        let attention = if priority == Priority::Critical {
+                            UserAttentionType::Critical
+                        } else {
+                            UserAttentionType::Informational
+                        };
+                        let _ = window.request_user_attention(Some(attention));
+                        log::info!("[MCP] 窗口已显示（未激活），已请求用户注意");
+                        // 置顶不会抢占焦点，保持窗口可见
+                        if priority == Priority::Critical {
+                            let _ = window.set_always_on_top(true);
+                        } else if behavior.always_on_top {
+                            let _ = window.set_always_on_top(true);
+                            if behavior.pin_duration_ms > 0 {
+                                tokio::time::sleep(std::time::Duration::from_millis(behavior.pin_duration_ms)).await;
+                                let _ = window.set_always_on_top(false);
+                            }
+                        }
+                    }
+                } else if is_mcp_mode {
                     log::info!("[MCP] 检测到 MCP 模式，强制激活窗口");
                     
                     // macOS: 使用 NSApplication 激活应用
@@ -142,13 +353,22 @@ pub fn run() {
                         let _ = window.unminimize();
                         let _ = window.show();
                         let _ = window.set_focus();
-                        let _ = window.set_always_on_top(true);
-                        log::info!("[MCP] 窗口已激活并置顶");
                         
-                        // 短暂延迟后取消置顶
-                        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-                        let _ = window.set_always_on_top(false);
-                        log::info!("[MCP] 窗口置顶已取消");
+                        // 紧急请求保持置顶，其他请求按配置置顶一段时间（0 表示一直置顶）
+                        if priority == Priority::Critical {
+                            let _ = window.set_always_on_top(true);
+                            log::info!("[MCP] 窗口已激活并置顶");
+                        } else if behavior.always_on_top {
+                            let _ = window.set_always_on_top(true);
+                            log::info!("[MCP] 窗口已激活并置顶");
+                            if behavior.pin_duration_ms > 0 {
+                                tokio::time::sleep(std::time::Duration::from_millis(behavior.pin_duration_ms)).await;
+                                let _ = window.set_always_on_top(false);
+                                log::info!("[MCP] 窗口置顶已取消");
+                            }
+                        } else {
+                            log::info!("[MCP] 窗口已激活");
+                        }
                     } else {
                         log::warn!("[MCP] 未找到主窗口");
                     }
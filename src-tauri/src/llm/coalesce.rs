@@ -0,0 +1,88 @@
+//! 进行中请求合并
+//!
+//! 快速连续点击“优化”会发出两个完全相同的 API 请求。相同请求（按请求内容哈希）
+//! 正在进行时，后来的调用直接等待第一个请求的结果，不再重复请求。
+//! 请求完成后立即移除，之后的相同请求会重新调用 API。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::OnceCell;
+
+type Slot = Arc<OnceCell<Result<String, String>>>;
+
+fn in_flight() -> &'static Mutex<HashMap<u64, Slot>> {
+    static IN_FLIGHT: OnceLock<Mutex<HashMap<u64, Slot>>> = OnceLock::new();
+    IN_FLIGHT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 计算请求键
+pub fn request_key(parts: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 执行请求；相同键的请求正在进行时等待其结果
+///
+/// 第一个请求被取消时，等待中的调用会改为执行自己的请求
+pub async fn coalesce<F, Fut>(key: u64, request: F) -> Result<String, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let slot = {
+        let mut map = in_flight().lock().unwrap();
+        if map.contains_key(&key) {
+            log::info!("[LLM] 相同请求正在进行，等待其结果");
+        }
+        map.entry(key).or_default().clone()
+    };
+
+    let result = slot.get_or_init(request).await.clone();
+
+    let mut map = in_flight().lock().unwrap();
+    if map.get(&key).is_some_and(|current| Arc::ptr_eq(current, &slot)) {
+        map.remove(&key);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_identical_requests_are_coalesced() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let request = |calls: Arc<AtomicUsize>| async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok("optimized".to_string())
+        };
+        let key = request_key(&("coalesce-test", "same text"));
+
+        let (a, b) = tokio::join!(
+            coalesce(key, || request(calls.clone())),
+            coalesce(key, || request(calls.clone())),
+        );
+        assert_eq!(a.unwrap(), "optimized");
+        assert_eq!(b.unwrap(), "optimized");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 完成后不再缓存
+        coalesce(key, || request(calls.clone())).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 不同请求互不影响
+        let other = request_key(&("coalesce-test", "other text"));
+        let (_, _) = tokio::join!(
+            coalesce(key, || request(calls.clone())),
+            coalesce(other, || request(calls.clone())),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
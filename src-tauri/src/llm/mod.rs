@@ -1,9 +1,16 @@
 //! LLM 模块
 //! 
-//! 提供 OpenAI 兼容 API 的统一接口，支持多个 AI 提供商
+//! 提供 OpenAI 兼容 API 和 Anthropic Messages API 的统一接口，支持多个 AI 提供商
 
+mod coalesce;
+pub mod ollama;
 mod provider;
 mod prompts;
+pub mod resolver;
+pub mod selection;
+mod stream;
+mod tokens;
 
-pub use provider::{LlmProvider, LlmConfig, ChatMessage, ChatResponse};
+pub use provider::{ApiFormat, LlmProvider, LlmConfig, ChatMessage, ChatResponse};
 pub use prompts::{get_optimization_prompt, OptimizationType};
+pub use tokens::{estimate_tokens, is_cjk};
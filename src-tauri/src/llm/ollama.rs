@@ -0,0 +1,95 @@
+//! 本地模型（Ollama）
+//!
+//! Ollama 在 `/v1` 下提供 OpenAI 兼容 API，文本优化直接复用 [`super::LlmProvider`]；
+//! 本地已下载的模型列表通过原生 API `GET /api/tags` 获取。
+
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// 获取模型列表的超时时间（本地服务，未启动时应尽快失败）
+const TAGS_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<ModelTag>,
+}
+
+#[derive(Deserialize)]
+struct ModelTag {
+    name: String,
+}
+
+/// 原生 API 的根地址：去掉 OpenAI 兼容地址末尾的 `/v1`
+fn api_root(base_url: &str) -> &str {
+    let base_url = base_url.trim_end_matches('/');
+    base_url.strip_suffix("/v1").unwrap_or(base_url)
+}
+
+/// OpenAI 兼容 API 的地址：用户填写的是服务根地址（如 `http://gpu-box:11434`）时补上 `/v1`
+pub fn openai_base_url(base_url: &str) -> String {
+    format!("{}/v1", api_root(base_url))
+}
+
+/// 解析 `/api/tags` 的响应，返回排序后的模型名称
+fn parse_tags(body: &str) -> Result<Vec<String>, String> {
+    let tags: TagsResponse = serde_json::from_str(body).map_err(|e| format!("解析模型列表失败: {}", e))?;
+    let mut models: Vec<String> = tags.models.into_iter().map(|m| m.name).collect();
+    models.sort();
+    Ok(models)
+}
+
+/// 获取本地已下载的模型
+pub async fn list_models(base_url: &str) -> Result<Vec<String>, String> {
+    let url = format!("{}/api/tags", api_root(base_url));
+    let client = Client::builder()
+        .timeout(TAGS_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let response = client.get(&url).send().await.map_err(|e| {
+        if e.is_connect() || e.is_timeout() {
+            format!("无法连接到本地模型服务 {}，请确认 Ollama 已启动", api_root(base_url))
+        } else {
+            format!("请求失败: {}", e)
+        }
+    })?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| format!("读取响应失败: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("HTTP 错误 {}: {}", status.as_u16(), body));
+    }
+    parse_tags(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_root() {
+        assert_eq!(api_root("http://localhost:11434/v1"), "http://localhost:11434");
+        assert_eq!(api_root("http://localhost:11434/v1/"), "http://localhost:11434");
+        assert_eq!(api_root("http://gpu-box:11434"), "http://gpu-box:11434");
+    }
+
+    #[test]
+    fn test_openai_base_url() {
+        assert_eq!(openai_base_url("http://localhost:11434/v1"), "http://localhost:11434/v1");
+        assert_eq!(openai_base_url("http://localhost:11434/v1/"), "http://localhost:11434/v1");
+        assert_eq!(openai_base_url("http://gpu-box:11434"), "http://gpu-box:11434/v1");
+        assert_eq!(openai_base_url("http://gpu-box:11434/"), "http://gpu-box:11434/v1");
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let body = r#"{"models":[
+            {"name":"qwen2.5:7b","model":"qwen2.5:7b","size":4683087332},
+            {"name":"llama3.2:latest","model":"llama3.2:latest","size":2019393189}
+        ]}"#;
+        assert_eq!(parse_tags(body).unwrap(), vec!["llama3.2:latest", "qwen2.5:7b"]);
+        assert!(parse_tags("{}").unwrap().is_empty());
+        assert!(parse_tags("not json").is_err());
+    }
+}
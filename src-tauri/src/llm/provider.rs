@@ -1,14 +1,37 @@
-//! OpenAI 兼容 API Provider
+//! LLM API Provider
 //! 
-//! 支持 OpenAI、Gemini、DeepSeek、火山引擎等提供商
+//! 支持 OpenAI、Gemini、DeepSeek、火山引擎等 OpenAI 兼容提供商，按部署路由的 Azure OpenAI，
+//! 以及使用 Messages API 的 Anthropic（请求格式和认证头不同）
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+use crate::types::{ApiKeys, AzureOpenAiConfig, CustomProvider, OllamaConfig};
+
+use super::coalesce;
+use super::stream::{self, SseDecoder, StreamEvent};
+
+/// Anthropic API 版本（`anthropic-version` 请求头）
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// 请求格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiFormat {
+    /// OpenAI Chat Completions（`/chat/completions`，`Authorization: Bearer`）
+    #[default]
+    OpenAi,
+    /// Anthropic Messages（`/messages`，`x-api-key`）
+    Anthropic,
+    /// Azure OpenAI（与 OpenAI 相同的请求格式，`api-key` 请求头）
+    AzureOpenAi,
+}
+
 /// LLM 配置
 #[derive(Debug, Clone)]
 pub struct LlmConfig {
+    /// 请求格式
+    pub format: ApiFormat,
     /// API 密钥
     pub api_key: String,
     /// API 基础 URL
@@ -17,56 +40,127 @@ pub struct LlmConfig {
     pub model: String,
     /// 请求超时（秒）
     pub timeout_secs: u64,
+    /// 额外的请求头
+    pub headers: Vec<(String, String)>,
+    /// 额外的 URL 查询参数
+    pub query: Vec<(String, String)>,
 }
 
 impl LlmConfig {
     /// 创建 OpenAI 配置
     pub fn openai(api_key: String) -> Self {
         Self {
+            format: ApiFormat::OpenAi,
             api_key,
             base_url: "https://api.openai.com/v1".to_string(),
             model: "gpt-4o-mini".to_string(),
             timeout_secs: 60,
+            headers: Vec::new(),
+            query: Vec::new(),
         }
     }
 
     /// 创建 Gemini 配置
     pub fn gemini(api_key: String) -> Self {
         Self {
+            format: ApiFormat::OpenAi,
             api_key,
             base_url: "https://generativelanguage.googleapis.com/v1beta/openai".to_string(),
             model: "gemini-2.0-flash-lite".to_string(),
             timeout_secs: 60,
+            headers: Vec::new(),
+            query: Vec::new(),
         }
     }
 
     /// 创建 DeepSeek 配置
     pub fn deepseek(api_key: String) -> Self {
         Self {
+            format: ApiFormat::OpenAi,
             api_key,
             base_url: "https://api.deepseek.com/v1".to_string(),
             model: "deepseek-chat".to_string(),
             timeout_secs: 60,
+            headers: Vec::new(),
+            query: Vec::new(),
         }
     }
 
     /// 创建火山引擎配置
     pub fn volcengine(api_key: String) -> Self {
         Self {
+            format: ApiFormat::OpenAi,
             api_key,
             base_url: "https://ark.cn-beijing.volces.com/api/v3".to_string(),
             model: "doubao-seed-1-6-lite-251015".to_string(),
             timeout_secs: 60,
+            headers: Vec::new(),
+            query: Vec::new(),
         }
     }
 
-    /// 根据提供商名称创建配置
-    pub fn from_provider(provider: &str, api_key: String) -> Option<Self> {
+    /// 创建 Anthropic 配置
+    pub fn anthropic(api_key: String) -> Self {
+        Self {
+            format: ApiFormat::Anthropic,
+            api_key,
+            base_url: "https://api.anthropic.com/v1".to_string(),
+            model: "claude-3-5-haiku-latest".to_string(),
+            timeout_secs: 60,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    /// 创建 Azure OpenAI 配置：请求发送到部署的地址，API 版本作为查询参数
+    pub fn azure(azure: &AzureOpenAiConfig, api_key: String) -> Self {
+        Self {
+            format: ApiFormat::AzureOpenAi,
+            api_key,
+            base_url: format!("{}/openai/deployments/{}", azure.endpoint.trim_end_matches('/'), azure.deployment),
+            model: azure.deployment.clone(),
+            timeout_secs: 60,
+            headers: Vec::new(),
+            query: vec![("api-version".to_string(), azure.api_version.clone())],
+        }
+    }
+
+    /// 创建本地模型（Ollama）配置，不发送 API 密钥
+    pub fn ollama(config: &OllamaConfig) -> Self {
+        Self {
+            format: ApiFormat::OpenAi,
+            api_key: String::new(),
+            base_url: super::ollama::openai_base_url(&config.base_url),
+            model: config.model.clone(),
+            // 本地模型首次调用需要加载到内存，CPU 推理也较慢
+            timeout_secs: 180,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    /// 创建自定义的 OpenAI 兼容提供商配置（API 密钥为空时不发送 Authorization 头）
+    pub fn custom(provider: &CustomProvider, api_key: String) -> Self {
+        Self {
+            format: ApiFormat::OpenAi,
+            api_key,
+            base_url: provider.base_url.trim_end_matches('/').to_string(),
+            model: provider.model.clone(),
+            timeout_secs: 60,
+            headers: provider.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            query: Vec::new(),
+        }
+    }
+
+    /// 根据提供商名称创建配置（Azure OpenAI 的地址和部署从 `keys` 读取）
+    pub fn from_provider(provider: &str, api_key: String, keys: &ApiKeys) -> Option<Self> {
         match provider.to_lowercase().as_str() {
             "openai" => Some(Self::openai(api_key)),
             "gemini" => Some(Self::gemini(api_key)),
             "deepseek" => Some(Self::deepseek(api_key)),
             "volcengine" => Some(Self::volcengine(api_key)),
+            "anthropic" => Some(Self::anthropic(api_key)),
+            "azure" => keys.azure.is_configured().then(|| Self::azure(&keys.azure, api_key)),
             _ => None,
         }
     }
@@ -104,6 +198,8 @@ struct ChatRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 /// 聊天响应
@@ -128,7 +224,51 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
-/// API 错误响应
+/// Anthropic Messages 请求（系统提示词是单独的字段，max_tokens 必填）
+#[derive(Debug, Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+impl AnthropicRequest {
+    fn new(model: String, messages: Vec<ChatMessage>, stream: bool) -> Self {
+        let (system, messages): (Vec<_>, Vec<_>) = messages.into_iter().partition(|m| m.role == "system");
+        let system: Vec<String> = system.into_iter().map(|m| m.content).collect();
+        Self {
+            model,
+            max_tokens: 2048,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages,
+            temperature: Some(0.7),
+            stream,
+        }
+    }
+}
+
+/// Anthropic Messages 响应
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    #[serde(default)]
+    content: Vec<AnthropicContent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicContent {
+    #[serde(rename = "type")]
+    content_type: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// API 错误响应（OpenAI 和 Anthropic 都把错误信息放在 `error.message`）
 #[derive(Debug, Deserialize)]
 struct ApiError {
     error: ApiErrorDetail,
@@ -142,6 +282,15 @@ struct ApiErrorDetail {
     code: Option<String>,
 }
 
+/// 从错误响应中提取错误信息
+fn api_error_message(status: reqwest::StatusCode, body: &str) -> String {
+    // 尝试解析错误响应
+    if let Ok(api_error) = serde_json::from_str::<ApiError>(body) {
+        return format!("API 错误: {}", api_error.error.message);
+    }
+    format!("HTTP 错误 {}: {}", status.as_u16(), body)
+}
+
 /// LLM Provider
 pub struct LlmProvider {
     config: LlmConfig,
@@ -150,67 +299,171 @@ pub struct LlmProvider {
 
 impl LlmProvider {
     /// 创建新的 Provider
+    ///
+    /// 客户端只限制连接和两次读取之间的等待时间；整体超时只用于非流式请求，
+    /// 流式响应持续输出时不会因总时长被中断
     pub fn new(config: LlmConfig) -> Result<Self, String> {
+        let timeout = Duration::from_secs(config.timeout_secs);
         let client = Client::builder()
-            .timeout(Duration::from_secs(config.timeout_secs))
+            .connect_timeout(timeout)
+            .read_timeout(timeout)
             .build()
             .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
         Ok(Self { config, client })
     }
 
-    /// 发送聊天请求
+    /// 发送聊天请求（相同请求正在进行时复用其结果）
     pub async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String, String> {
-        let url = format!("{}/chat/completions", self.config.base_url);
-
-        let request = ChatRequest {
-            model: self.config.model.clone(),
-            messages,
-            temperature: Some(0.7),
-            max_tokens: Some(2048),
+        let key = {
+            let contents: Vec<(&str, &str)> = messages.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+            let config = &self.config;
+            coalesce::request_key(&(&config.base_url, &config.model, &config.api_key, &config.headers, &config.query, contents))
         };
+        coalesce::coalesce(key, || self.send_chat(messages)).await
+    }
 
-        log::info!("[LLM] Sending request to: {}", url);
-        log::info!("[LLM] Model: {}, Timeout: {}s", self.config.model, self.config.timeout_secs);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
-                log::error!("[LLM] Request error: {:?}", e);
-                if e.is_timeout() {
-                    format!("请求超时({}秒)，请稍后重试", self.config.timeout_secs)
-                } else if e.is_connect() {
-                    format!("无法连接到 API 服务器: {}", e)
-                } else {
-                    format!("请求失败: {}", e)
-                }
-            })?;
-
+    async fn send_chat(&self, messages: Vec<ChatMessage>) -> Result<String, String> {
+        let response = self.send_request(messages, false).await?;
         let status = response.status();
         let body = response.text().await.map_err(|e| format!("读取响应失败: {}", e))?;
 
         if !status.is_success() {
-            // 尝试解析错误响应
-            if let Ok(api_error) = serde_json::from_str::<ApiError>(&body) {
-                return Err(format!("API 错误: {}", api_error.error.message));
+            return Err(api_error_message(status, &body));
+        }
+
+        match self.config.format {
+            ApiFormat::OpenAi | ApiFormat::AzureOpenAi => {
+                let chat_response: ChatResponse =
+                    serde_json::from_str(&body).map_err(|e| format!("解析响应失败: {}", e))?;
+
+                chat_response
+                    .choices
+                    .first()
+                    .map(|c| c.message.content.clone())
+                    .ok_or_else(|| "API 返回空响应".to_string())
+            }
+            ApiFormat::Anthropic => {
+                let response: AnthropicResponse =
+                    serde_json::from_str(&body).map_err(|e| format!("解析响应失败: {}", e))?;
+
+                let text: String = response
+                    .content
+                    .into_iter()
+                    .filter(|c| c.content_type == "text")
+                    .map(|c| c.text)
+                    .collect();
+                if text.is_empty() {
+                    return Err("API 返回空响应".to_string());
+                }
+                Ok(text)
+            }
+        }
+    }
+
+    /// 以流式方式发送聊天请求，每收到一段文本调用一次 `on_chunk`，返回完整结果
+    ///
+    /// 每次调用都单独请求，不与相同的请求合并
+    pub async fn chat_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        mut on_chunk: impl FnMut(&str),
+    ) -> Result<String, String> {
+        let mut response = self.send_request(messages, true).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.map_err(|e| format!("读取响应失败: {}", e))?;
+            return Err(api_error_message(status, &body));
+        }
+
+        let parse_event = match self.config.format {
+            ApiFormat::OpenAi | ApiFormat::AzureOpenAi => stream::parse_event,
+            ApiFormat::Anthropic => stream::parse_anthropic_event,
+        };
+        let mut decoder = SseDecoder::default();
+        let mut content = String::new();
+        loop {
+            let chunk = response.chunk().await.map_err(|e| self.request_error(e))?;
+            let events = match &chunk {
+                Some(bytes) => decoder.push(bytes),
+                None => decoder.finish().into_iter().collect(),
+            };
+            for data in events {
+                match parse_event(&data)? {
+                    Some(StreamEvent::Delta(delta)) => {
+                        content.push_str(&delta);
+                        on_chunk(&delta);
+                    }
+                    Some(StreamEvent::Done) => return Ok(content),
+                    Some(StreamEvent::Error(message)) => return Err(format!("API 错误: {}", message)),
+                    None => {}
+                }
+            }
+            if chunk.is_none() {
+                break;
             }
-            return Err(format!("HTTP 错误 {}: {}", status.as_u16(), body));
         }
 
-        let chat_response: ChatResponse =
-            serde_json::from_str(&body).map_err(|e| format!("解析响应失败: {}", e))?;
+        // 部分提供商不发送 [DONE]，连接正常关闭即视为结束
+        if content.is_empty() {
+            return Err("API 返回空响应".to_string());
+        }
+        Ok(content)
+    }
+
+    async fn send_request(&self, messages: Vec<ChatMessage>, stream: bool) -> Result<reqwest::Response, String> {
+        let path = match self.config.format {
+            ApiFormat::OpenAi | ApiFormat::AzureOpenAi => "chat/completions",
+            ApiFormat::Anthropic => "messages",
+        };
+        let url = format!("{}/{}", self.config.base_url, path);
+
+        log::info!("[LLM] Sending request to: {}", url);
+        log::info!("[LLM] Model: {}, Timeout: {}s, Stream: {}", self.config.model, self.config.timeout_secs, stream);
+
+        let mut builder = self.client.post(&url).header("Content-Type", "application/json");
+        if !stream {
+            builder = builder.timeout(Duration::from_secs(self.config.timeout_secs));
+        }
+        builder = match self.config.format {
+            ApiFormat::OpenAi | ApiFormat::AzureOpenAi => {
+                if !self.config.api_key.is_empty() {
+                    builder = match self.config.format {
+                        ApiFormat::AzureOpenAi => builder.header("api-key", &self.config.api_key),
+                        _ => builder.header("Authorization", format!("Bearer {}", self.config.api_key)),
+                    };
+                }
+                builder.json(&ChatRequest {
+                    model: self.config.model.clone(),
+                    messages,
+                    temperature: Some(0.7),
+                    max_tokens: Some(2048),
+                    stream,
+                })
+            }
+            ApiFormat::Anthropic => builder
+                .header("x-api-key", &self.config.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&AnthropicRequest::new(self.config.model.clone(), messages, stream)),
+        };
+        for (name, value) in &self.config.headers {
+            builder = builder.header(name, value);
+        }
+        if !self.config.query.is_empty() {
+            builder = builder.query(&self.config.query);
+        }
+        builder.send().await.map_err(|e| self.request_error(e))
+    }
 
-        chat_response
-            .choices
-            .first()
-            .map(|c| c.message.content.clone())
-            .ok_or_else(|| "API 返回空响应".to_string())
+    fn request_error(&self, e: reqwest::Error) -> String {
+        log::error!("[LLM] Request error: {:?}", e);
+        if e.is_timeout() {
+            format!("请求超时({}秒)，请稍后重试", self.config.timeout_secs)
+        } else if e.is_connect() {
+            format!("无法连接到 API 服务器: {}", e)
+        } else {
+            format!("请求失败: {}", e)
+        }
     }
 
     /// 测试 API 连接
@@ -233,6 +486,21 @@ impl LlmProvider {
         self.chat(messages).await
     }
 
+    /// 流式优化文本
+    pub async fn optimize_text_stream(
+        &self,
+        text: &str,
+        system_prompt: &str,
+        on_chunk: impl FnMut(&str),
+    ) -> Result<String, String> {
+        let messages = vec![
+            ChatMessage::system(system_prompt),
+            ChatMessage::user(text),
+        ];
+
+        self.chat_stream(messages, on_chunk).await
+    }
+
     /// 使用完整提示词优化文本（提示词中已包含待处理的文本）
     pub async fn optimize_text_with_prompt(&self, full_prompt: &str) -> Result<String, String> {
         let messages = vec![
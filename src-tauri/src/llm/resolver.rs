@@ -0,0 +1,246 @@
+//! 提供商选择
+//!
+//! 设置界面的文本优化和 MCP `whale_optimize_user_input` 工具共用同一套选择逻辑：
+//! 按 `provider_order` 排列已配置 API 密钥的提供商（未列出的按默认顺序排在后面），
+//! 调用失败时依次尝试下一个提供商。自定义提供商按添加顺序排在内置提供商之后。
+
+use crate::api_keys::ApiKeyManager;
+use crate::types::{ApiKeys, AppConfig};
+
+use super::{LlmConfig, LlmProvider};
+
+/// 未配置任何 API 密钥时的错误信息
+const NO_PROVIDER_ERROR: &str = "未配置任何 API 密钥，请先在设置中配置";
+
+/// 按优先级排列已配置的提供商
+pub fn ordered_providers(config: &AppConfig) -> Vec<&str> {
+    let configured = config.api_keys.configured_providers();
+    let mut providers: Vec<&str> = Vec::new();
+    let preferred = config.provider_order.iter().filter_map(|name| {
+        // 内置提供商不区分大小写，自定义提供商按名称精确匹配
+        configured.iter().copied().find(|p| {
+            *p == name.as_str() || (ApiKeys::PROVIDERS.iter().any(|b| b == p) && p.eq_ignore_ascii_case(name))
+        })
+    });
+    for provider in preferred.chain(configured.iter().copied()) {
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    }
+    providers
+}
+
+/// 解密指定提供商的 API 密钥
+pub fn api_key(config: &AppConfig, provider: &str) -> Result<String, String> {
+    let obfuscated = config.api_keys.get(provider)
+        .ok_or_else(|| format!("未配置 {} 的 API 密钥", provider))?;
+    ApiKeyManager::deobfuscate(obfuscated).map_err(|e| e.to_string())
+}
+
+/// 指定提供商的 LLM 配置（内置提供商需要已设置 API 密钥，本地模型和自定义提供商不需要）
+pub fn llm_config(config: &AppConfig, provider: &str) -> Result<LlmConfig, String> {
+    if provider == "ollama" {
+        let ollama = &config.api_keys.ollama;
+        if ollama.model.trim().is_empty() {
+            return Err("未选择本地模型，请先在设置中选择".to_string());
+        }
+        return Ok(LlmConfig::ollama(ollama));
+    }
+    if let Some(custom) = config.api_keys.custom_provider(provider) {
+        let api_key = match config.api_keys.get(provider) {
+            Some(obfuscated) => ApiKeyManager::deobfuscate(obfuscated).map_err(|e| e.to_string())?,
+            None => String::new(),
+        };
+        return Ok(LlmConfig::custom(custom, api_key));
+    }
+    let api_key = api_key(config, provider)?;
+    LlmConfig::from_provider(provider, api_key, &config.api_keys).ok_or_else(|| match provider {
+        "azure" => "未配置 Azure OpenAI 的终结点和部署名称".to_string(),
+        _ => format!("不支持的提供商: {}", provider),
+    })
+}
+
+/// 按优先级依次调用提供商优化文本，返回第一个成功的结果
+///
+/// # Returns
+/// * 成功的提供商名称和优化结果；全部失败时返回各提供商的错误
+pub async fn optimize_with_fallback<'a>(
+    config: &'a AppConfig,
+    text: &str,
+    system_prompt: &str,
+) -> Result<(&'a str, String), String> {
+    let providers = ordered_providers(config);
+    if providers.is_empty() {
+        return Err(NO_PROVIDER_ERROR.to_string());
+    }
+
+    let mut errors = Vec::new();
+    for provider in providers {
+        match optimize_with(config, provider, text, system_prompt).await {
+            Ok(result) => {
+                log::info!("[优化] 使用提供商: {}", provider);
+                return Ok((provider, result));
+            }
+            Err(e) => {
+                log::warn!("[优化] {} 调用失败，尝试下一个提供商: {}", provider, e);
+                errors.push(format!("{}: {}", provider, e));
+            }
+        }
+    }
+    Err(errors.join("; "))
+}
+
+/// 按优先级依次调用提供商流式优化文本，每收到一段文本调用一次 `on_chunk`
+///
+/// 只有在提供商还没有返回任何文本时失败才尝试下一个提供商，避免把两个提供商的结果拼在一起
+pub async fn optimize_stream_with_fallback<'a>(
+    config: &'a AppConfig,
+    text: &str,
+    system_prompt: &str,
+    mut on_chunk: impl FnMut(&str),
+) -> Result<(&'a str, String), String> {
+    let providers = ordered_providers(config);
+    if providers.is_empty() {
+        return Err(NO_PROVIDER_ERROR.to_string());
+    }
+
+    let mut errors = Vec::new();
+    for provider in providers {
+        let mut received = false;
+        let result = async {
+            LlmProvider::new(llm_config(config, provider)?)?
+                .optimize_text_stream(text, system_prompt, |chunk| {
+                    received = true;
+                    on_chunk(chunk);
+                })
+                .await
+        }
+        .await;
+        match result {
+            Ok(result) => {
+                log::info!("[优化] 使用提供商: {}（流式）", provider);
+                return Ok((provider, result));
+            }
+            Err(e) if received => {
+                log::error!("[优化] {} 流式输出中断: {}", provider, e);
+                errors.push(format!("{}: {}", provider, e));
+                break;
+            }
+            Err(e) => {
+                log::warn!("[优化] {} 调用失败，尝试下一个提供商: {}", provider, e);
+                errors.push(format!("{}: {}", provider, e));
+            }
+        }
+    }
+    Err(errors.join("; "))
+}
+
+async fn optimize_with(config: &AppConfig, provider: &str, text: &str, system_prompt: &str) -> Result<String, String> {
+    LlmProvider::new(llm_config(config, provider)?)?.optimize_text(text, system_prompt).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ApiFormat;
+
+    #[test]
+    fn test_ordered_providers() {
+        let mut config = AppConfig::default();
+        assert!(ordered_providers(&config).is_empty());
+
+        config.api_keys.openai = Some("a".to_string());
+        config.api_keys.deepseek = Some("b".to_string());
+        config.api_keys.volcengine = Some(String::new());
+        assert_eq!(ordered_providers(&config), vec!["openai", "deepseek"]);
+
+        // provider_order 优先，未配置和未知的提供商被跳过，未列出的排在后面
+        config.provider_order = vec!["DeepSeek".into(), "gemini".into(), "unknown".into()];
+        assert_eq!(ordered_providers(&config), vec!["deepseek", "openai"]);
+
+        // 自定义提供商不需要密钥，默认排在内置提供商之后，也可以通过 provider_order 提前
+        config.api_keys.custom.push(custom_provider("gateway"));
+        assert_eq!(ordered_providers(&config), vec!["deepseek", "openai", "gateway"]);
+        config.provider_order = vec!["gateway".into(), "Gateway".into()];
+        assert_eq!(ordered_providers(&config), vec!["gateway", "openai", "deepseek"]);
+    }
+
+    #[test]
+    fn test_custom_llm_config() {
+        let mut config = AppConfig::default();
+        let mut custom = custom_provider("gateway");
+        custom.headers.insert("X-Team".into(), "infra".into());
+        config.api_keys.custom.push(custom);
+
+        let llm = llm_config(&config, "gateway").unwrap();
+        assert_eq!(llm.base_url, "http://localhost:4000/v1");
+        assert_eq!(llm.model, "claude-via-litellm");
+        assert!(llm.api_key.is_empty());
+        assert_eq!(llm.headers, vec![("X-Team".to_string(), "infra".to_string())]);
+
+        config.api_keys.custom[0].api_key = Some(ApiKeyManager::obfuscate("sk-gateway"));
+        assert_eq!(llm_config(&config, "gateway").unwrap().api_key, "sk-gateway");
+        assert!(llm_config(&config, "openai").is_err());
+        assert!(llm_config(&config, "unknown").is_err());
+    }
+
+    #[test]
+    fn test_ollama_llm_config() {
+        let mut config = AppConfig::default();
+        config.api_keys.ollama.model = "qwen2.5:7b".to_string();
+        // 未启用时不参与排序，但设置界面仍可测试连接
+        assert!(ordered_providers(&config).is_empty());
+        let llm = llm_config(&config, "ollama").unwrap();
+        assert_eq!(llm.base_url, "http://localhost:11434/v1");
+        assert!(llm.api_key.is_empty());
+
+        config.api_keys.ollama.enabled = true;
+        config.api_keys.openai = Some("a".to_string());
+        assert_eq!(ordered_providers(&config), vec!["openai", "ollama"]);
+
+        config.api_keys.ollama.model.clear();
+        assert!(llm_config(&config, "ollama").is_err());
+        assert_eq!(ordered_providers(&config), vec!["openai"]);
+    }
+
+    #[test]
+    fn test_azure_llm_config() {
+        let mut config = AppConfig::default();
+        config.api_keys.azure.api_key = Some(ApiKeyManager::obfuscate("azure-key"));
+        // 未填写终结点和部署时不可用
+        assert!(ordered_providers(&config).is_empty());
+        assert!(llm_config(&config, "azure").is_err());
+
+        config.api_keys.azure.endpoint = "https://corp.openai.azure.com/".to_string();
+        config.api_keys.azure.deployment = "gpt-4o-mini-prod".to_string();
+        assert_eq!(ordered_providers(&config), vec!["azure"]);
+
+        let llm = llm_config(&config, "azure").unwrap();
+        assert_eq!(llm.format, ApiFormat::AzureOpenAi);
+        assert_eq!(llm.base_url, "https://corp.openai.azure.com/openai/deployments/gpt-4o-mini-prod");
+        assert_eq!(llm.api_key, "azure-key");
+        assert_eq!(llm.query, vec![("api-version".to_string(), "2024-10-21".to_string())]);
+    }
+
+    #[test]
+    fn test_anthropic_llm_config() {
+        let mut config = AppConfig::default();
+        config.api_keys.anthropic = Some(ApiKeyManager::obfuscate("sk-ant-test"));
+        assert_eq!(ordered_providers(&config), vec!["anthropic"]);
+
+        let llm = llm_config(&config, "anthropic").unwrap();
+        assert_eq!(llm.format, ApiFormat::Anthropic);
+        assert_eq!(llm.api_key, "sk-ant-test");
+        assert_eq!(llm.base_url, "https://api.anthropic.com/v1");
+    }
+
+    fn custom_provider(name: &str) -> crate::types::CustomProvider {
+        crate::types::CustomProvider {
+            name: name.to_string(),
+            base_url: "http://localhost:4000/v1/".to_string(),
+            model: "claude-via-litellm".to_string(),
+            headers: Default::default(),
+            api_key: None,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! 选区优化模块
+//!
+//! 只优化用户选中的一段文本，返回可直接替换选区的结果，避免改写整条回复。
+//! 选区偏移使用 UTF-16 码元，与前端 textarea 的 `selectionStart` / `selectionEnd` 一致。
+
+use serde::{Deserialize, Serialize};
+
+/// 文本选区（UTF-16 偏移，左闭右开）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 选区优化结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionReplacement {
+    /// 原始选区（替换时使用）
+    pub range: TextRange,
+    /// 原始选中文本
+    pub original: String,
+    /// 替换文本
+    pub replacement: String,
+}
+
+/// UTF-16 偏移转换为字节偏移，偏移落在代理对中间或超出文本时返回 None
+fn utf16_to_byte_offset(text: &str, offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (byte_index, c) in text.char_indices() {
+        if units == offset {
+            return Some(byte_index);
+        }
+        units += c.len_utf16();
+        if units > offset {
+            return None;
+        }
+    }
+    (units == offset).then_some(text.len())
+}
+
+/// 取出选区对应的文本
+pub fn selected_text(text: &str, range: TextRange) -> Result<&str, String> {
+    if range.start > range.end {
+        return Err(format!("无效的选区: {}..{}", range.start, range.end));
+    }
+    let start = utf16_to_byte_offset(text, range.start);
+    let end = utf16_to_byte_offset(text, range.end);
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(&text[start..end]),
+        _ => Err(format!("选区超出文本范围: {}..{}", range.start, range.end)),
+    }
+}
+
+/// 保留选区首尾的空白（LLM 返回的结果通常会去掉首尾换行），替换后段落结构不变
+pub fn fit_replacement(original: &str, optimized: &str) -> String {
+    let leading = &original[..original.len() - original.trim_start().len()];
+    let trailing = &original[original.trim_end().len()..];
+    format!("{}{}{}", leading, optimized.trim(), trailing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selected_text_uses_utf16_offsets() {
+        let text = "你好 😀 world";
+        // "😀" 占两个 UTF-16 码元
+        assert_eq!(selected_text(text, TextRange { start: 3, end: 5 }).unwrap(), "😀");
+        assert_eq!(selected_text(text, TextRange { start: 6, end: 11 }).unwrap(), "world");
+        assert_eq!(selected_text(text, TextRange { start: 0, end: 0 }).unwrap(), "");
+        assert!(selected_text(text, TextRange { start: 4, end: 6 }).is_err());
+        assert!(selected_text(text, TextRange { start: 6, end: 12 }).is_err());
+        assert!(selected_text(text, TextRange { start: 5, end: 3 }).is_err());
+    }
+
+    #[test]
+    fn test_fit_replacement_keeps_surrounding_whitespace() {
+        assert_eq!(fit_replacement("\n  teh text \n\n", "The text.\n"), "\n  The text. \n\n");
+        assert_eq!(fit_replacement("abc", "  ABC  "), "ABC");
+    }
+}
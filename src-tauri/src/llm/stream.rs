@@ -0,0 +1,193 @@
+//! 流式响应解析
+//!
+//! 请求设置 `stream: true` 后，OpenAI 兼容 API 以 SSE（Server-Sent Events）逐段返回结果：
+//! 每个事件是一行或多行 `data: {...}`，以空行结束，最后发送 `data: [DONE]`。
+//! 网络分块可能在任意字节处切断（包括 UTF-8 字符中间），这里缓冲到完整的行再解析。
+//!
+//! Anthropic Messages API 同样使用 SSE，但事件内容不同：文本在 `content_block_delta` 事件中，
+//! 以 `message_stop` 事件结束，没有 `[DONE]`，由 [`parse_anthropic_event`] 解析。
+
+use serde::Deserialize;
+
+/// 结束标记
+const DONE: &str = "[DONE]";
+
+/// SSE 解码器
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// 写入收到的字节，返回其中完整事件的 data 内容（多行 data 以换行连接）
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(std::mem::take(&mut self.data).join("\n"));
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // 忽略注释（`:` 开头）和 event/id/retry 字段
+        }
+        events
+    }
+
+    /// 连接关闭时取出最后一个没有以空行结束的事件
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+        if let Some(value) = rest.trim_end().strip_prefix("data:") {
+            self.data.push(value.trim_start().to_string());
+        }
+        (!self.data.is_empty()).then(|| std::mem::take(&mut self.data).join("\n"))
+    }
+}
+
+/// 一个流式事件的内容
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamEvent {
+    /// 新生成的文本
+    Delta(String),
+    /// 生成结束
+    Done,
+    /// 流中返回的错误
+    Error(String),
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    error: Option<StreamError>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+}
+
+#[derive(Default, Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamError {
+    message: String,
+}
+
+/// 解析一个事件的 data 内容；没有文本的事件（如只有 role 的第一个事件）返回 None
+pub fn parse_event(data: &str) -> Result<Option<StreamEvent>, String> {
+    if data.trim() == DONE {
+        return Ok(Some(StreamEvent::Done));
+    }
+    let chunk: StreamChunk = serde_json::from_str(data).map_err(|e| format!("解析流式响应失败: {}", e))?;
+    if let Some(error) = chunk.error {
+        return Ok(Some(StreamEvent::Error(error.message)));
+    }
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.content)
+        .filter(|content| !content.is_empty())
+        .map(StreamEvent::Delta))
+}
+
+#[derive(Deserialize)]
+struct AnthropicEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<AnthropicDelta>,
+    error: Option<StreamError>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicDelta {
+    text: Option<String>,
+}
+
+/// 解析 Anthropic 流式事件的 data 内容；message_start、ping 等不含文本的事件返回 None
+pub fn parse_anthropic_event(data: &str) -> Result<Option<StreamEvent>, String> {
+    let event: AnthropicEvent = serde_json::from_str(data).map_err(|e| format!("解析流式响应失败: {}", e))?;
+    Ok(match event.event_type.as_str() {
+        "content_block_delta" => event
+            .delta
+            .and_then(|delta| delta.text)
+            .filter(|text| !text.is_empty())
+            .map(StreamEvent::Delta),
+        "message_stop" => Some(StreamEvent::Done),
+        "error" => Some(StreamEvent::Error(
+            event.error.map(|e| e.message).unwrap_or_else(|| "未知错误".to_string()),
+        )),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_handles_split_chunks() {
+        let body = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+                    : keep-alive\n\n\
+                    data: {\"choices\":[{\"delta\":{\"content\":\"你好\"}}]}\r\n\r\n\
+                    data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+                    data: [DONE]\n\n";
+        // 逐字节写入，模拟在 UTF-8 字符中间切断
+        let mut decoder = SseDecoder::default();
+        let events: Vec<String> = body.as_bytes().chunks(1).flat_map(|b| decoder.push(b)).collect();
+        assert_eq!(events.len(), 4);
+        assert!(decoder.finish().is_none());
+
+        let parsed: Vec<StreamEvent> = events.iter().filter_map(|e| parse_event(e).unwrap()).collect();
+        assert_eq!(
+            parsed,
+            vec![StreamEvent::Delta("你好".into()), StreamEvent::Delta(" world".into()), StreamEvent::Done]
+        );
+    }
+
+    #[test]
+    fn test_finish_and_errors() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"data: [DONE]").is_empty());
+        assert_eq!(decoder.finish().as_deref(), Some("[DONE]"));
+
+        assert_eq!(
+            parse_event(r#"{"error":{"message":"quota exceeded"}}"#).unwrap(),
+            Some(StreamEvent::Error("quota exceeded".into()))
+        );
+        assert!(parse_event("not json").is_err());
+    }
+
+    #[test]
+    fn test_anthropic_events() {
+        let body = "event: message_start\n\
+                    data: {\"type\":\"message_start\",\"message\":{\"role\":\"assistant\"}}\n\n\
+                    event: ping\ndata: {\"type\":\"ping\"}\n\n\
+                    event: content_block_delta\n\
+                    data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"你好\"}}\n\n\
+                    event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let mut decoder = SseDecoder::default();
+        let parsed: Vec<StreamEvent> = decoder
+            .push(body.as_bytes())
+            .iter()
+            .filter_map(|e| parse_anthropic_event(e).unwrap())
+            .collect();
+        assert_eq!(parsed, vec![StreamEvent::Delta("你好".into()), StreamEvent::Done]);
+
+        assert_eq!(
+            parse_anthropic_event(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#).unwrap(),
+            Some(StreamEvent::Error("Overloaded".into()))
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Token 估算模块
+//! 
+//! 不依赖具体分词器的粗略估算，用于提示词预算提示
+
+/// 估算文本的 token 数量
+/// 
+/// 规则：CJK 字符按 1 个 token 计算，其余字符按约 4 个字符 1 个 token 计算
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut cjk_chars = 0usize;
+    let mut other_chars = 0usize;
+    
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk_chars += 1;
+        } else {
+            other_chars += 1;
+        }
+    }
+    
+    cjk_chars + other_chars.div_ceil(4)
+}
+
+/// 判断字符是否为 CJK 字符（含全角标点）
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x4E00..=0x9FFF      // CJK 统一表意文字
+        | 0x3400..=0x4DBF    // CJK 扩展 A
+        | 0x3000..=0x303F    // CJK 标点
+        | 0x3040..=0x30FF    // 日文假名
+        | 0xAC00..=0xD7AF    // 韩文音节
+        | 0xFF00..=0xFFEF    // 全角字符
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_empty() {
+        assert_eq!(estimate_tokens(""), 0);
+    }
+
+    #[test]
+    fn test_estimate_ascii() {
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_estimate_cjk() {
+        assert_eq!(estimate_tokens("你好世界"), 4);
+        assert_eq!(estimate_tokens("你好 abc"), 3);
+    }
+}
@@ -0,0 +1,146 @@
+//! MCP 日志转发
+//!
+//! MCP 服务器的日志默认只写到 stderr，弹窗启动失败等问题需要翻找客户端的进程输出。
+//! 客户端通过 `logging/setLevel` 设置级别后，不低于该级别的日志会以
+//! `notifications/message` 发送给客户端，直接显示在客户端的 MCP 日志面板中。
+//!
+//! 日志记录器包装 env_logger：stderr 输出不变，同时把日志广播给转发任务。
+
+use std::sync::OnceLock;
+
+use rmcp::model::LoggingLevel;
+use tokio::sync::broadcast;
+
+/// 广播通道容量，转发任务跟不上时丢弃最旧的日志
+const CHANNEL_CAPACITY: usize = 256;
+
+/// 本 crate 的日志 target 前缀，依赖库只转发警告和错误
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// 一条待转发的日志
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+}
+
+struct ForwardingLogger {
+    inner: env_logger::Logger,
+    events: broadcast::Sender<LogEvent>,
+}
+
+static LOGGER: OnceLock<&'static ForwardingLogger> = OnceLock::new();
+
+impl log::Log for ForwardingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata) || self.events.receiver_count() > 0
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        let ours = record.target().starts_with(CRATE_TARGET);
+        if self.events.receiver_count() > 0 && (ours || record.level() <= log::Level::Warn) {
+            let _ = self.events.send(LogEvent {
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// 安装日志记录器（替代 `env_logger::Builder::init`）
+pub fn init(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let filter = inner.filter();
+    let logger: &'static ForwardingLogger = Box::leak(Box::new(ForwardingLogger {
+        inner,
+        events: broadcast::channel(CHANNEL_CAPACITY).0,
+    }));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(filter);
+        let _ = LOGGER.set(logger);
+    }
+}
+
+/// 订阅日志，未安装转发日志记录器时返回 None
+///
+/// # Arguments
+/// * `level` - 客户端设置的最低级别，必要时放宽全局日志级别以便生成这些日志
+pub fn subscribe(level: LoggingLevel) -> Option<broadcast::Receiver<LogEvent>> {
+    let logger = LOGGER.get()?;
+    let needed = to_level_filter(level);
+    if needed > log::max_level() {
+        log::set_max_level(needed);
+    }
+    Some(logger.events.subscribe())
+}
+
+/// 日志是否达到客户端设置的级别
+pub fn should_forward(event: &LogEvent, min: LoggingLevel) -> bool {
+    severity(to_logging_level(event.level)) >= severity(min)
+}
+
+/// 转换为 MCP 日志级别
+pub fn to_logging_level(level: log::Level) -> LoggingLevel {
+    match level {
+        log::Level::Error => LoggingLevel::Error,
+        log::Level::Warn => LoggingLevel::Warning,
+        log::Level::Info => LoggingLevel::Info,
+        log::Level::Debug | log::Level::Trace => LoggingLevel::Debug,
+    }
+}
+
+fn to_level_filter(level: LoggingLevel) -> log::LevelFilter {
+    match level {
+        LoggingLevel::Debug => log::LevelFilter::Debug,
+        LoggingLevel::Info | LoggingLevel::Notice => log::LevelFilter::Info,
+        LoggingLevel::Warning => log::LevelFilter::Warn,
+        _ => log::LevelFilter::Error,
+    }
+}
+
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(level: log::Level) -> LogEvent {
+        LogEvent { level, target: "test".to_string(), message: "msg".to_string() }
+    }
+
+    #[test]
+    fn test_should_forward() {
+        assert!(should_forward(&event(log::Level::Error), LoggingLevel::Warning));
+        assert!(should_forward(&event(log::Level::Warn), LoggingLevel::Warning));
+        assert!(!should_forward(&event(log::Level::Info), LoggingLevel::Warning));
+        assert!(should_forward(&event(log::Level::Trace), LoggingLevel::Debug));
+        assert!(!should_forward(&event(log::Level::Error), LoggingLevel::Critical));
+    }
+
+    #[test]
+    fn test_level_filter() {
+        assert_eq!(to_level_filter(LoggingLevel::Debug), log::LevelFilter::Debug);
+        assert_eq!(to_level_filter(LoggingLevel::Notice), log::LevelFilter::Info);
+        assert_eq!(to_level_filter(LoggingLevel::Emergency), log::LevelFilter::Error);
+    }
+}
@@ -1,16 +1,47 @@
 //! MCP (Model Context Protocol) 服务器模块
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use rmcp::{
     ServerHandler, ServiceExt,
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
-    model::{ServerCapabilities, ServerInfo, ListToolsResult, PaginatedRequestParam, Tool},
-    service::RequestContext,
+    model::{
+        ServerCapabilities, ServerInfo, ListToolsResult, PaginatedRequestParam, ProgressNotificationParam, Tool,
+        GetPromptRequestParam, GetPromptResult, ListPromptsResult, Prompt, PromptArgument, PromptMessage,
+        PromptMessageRole, RequestId, CreateElicitationRequestParam, CreateMessageRequestParam, ElicitationAction,
+        CallToolResult, Content, JsonObject, LoggingLevel, LoggingMessageNotificationParam, SetLevelRequestParam,
+    },
+    service::{Peer, RequestContext},
     schemars, tool, tool_router, RoleServer, ErrorData as McpError,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::popup::{PopupRequest, launch_popup_and_wait, cleanup_request_file};
+use crate::attachments;
+use crate::availability::{self, Availability, Escalation};
+use crate::option_packs;
+use crate::audio::AudioNotifier;
+use crate::notification;
+use crate::types::{AppConfig, GuiLimitAction, UnavailableAction, WorkingHours};
+use crate::audit::{self, AuditRecord};
+use crate::diagnostics;
+use crate::mcp_logging;
+use crate::elicitation;
+use crate::popup::{self, ActionClass, AgentAttachment, AutoSubmit, ChangeRequest, ClientInfo, ContextCapture, PopupRequest, PopupResponse, PopupUpdate, FilePickerOptions, FormField, FormFieldKind, OptionDetail, Priority, cleanup_request_file};
+use crate::archive::{archive_session, request_project_name};
+use crate::popup_queue::{PopupQueue, QueueTicket};
+use crate::popup_session::PopupSessions;
+use crate::popup_updates::{Handoff, OpenPopups};
+use crate::repeat_answer::{self, RepeatAnswerMode, RepeatAnswerPolicy};
+use crate::result_language::{fill, ResultLanguage};
+use crate::result_template::{self, TemplateData, TemplateFile, TemplateMetadata};
+use crate::sampling;
+use crate::session::{SessionRecord, SessionSummary};
+use crate::rate_limit::{CallLimiter, FloodGuard, Rejection, BREAKER_RESET_AT_KEY, BREAKER_TRIPPED_AT_KEY};
+use crate::sla;
+use crate::gui_limit;
+use crate::storage::{Storage, UserNote};
+use crate::text_metrics::{text_metrics, TextMetrics};
+use crate::workspace::find_workspace;
 
 /// MCP 工具调用参数 - interactive_feedback
 #[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
@@ -23,8 +54,191 @@ pub struct InteractiveFeedbackParams {
     pub full_response: Option<String>,
     
     #[serde(default)]
-    #[schemars(description = "List of predefined options for the user to choose from")]
-    pub predefined_options: Option<Vec<String>>,
+    #[schemars(description = "List of predefined options for the user to choose from. Each entry is either a plain label string or an object {id, label, description?, exclusive?}; selected ids are returned in the result")]
+    pub predefined_options: Option<Vec<PredefinedOptionParam>>,
+    
+    #[serde(default)]
+    #[schemars(description = "Name of an option pack defined in the user's settings (e.g. 'code-review'). Its options are shown first, followed by any predefined_options")]
+    pub options_pack: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Seconds to wait for the user before the popup is closed and a timed-out result is returned (0 = wait forever; defaults to the user's configured value)")]
+    pub timeout_secs: Option<u64>,
+    
+    #[serde(default)]
+    #[schemars(description = "Id or label of the predefined option to choose automatically when the user does not respond within auto_submit_after_secs")]
+    pub default_option: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Seconds to wait before auto-submitting default_option (for unattended runs); the result states that the option was auto-selected")]
+    pub auto_submit_after_secs: Option<u64>,
+    
+    #[serde(default)]
+    #[schemars(description = "Window title for the popup (defaults to the application name)")]
+    pub title: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Name of the project the request belongs to, shown in the window title so the user can tell concurrent sessions apart")]
+    pub project_name: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Absolute working directory of the agent session; its folder name is used when project_name is not given")]
+    pub working_dir: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Urgency of the request: 'low' shows the popup without stealing focus, 'normal' (default) activates it, 'critical' keeps it on top and plays a louder repeated sound")]
+    pub priority: Option<Priority>,
+    
+    #[serde(default)]
+    #[schemars(description = "Deadline for the user's answer: either seconds from now or an RFC 3339 timestamp. The popup escalates reminders as the deadline approaches and the result states whether it was met")]
+    pub respond_by: Option<RespondBy>,
+    
+    #[serde(default)]
+    #[schemars(description = "Set to true to update this client's feedback popup that is still open instead of opening another one: the message is appended as a clarification and predefined_options are added. The earlier call then returns a 'superseded' result and this call receives the user's answer. Opens a new popup when none is open")]
+    pub supersede: Option<bool>,
+    
+    #[serde(default)]
+    #[schemars(description = "Identifier grouping related requests into a thread (e.g. one per task). The popup shows the previous questions and answers of the same thread above the new question, so earlier context does not need to be repeated in the message")]
+    pub thread_id: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Multi-round conversation id. Consecutive calls with the same session_id reuse the popup that is still open instead of starting a new one, and show the earlier rounds of the conversation (implies thread_id when that is not set). The popup stays open between calls until the user closes it")]
+    pub session_id: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Kind of action being asked about. 'review' lets the user answer with a structured list of change requests (file, location, comment, severity), returned as JSON in the result and as change_requests in the structured content")]
+    pub action_class: Option<ActionClass>,
+    
+    #[serde(default)]
+    #[schemars(description = "Images (base64) or code snippets/diffs to show below the message, e.g. a screenshot of the change or the diff being reviewed")]
+    pub attachments: Option<Vec<AgentAttachment>>,
+    
+    #[serde(default)]
+    #[schemars(description = "Language of the result labels and error messages: 'en' or 'zh' (defaults to the user's configured value, normally 'en')")]
+    pub language: Option<ResultLanguage>,
+    
+    #[serde(default)]
+    #[schemars(description = "BCP 47 locale hint for this request, e.g. 'en-US' or 'zh-CN'. Localizes the popup's built-in strings and, unless 'language' is set, the result labels")]
+    pub locale: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Set to true to echo the original message back in the result as a question/answer pair (defaults to the user's configured value)")]
+    pub echo_request: Option<bool>,
+    
+    #[serde(default)]
+    #[schemars(description = "Set to true to capture the user's active monitor when the popup opens and attach it as context (only honored if the user enabled automatic capture; the user sees a countdown and can cancel)")]
+    pub capture_context: Option<bool>,
+}
+
+/// 响应截止时间：相对秒数或 RFC 3339 时间
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum RespondBy {
+    Seconds(u64),
+    Timestamp(String),
+}
+
+impl RespondBy {
+    /// 解析为绝对截止时间（秒数超出可表示的范围时视为没有截止时间）
+    fn deadline(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        match self {
+            RespondBy::Seconds(secs) => i64::try_from(*secs)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|delay| chrono::Utc::now().checked_add_signed(delay)),
+            RespondBy::Timestamp(value) => sla::parse_deadline(value),
+        }
+    }
+}
+
+impl InteractiveFeedbackParams {
+    /// 查找默认选项（按 ID 或显示文本匹配）
+    fn default_option(&self) -> Option<&PredefinedOptionParam> {
+        let default = self.default_option.as_deref()?;
+        self.predefined_options.iter().flatten()
+            .find(|o| o.id() == default || o.label() == default)
+    }
+    
+    /// 超时自动提交设置
+    /// 
+    /// `default_option` 与大于 0 的 `auto_submit_after_secs` 需同时提供且默认选项必须存在，
+    /// 否则忽略自动提交。GUI 按显示文本选择，因此返回默认选项的文本
+    fn auto_submit(&self) -> Option<AutoSubmit> {
+        let after_secs = self.auto_submit_after_secs.filter(|secs| *secs > 0);
+        match (self.default_option(), after_secs) {
+            (Some(default), Some(after_secs)) => Some(AutoSubmit {
+                option: default.label().to_string(),
+                after_secs,
+            }),
+            _ => {
+                if self.default_option.is_some() || after_secs.is_some() {
+                    log::warn!("default_option 与 auto_submit_after_secs 需同时提供且默认选项必须存在，已忽略自动提交");
+                }
+                None
+            }
+        }
+    }
+    
+    /// 结果语言：`language` 优先，其次 `locale` 提示，最后使用用户配置
+    fn result_language(&self, configured: ResultLanguage) -> ResultLanguage {
+        self.language
+            .or_else(|| self.locale.as_deref().and_then(ResultLanguage::from_locale))
+            .unwrap_or(configured)
+    }
+}
+
+/// 预定义选项：纯文本或结构化选项
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(untagged)]
+pub enum PredefinedOptionParam {
+    Label(String),
+    Structured {
+        #[schemars(description = "Machine-readable option id returned when selected")]
+        id: String,
+        #[schemars(description = "Text shown to the user")]
+        label: String,
+        #[serde(default)]
+        #[schemars(description = "Optional longer explanation shown on hover")]
+        description: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Selecting this option deselects all others")]
+        exclusive: Option<bool>,
+    },
+}
+
+impl PredefinedOptionParam {
+    /// 显示文本
+    pub fn label(&self) -> &str {
+        match self {
+            PredefinedOptionParam::Label(label) => label,
+            PredefinedOptionParam::Structured { label, .. } => label,
+        }
+    }
+
+    /// 选项 ID（纯文本选项的 ID 即文本本身）
+    pub fn id(&self) -> &str {
+        match self {
+            PredefinedOptionParam::Label(label) => label,
+            PredefinedOptionParam::Structured { id, .. } => id,
+        }
+    }
+
+    fn to_detail(&self) -> OptionDetail {
+        match self {
+            PredefinedOptionParam::Label(label) => OptionDetail {
+                id: label.clone(),
+                label: label.clone(),
+                description: None,
+                exclusive: false,
+            },
+            PredefinedOptionParam::Structured { id, label, description, exclusive } => OptionDetail {
+                id: id.clone(),
+                label: label.clone(),
+                description: description.clone(),
+                exclusive: exclusive.unwrap_or(false),
+            },
+        }
+    }
 }
 
 /// MCP 工具调用参数 - optimize_user_input
@@ -38,20 +252,239 @@ pub struct OptimizeUserInputParams {
     
     #[schemars(description = "自定义增强指令，仅在 mode 为 'enhance' 时使用")]
     pub custom_prompt: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "错误信息的语言: 'en' 或 'zh'（默认使用用户配置）")]
+    pub language: Option<ResultLanguage>,
+}
+
+/// MCP 工具调用参数 - confirm
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ConfirmParams {
+    #[serde(default)]
+    #[schemars(description = "Short title of the confirmation dialog")]
+    pub title: Option<String>,
+    
+    #[schemars(description = "The question to confirm, e.g. 'Delete the build directory?'")]
+    pub message: String,
+    
+    #[serde(default)]
+    #[schemars(description = "Show a 'remember my choice' checkbox; remembered answers are returned without showing the dialog again during this server session")]
+    pub allow_remember: Option<bool>,
+}
+
+/// 确认结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmResult {
+    /// 用户是否确认
+    pub confirmed: bool,
+    /// 用户是否关闭了对话框而未作答
+    pub cancelled: bool,
+    /// 结果是否来自之前记住的选择
+    pub remembered: bool,
+}
+
+/// MCP 工具调用参数 - select_file
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SelectFileParams {
+    #[serde(default)]
+    #[schemars(description = "Title of the file picker dialog, e.g. 'Which file should I modify?'")]
+    pub title: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Pick directories instead of files (default: false)")]
+    pub directory: Option<bool>,
+    
+    #[serde(default)]
+    #[schemars(description = "Allow selecting multiple entries (default: false)")]
+    pub multiple: Option<bool>,
+    
+    #[serde(default)]
+    #[schemars(description = "Allowed file extensions without the dot, e.g. [\"rs\", \"toml\"]")]
+    pub extensions: Option<Vec<String>>,
+    
+    #[serde(default)]
+    #[schemars(description = "Directory the picker starts in")]
+    pub default_path: Option<String>,
+}
+
+/// MCP 工具调用参数 - collect_form
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CollectFormParams {
+    #[serde(default)]
+    #[schemars(description = "Title of the form window")]
+    pub title: Option<String>,
+    
+    #[serde(default)]
+    #[schemars(description = "Explanation shown above the fields (Markdown)")]
+    pub message: Option<String>,
+    
+    #[schemars(description = "Fields to collect, e.g. [{\"name\": \"port\", \"kind\": \"number\", \"required\": true}, {\"name\": \"env\", \"kind\": \"select\", \"options\": [\"dev\", \"prod\"]}, {\"name\": \"overwrite\", \"kind\": \"checkbox\"}]")]
+    pub fields: Vec<FormField>,
+}
+
+/// 表单结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectFormResult {
+    pub cancelled: bool,
+    /// 字段名到用户填写值的映射（未填写的可选字段不包含在内）
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
+/// MCP 工具调用参数 - notify
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct NotifyParams {
+    #[serde(default)]
+    #[schemars(description = "Notification title (defaults to the application name)")]
+    pub title: Option<String>,
+    
+    #[schemars(description = "Notification text, e.g. 'Long-running build finished'")]
+    pub message: String,
+    
+    #[serde(default)]
+    #[schemars(description = "Also play the user's configured notification sound (default: false; ignored when sounds are disabled in settings)")]
+    pub play_sound: Option<bool>,
+}
+
+/// 用户选择的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectedFile {
+    pub path: String,
+    pub is_directory: bool,
+    /// 文件大小（字节），目录为 None
+    pub size: Option<u64>,
 }
 
-/// 优化结果
+/// 文件选择结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectFileResult {
+    pub cancelled: bool,
+    pub files: Vec<SelectedFile>,
+}
+
+/// 优化结果（whale_optimize_user_input 的结构化结果）
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct OptimizeResult {
     pub optimized_text: String,
     pub success: bool,
     pub error: Option<String>,
 }
 
+impl OptimizeResult {
+    fn ok(optimized_text: String) -> Self {
+        Self { optimized_text, success: true, error: None }
+    }
+}
+
+/// 用户未作答的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// 用户关闭了弹窗或未填写任何内容
+    UserCancelled,
+    /// 超时未响应
+    TimedOut,
+    /// 客户端取消了调用
+    ClientCancelled,
+    /// 客户端断开连接
+    ClientDisconnected,
+    /// 请求被后续调用更新，用户的回答由更新的调用返回
+    Superseded,
+    /// 用户在开会或不在工作时间，问题未显示
+    UserUnavailable,
+}
+
+/// 附加图片的引用（图片数据不包含在结构化结果中）
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ImageRef {
+    /// 在用户附加的图片中的序号（从 0 开始）
+    pub index: usize,
+    pub mime_type: String,
+    /// 解码后的字节数
+    pub size_bytes: usize,
+}
+
+/// 附加文件的引用
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FileRef {
+    pub path: String,
+    pub is_directory: bool,
+}
+
+/// whale_interactive_feedback 的结构化结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FeedbackResult {
+    /// 用户是否未作答
+    pub cancelled: bool,
+    /// 未作答的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancel_reason: Option<CancelReason>,
+    /// 回答是否由服务端代为给出（自动提交默认选项、工作区自动回复、复用上次的回答）
+    pub auto_answered: bool,
+    pub selected_options: Vec<String>,
+    /// 选中选项的 id（纯文本选项的 id 即文本本身）
+    pub selected_option_ids: Vec<String>,
+    pub feedback: Option<String>,
+    pub images: Vec<ImageRef>,
+    pub files: Vec<FileRef>,
+    /// 等待期间请求被更新过时，各修订的问题文本（第一个为原始问题）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revisions: Vec<String>,
+    /// 用户作答时看到的修订号（从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answered_revision: Option<u32>,
+    /// 用户主动留给 Agent 的消息（自上次送达以来）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_notes: Vec<String>,
+    /// 原始问题（开启 `echo_request` 时回显，与回答配对）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question: Option<String>,
+    /// 审查请求中用户逐条填写的修改意见
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub change_requests: Vec<ChangeRequest>,
+}
+
+impl FeedbackResult {
+    fn cancelled(reason: CancelReason) -> Self {
+        Self { cancelled: true, cancel_reason: Some(reason), ..Default::default() }
+    }
+
+    /// 服务端代为给出的回答，选项文本按 `request` 的选项映射为 ID
+    fn auto_answered(request: &PopupRequest, selected_options: Vec<String>, feedback: Option<String>) -> Self {
+        Self {
+            auto_answered: true,
+            selected_option_ids: request.selected_option_ids(&selected_options),
+            selected_options,
+            feedback: feedback.filter(|f| !f.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
 /// MCP 服务器
 #[derive(Debug, Clone)]
 pub struct McpServer {
     tool_router: ToolRouter<Self>,
+    /// 用户选择“记住”的确认结果（key: 标题 + 消息）
+    remembered_confirmations: Arc<Mutex<HashMap<String, bool>>>,
+    /// 弹窗队列，同一时间只显示一个弹窗
+    popup_queue: PopupQueue,
+    /// 多轮对话会话，同一会话的请求复用同一个弹窗
+    popup_sessions: PopupSessions,
+    /// 弹窗类工具按客户端的限流和熔断
+    flood_guard: FloodGuard,
+    /// 文本优化工具的每分钟预算和并发限制
+    optimize_limiter: CallLimiter,
+    /// 本地存储（反馈历史、使用统计），打开失败时为 None
+    storage: Option<Arc<Storage>>,
+    /// 用户最终输入的文本统计（key: MCP 请求 ID），写入审计日志后移除
+    input_metrics: Arc<Mutex<HashMap<RequestId, TextMetrics>>>,
+    /// MCP 客户端是否已断开（stdin 关闭），断开后关闭所有未完成的弹窗
+    disconnected: Arc<tokio::sync::watch::Sender<bool>>,
+    /// 等待回答的反馈弹窗，Agent 可以用新信息更新
+    open_feedback: OpenPopups<(String, FeedbackResult)>,
+    /// 客户端通过 `logging/setLevel` 设置的日志级别（转发任务运行时为 Some）
+    client_log_level: Arc<Mutex<Option<tokio::sync::watch::Sender<LoggingLevel>>>>,
 }
 
 #[tool_router]
@@ -59,147 +492,877 @@ impl McpServer {
     pub fn new() -> Self {
         Self {
             tool_router: Self::tool_router(),
+            remembered_confirmations: Arc::new(Mutex::new(HashMap::new())),
+            popup_queue: PopupQueue::new(),
+            popup_sessions: PopupSessions::new(),
+            flood_guard: FloodGuard::new(),
+            optimize_limiter: CallLimiter::new(),
+            storage: Storage::open_default()
+                .map(Arc::new)
+                .inspect_err(|e| log::warn!("打开本地存储失败，历史记录将不会保存: {}", e))
+                .ok(),
+            input_metrics: Arc::new(Mutex::new(HashMap::new())),
+            disconnected: Arc::new(tokio::sync::watch::channel(false).0),
+            open_feedback: OpenPopups::new(),
+            client_log_level: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 排队等待显示弹窗
+    /// 
+    /// 前面还有其他弹窗时，通过进度通知告知客户端当前排队位置（客户端未提供 progressToken 时仅记录日志）
+    async fn wait_popup_turn(&self, context: &RequestContext<RoleServer>) -> QueueTicket {
+        let ticket = self.popup_queue.join();
+        let progress_token = context.meta.get_progress_token();
+        
+        ticket.wait_turn(|ahead| {
+            let progress_token = progress_token.clone();
+            async move {
+                log::info!("弹窗排队中，前面还有 {} 个请求", ahead);
+                if let Some(progress_token) = progress_token {
+                    let param = ProgressNotificationParam {
+                        progress_token,
+                        progress: 0.0,
+                        total: None,
+                        message: Some(format!("Waiting for {} earlier feedback request(s) to finish", ahead)),
+                    };
+                    if let Err(e) = context.peer.notify_progress(param).await {
+                        log::warn!("Failed to send queue progress notification: {}", e);
+                    }
+                }
+            }
+        }).await;
+        
+        ticket
+    }
+
+    /// 收集用户响应
+    /// 
+    /// 用户启用了 elicitation 且客户端支持时，由客户端界面收集输入；否则（或 elicitation 失败时）启动 GUI 弹窗
+    async fn collect_response(
+        &self,
+        request: &PopupRequest,
+        timeout: Option<std::time::Duration>,
+        context: &RequestContext<RoleServer>,
+    ) -> anyhow::Result<PopupResponse> {
+        let client_supports = context.peer.peer_info()
+            .is_some_and(|info| info.capabilities.elicitation.is_some());
+        let schema = elicitation::requested_schema(request);
+        
+        if let (true, Some(schema)) = (client_supports, schema) {
+            if load_app_config().await.prefer_elicitation {
+                let param = CreateElicitationRequestParam {
+                    message: elicitation::message(request),
+                    requested_schema: serde_json::from_value(schema)?,
+                };
+                let elicit = context.peer.create_elicitation(param);
+                let result = tokio::select! {
+                    result = async {
+                        match timeout {
+                            Some(timeout) => tokio::time::timeout(timeout, elicit).await.ok(),
+                            None => Some(elicit.await),
+                        }
+                    } => result,
+                    _ = context.ct.cancelled() => return Ok(PopupResponse::cancelled(&request.id)),
+                };
+                match result {
+                    None => return Ok(PopupResponse::timed_out(&request.id)),
+                    Some(Ok(result)) => {
+                        let content = result.content.as_ref().filter(|_| result.action == ElicitationAction::Accept);
+                        return Ok(elicitation::to_response(request, content));
+                    }
+                    Some(Err(e)) => log::warn!("Elicitation failed, falling back to popup: {}", e),
+                }
+            }
+        }
+        
+        let updates = self.open_feedback.take_updates(&request.id);
+        wait_for_popup(&self.popup_sessions, request, timeout, context, self.disconnected.subscribe(), updates).await
+    }
+
+    /// 检查客户端是否可以发起新的弹窗请求（弹窗数量上限、限流和熔断）
+    ///
+    /// 熔断时记录熔断时间，供界面显示和重置
+    fn admit_popup(&self, owner: &str, app_config: &AppConfig) -> Result<(), Rejection> {
+        let gui_limit = &app_config.gui_limit;
+        if gui_limit.when_full == GuiLimitAction::Reject && !app_config.daemon_mode.enabled && gui_limit::is_full(gui_limit) {
+            return Err(Rejection::GuiBusy { running: gui_limit.max_concurrent, retry_after_secs: gui_limit.retry_after_secs });
+        }
+        let reset_at = self.storage.as_ref()
+            .and_then(|storage| storage.get_state(BREAKER_RESET_AT_KEY).ok().flatten())
+            .and_then(|value| chrono::DateTime::parse_from_rfc3339(&value).ok())
+            .map(|reset_at| reset_at.with_timezone(&chrono::Utc));
+        let admitted = self.flood_guard.admit(owner, &app_config.flood_protection, reset_at);
+        if let (Err(Rejection::UserOverwhelmed { tripped_at, .. }), Some(storage)) = (&admitted, &self.storage) {
+            if let Err(e) = storage.set_state(BREAKER_TRIPPED_AT_KEY, tripped_at) {
+                log::warn!("Failed to save circuit breaker state: {}", e);
+            }
+        }
+        admitted
+    }
+
+    /// 按反馈结果更新客户端的未回答计数（自动回答、被更新和用户不方便未显示的请求不计入）
+    fn record_feedback_outcome(&self, owner: &str, result: &FeedbackResult) {
+        match result.cancel_reason {
+            None if result.auto_answered => {}
+            None | Some(CancelReason::UserCancelled) => self.flood_guard.record_answered(owner),
+            Some(CancelReason::Superseded | CancelReason::UserUnavailable) => {}
+            Some(_) => self.flood_guard.record_unanswered(owner),
+        }
+    }
+
+    /// 标记客户端已断开，未完成的弹窗随之关闭
+    fn mark_disconnected(&self) {
+        self.disconnected.send_replace(true);
+    }
+
+    /// 退出前的清理：关闭未完成的弹窗（终止 GUI 进程），删除残留的请求和响应文件
+    async fn shutdown(&self) {
+        self.mark_disconnected();
+        self.wait_popups_closed(DISCONNECT_CLEANUP_TIMEOUT).await;
+        let idle = self.popup_sessions.close_all().await;
+        if idle > 0 {
+            log::info!("已关闭 {} 个空闲的会话弹窗", idle);
+        }
+        let stale = popup::cleanup_in_flight_files().await;
+        if stale > 0 {
+            log::warn!("已清理 {} 个未完成请求的临时文件", stale);
+        }
+    }
+
+    /// 等待未完成的弹窗关闭（最多等待 `timeout`）
+    async fn wait_popups_closed(&self, timeout: std::time::Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.popup_queue.is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
         }
     }
 
+    /// 取出用户留给 Agent 的消息（取出后不再重复送达）
+    fn take_user_notes(&self) -> Vec<UserNote> {
+        let Some(storage) = self.storage.as_ref() else {
+            return Vec::new();
+        };
+        storage.take_user_notes().unwrap_or_else(|e| {
+            log::warn!("Failed to load user notes: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// 把用户留言附加到反馈结果中
+    fn attach_user_notes(&self, text: String, result: &mut FeedbackResult, language: ResultLanguage) -> String {
+        let notes = self.take_user_notes();
+        if notes.is_empty() {
+            return text;
+        }
+        log::info!("附加 {} 条用户留言", notes.len());
+        let list = notes.iter().map(|note| format!("- {}", note.message)).collect::<Vec<_>>().join("\n");
+        result.user_notes = notes.into_iter().map(|note| note.message).collect();
+        format!("{}\n\n{}", text, fill(language.text().user_notes, &[&list]))
+    }
+
+    /// 同一线程中之前的问答
+    fn thread_history(&self, thread_id: &str) -> Vec<SessionSummary> {
+        let Some(storage) = self.storage.as_ref() else {
+            return Vec::new();
+        };
+        storage.thread_sessions(thread_id, THREAD_HISTORY_LIMIT).unwrap_or_else(|e| {
+            log::warn!("Failed to load thread history: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// 查找近期相同问题的回答
+    fn find_previous_answer(&self, request: &PopupRequest, policy: &RepeatAnswerPolicy) -> Option<SessionSummary> {
+        let storage = self.storage.as_ref()?;
+        let message = request.message.as_deref().unwrap_or_default();
+        let options = request.predefined_options.as_deref().unwrap_or_default();
+        repeat_answer::find_previous_answer(storage, message, options, policy)
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to look up previous answers: {}", e);
+                None
+            })
+    }
+
     /// whale_interactive_feedback 工具 - 启动 GUI 弹窗收集用户反馈
     #[tool(
         name = "whale_interactive_feedback",
-        description = "Request interactive feedback from the user. Opens a popup for the user to review AI's work and provide feedback, select options, or attach images."
+        description = "Request interactive feedback from the user. Opens a popup for the user to review AI's work and provide feedback, select options, or attach images. Returns markdown text plus structured content (selected option ids, feedback text, image and file refs, cancel reason)."
     )]
     async fn interactive_feedback(
         &self,
         Parameters(params): Parameters<InteractiveFeedbackParams>,
-    ) -> String {
+        context: RequestContext<RoleServer>,
+    ) -> CallToolResult {
+        let app_config = load_app_config().await;
+        let language = params.result_language(app_config.result_language);
+        let echo_request = params.echo_request.unwrap_or(app_config.echo_request_in_result);
+        let message = params.message.clone();
+        let owner = client_name(&context).unwrap_or_default();
+        if let Err(rejection) = self.admit_popup(&owner, &app_config) {
+            return rejected_result(&rejection, language);
+        }
+        match self.request_feedback(params, context).await {
+            Ok((mut text, mut result)) => {
+                self.record_feedback_outcome(&owner, &result);
+                if echo_request {
+                    text = format!("{}\n\n{}", fill(language.text().question, &[&message]), text);
+                    result.question = Some(message);
+                }
+                let text = self.attach_user_notes(text, &mut result, language);
+                structured_result(text, &result)
+            }
+            Err(message) => CallToolResult::error(vec![Content::text(message)]),
+        }
+    }
+    
+    /// 收集用户反馈，返回 Markdown 文本和结构化结果
+    async fn request_feedback(
+        &self,
+        mut params: InteractiveFeedbackParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(String, FeedbackResult), String> {
         log::info!("interactive_feedback called with message: {}", params.message);
         
+        let mut app_config = load_app_config().await;
+        let language = params.result_language(app_config.result_language);
+        let labels = language.text();
+        
+        // 展开配置中的选项组
+        if let Some(name) = params.options_pack.as_deref().filter(|n| !n.trim().is_empty()) {
+            let pack = option_packs::find_pack(&app_config.option_packs, name)
+                .ok_or_else(|| fill(labels.unknown_options_pack, &[&name, &option_packs::pack_names(&app_config.option_packs)]))?;
+            log::info!("使用选项组: {}", pack.name);
+            params.predefined_options = Some(option_packs::expand(pack, params.predefined_options.take()));
+        }
+        
+        let attachments = params.attachments.clone().unwrap_or_default();
+        for (index, attachment) in attachments.iter().enumerate() {
+            attachment.validate()
+                .map_err(|e| fill(labels.invalid_attachment, &[&(index + 1), &e]))?;
+        }
+        
+        // 更新同一客户端仍在等待回答的弹窗，而不是再打开一个
+        let owner = client_name(&context).unwrap_or_default();
+        if params.supersede.unwrap_or(false) {
+            let add_options = params.predefined_options.iter().flatten()
+                .map(PredefinedOptionParam::to_detail)
+                .collect();
+            if let Some(handoff) = self.open_feedback.supersede(&owner, &params.message, add_options) {
+                log::info!("已更新等待回答的弹窗，等待用户回答");
+                return tokio::select! {
+                    handoff = handoff => match handoff {
+                        Ok(Handoff::Answered(answer)) => Ok(answer),
+                        Ok(Handoff::Superseded) => Ok(superseded_feedback(language)),
+                        // 弹窗未给出结果就关闭了（如获取反馈失败）
+                        Err(_) => Ok(cancelled_feedback(CancelReason::UserCancelled, 0, language)),
+                    },
+                    _ = context.ct.cancelled() => Ok(cancelled_feedback(CancelReason::ClientCancelled, 0, language)),
+                };
+            }
+            log::info!("没有等待回答的弹窗，打开新的弹窗");
+        }
+        
         // 创建 popup 请求
-        let request = PopupRequest::new(
+        let mut request = PopupRequest::new(
             Some(params.message.clone()),
             params.full_response.clone(),
-            params.predefined_options.clone(),
-        );
+            None,
+        ).with_agent(client_info(&context), tool_call_id(&context));
+        let deadline = params.respond_by.as_ref().and_then(RespondBy::deadline);
+        request.respond_by = deadline.map(|d| d.to_rfc3339());
+        request.title = params.title.clone();
+        request.project_name = params.project_name.clone();
+        request.working_dir = params.working_dir.clone();
+        request.agent_attachments = attachments;
+        request.strings = params.locale.as_deref()
+            .and_then(ResultLanguage::from_locale)
+            .map(ResultLanguage::popup_strings);
+        request.locale = params.locale.clone();
+        if params.capture_context.unwrap_or(false) {
+            if app_config.context_capture.enabled {
+                request.capture_context = Some(ContextCapture {
+                    countdown_secs: app_config.context_capture.countdown_secs,
+                });
+            } else {
+                log::info!("用户未开启自动截图，忽略 capture_context");
+            }
+        }
+        let has_structured_options = params.predefined_options.iter().flatten()
+            .any(|o| matches!(o, PredefinedOptionParam::Structured { .. }));
+        if let Some(options) = &params.predefined_options {
+            request = request.with_option_details(options.iter().map(PredefinedOptionParam::to_detail).collect());
+        }
+        request.auto_submit = params.auto_submit();
+        
+        // 按 Agent 应用工作区设置
+        let workspace = client_name(&context)
+            .and_then(|agent| find_workspace(&app_config.workspaces, &agent).cloned());
+        if let Some(workspace) = &workspace {
+            log::info!("使用工作区设置: {}", workspace.agent);
+            workspace.apply(&mut app_config);
+            if let Some(reply) = &workspace.auto_reply {
+                let text = format!("{}\n\n{}", fill(labels.user_feedback, &[reply]), labels.workspace_auto_reply);
+                return Ok((text, FeedbackResult::auto_answered(&request, Vec::new(), Some(reply.clone()))));
+            }
+        }
+        request.priority = params.priority
+            .or(workspace.as_ref().and_then(|w| w.priority))
+            .unwrap_or_default();
+        request.attachment_format = app_config.image_encoder.attachment.resolve();
+        request.action_class = params.action_class.unwrap_or_default();
+        
+        // 近期回答过相同问题时，按策略直接复用或在弹窗中提示上次的回答
+        if let Some(previous) = self.find_previous_answer(&request, &app_config.repeat_answer_policy) {
+            if app_config.repeat_answer_policy.mode == RepeatAnswerMode::AutoApply {
+                log::info!("重复问题，自动使用上次的回答: {}", previous.id);
+                let result = FeedbackResult::auto_answered(&request, previous.selected_options.clone(), previous.user_input.clone());
+                return Ok((format_previous_answer(&previous, language), result));
+            }
+            request.previous_answer = Some(previous);
+        }
+        
+        // 同一线程的历史问答显示在问题上方（多轮会话默认以会话 ID 作为线程）
+        let session_id = params.session_id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+        request.session_id = session_id.map(str::to_string);
+        if let Some(thread_id) = params.thread_id.as_deref().map(str::trim).filter(|id| !id.is_empty()).or(session_id) {
+            request.thread_history = self.thread_history(thread_id);
+            request.thread_id = Some(thread_id.to_string());
+        }
+        
+        // 合并用户自定义快捷选项
+        if app_config.custom_options_enabled {
+            request = request.with_quick_options(&app_config.custom_options);
+        }
+        request.assign_hotkeys();
         let request_id = request.id.clone();
+        let timeout_secs = params.timeout_secs.unwrap_or(app_config.feedback_timeout_secs);
         
-        // 启动 GUI 并等待响应
-        match launch_popup_and_wait(&request).await {
-            Ok(response) => {
-                // 清理请求文件
-                if let Err(e) = cleanup_request_file(&request_id).await {
-                    log::warn!("Failed to cleanup request file: {}", e);
+        // 用户在开会或不在工作时间时，按设置等到有空再弹窗或通过 Webhook 升级
+        let availability = availability::current(&app_config.availability).await;
+        let mut deferred = std::time::Duration::ZERO;
+        match availability.action(&app_config.availability, request.priority) {
+            UnavailableAction::Popup => {}
+            UnavailableAction::Webhook => {
+                match availability::escalate(&app_config.availability, &Escalation::new(&request, &availability)).await {
+                    Ok(()) => return Ok(unavailable_feedback(&availability, true, language)),
+                    Err(e) => log::warn!("发送到 Webhook 失败，照常弹窗: {}", e),
                 }
-                
-                if response.cancelled {
-                    return "[User cancelled or provided no feedback]".to_string();
+            }
+            UnavailableAction::Defer => {
+                deferred = availability.wait_time();
+                if timeout_secs > 0 && deferred.as_secs() >= timeout_secs {
+                    return Ok(unavailable_feedback(&availability, false, language));
+                }
+                log::info!("用户暂时不方便，{} 秒后弹窗", deferred.as_secs());
+                tokio::select! {
+                    _ = tokio::time::sleep(deferred) => {}
+                    _ = context.ct.cancelled() => return Ok(cancelled_feedback(CancelReason::ClientCancelled, timeout_secs, language)),
                 }
+            }
+        }
+        
+        // 自动提交由 GUI 倒计时完成：用户有任何操作都会取消倒计时，服务端不另设截止时间，
+        // 只有 GUI 报告倒计时结束（见 PopupOutcome::AutoSelected）时才由服务端选择默认选项
+        let timeout = (timeout_secs > 0).then(|| std::time::Duration::from_secs(timeout_secs).saturating_sub(deferred));
+        
+        // 登记弹窗，等待期间 Agent 可以用新信息更新
+        let registration = self.open_feedback.register(&request_id, &owner, &params.message);
+        let outcome: Result<(String, FeedbackResult), String> = async {
+            // 排队等待，避免多个弹窗同时抢占焦点
+            let _turn = tokio::select! {
+                turn = self.wait_popup_turn(&context) => turn,
+                _ = context.ct.cancelled() => return Ok(cancelled_feedback(CancelReason::ClientCancelled, timeout_secs, language)),
+            };
+        
+            // 启动 GUI 并等待响应
+            match self.collect_response(&request, timeout, &context).await {
+                Ok(mut response) => {
+                    // 清理请求文件
+                    if let Err(e) = cleanup_request_file(&request_id).await {
+                        log::warn!("Failed to cleanup request file: {}", e);
+                    }
                 
-                // 格式化结果
-                let mut parts = Vec::new();
+                    if context.ct.is_cancelled() {
+                        return Ok(cancelled_feedback(CancelReason::ClientCancelled, timeout_secs, language));
+                    }
                 
-                if !response.selected_options.is_empty() {
-                    parts.push(format!("**Selected Options:** {}", response.selected_options.join(", ")));
-                }
+                    if response.client_disconnected {
+                        return Ok(cancelled_feedback(CancelReason::ClientDisconnected, timeout_secs, language));
+                    }
                 
-                if let Some(ref feedback) = response.user_input {
-                    if !feedback.is_empty() {
-                        parts.push(format!("**User Feedback:**\n{}", feedback));
+                    // 保存会话记录，供导出报告使用（图片只保存附件 ID）
+                    let attachment_store = attachments::store();
+                    attachment_store.intern_images(&mut response.images);
+                    let record = SessionRecord::new(&request, &response);
+                    if let Some(storage) = &self.storage {
+                        if let Err(e) = storage.save_session(&record) {
+                            log::warn!("Failed to save session record: {}", e);
+                        }
+                    }
+                    if app_config.archive.archive_dir().is_some() {
+                        let mut archived = record.clone();
+                        attachment_store.resolve_images(&mut archived.response.images);
+                        if let Err(e) = archive_session(&archived, &app_config.archive, &request_project_name(&request)).await {
+                            log::warn!("Failed to archive session: {}", e);
+                        }
                     }
-                }
                 
-                if !response.images.is_empty() {
-                    parts.push(format!("**Attached Images:** {} image(s)", response.images.len()));
-                }
+                    if response.timed_out {
+                        return Ok(cancelled_feedback(CancelReason::TimedOut, timeout_secs, language));
+                    }
                 
-                if !response.file_references.is_empty() {
-                    let file_list: Vec<String> = response.file_references.iter()
-                        .map(|f| {
-                            let icon = if f.is_directory { "📁" } else { "📄" };
-                            format!("{} {}", icon, f.path)
-                        })
-                        .collect();
-                    parts.push(format!("**Attached Files:**\n{}", file_list.join("\n")));
-                }
+                    if response.cancelled {
+                        return Ok(cancelled_feedback(CancelReason::UserCancelled, timeout_secs, language));
+                    }
+                
+                    if let Some(key) = &response.hotkey {
+                        log::info!("用户通过快捷键 {} 作答: {:?}", key, request.hotkey_option(key));
+                    }
+                
+                    // 格式化结果
+                    let selected_option_ids = request.selected_option_ids(&response.selected_options);
+                    let result = FeedbackResult {
+                        cancelled: false,
+                        cancel_reason: None,
+                        auto_answered: response.auto_selected,
+                        selected_options: response.selected_options.clone(),
+                        selected_option_ids,
+                        feedback: response.user_input.clone().filter(|f| !f.is_empty()),
+                        images: response.images.iter().enumerate()
+                            .map(|(index, image)| ImageRef {
+                                index,
+                                mime_type: image.mime_type.clone(),
+                                size_bytes: match image.attachment_id.as_deref() {
+                                    Some(id) if image.data.is_empty() => attachment_store.size_of(id).unwrap_or(0),
+                                    _ => base64_decoded_len(&image.data),
+                                },
+                            })
+                            .collect(),
+                        files: response.file_references.iter()
+                            .map(|f| FileRef { path: f.path.clone(), is_directory: f.is_directory })
+                            .collect(),
+                        revisions: Vec::new(),
+                        answered_revision: response.revision,
+                        user_notes: Vec::new(),
+                        question: None,
+                        change_requests: response.change_requests.clone(),
+                    };
                 
-                if parts.is_empty() {
-                    "No feedback provided by user.".to_string()
-                } else {
-                    parts.join("\n\n")
+                    if let Some(feedback) = &result.feedback {
+                        self.input_metrics.lock().unwrap().insert(context.id.clone(), text_metrics(feedback));
+                    }
+                    let sla = deadline.map(|deadline| sla::describe_outcome(deadline, chrono::Utc::now(), language));
+                    let mut files = Vec::new();
+                    for f in &response.file_references {
+                        let content = if f.is_directory {
+                            None
+                        } else {
+                            read_embeddable_text(&f.path, app_config.embed_file_max_bytes).await
+                        };
+                        files.push(TemplateFile {
+                            path: f.path.clone(),
+                            is_directory: f.is_directory,
+                            content: content.map(|c| c.trim_end().to_string()),
+                            language: code_fence_language(&f.path).to_string(),
+                        });
+                    }
+                    
+                    // 按配置的模板格式化结果，未配置或渲染失败时使用默认格式
+                    let template = app_config.feedback_result_template.as_deref().filter(|t| !t.trim().is_empty());
+                    let rendered = template.and_then(|template| {
+                        let data = TemplateData {
+                            selected_options: result.selected_options.clone(),
+                            selected_option_ids: result.selected_option_ids.clone(),
+                            feedback: result.feedback.clone(),
+                            images: result.images.clone(),
+                            image_count: result.images.len(),
+                            files: files.clone(),
+                            change_requests: result.change_requests.clone(),
+                            auto_selected: response.auto_selected,
+                            sla: sla.clone(),
+                            metadata: TemplateMetadata {
+                                request_id: request_id.clone(),
+                                project_name: request.project_name.clone(),
+                                priority: request.priority,
+                                responded_at: chrono::Utc::now().to_rfc3339(),
+                                language,
+                                revision: response.revision,
+                                hotkey: response.hotkey.clone(),
+                            },
+                        };
+                        result_template::render(template, &data)
+                            .inspect_err(|e| log::warn!("{}，使用默认格式", e))
+                            .ok()
+                    });
+                    if let Some(text) = rendered {
+                        return Ok((text, result));
+                    }
+                    
+                    let mut parts = Vec::new();
+                    if let (true, Some(auto_submit)) = (response.auto_selected, &request.auto_submit) {
+                        parts.push(fill(labels.auto_selected, &[&auto_submit.after_secs]));
+                    }
+                
+                    if !result.selected_options.is_empty() {
+                        parts.push(fill(labels.selected_options, &[&result.selected_options.join(", ")]));
+                        if has_structured_options {
+                            parts.push(fill(labels.selected_option_ids, &[&result.selected_option_ids.join(", ")]));
+                        }
+                    }
+                
+                    if let Some(feedback) = &result.feedback {
+                        parts.push(fill(labels.user_feedback, &[feedback]));
+                    }
+                
+                    parts.extend(sla);
+                
+                    if !result.images.is_empty() {
+                        parts.push(fill(labels.attached_images, &[&result.images.len()]));
+                    }
+                
+                    if !files.is_empty() {
+                        let file_list: Vec<String> = files.iter()
+                            .map(|f| {
+                                let icon = if f.is_directory { "📁" } else { "📄" };
+                                match &f.content {
+                                    Some(content) => format!("{} {}\n```{}\n{}\n```", icon, f.path, f.language, content),
+                                    None => format!("{} {}", icon, f.path),
+                                }
+                            })
+                            .collect();
+                        parts.push(fill(labels.attached_files, &[&file_list.join("\n")]));
+                    }
+                
+                    if !result.change_requests.is_empty() {
+                        let json = serde_json::to_string_pretty(&result.change_requests).unwrap_or_default();
+                        parts.push(fill(labels.change_requests, &[&result.change_requests.len(), &json]));
+                    }
+                
+                    let text = if parts.is_empty() {
+                        labels.no_feedback.to_string()
+                    } else {
+                        parts.join("\n\n")
+                    };
+                    Ok((text, result))
+                }
+                Err(e) => {
+                    let _ = cleanup_request_file(&request_id).await;
+                    log::error!("Failed to get feedback: {}", e);
+                    Err(fill(labels.feedback_failed, &[&e]))
                 }
             }
+        }.await;
+        
+        // 请求被更新过时，回答交给最后一次更新的调用
+        let (text, mut result) = outcome?;
+        let revisions = registration.revisions();
+        let text = if revisions.len() > 1 {
+            result.revisions = revisions.clone();
+            format!("{}\n\n{}", text, describe_revisions(&revisions, result.answered_revision, language))
+        } else {
+            text
+        };
+        match registration.hand_off((text, result)) {
+            Ok(()) => Ok(superseded_feedback(language)),
+            Err(answer) => Ok(answer),
+        }
+    }
+
+    /// whale_confirm 工具 - 轻量的是/否确认对话框
+    #[tool(
+        name = "whale_confirm",
+        description = "Ask the user a yes/no confirmation question (e.g. approval before a risky action). Shows a minimal dialog and returns JSON: {\"confirmed\": bool, \"cancelled\": bool, \"remembered\": bool}."
+    )]
+    async fn confirm(
+        &self,
+        Parameters(params): Parameters<ConfirmParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<String, String> {
+        let app_config = load_app_config().await;
+        let labels = app_config.result_language.text();
+        if params.message.trim().is_empty() {
+            return Err(fill(labels.empty_param, &[&"message"]));
+        }
+        
+        log::info!("confirm called with message: {}", params.message);
+        
+        let owner = client_name(&context).unwrap_or_default();
+        let remember_key = format!("{}\n{}", params.title.as_deref().unwrap_or(""), params.message);
+        let remembered = self.remembered_confirmations.lock().unwrap().get(&remember_key).copied();
+        let result = if let Some(confirmed) = remembered {
+            log::info!("confirm 使用已记住的选择: {}", confirmed);
+            ConfirmResult { confirmed, cancelled: false, remembered: true }
+        } else {
+            if let Err(rejection) = self.admit_popup(&owner, &app_config) {
+                return Err(rejection.describe(app_config.result_language));
+            }
+            let request = PopupRequest::confirm(
+                params.title.clone(),
+                params.message.clone(),
+                params.allow_remember.unwrap_or(false),
+            ).with_agent(client_info(&context), tool_call_id(&context));
+            let request_id = request.id.clone();
+            
+            let _turn = tokio::select! {
+                turn = self.wait_popup_turn(&context) => turn,
+                _ = context.ct.cancelled() => return Ok(labels.request_cancelled.to_string()),
+            };
+            let response = self.collect_response(&request, None, &context).await;
+            if let Err(e) = cleanup_request_file(&request_id).await {
+                log::warn!("Failed to cleanup request file: {}", e);
+            }
+            
+            match response {
+                Ok(response) => match response.confirmation() {
+                    Some(confirmed) => {
+                        self.flood_guard.record_answered(&owner);
+                        if request.allow_remember && response.remember_choice {
+                            self.remembered_confirmations.lock().unwrap().insert(remember_key, confirmed);
+                        }
+                        ConfirmResult { confirmed, cancelled: false, remembered: false }
+                    }
+                    None => ConfirmResult { confirmed: false, cancelled: true, remembered: false },
+                },
+                Err(e) => {
+                    log::error!("Failed to get confirmation: {}", e);
+                    return Err(fill(labels.confirm_failed, &[&e]));
+                }
+            }
+        };
+        
+        serde_json::to_string(&result)
+            .map_err(|e| fill(labels.serialize_failed, &[&e]))
+    }
+
+    /// whale_select_file 工具 - 弹出原生文件/目录选择器
+    #[tool(
+        name = "whale_select_file",
+        description = "Ask the user to pick file(s) or directory(ies) with a native picker dialog. Returns JSON: {\"cancelled\": bool, \"files\": [{\"path\", \"is_directory\", \"size\"}]}."
+    )]
+    async fn select_file(
+        &self,
+        Parameters(params): Parameters<SelectFileParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<String, String> {
+        log::info!("select_file called with title: {:?}", params.title);
+        
+        let owner = client_name(&context).unwrap_or_default();
+        let app_config = load_app_config().await;
+        let labels = app_config.result_language.text();
+        if let Err(rejection) = self.admit_popup(&owner, &app_config) {
+            return Err(rejection.describe(app_config.result_language));
+        }
+        
+        let options = FilePickerOptions {
+            directory: params.directory.unwrap_or(false),
+            multiple: params.multiple.unwrap_or(false),
+            extensions: params.extensions.clone().unwrap_or_default(),
+            default_path: params.default_path.clone(),
+        };
+        let request = PopupRequest::select_file(params.title.clone(), None, options)
+            .with_agent(client_info(&context), tool_call_id(&context));
+        let request_id = request.id.clone();
+        
+        let _turn = tokio::select! {
+            turn = self.wait_popup_turn(&context) => turn,
+            _ = context.ct.cancelled() => return Ok(labels.request_cancelled.to_string()),
+        };
+        let response = self.collect_response(&request, None, &context).await;
+        if let Err(e) = cleanup_request_file(&request_id).await {
+            log::warn!("Failed to cleanup request file: {}", e);
+        }
+        
+        let response = match response {
+            Ok(r) => r,
             Err(e) => {
-                let _ = cleanup_request_file(&request_id).await;
-                log::error!("Failed to get feedback: {}", e);
-                format!("Error: Failed to get user feedback - {}", e)
+                log::error!("Failed to select file: {}", e);
+                return Err(fill(labels.file_picker_failed, &[&e]));
             }
+        };
+        
+        let mut files = Vec::new();
+        for file in &response.file_references {
+            let size = if file.is_directory {
+                None
+            } else {
+                tokio::fs::metadata(&file.path).await.ok().map(|m| m.len())
+            };
+            files.push(SelectedFile {
+                path: file.path.clone(),
+                is_directory: file.is_directory,
+                size,
+            });
+        }
+        
+        let result = SelectFileResult {
+            cancelled: response.cancelled || files.is_empty(),
+            files,
+        };
+        if !response.cancelled {
+            self.flood_guard.record_answered(&owner);
+        }
+        serde_json::to_string(&result)
+            .map_err(|e| fill(labels.serialize_failed, &[&e]))
+    }
+
+    /// whale_collect_form 工具 - 在一个弹窗中收集多个结构化字段
+    #[tool(
+        name = "whale_collect_form",
+        description = "Collect structured input from the user in a single form (text, multiline, number, select and checkbox fields). Returns JSON: {\"cancelled\": bool, \"values\": {<field name>: <value>}}."
+    )]
+    async fn collect_form(
+        &self,
+        Parameters(params): Parameters<CollectFormParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<String, String> {
+        let app_config = load_app_config().await;
+        let labels = app_config.result_language.text();
+        if let Err(e) = validate_form_fields(&params.fields, app_config.result_language) {
+            return Err(e);
+        }
+        
+        log::info!("collect_form called with {} field(s)", params.fields.len());
+        
+        let owner = client_name(&context).unwrap_or_default();
+        if let Err(rejection) = self.admit_popup(&owner, &app_config) {
+            return Err(rejection.describe(app_config.result_language));
+        }
+        
+        let request = PopupRequest::form(params.title.clone(), params.message.clone(), params.fields.clone())
+            .with_agent(client_info(&context), tool_call_id(&context));
+        let request_id = request.id.clone();
+        
+        let _turn = tokio::select! {
+            turn = self.wait_popup_turn(&context) => turn,
+            _ = context.ct.cancelled() => return Ok(labels.request_cancelled.to_string()),
+        };
+        let response = self.collect_response(&request, None, &context).await;
+        if let Err(e) = cleanup_request_file(&request_id).await {
+            log::warn!("Failed to cleanup request file: {}", e);
+        }
+        
+        let response = match response {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("Failed to collect form: {}", e);
+                return Err(fill(labels.form_failed, &[&e]));
+            }
+        };
+        
+        let result = match response.form_values.filter(|_| !response.cancelled) {
+            None => CollectFormResult { cancelled: true, values: serde_json::Map::new() },
+            Some(submitted) => {
+                let mut values = serde_json::Map::new();
+                for field in &params.fields {
+                    match field.normalize(submitted.get(&field.name)) {
+                        Ok(Some(value)) => {
+                            values.insert(field.name.clone(), value);
+                        }
+                        Ok(None) => {}
+                        Err(e) => return Err(fill(labels.invalid_form_response, &[&e])),
+                    }
+                }
+                self.flood_guard.record_answered(&owner);
+                CollectFormResult { cancelled: false, values }
+            }
+        };
+        serde_json::to_string(&result)
+            .map_err(|e| fill(labels.serialize_failed, &[&e]))
+    }
+
+    /// whale_notify 工具 - 显示系统通知，不等待用户响应
+    #[tool(
+        name = "whale_notify",
+        description = "Show a desktop notification (optionally with a sound) without waiting for any user response. Use it to announce that a long task has finished."
+    )]
+    async fn notify(&self, Parameters(params): Parameters<NotifyParams>) -> Result<String, String> {
+        if params.message.trim().is_empty() {
+            return Err("Error: 'message' 参数不能为空".to_string());
+        }
+        
+        log::info!("notify called with message: {}", params.message);
+        
+        if params.play_sound.unwrap_or(false) {
+            let app_config = load_app_config().await;
+            if app_config.audio_enabled {
+                AudioNotifier::play_notification_async(app_config.audio_file.as_deref());
+            }
+        }
+        
+        let title = params.title.clone().unwrap_or_else(|| notification::DEFAULT_TITLE.to_string());
+        let message = params.message.clone();
+        match tokio::task::spawn_blocking(move || notification::show(&title, &message)).await {
+            Ok(Ok(())) => Ok("Notification sent".to_string()),
+            Ok(Err(e)) => {
+                log::error!("Failed to show notification: {}", e);
+                Err(format!("Error: Failed to show notification - {}", e))
+            }
+            Err(e) => Err(format!("Error: Failed to show notification - {}", e)),
         }
     }
 
     /// whale_optimize_user_input 工具
     #[tool(
         name = "whale_optimize_user_input",
-        description = "使用 AI 优化用户输入文本，将口语化输入转换为结构化指令。"
+        description = "使用 AI 优化用户输入文本，将口语化输入转换为结构化指令。未配置 API 密钥时可通过 MCP sampling 使用客户端的模型。"
     )]
     async fn optimize_user_input(
         &self,
-        Parameters(params): Parameters<OptimizeUserInputParams>,
-    ) -> String {
+        Parameters(params): Parameters<OptimizeUserInputParams>,
+        context: RequestContext<RoleServer>,
+    ) -> CallToolResult {
+        let app_config = load_app_config().await;
+        let _permit = match self.optimize_limiter.acquire(&app_config.optimize_rate_limit) {
+            Ok(permit) => permit,
+            Err(rejection) => {
+                let language = params.language.unwrap_or(app_config.result_language);
+                return rejected_result(&rejection, language);
+            }
+        };
+        match self.optimize(params, context).await {
+            Ok(text) => structured_result(text.clone(), &OptimizeResult::ok(text)),
+            Err(message) => {
+                let mut result = CallToolResult::error(vec![Content::text(message.clone())]);
+                let output = OptimizeResult { optimized_text: String::new(), success: false, error: Some(message) };
+                result.structured_content = serde_json::to_value(output).ok();
+                result
+            }
+        }
+    }
+    
+    /// 优化文本，返回优化结果或错误信息
+    async fn optimize(
+        &self,
+        params: OptimizeUserInputParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<String, String> {
+        // 直接从配置文件加载配置
+        let config = match crate::config::load_config_direct().await {
+            Ok(c) => c,
+            Err(e) => {
+                let labels = params.language.unwrap_or_default().text();
+                return Err(fill(labels.config_load_failed, &[&e]));
+            }
+        };
+        let labels = params.language.unwrap_or(config.result_language).text();
+        
         if params.text.trim().is_empty() {
-            return "Error: 'text' 参数不能为空".to_string();
+            return Err(fill(labels.empty_param, &[&"text"]));
         }
         
         let mode = params.mode.as_deref().unwrap_or("optimize");
         
         if mode != "optimize" && mode != "enhance" {
-            return "Error: 'mode' 参数必须是 'optimize' 或 'enhance'".to_string();
+            return Err(labels.invalid_mode.to_string());
         }
         
         if mode == "enhance" && params.custom_prompt.is_none() {
-            return "Error: 当 mode 为 'enhance' 时，必须提供 'custom_prompt' 参数".to_string();
+            return Err(labels.custom_prompt_required.to_string());
         }
         
         log::info!("optimize_user_input 工具被调用，模式: {}", mode);
         
-        // 直接从配置文件加载配置
-        let config = match crate::config::load_config_direct().await {
-            Ok(c) => c,
-            Err(e) => return format!("Error: 加载配置失败: {}", e),
-        };
-        
-        // 获取已配置的提供商和 API 密钥
-        let (provider_name, obfuscated_key) = if let Some(ref key) = config.api_keys.openai {
-            if !key.is_empty() { ("openai", key.clone()) } else { ("", String::new()) }
-        } else if let Some(ref key) = config.api_keys.gemini {
-            if !key.is_empty() { ("gemini", key.clone()) } else { ("", String::new()) }
-        } else if let Some(ref key) = config.api_keys.deepseek {
-            if !key.is_empty() { ("deepseek", key.clone()) } else { ("", String::new()) }
-        } else if let Some(ref key) = config.api_keys.volcengine {
-            if !key.is_empty() { ("volcengine", key.clone()) } else { ("", String::new()) }
-        } else {
-            return "Error: 未配置任何 API 密钥，请先在设置中配置".to_string();
-        };
-        
-        if provider_name.is_empty() {
-            return "Error: 未配置任何 API 密钥，请先在设置中配置".to_string();
-        }
-        
-        // 解混淆 API 密钥
-        let api_key = match crate::api_keys::ApiKeyManager::deobfuscate(&obfuscated_key) {
-            Ok(key) => key,
-            Err(e) => return format!("Error: 解密 API 密钥失败: {}", e),
-        };
-        
-        // 创建 LLM 配置
-        let config = match crate::llm::LlmConfig::from_provider(provider_name, api_key) {
-            Some(c) => c,
-            None => return format!("Error: 不支持的提供商: {}", provider_name),
-        };
-        
-        // 创建 Provider
-        let llm = match crate::llm::LlmProvider::new(config) {
-            Ok(l) => l,
-            Err(e) => return format!("Error: 创建 LLM Provider 失败: {}", e),
-        };
-        
         // 获取优化类型
         let opt_type = if mode == "enhance" {
             crate::llm::OptimizationType::Reinforce
@@ -210,14 +1373,160 @@ impl McpServer {
         // 获取提示词
         let system_prompt = crate::llm::get_optimization_prompt(opt_type, params.custom_prompt.as_deref());
         
-        // 调用 LLM
-        match llm.optimize_text(&params.text, &system_prompt).await {
-            Ok(result) => result,
-            Err(e) => format!("Error: 优化失败: {}", e),
+        // 发送前按配置检查和屏蔽敏感信息（Agent 无法代替用户确认，提示模式下直接拒绝）
+        let masked = crate::pii::guard(&config.pii_scan, &params.text, false)?;
+        if !masked.replacements.is_empty() {
+            log::info!("optimize_user_input 已屏蔽 {} 处敏感信息", masked.replacements.len());
+        }
+        
+        // 使用客户端的模型优化
+        let client_supports = client_supports_sampling(&context);
+        if config.optimize_sampling.should_sample(config.api_keys.any_configured(), client_supports) {
+            if !client_supports {
+                return Err(labels.sampling_unsupported.to_string());
+            }
+            return optimize_via_sampling(&context, &masked.text, &system_prompt).await
+                .map(|result| masked.unmask(&result))
+                .map_err(|e| fill(labels.optimize_failed, &[&e]));
+        }
+        
+        // 按 provider_order 依次尝试已配置的提供商
+        crate::llm::resolver::optimize_with_fallback(&config, &masked.text, &system_prompt).await
+            .map(|(_, result)| masked.unmask(&result))
+            .map_err(|e| fill(labels.optimize_failed, &[&e]))
+    }
+    
+    /// whale_get_pending_user_notes 工具 - 读取用户主动留下的消息
+    #[tool(
+        name = "whale_get_pending_user_notes",
+        description = "Read notes the user left for the agent without being asked (e.g. changed requirements or hints). Notes are returned once and are also attached to the next interactive feedback result. Returns JSON: {\"notes\": [{\"id\", \"message\", \"createdAt\"}]}."
+    )]
+    async fn get_pending_user_notes(&self) -> Result<String, String> {
+        log::info!("get_pending_user_notes called");
+        let notes = self.take_user_notes();
+        serde_json::to_string(&serde_json::json!({ "notes": notes }))
+            .map_err(|e| format!("Error: Failed to serialize result - {}", e))
+    }
+    
+    /// whale_get_user_preferences 工具 - 读取用户偏好（只读）
+    #[tool(
+        name = "whale_get_user_preferences",
+        description = "Read the user's preferences so questions can be tailored without the user restating them: preferred language, response style notes, working hours, current availability (in a meeting or outside working hours, and until when) and the option packs available for interactive feedback's options_pack. Read-only. Returns JSON: {\"preferredLanguage\", \"resultLanguage\", \"styleNotes\", \"workingHours\", \"workingHoursSchedule\": [{\"days\", \"start\", \"end\"}], \"availability\": {\"status\": \"available\"|\"in_meeting\"|\"outside_working_hours\", \"until\", \"meeting\"}, \"optionPacks\": [{\"name\", \"description\", \"options\"}]}."
+    )]
+    async fn get_user_preferences(&self) -> Result<String, String> {
+        log::info!("get_user_preferences called");
+        let app_config = load_app_config().await;
+        let availability = availability::current(&app_config.availability).await;
+        serde_json::to_string(&UserPreferences::from_config(&app_config, availability))
+            .map_err(|e| format!("Error: Failed to serialize result - {}", e))
+    }
+    
+    /// whale_diagnostics 工具 - 环境自检
+    #[tool(
+        name = "whale_diagnostics",
+        description = "Run an environment self-check: UI executable discovery, config file validity, configured LLM providers, temp-dir writability, audio device and screen-capture permission. Use it when the popup never appears. Returns JSON: {\"ok\": bool, \"checks\": [{\"name\", \"ok\", \"detail\"}]}."
+    )]
+    async fn diagnostics(&self) -> Result<String, String> {
+        log::info!("diagnostics called");
+        let report = diagnostics::run_diagnostics().await;
+        serde_json::to_string(&report)
+            .map_err(|e| format!("Error: Failed to serialize result - {}", e))
+    }
+}
+
+/// `whale_get_user_preferences` 的结果，只包含用户愿意提供给 Agent 的配置
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserPreferences {
+    preferred_language: Option<String>,
+    result_language: ResultLanguage,
+    style_notes: Option<String>,
+    working_hours: Option<String>,
+    /// 结构化的工作时间段（本地时间）
+    working_hours_schedule: Vec<WorkingHours>,
+    /// 当前是否方便回答（开会或不在工作时间时弹窗可能被延后或转发）
+    availability: Availability,
+    option_packs: Vec<OptionPackSummary>,
+}
+
+/// 可用的选项组（只列出选项文本）
+#[derive(Debug, Serialize)]
+struct OptionPackSummary {
+    name: String,
+    description: Option<String>,
+    options: Vec<String>,
+}
+
+impl UserPreferences {
+    fn from_config(config: &AppConfig, availability: Availability) -> Self {
+        let preferences = &config.user_preferences;
+        let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        Self {
+            preferred_language: non_empty(&preferences.preferred_language),
+            result_language: config.result_language,
+            style_notes: non_empty(&preferences.style_notes),
+            working_hours: non_empty(&preferences.working_hours),
+            working_hours_schedule: config.availability.working_hours.clone(),
+            availability,
+            option_packs: config.option_packs.iter()
+                .map(|pack| OptionPackSummary {
+                    name: pack.name.clone(),
+                    description: pack.description.clone(),
+                    options: pack.options.iter().map(|o| o.label().to_string()).collect(),
+                })
+                .collect(),
         }
     }
 }
 
+/// 通过 MCP sampling 请求客户端的模型优化文本
+async fn optimize_via_sampling(
+    context: &RequestContext<RoleServer>,
+    text: &str,
+    system_prompt: &str,
+) -> anyhow::Result<String> {
+    log::info!("通过 MCP sampling 优化文本");
+    let param: CreateMessageRequestParam = serde_json::from_value(sampling::request_param(system_prompt, text))?;
+    let result = context.peer.create_message(param).await?;
+    sampling::response_text(&serde_json::to_value(&result.message)?)
+        .ok_or_else(|| anyhow::anyhow!("客户端返回的结果不包含文本"))
+}
+
+/// 读取可内嵌到结果中的小文本文件
+/// 
+/// 文件超过 `max_bytes`（为 0 时禁用）、读取失败或判定为二进制文件时返回 None
+async fn read_embeddable_text(path: &str, max_bytes: u64) -> Option<String> {
+    if max_bytes == 0 {
+        return None;
+    }
+    
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    if !metadata.is_file() || metadata.len() > max_bytes {
+        return None;
+    }
+    
+    let bytes = tokio::fs::read(path).await.ok()?;
+    if is_probably_binary(&bytes) {
+        log::debug!("跳过二进制文件: {}", path);
+        return None;
+    }
+    
+    String::from_utf8(bytes).ok()
+}
+
+/// 粗略判断数据是否为二进制（前 8KB 中包含 NUL 字节）
+fn is_probably_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|b| *b == 0)
+}
+
+/// 根据文件扩展名推断代码块语言标识
+fn code_fence_language(path: &str) -> &str {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
+
 impl Default for McpServer {
     fn default() -> Self {
         Self::new()
@@ -241,28 +1550,277 @@ fn remove_schema_field(tool: &Tool) -> Tool {
     }
 }
 
+/// 返回结构化结果的工具的输出 schema
+fn output_schema(tool_name: &str) -> Option<Arc<JsonObject>> {
+    let schema = match tool_name {
+        "whale_interactive_feedback" => schemars::schema_for!(FeedbackResult),
+        "whale_optimize_user_input" => schemars::schema_for!(OptimizeResult),
+        _ => return None,
+    };
+    let mut schema = serde_json::to_value(schema).ok()?.as_object()?.clone();
+    schema.remove("$schema");
+    Some(Arc::new(schema))
+}
+
+/// 优化类型 prompt 的参数名
+const OPTIMIZATION_PROMPT_ARGUMENT: &str = "text";
+
+/// 弹窗中显示的同一线程历史问答条数
+const THREAD_HISTORY_LIMIT: usize = 5;
+
+/// 客户端断开后等待弹窗关闭的最长时间
+const DISCONNECT_CLEANUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 定期清理残留临时文件的间隔
+const TEMP_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// 等待用户响应期间发送进度通知的间隔
+const WAITING_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// 启动弹窗并等待响应
+/// 
+/// 客户端提供 progressToken 时，等待期间定期发送进度通知（已等待的秒数），
+/// 避免超时较短的客户端在用户作答前中止请求。
+/// 客户端断开连接时关闭弹窗并返回 `client_disconnected` 响应
+async fn wait_for_popup(
+    sessions: &PopupSessions,
+    request: &PopupRequest,
+    timeout: Option<std::time::Duration>,
+    context: &RequestContext<RoleServer>,
+    disconnected: tokio::sync::watch::Receiver<bool>,
+    updates: Option<tokio::sync::mpsc::UnboundedReceiver<PopupUpdate>>,
+) -> anyhow::Result<PopupResponse> {
+    let mut disconnected_signal = disconnected.clone();
+    let cancelled = async move {
+        tokio::select! {
+            _ = context.ct.cancelled() => {}
+            _ = disconnected_signal.wait_for(|d| *d) => {}
+        }
+    };
+    let result = wait_for_popup_progress(sessions, request, timeout, context, cancelled, updates).await;
+    
+    match result {
+        Ok(response) if response.cancelled && *disconnected.borrow() => {
+            log::warn!("MCP 客户端已断开，弹窗 {} 已关闭", request.id);
+            Ok(PopupResponse::client_disconnected(&request.id))
+        }
+        other => other,
+    }
+}
+
+/// 等待弹窗响应，期间定期发送进度通知
+async fn wait_for_popup_progress(
+    sessions: &PopupSessions,
+    request: &PopupRequest,
+    timeout: Option<std::time::Duration>,
+    context: &RequestContext<RoleServer>,
+    cancelled: impl std::future::Future<Output = ()>,
+    updates: Option<tokio::sync::mpsc::UnboundedReceiver<PopupUpdate>>,
+) -> anyhow::Result<PopupResponse> {
+    let waiting = sessions.launch_and_wait(request, timeout, cancelled, updates);
+    tokio::pin!(waiting);
+    
+    let Some(progress_token) = context.meta.get_progress_token() else {
+        return waiting.await;
+    };
+    
+    let started = std::time::Instant::now();
+    let mut ticker = tokio::time::interval_at(
+        tokio::time::Instant::now() + WAITING_PROGRESS_INTERVAL,
+        WAITING_PROGRESS_INTERVAL,
+    );
+    loop {
+        tokio::select! {
+            result = &mut waiting => return result,
+            _ = ticker.tick() => {
+                let elapsed = started.elapsed().as_secs();
+                let param = ProgressNotificationParam {
+                    progress_token: progress_token.clone(),
+                    progress: elapsed as f64,
+                    total: timeout.map(|t| t.as_secs() as f64),
+                    message: Some(format!("Waiting for user, {}s elapsed", elapsed)),
+                };
+                if let Err(e) = context.peer.notify_progress(param).await {
+                    log::warn!("Failed to send waiting progress notification: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 用户未作答时的文本和结构化结果
+fn cancelled_feedback(reason: CancelReason, timeout_secs: u64, language: ResultLanguage) -> (String, FeedbackResult) {
+    let labels = language.text();
+    let text = match reason {
+        CancelReason::UserCancelled => labels.user_cancelled.to_string(),
+        CancelReason::TimedOut => fill(labels.timed_out, &[&timeout_secs]),
+        CancelReason::ClientCancelled => labels.client_cancelled.to_string(),
+        CancelReason::ClientDisconnected => labels.client_disconnected.to_string(),
+        CancelReason::Superseded => labels.superseded.to_string(),
+        CancelReason::UserUnavailable => fill(labels.user_unavailable, &[&labels.busy]),
+    };
+    (text, FeedbackResult::cancelled(reason))
+}
+
+/// 用户不方便、问题未显示的请求的结果
+fn unavailable_feedback(availability: &Availability, escalated: bool, language: ResultLanguage) -> (String, FeedbackResult) {
+    let labels = language.text();
+    let mut text = fill(labels.user_unavailable, &[&availability.describe(language)]);
+    if escalated {
+        text = format!("{}\n{}", text, labels.escalated_via_webhook);
+    }
+    (text, FeedbackResult::cancelled(CancelReason::UserUnavailable))
+}
+
+/// 被后续调用更新的请求的结果
+fn superseded_feedback(language: ResultLanguage) -> (String, FeedbackResult) {
+    cancelled_feedback(CancelReason::Superseded, 0, language)
+}
+
+/// 描述请求的各修订，说明用户回答的是哪一版问题
+fn describe_revisions(revisions: &[String], answered: Option<u32>, language: ResultLanguage) -> String {
+    let labels = language.text();
+    let mut lines = vec![match answered {
+        Some(answered) => fill(labels.revisions_answered, &[&answered, &revisions.len()]),
+        None => fill(labels.revisions_updated, &[&(revisions.len() - 1)]),
+    }];
+    lines.extend(revisions.iter().enumerate().map(|(i, message)| format!("{}. {}", i + 1, audit::summarize(message))));
+    lines.join("\n")
+}
+
+/// 同时包含 Markdown 文本和结构化内容的工具结果
+fn structured_result(text: String, output: &impl Serialize) -> CallToolResult {
+    let mut result = CallToolResult::success(vec![Content::text(text)]);
+    result.structured_content = serde_json::to_value(output)
+        .inspect_err(|e| log::warn!("Failed to serialize structured result: {}", e))
+        .ok();
+    result
+}
+
+/// 被限流或熔断拒绝的调用：错误信息和结构化的拒绝原因
+fn rejected_result(rejection: &Rejection, language: ResultLanguage) -> CallToolResult {
+    let mut result = CallToolResult::error(vec![Content::text(rejection.describe(language))]);
+    result.structured_content = serde_json::to_value(rejection).ok();
+    result
+}
+
+/// base64 数据解码后的字节数
+fn base64_decoded_len(data: &str) -> usize {
+    let data = data.trim_end();
+    let padding = data.bytes().rev().take_while(|&b| b == b'=').count();
+    (data.len() / 4 * 3).saturating_sub(padding)
+}
+
+/// 格式化自动复用的上次回答
+fn format_previous_answer(previous: &SessionSummary, language: ResultLanguage) -> String {
+    let labels = language.text();
+    let mut parts = Vec::new();
+    if !previous.selected_options.is_empty() {
+        parts.push(fill(labels.selected_options, &[&previous.selected_options.join(", ")]));
+    }
+    if let Some(feedback) = previous.user_input.as_deref().filter(|f| !f.is_empty()) {
+        parts.push(fill(labels.user_feedback, &[&feedback]));
+    }
+    parts.push(fill(labels.previous_answer_applied, &[&previous.responded_at]));
+    parts.join("\n\n")
+}
+
+/// 当前请求的 MCP 客户端名称（用于匹配工作区设置）
+fn client_name(context: &RequestContext<RoleServer>) -> Option<String> {
+    context.peer.peer_info().map(|info| info.client_info.name.clone())
+}
+
+/// 发起调用的 MCP 客户端名称和版本（随请求传给弹窗）
+fn client_info(context: &RequestContext<RoleServer>) -> Option<ClientInfo> {
+    context.peer.peer_info().map(|info| ClientInfo {
+        name: info.client_info.name.clone(),
+        version: Some(info.client_info.version.clone()).filter(|v| !v.is_empty()),
+    })
+}
+
+/// 工具调用的 JSON-RPC 请求 ID
+fn tool_call_id(context: &RequestContext<RoleServer>) -> Option<String> {
+    match serde_json::to_value(&context.id).ok()? {
+        serde_json::Value::String(id) => Some(id),
+        id => Some(id.to_string()),
+    }
+}
+
+/// 客户端是否声明支持 sampling
+fn client_supports_sampling(context: &RequestContext<RoleServer>) -> bool {
+    context.peer.peer_info().is_some_and(|info| info.capabilities.sampling.is_some())
+}
+
+/// 是否能够进行文本优化（配置了 API 密钥，或可以使用客户端的模型）
+fn can_optimize(app_config: &AppConfig, context: &RequestContext<RoleServer>) -> bool {
+    app_config.api_keys.any_configured() || app_config.optimize_sampling.available(client_supports_sampling(context))
+}
+
+/// 加载应用配置（每次读取，设置修改后无需重启服务器），失败时使用默认配置
+async fn load_app_config() -> AppConfig {
+    crate::config::load_config_direct().await.unwrap_or_else(|e| {
+        log::warn!("加载配置失败，使用默认配置: {}", e);
+        AppConfig::default()
+    })
+}
+
 impl ServerHandler for McpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some(
                 "Whale Interactive Feedback MCP 服务器 - 通过 GUI 弹窗收集用户反馈".into()
             ),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder().enable_tools().enable_prompts().enable_logging().build(),
             ..Default::default()
         }
     }
     
+    fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<(), McpError>> + Send + '_ {
+        async move {
+            log::info!("客户端设置日志级别: {:?}", request.level);
+            let mut current = self.client_log_level.lock().unwrap();
+            if let Some(sender) = current.as_ref().filter(|sender| !sender.is_closed()) {
+                sender.send_replace(request.level);
+                return Ok(());
+            }
+            let Some(events) = mcp_logging::subscribe(request.level) else {
+                return Err(McpError::internal_error("日志转发不可用", None));
+            };
+            let (sender, level) = tokio::sync::watch::channel(request.level);
+            *current = Some(sender);
+            tokio::spawn(forward_logs(context.peer, events, level));
+            Ok(())
+        }
+    }
+    
     fn list_tools(
         &self,
         _request: Option<PaginatedRequestParam>,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> impl std::future::Future<Output = Result<ListToolsResult, McpError>> + Send + '_ {
         async move {
             // 获取原始工具列表
             let tools = self.tool_router.list_all();
             
-            // 移除每个工具 schema 中的 $schema 字段
-            let fixed_tools: Vec<Tool> = tools.iter().map(remove_schema_field).collect();
+            // 按配置过滤和重命名，并移除每个工具 schema 中的 $schema 字段
+            let app_config = load_app_config().await;
+            let tools_config = app_config.mcp_tools.clone().with_env();
+            let can_optimize = can_optimize(&app_config, &context);
+            let fixed_tools: Vec<Tool> = tools.iter()
+                .filter(|tool| tools_config.is_enabled(&tool.name, can_optimize))
+                .map(|tool| {
+                    let mut tool = remove_schema_field(tool);
+                    if let Some(schema) = output_schema(&tool.name) {
+                        tool.output_schema = Some(schema);
+                    }
+                    tool.name = tools_config.exposed_name(&tool.name).into();
+                    tool
+                })
+                .collect();
             
             Ok(ListToolsResult {
                 tools: fixed_tools,
@@ -272,28 +1830,311 @@ impl ServerHandler for McpServer {
         }
     }
     
+    fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ListPromptsResult, McpError>> + Send + '_ {
+        async move {
+            let config = load_app_config().await;
+            let prompts = config.visible_optimization_types()
+                .iter()
+                .map(|t| Prompt::new(
+                    t.id.clone(),
+                    Some(format!("{} - {}", t.label, t.description)),
+                    Some(vec![PromptArgument {
+                        name: OPTIMIZATION_PROMPT_ARGUMENT.to_string(),
+                        title: None,
+                        description: Some("The text to process with this prompt".to_string()),
+                        required: Some(true),
+                    }]),
+                ))
+                .collect();
+            
+            Ok(ListPromptsResult {
+                prompts,
+                next_cursor: None,
+                meta: Default::default(),
+            })
+        }
+    }
+    
+    fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<GetPromptResult, McpError>> + Send + '_ {
+        async move {
+            let config = load_app_config().await;
+            let opt_type = config.visible_optimization_types()
+                .into_iter()
+                .find(|t| t.id == request.name)
+                .ok_or_else(|| McpError::invalid_params(format!("Unknown prompt: {}", request.name), None))?;
+            
+            let text = request.arguments
+                .as_ref()
+                .and_then(|args| args.get(OPTIMIZATION_PROMPT_ARGUMENT))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            
+            Ok(GetPromptResult {
+                description: Some(opt_type.label.clone()),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    format!("{}\n\n---\n\n{}", opt_type.prompt.trim_end(), text),
+                )],
+            })
+        }
+    }
+    
     fn call_tool(
         &self,
         request: rmcp::model::CallToolRequestParam,
         context: RequestContext<RoleServer>,
     ) -> impl std::future::Future<Output = Result<rmcp::model::CallToolResult, McpError>> + Send + '_ {
         use rmcp::handler::server::tool::ToolCallContext;
-        let tool_context = ToolCallContext::new(self, request, context);
-        self.tool_router.call(tool_context)
+        let arguments = request.arguments.clone();
+        let client = client_name(&context);
+        let request_id = context.id.clone();
+        let started = std::time::Instant::now();
+        async move {
+            // 将对外名称映射回原始工具名，已禁用的工具视为不存在
+            let app_config = load_app_config().await;
+            let tools_config = app_config.mcp_tools.clone().with_env();
+            let all_tools = self.tool_router.list_all();
+            let tool_name = tools_config
+                .resolve(&request.name, all_tools.iter().map(|t| t.name.as_ref()))
+                .filter(|name| tools_config.is_enabled(name, can_optimize(&app_config, &context)))
+                .map(str::to_string)
+                .ok_or_else(|| McpError::invalid_params(format!("Unknown tool: {}", request.name), None))?;
+            
+            let mut request = request;
+            request.name = tool_name.clone().into();
+            let tool_context = ToolCallContext::new(self, request, context);
+            let result = self.tool_router.call(tool_context).await;
+            // 工具失败时返回 `Err` 文本（is_error 为 true），统计和审计日志据此记为 error
+            let outcome = match &result {
+                Ok(r) if r.is_error != Some(true) => "ok",
+                _ => "error",
+            };
+            
+            // 记录使用统计
+            if let Some(storage) = &self.storage {
+                if let Err(e) = storage.record_usage(&tool_name, outcome, started.elapsed()) {
+                    log::warn!("Failed to record usage: {}", e);
+                }
+            }
+            
+            // 写入审计日志
+            let input_metrics = self.input_metrics.lock().unwrap().remove(&request_id);
+            if let Some(path) = app_config.audit_log.log_path() {
+                let summary = match &result {
+                    Ok(r) => r.content.iter()
+                        .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    Err(e) => e.message.to_string(),
+                };
+                let record = AuditRecord {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    tool: tool_name.clone(),
+                    client,
+                    params: audit::redact(&serde_json::Value::Object(arguments.unwrap_or_default())),
+                    summary: audit::summarize(&summary),
+                    outcome: outcome.to_string(),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    input_metrics,
+                };
+                if let Err(e) = audit::append(&path, &record) {
+                    log::warn!("Failed to write audit log: {}", e);
+                }
+            }
+            
+            result
+        }
+    }
+}
+
+/// 默认 HTTP 监听地址
+pub const DEFAULT_HTTP_BIND: &str = "127.0.0.1:8765";
+
+/// MCP 传输方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McpTransport {
+    /// 标准输入输出（默认）
+    Stdio,
+    /// Streamable HTTP（POST + SSE），路径为 `/mcp`
+    Http { bind: std::net::SocketAddr },
+}
+
+impl McpTransport {
+    /// 从命令行参数和环境变量解析传输方式
+    /// 
+    /// - `--transport stdio|http` 或环境变量 `WHALE_MCP_TRANSPORT`
+    /// - `--bind <addr>` 或环境变量 `WHALE_MCP_HTTP_BIND`（默认 127.0.0.1:8765）
+    /// 
+    /// 命令行参数优先于环境变量
+    pub fn from_args_and_env(args: &[String]) -> anyhow::Result<Self> {
+        let arg_value = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .cloned()
+        };
+        
+        let transport = arg_value("--transport")
+            .or_else(|| std::env::var("WHALE_MCP_TRANSPORT").ok())
+            .unwrap_or_else(|| "stdio".to_string());
+        
+        match transport.to_ascii_lowercase().as_str() {
+            "stdio" => Ok(McpTransport::Stdio),
+            "http" | "streamable-http" => {
+                let bind = arg_value("--bind")
+                    .or_else(|| std::env::var("WHALE_MCP_HTTP_BIND").ok())
+                    .unwrap_or_else(|| DEFAULT_HTTP_BIND.to_string());
+                let bind = bind.parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid bind address '{}': {}", bind, e))?;
+                Ok(McpTransport::Http { bind })
+            }
+            other => Err(anyhow::anyhow!("Unknown transport '{}', expected 'stdio' or 'http'", other)),
+        }
+    }
+}
+
+/// 等待 SIGINT（Ctrl+C）或 SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => log::warn!("无法监听 SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// 把日志以 `notifications/message` 转发给客户端，客户端断开时结束
+async fn forward_logs(
+    peer: Peer<RoleServer>,
+    mut events: tokio::sync::broadcast::Receiver<mcp_logging::LogEvent>,
+    level: tokio::sync::watch::Receiver<LoggingLevel>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+        if !mcp_logging::should_forward(&event, *level.borrow()) {
+            continue;
+        }
+        let param = LoggingMessageNotificationParam {
+            level: mcp_logging::to_logging_level(event.level),
+            logger: Some(event.target),
+            data: serde_json::Value::String(event.message),
+        };
+        // 发送失败说明客户端已断开；这里不能再写日志，否则会产生新的转发
+        if peer.notify_logging_message(param).await.is_err() {
+            return;
+        }
     }
 }
 
-/// 运行 MCP 服务器
+/// 运行 MCP 服务器（stdio 传输）
 pub async fn run_mcp_server() -> anyhow::Result<()> {
+    run_mcp_server_with(McpTransport::Stdio).await
+}
+
+/// 使用指定传输方式运行 MCP 服务器
+pub async fn run_mcp_server_with(transport: McpTransport) -> anyhow::Result<()> {
     log::info!("启动 MCP 服务器...");
     
-    let server = McpServer::new();
-    let transport = rmcp::transport::io::stdio();
-    let server_handle = server.serve(transport).await?;
+    let app_config = load_app_config().await;
+    // 配置的临时文件目录（环境变量优先）
+    crate::popup::set_configured_ipc_dir(app_config.ipc_dir.as_deref());
+    let ipc_dir = crate::popup::ipc_dir();
+    if let Err(e) = std::fs::create_dir_all(&ipc_dir) {
+        log::warn!("创建临时文件目录失败 {:?}: {}", ipc_dir, e);
+    }
+    log::info!("临时文件目录: {:?}", ipc_dir);
+    // 启动时和之后每小时清理崩溃残留的请求/响应临时文件
+    if app_config.temp_file_max_age_hours > 0 {
+        let max_age = std::time::Duration::from_secs(app_config.temp_file_max_age_hours * 3600);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(TEMP_SWEEP_INTERVAL);
+            loop {
+                ticker.tick().await;
+                crate::popup::sweep_orphaned_temp_files(max_age).await;
+            }
+        });
+    }
+    // 启动时和之后每天按保留策略清理本地数据和孤立附件
+    tokio::spawn(crate::janitor::run_daily(load_app_config));
+    // 按间隔刷新日历，判断用户是否在开会
+    tokio::spawn(availability::poll_calendar(load_app_config));
+    // 启动时检测一次音频设备，没有设备时之后的通知音直接跳过
+    tokio::task::spawn_blocking(AudioNotifier::availability);
     
-    log::info!("MCP 服务器已启动，等待连接...");
+    let server = McpServer::new();
     
-    server_handle.waiting().await?;
+    match transport {
+        McpTransport::Stdio => {
+            let transport = rmcp::transport::io::stdio();
+            let server_handle = server.clone().serve(transport).await?;
+            
+            log::info!("MCP 服务器已启动，等待连接...");
+            
+            // stdin 关闭说明客户端（IDE）已退出；收到终止信号时同样停止服务
+            let cancellation = server_handle.cancellation_token();
+            let result = tokio::select! {
+                result = server_handle.waiting() => result.map(|_| ()),
+                _ = shutdown_signal() => {
+                    log::info!("收到终止信号，停止 MCP 服务器");
+                    cancellation.cancel();
+                    Ok(())
+                }
+            };
+            
+            // 弹窗的回答已无人接收，关闭所有未完成的弹窗
+            log::info!("MCP 客户端已断开连接，关闭未完成的弹窗");
+            server.shutdown().await;
+            result?;
+        }
+        McpTransport::Http { bind } => {
+            use rmcp::transport::streamable_http_server::{
+                session::local::LocalSessionManager, StreamableHttpService,
+            };
+            
+            // 所有 HTTP 会话共享同一个服务器实例的状态（弹窗队列、记住的确认结果），
+            // 客户端断线重连后仍然有效
+            let service = StreamableHttpService::new(
+                move || Ok(server.clone()),
+                LocalSessionManager::default().into(),
+                Default::default(),
+            );
+            let router = axum::Router::new().nest_service("/mcp", service);
+            let listener = tokio::net::TcpListener::bind(bind).await?;
+            
+            log::info!("MCP 服务器已启动，监听 http://{}/mcp", bind);
+            
+            let shutdown_server = server.clone();
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    shutdown_signal().await;
+                    log::info!("收到终止信号，关闭未完成的弹窗");
+                    shutdown_server.shutdown().await;
+                })
+                .await?;
+        }
+    }
     
     log::info!("MCP 服务器已关闭");
     Ok(())
@@ -309,14 +2150,56 @@ pub fn validate_interactive_feedback_params(params: &InteractiveFeedbackParams)
     }
     
     if let Some(ref options) = params.predefined_options {
-        if options.iter().any(|opt| opt.trim().is_empty()) {
+        if options.iter().any(|opt| opt.label().trim().is_empty() || opt.id().trim().is_empty()) {
             return Err("predefined_options 中不能包含空字符串".to_string());
         }
+        
+        let mut ids = std::collections::HashSet::new();
+        if let Some(duplicate) = options.iter().find(|opt| !ids.insert(opt.id())) {
+            return Err(format!("predefined_options 中的 id 重复: {}", duplicate.id()));
+        }
+    }
+    
+    if let Some(RespondBy::Timestamp(value)) = &params.respond_by {
+        if sla::parse_deadline(value).is_none() {
+            return Err("respond_by 必须是秒数或 RFC 3339 时间".to_string());
+        }
+    }
+    
+    if params.auto_submit_after_secs.is_some_and(|secs| secs > 0) {
+        if params.default_option.is_none() {
+            return Err("设置 auto_submit_after_secs 时必须提供 default_option".to_string());
+        }
+        if params.default_option().is_none() {
+            return Err("default_option 必须是 predefined_options 中某个选项的 id 或文本".to_string());
+        }
     }
     
     Ok(())
 }
 
+/// 验证表单字段定义，错误信息使用指定的结果语言
+pub fn validate_form_fields(fields: &[FormField], language: ResultLanguage) -> Result<(), String> {
+    let labels = language.text();
+    if fields.is_empty() {
+        return Err(fill(labels.empty_param, &[&"fields"]));
+    }
+    
+    let mut names = std::collections::HashSet::new();
+    for field in fields {
+        if field.name.trim().is_empty() {
+            return Err(labels.form_field_name_empty.to_string());
+        }
+        if !names.insert(field.name.as_str()) {
+            return Err(fill(labels.form_field_duplicate, &[&field.name]));
+        }
+        if field.kind == FormFieldKind::Select && field.options.is_empty() {
+            return Err(fill(labels.form_select_options_required, &[&field.name]));
+        }
+    }
+    Ok(())
+}
+
 /// 验证 optimize_user_input 参数
 pub fn validate_optimize_user_input_params(params: &OptimizeUserInputParams) -> Result<(), String> {
     if params.text.trim().is_empty() {
@@ -335,3 +2218,223 @@ pub fn validate_optimize_user_input_params(params: &OptimizeUserInputParams) ->
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feedback_params(value: serde_json::Value) -> InteractiveFeedbackParams {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_mixed_predefined_options() {
+        let params = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": [
+                "继续",
+                { "id": "abort", "label": "停止", "description": "放弃本次修改", "exclusive": true },
+                { "id": "later", "label": "稍后" }
+            ]
+        }));
+        let options = params.predefined_options.as_deref().unwrap();
+        assert!(matches!(&options[0], PredefinedOptionParam::Label(label) if label == "继续"));
+        assert!(matches!(&options[1], PredefinedOptionParam::Structured { .. }));
+        // 纯文本选项的 ID 即文本本身
+        assert_eq!(options.iter().map(PredefinedOptionParam::id).collect::<Vec<_>>(), ["继续", "abort", "later"]);
+        assert_eq!(options.iter().map(PredefinedOptionParam::label).collect::<Vec<_>>(), ["继续", "停止", "稍后"]);
+
+        let details: Vec<OptionDetail> = options.iter().map(PredefinedOptionParam::to_detail).collect();
+        assert_eq!(details[1].description.as_deref(), Some("放弃本次修改"));
+        assert!(details[1].exclusive);
+        assert!(!details[0].exclusive && !details[2].exclusive);
+        assert!(validate_interactive_feedback_params(&params).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_option_ids_rejected() {
+        let structured = feedback_params(json!({
+            "message": "选哪个？",
+            "predefined_options": [{ "id": "a", "label": "选项 A" }, { "id": "a", "label": "另一个 A" }]
+        }));
+        assert!(validate_interactive_feedback_params(&structured).unwrap_err().contains("a"));
+
+        // 纯文本选项与结构化选项的 ID 冲突
+        let mixed = feedback_params(json!({
+            "message": "选哪个？",
+            "predefined_options": ["yes", { "id": "yes", "label": "确定" }]
+        }));
+        assert!(validate_interactive_feedback_params(&mixed).is_err());
+
+        // 显示文本相同但 ID 不同是允许的
+        let same_label = feedback_params(json!({
+            "message": "选哪个？",
+            "predefined_options": [{ "id": "a", "label": "同名" }, { "id": "b", "label": "同名" }]
+        }));
+        assert!(validate_interactive_feedback_params(&same_label).is_ok());
+    }
+
+    #[test]
+    fn test_selected_labels_map_to_ids() {
+        let params = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", { "id": "abort", "label": "停止" }]
+        }));
+        let details = params.predefined_options.iter().flatten().map(PredefinedOptionParam::to_detail).collect();
+        let request = PopupRequest::new(Some(params.message.clone()), None, None).with_option_details(details);
+        assert_eq!(request.predefined_options.as_deref(), Some(&["继续".to_string(), "停止".to_string()][..]));
+
+        let selected = vec!["停止".to_string(), "继续".to_string(), "自定义快捷选项".to_string()];
+        // 按选择顺序映射；未知文本（如用户自定义快捷选项）原样返回
+        assert_eq!(request.selected_option_ids(&selected), ["abort", "继续", "自定义快捷选项"]);
+        assert!(request.selected_option_ids(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_auto_answered_structured_result() {
+        let params = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", { "id": "abort", "label": "停止" }]
+        }));
+        let details = params.predefined_options.iter().flatten().map(PredefinedOptionParam::to_detail).collect();
+        let request = PopupRequest::new(Some(params.message.clone()), None, None).with_option_details(details);
+
+        // 复用上次的回答时，结构化结果中的 ID 来自当前请求的选项而不是显示文本
+        let answer = FeedbackResult::auto_answered(&request, vec!["停止".to_string()], Some(String::new()));
+        let result = structured_result("停止".to_string(), &answer);
+        let structured = result.structured_content.expect("structured content");
+        assert_eq!(structured["auto_answered"], json!(true));
+        assert_eq!(structured["selected_options"], json!(["停止"]));
+        assert_eq!(structured["selected_option_ids"], json!(["abort"]));
+        assert_eq!(structured["feedback"], json!(null));
+
+        // 输出 schema 声明了结构化结果中的字段
+        let schema = output_schema("whale_interactive_feedback").expect("feedback schema");
+        let properties = schema["properties"].as_object().expect("schema properties");
+        for field in ["auto_answered", "selected_options", "selected_option_ids", "feedback"] {
+            assert!(properties.contains_key(field), "schema 缺少字段 {}", field);
+        }
+        assert!(output_schema("whale_confirm").is_none());
+    }
+
+    #[test]
+    fn test_auto_submit_uses_default_label() {
+        let params = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", { "id": "abort", "label": "停止" }],
+            "default_option": "abort",
+            "auto_submit_after_secs": 30
+        }));
+        // 按 ID 匹配默认选项，GUI 使用显示文本
+        assert_eq!(params.auto_submit(), Some(AutoSubmit { option: "停止".to_string(), after_secs: 30 }));
+
+        let by_label = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", "停止"],
+            "default_option": "继续",
+            "auto_submit_after_secs": 5
+        }));
+        assert_eq!(by_label.auto_submit().map(|a| a.option), Some("继续".to_string()));
+    }
+
+    #[test]
+    fn test_auto_submit_ignored_when_incomplete() {
+        // 默认选项不在 predefined_options 中
+        let unknown_default = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", "停止"],
+            "default_option": "跳过",
+            "auto_submit_after_secs": 30
+        }));
+        assert!(unknown_default.default_option().is_none());
+        assert_eq!(unknown_default.auto_submit(), None);
+        assert!(validate_interactive_feedback_params(&unknown_default).is_err());
+
+        // 设置了自动提交但没有默认选项
+        let no_default = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", "停止"],
+            "auto_submit_after_secs": 30
+        }));
+        assert_eq!(no_default.auto_submit(), None);
+        assert!(validate_interactive_feedback_params(&no_default).is_err());
+
+        // 只有默认选项或自动提交时间为 0 时不自动提交
+        let no_timer = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续", "停止"],
+            "default_option": "继续"
+        }));
+        assert_eq!(no_timer.auto_submit(), None);
+        assert!(validate_interactive_feedback_params(&no_timer).is_ok());
+        let zero = feedback_params(json!({
+            "message": "继续吗？",
+            "predefined_options": ["继续"],
+            "default_option": "继续",
+            "auto_submit_after_secs": 0
+        }));
+        assert_eq!(zero.auto_submit(), None);
+    }
+
+    #[test]
+    fn test_respond_by_deadline() {
+        let deadline = RespondBy::Seconds(60).deadline().unwrap();
+        assert!(deadline > chrono::Utc::now());
+        assert!(RespondBy::Timestamp("2024-01-01T10:00:00Z".to_string()).deadline().is_some());
+        // 客户端传入的超大秒数不会导致 panic
+        assert_eq!(RespondBy::Seconds(u64::MAX).deadline(), None);
+        assert_eq!(RespondBy::Seconds(i64::MAX as u64).deadline(), None);
+    }
+
+    #[test]
+    fn test_is_probably_binary() {
+        assert!(!is_probably_binary(b"fn main() {}\n"));
+        assert!(!is_probably_binary(b""));
+        assert!(is_probably_binary(b"PK\x03\x04\x00\x00"));
+        // 只检查前 8KB
+        let mut late_nul = vec![b'a'; 8192];
+        late_nul.push(0);
+        assert!(!is_probably_binary(&late_nul));
+    }
+
+    #[tokio::test]
+    async fn test_read_embeddable_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("main.rs");
+        std::fs::write(&text, "fn main() {}\n").unwrap();
+        let text = text.to_str().unwrap();
+
+        assert_eq!(read_embeddable_text(text, 1024).await.as_deref(), Some("fn main() {}\n"));
+        // 上限为 0 时禁用内嵌
+        assert_eq!(read_embeddable_text(text, 0).await, None);
+        // 目录和不存在的文件
+        assert_eq!(read_embeddable_text(dir.path().to_str().unwrap(), 1024).await, None);
+        assert_eq!(read_embeddable_text(&format!("{}.missing", text), 1024).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_read_embeddable_text_oversized() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.txt");
+        std::fs::write(&path, "a".repeat(2048)).unwrap();
+        let path = path.to_str().unwrap();
+
+        assert_eq!(read_embeddable_text(path, 2047).await, None);
+        // 恰好等于上限时仍然内嵌
+        assert_eq!(read_embeddable_text(path, 2048).await.map(|t| t.len()), Some(2048));
+    }
+
+    #[tokio::test]
+    async fn test_read_embeddable_text_skips_binary_and_non_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("image.png");
+        std::fs::write(&binary, b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").unwrap();
+        assert_eq!(read_embeddable_text(binary.to_str().unwrap(), 1024).await, None);
+
+        // 不含 NUL 但不是合法 UTF-8（Latin-1 编码）
+        let latin1 = dir.path().join("latin1.txt");
+        std::fs::write(&latin1, b"caf\xe9 cr\xe8me").unwrap();
+        assert_eq!(read_embeddable_text(latin1.to_str().unwrap(), 1024).await, None);
+    }
+}
@@ -0,0 +1,133 @@
+//! MCP 工具命名与启用配置
+//!
+//! 支持为工具设置统一前缀（多实例时避免重名）、单独重命名或禁用工具，
+//! 并在未配置 API 密钥时隐藏依赖 LLM 的工具。`list_tools` 每次按当前配置过滤。
+//!
+//! 环境变量优先于配置文件：
+//! - `WHALE_TOOL_PREFIX`：工具名前缀（替换默认的 `whale_`）
+//! - `WHALE_DISABLED_TOOLS`：逗号分隔的禁用工具列表（原始工具名）
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 工具默认前缀
+pub const DEFAULT_TOOL_PREFIX: &str = "whale_";
+
+/// 需要 LLM API 密钥的工具
+pub const OPTIMIZE_TOOL: &str = "whale_optimize_user_input";
+
+/// 前缀环境变量
+pub const TOOL_PREFIX_ENV: &str = "WHALE_TOOL_PREFIX";
+/// 禁用工具环境变量
+pub const DISABLED_TOOLS_ENV: &str = "WHALE_DISABLED_TOOLS";
+
+fn default_true() -> bool {
+    true
+}
+
+/// MCP 工具配置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolsConfig {
+    /// 工具名前缀（替换默认的 `whale_`）
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// 单独重命名（key: 原始工具名），优先于前缀
+    #[serde(default)]
+    pub rename: HashMap<String, String>,
+    /// 禁用的工具（原始工具名）
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// 未配置 API 密钥（且无法使用客户端 sampling）时隐藏文本优化工具
+    #[serde(default = "default_true")]
+    pub hide_optimize_without_api_keys: bool,
+}
+
+impl Default for McpToolsConfig {
+    fn default() -> Self {
+        Self {
+            prefix: None,
+            rename: HashMap::new(),
+            disabled: Vec::new(),
+            hide_optimize_without_api_keys: true,
+        }
+    }
+}
+
+impl McpToolsConfig {
+    /// 应用环境变量覆盖
+    pub fn with_env(mut self) -> Self {
+        if let Ok(prefix) = std::env::var(TOOL_PREFIX_ENV) {
+            self.prefix = Some(prefix);
+        }
+        if let Ok(disabled) = std::env::var(DISABLED_TOOLS_ENV) {
+            self.disabled = disabled
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        self
+    }
+
+    /// 对外暴露的工具名
+    pub fn exposed_name(&self, original: &str) -> String {
+        if let Some(name) = self.rename.get(original).filter(|n| !n.trim().is_empty()) {
+            return name.trim().to_string();
+        }
+        match (self.prefix.as_deref(), original.strip_prefix(DEFAULT_TOOL_PREFIX)) {
+            (Some(prefix), Some(rest)) => format!("{}{}", prefix, rest),
+            _ => original.to_string(),
+        }
+    }
+
+    /// 工具是否可用
+    ///
+    /// # Arguments
+    /// * `can_optimize` - 是否能够进行文本优化（配置了 LLM API 密钥或可使用客户端 sampling）
+    pub fn is_enabled(&self, original: &str, can_optimize: bool) -> bool {
+        if self.disabled.iter().any(|d| d == original) {
+            return false;
+        }
+        !(original == OPTIMIZE_TOOL && self.hide_optimize_without_api_keys && !can_optimize)
+    }
+
+    /// 根据对外名称查找原始工具名
+    pub fn resolve<'a>(&self, exposed: &str, originals: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+        originals.into_iter().find(|original| self.exposed_name(original) == exposed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exposed_name_and_resolve() {
+        let config = McpToolsConfig {
+            prefix: Some("whale2_".to_string()),
+            rename: HashMap::from([("whale_confirm".to_string(), "approve".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(config.exposed_name("whale_interactive_feedback"), "whale2_interactive_feedback");
+        assert_eq!(config.exposed_name("whale_confirm"), "approve");
+
+        let originals = ["whale_interactive_feedback", "whale_confirm"];
+        assert_eq!(config.resolve("approve", originals), Some("whale_confirm"));
+        assert_eq!(config.resolve("whale2_interactive_feedback", originals), Some("whale_interactive_feedback"));
+        assert_eq!(config.resolve("whale_confirm", originals), None);
+    }
+
+    #[test]
+    fn test_is_enabled() {
+        let config = McpToolsConfig { disabled: vec!["whale_notify".to_string()], ..Default::default() };
+        assert!(!config.is_enabled("whale_notify", true));
+        assert!(config.is_enabled("whale_confirm", false));
+        assert!(!config.is_enabled(OPTIMIZE_TOOL, false));
+        assert!(config.is_enabled(OPTIMIZE_TOOL, true));
+
+        let show_all = McpToolsConfig { hide_optimize_without_api_keys: false, ..Default::default() };
+        assert!(show_all.is_enabled(OPTIMIZE_TOOL, false));
+    }
+}
@@ -0,0 +1,18 @@
+//! 系统通知模块
+//!
+//! 供 `whale_notify` 工具使用：显示桌面通知，不启动 GUI 也不等待用户响应。
+
+use anyhow::Result;
+
+/// 未指定标题时使用的通知标题
+pub const DEFAULT_TITLE: &str = "Whale Interactive Feedback";
+
+/// 显示桌面通知（阻塞直到通知提交给系统，需在阻塞线程中调用）
+pub fn show(title: &str, message: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname(DEFAULT_TITLE)
+        .summary(title)
+        .body(message)
+        .show()?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! 预定义选项组
+//!
+//! 在配置中定义常用的选项组（如 `code-review`：LGTM / Needs changes / Question），
+//! Agent 通过 `options_pack` 参数按名称引用，服务端在创建弹窗请求前展开为预定义选项。
+
+use serde::{Deserialize, Serialize};
+
+use crate::mcp_server::PredefinedOptionParam;
+
+/// 命名的选项组
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OptionPack {
+    /// 名称（Agent 引用时不区分大小写）
+    pub name: String,
+    /// 选项组用途说明
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 纯文本选项或结构化选项 `{id, label, description?, exclusive?}`
+    pub options: Vec<PredefinedOptionParam>,
+}
+
+/// 按名称查找选项组
+pub fn find_pack<'a>(packs: &'a [OptionPack], name: &str) -> Option<&'a OptionPack> {
+    let name = name.trim();
+    packs.iter().find(|pack| pack.name.trim().eq_ignore_ascii_case(name))
+}
+
+/// 可用的选项组名称（用于错误提示）
+pub fn pack_names(packs: &[OptionPack]) -> String {
+    packs.iter().map(|pack| pack.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// 展开选项组：选项组中的选项在前，调用时额外提供的选项追加在后（ID 重复的以选项组为准）
+pub fn expand(pack: &OptionPack, extra: Option<Vec<PredefinedOptionParam>>) -> Vec<PredefinedOptionParam> {
+    let mut options = pack.options.clone();
+    for option in extra.into_iter().flatten() {
+        if !options.iter().any(|o| o.id() == option.id()) {
+            options.push(option);
+        }
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_review() -> OptionPack {
+        serde_json::from_value(serde_json::json!({
+            "name": "code-review",
+            "options": [
+                "LGTM",
+                { "id": "changes", "label": "Needs changes", "exclusive": true },
+                "Question"
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_pack_ignores_case() {
+        let packs = vec![code_review()];
+        assert!(find_pack(&packs, " Code-Review ").is_some());
+        assert!(find_pack(&packs, "deploy").is_none());
+        assert_eq!(pack_names(&packs), "code-review");
+    }
+
+    #[test]
+    fn test_expand_appends_extra_options() {
+        let extra = vec![
+            PredefinedOptionParam::Label("Ship it".to_string()),
+            PredefinedOptionParam::Label("LGTM".to_string()),
+        ];
+        let options = expand(&code_review(), Some(extra));
+        let ids: Vec<_> = options.iter().map(PredefinedOptionParam::id).collect();
+        assert_eq!(ids, vec!["LGTM", "changes", "Question", "Ship it"]);
+        assert_eq!(options[1].label(), "Needs changes");
+    }
+}
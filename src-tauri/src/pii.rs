@@ -0,0 +1,240 @@
+//! 敏感信息扫描模块
+//!
+//! 文本优化会把用户输入发送给第三方 LLM API。发送前在本地扫描邮箱、手机号、
+//! 证件号、银行卡号等敏感信息，根据配置提示用户确认，或自动替换为占位符
+//! （优化结果返回后再还原），避免误把个人信息发给外部服务。
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// 检测到敏感信息时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum PiiAction {
+    /// 不扫描
+    Off,
+    /// 提示用户确认后再发送
+    #[default]
+    Warn,
+    /// 自动替换为占位符
+    Mask,
+}
+
+/// 敏感信息扫描配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PiiScanConfig {
+    #[serde(default)]
+    pub action: PiiAction,
+}
+
+/// 敏感信息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    IdNumber,
+    CardNumber,
+}
+
+impl PiiKind {
+    /// 占位符名称
+    fn placeholder(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "EMAIL",
+            PiiKind::Phone => "PHONE",
+            PiiKind::IdNumber => "ID_NUMBER",
+            PiiKind::CardNumber => "CARD_NUMBER",
+        }
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "邮箱",
+            PiiKind::Phone => "手机号",
+            PiiKind::IdNumber => "证件号",
+            PiiKind::CardNumber => "银行卡号",
+        }
+    }
+}
+
+/// 单个匹配（字节偏移）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// 扫描结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PiiScanReport {
+    pub action: PiiAction,
+    pub matches: Vec<PiiMatch>,
+}
+
+/// 替换后的文本及占位符对应的原文
+#[derive(Debug, Clone, Default)]
+pub struct MaskedText {
+    pub text: String,
+    pub replacements: Vec<(String, String)>,
+}
+
+impl MaskedText {
+    /// 将 LLM 返回结果中的占位符还原为原文
+    pub fn unmask(&self, text: &str) -> String {
+        self.replacements
+            .iter()
+            .fold(text.to_string(), |acc, (placeholder, original)| acc.replace(placeholder, original))
+    }
+}
+
+fn patterns() -> &'static [(PiiKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(PiiKind, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (PiiKind::Email, r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+            // 中国居民身份证号、美国 SSN
+            (PiiKind::IdNumber, r"\b[1-9]\d{5}(?:19|20)\d{2}(?:0[1-9]|1[0-2])(?:0[1-9]|[12]\d|3[01])\d{3}[\dXx]\b|\b\d{3}-\d{2}-\d{4}\b"),
+            // 13~19 位数字，允许空格或短横线分组，需通过 Luhn 校验
+            (PiiKind::CardNumber, r"\b\d(?:[ -]?\d){12,18}\b"),
+            // 中国大陆手机号、国际格式号码
+            (PiiKind::Phone, r"(?:\+86[ -]?)?\b1[3-9]\d{9}\b|\+\d{1,3}[ -]?\(?\d{1,4}\)?(?:[ -]?\d{2,4}){2,4}"),
+        ]
+        .into_iter()
+        .map(|(kind, pattern)| (kind, Regex::new(pattern).expect("invalid PII pattern")))
+        .collect()
+    })
+}
+
+/// Luhn 校验（银行卡号）
+fn luhn_valid(digits: &str) -> bool {
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum % 10 == 0
+}
+
+/// 扫描文本中的敏感信息，结果按位置排序且互不重叠（先匹配的类型优先）
+pub fn scan(text: &str) -> Vec<PiiMatch> {
+    let mut matches: Vec<PiiMatch> = Vec::new();
+    for (kind, regex) in patterns() {
+        for m in regex.find_iter(text) {
+            if *kind == PiiKind::CardNumber && !luhn_valid(m.as_str()) {
+                continue;
+            }
+            if matches.iter().any(|existing| m.start() < existing.end && existing.start < m.end()) {
+                continue;
+            }
+            matches.push(PiiMatch { kind: *kind, start: m.start(), end: m.end(), text: m.as_str().to_string() });
+        }
+    }
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+/// 将敏感信息替换为编号占位符（如 `[EMAIL_1]`），相同内容使用同一个占位符
+pub fn mask(text: &str) -> MaskedText {
+    let mut masked = MaskedText::default();
+    let mut last = 0;
+    for m in scan(text) {
+        masked.text.push_str(&text[last..m.start]);
+        let placeholder = match masked.replacements.iter().find(|(_, original)| *original == m.text) {
+            Some((placeholder, _)) => placeholder.clone(),
+            None => {
+                let index = masked.replacements.iter().filter(|(p, _)| p.starts_with(&format!("[{}_", m.kind.placeholder()))).count() + 1;
+                let placeholder = format!("[{}_{}]", m.kind.placeholder(), index);
+                masked.replacements.push((placeholder.clone(), m.text.clone()));
+                placeholder
+            }
+        };
+        masked.text.push_str(&placeholder);
+        last = m.end;
+    }
+    masked.text.push_str(&text[last..]);
+    masked
+}
+
+/// 描述扫描结果，如 "邮箱 ×1、手机号 ×2"
+pub fn describe(matches: &[PiiMatch]) -> String {
+    let mut counts: Vec<(PiiKind, usize)> = Vec::new();
+    for m in matches {
+        match counts.iter_mut().find(|(kind, _)| *kind == m.kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((m.kind, 1)),
+        }
+    }
+    counts
+        .iter()
+        .map(|(kind, count)| format!("{} ×{}", kind.label(), count))
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+/// 按配置处理即将发送给 LLM 的文本
+///
+/// # Arguments
+/// * `allow` - 用户已确认发送（提示模式下跳过检查）
+///
+/// # Returns
+/// * 可以发送的文本（屏蔽模式下已替换占位符）；提示模式下检测到敏感信息且未确认时返回错误
+pub fn guard(config: &PiiScanConfig, text: &str, allow: bool) -> Result<MaskedText, String> {
+    let unchanged = || MaskedText { text: text.to_string(), replacements: Vec::new() };
+    match config.action {
+        PiiAction::Off => Ok(unchanged()),
+        PiiAction::Mask => Ok(mask(text)),
+        PiiAction::Warn if allow => Ok(unchanged()),
+        PiiAction::Warn => {
+            let matches = scan(text);
+            if matches.is_empty() {
+                Ok(unchanged())
+            } else {
+                Err(format!("文本中可能包含敏感信息（{}），请确认后再发送", describe(&matches)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_detects_pii() {
+        let text = "联系 alice@example.com 或 13812345678，卡号 4111 1111 1111 1111，身份证 11010519491231002X";
+        let kinds: Vec<PiiKind> = scan(text).iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, vec![PiiKind::Email, PiiKind::Phone, PiiKind::CardNumber, PiiKind::IdNumber]);
+
+        // 不通过 Luhn 校验的长数字不算银行卡号
+        assert!(scan("build 1234567890123 finished").is_empty());
+        assert!(scan("no personal data here, version 1.2.3").is_empty());
+    }
+
+    #[test]
+    fn test_mask_and_unmask() {
+        let text = "mail bob@corp.io, cc bob@corp.io and eve@corp.io";
+        let masked = mask(text);
+        assert_eq!(masked.text, "mail [EMAIL_1], cc [EMAIL_1] and [EMAIL_2]");
+        assert_eq!(masked.unmask("Please mail [EMAIL_1] and [EMAIL_2]."), "Please mail bob@corp.io and eve@corp.io.");
+    }
+
+    #[test]
+    fn test_guard_modes() {
+        let text = "call 13812345678";
+        let warn = PiiScanConfig { action: PiiAction::Warn };
+        assert!(guard(&warn, text, false).unwrap_err().contains("手机号 ×1"));
+        assert_eq!(guard(&warn, text, true).unwrap().text, text);
+        assert_eq!(guard(&PiiScanConfig { action: PiiAction::Mask }, text, false).unwrap().text, "call [PHONE_1]");
+        assert_eq!(guard(&PiiScanConfig { action: PiiAction::Off }, text, false).unwrap().text, text);
+    }
+}
@@ -8,27 +8,513 @@
 //! 3. GUI 进程写入响应文件后退出
 //! 4. MCP Server 读取响应文件并返回结果
 //!
+//! 等待期间 MCP Server 可以通过 GUI 进程的 stdin 发送 [`PopupUpdate`]（每行一个 JSON），
+//! 弹窗原地刷新；stdin 关闭说明 MCP Server 已退出，GUI 随之关闭。
+//!
+//! ## 响应通道
+//! 服务端为每个请求监听一个本地 socket（macOS/Linux 使用 Unix 域套接字，Windows 使用命名管道），
+//! GUI 通过它直接发送响应，避免轮询临时文件的竞争和进程崩溃后残留的文件。
+//! 无法创建 socket 或 GUI 连接失败时仍使用响应文件。
+//! 响应文件先写入临时文件再重命名，并带有格式版本和 SHA-256 校验和，
+//! GUI 进程很快退出时服务端也不会解析到写了一半的内容。
+//! 请求文件和响应文件使用每个请求的密钥加密（见 [`crate::ipc_crypto`]）。
+//!
+//! ## 版本兼容
+//! MCP Server 和 GUI 可能来自不同版本（自动更新不同步）。请求和响应带有 `schema_version`，
+//! 解析失败时按 [`decode_request`] / [`decode_response`] 的兼容规则处理，而不是直接丢弃。
+//! 双方还在请求和响应中交换程序版本和各自能兼容的最低格式版本：版本不同时记录一次日志，
+//! 格式版本超出兼容范围时返回 [`VersionMismatch`]，GUI 以 [`PopupOutcome::Incompatible`] 退出，
+//! 服务端不再重试并返回明确的错误，而不是含糊的解析错误。
+//!
 //! ## 休眠处理
 //! 同步阻塞方式在休眠时进程被挂起，恢复后继续等待，更简单可靠
 
 use anyhow::{Result, anyhow};
+use rmcp::schemars;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use uuid::Uuid;
 
+use crate::image_processor::AttachmentFormat;
+use crate::ipc_crypto::{self, FileKey};
+use crate::session::SessionSummary;
+use crate::types::{CustomOption, LaunchRetryConfig};
+
 /// MCP request file prefix
 pub const MCP_REQUEST_FILE_PREFIX: &str = "whale_mcp_request_";
 /// MCP response file prefix  
 pub const MCP_RESPONSE_FILE_PREFIX: &str = "whale_mcp_response_";
+/// 多轮会话中 GUI 写完响应文件后输出到 stdout 的通知前缀（后接请求 ID）
+pub const SESSION_RESPONSE_READY_PREFIX: &str = "whale-response-ready:";
+
+/// 请求/响应的格式版本，字段含义不兼容地变化时递增（旧版本不发送该字段，视为 0）
+pub const POPUP_SCHEMA_VERSION: u32 = 1;
+/// 仍能兼容的对方最低格式版本
+pub const POPUP_MIN_SCHEMA_VERSION: u32 = 1;
+/// 程序版本，随请求和响应发送给对方
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 已记录过对方程序版本不同的日志（每个进程只记录一次）
+static VERSION_MISMATCH_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// 覆盖请求/响应临时文件目录的环境变量（优先于配置中的 `ipcDir`）
+pub const IPC_DIR_ENV: &str = "WHALE_IPC_DIR";
+
+/// 配置中的临时文件目录（MCP Server 和 GUI 启动时设置）
+static CONFIGURED_IPC_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 设置配置中的临时文件目录（空字符串视为未配置）
+pub fn set_configured_ipc_dir(dir: Option<&str>) {
+    let dir = dir.map(str::trim).filter(|d| !d.is_empty()).map(PathBuf::from);
+    *CONFIGURED_IPC_DIR.lock().unwrap() = dir;
+}
+
+/// 请求/响应临时文件和本地 socket 所在目录
+///
+/// 依次使用 [`IPC_DIR_ENV`]、配置中的目录和系统临时目录。
+/// 系统临时目录被频繁清理或受策略限制时可以改用其他目录；GUI 子进程通过同一个环境变量获得服务端使用的目录
+pub fn ipc_dir() -> PathBuf {
+    std::env::var_os(IPC_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| CONFIGURED_IPC_DIR.lock().unwrap().clone())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// 本进程中正在等待响应的请求 ID 及其临时文件密钥（退出时清理它们的临时文件）
+static IN_FLIGHT: Mutex<Vec<(String, FileKey)>> = Mutex::new(Vec::new());
+
+/// 在等待期间登记请求并生成临时文件密钥，释放时移除
+pub(crate) struct InFlightGuard(String);
+
+impl InFlightGuard {
+    pub(crate) fn new(request_id: &str) -> Self {
+        IN_FLIGHT.lock().unwrap().push((request_id.to_string(), FileKey::generate()));
+        Self(request_id.to_string())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().retain(|(id, _)| id != &self.0);
+    }
+}
+
+/// 正在等待响应的请求的临时文件密钥（MCP Server 使用）
+pub(crate) fn request_file_key(request_id: &str) -> Option<FileKey> {
+    IN_FLIGHT.lock().unwrap().iter()
+        .find(|(id, _)| id == request_id)
+        .map(|(_, key)| key.clone())
+}
+
+/// 附带临时文件密钥的请求副本，通过 stdin 管道或本地 socket 发送给 GUI
+pub(crate) fn with_file_key(request: &PopupRequest) -> PopupRequest {
+    let mut request = request.clone();
+    request.file_key = request_file_key(&request.id).map(|key| key.encode());
+    request
+}
+
+/// Popup 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum PopupKind {
+    /// 完整反馈窗口
+    #[default]
+    Feedback,
+    /// 简单的是/否确认对话框
+    Confirm,
+    /// 原生文件/目录选择器
+    SelectFile,
+    /// 多字段表单
+    Form,
+}
+
+impl PopupKind {
+    /// 与序列化名称一致的字符串
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PopupKind::Feedback => "feedback",
+            PopupKind::Confirm => "confirm",
+            PopupKind::SelectFile => "select_file",
+            PopupKind::Form => "form",
+        }
+    }
+}
+
+/// 请求优先级，决定窗口激活方式和提示音
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// 不抢占焦点
+    Low,
+    /// 激活窗口并短暂置顶
+    #[default]
+    Normal,
+    /// 保持置顶并重复播放较响的提示音
+    Critical,
+}
+
+/// Agent 请求的操作类别，决定弹窗提供的作答方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionClass {
+    /// 普通提问
+    #[default]
+    General,
+    /// 代码审查：用户可以逐条填写修改意见
+    Review,
+}
+
+/// 修改意见的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSeverity {
+    /// 必须修改
+    Blocker,
+    /// 应该修改
+    Major,
+    #[default]
+    Minor,
+    /// 可选的细节建议
+    Nit,
+}
+
+/// 审查请求中用户提出的一条修改意见
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+pub struct ChangeRequest {
+    /// 文件路径
+    pub file: String,
+    /// 位置（行号、行范围或符号名），为空时针对整个文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    pub comment: String,
+    #[serde(default)]
+    pub severity: ChangeSeverity,
+}
+
+/// 文件选择器选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+pub struct FilePickerOptions {
+    /// 选择目录而不是文件
+    #[serde(default)]
+    pub directory: bool,
+    /// 允许多选
+    #[serde(default)]
+    pub multiple: bool,
+    /// 允许的文件扩展名（不含点号），为空表示不限制
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// 初始目录
+    #[serde(default)]
+    pub default_path: Option<String>,
+}
+
+/// 表单字段类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum FormFieldKind {
+    /// 单行文本
+    Text,
+    /// 多行文本
+    Multiline,
+    /// 数字
+    Number,
+    /// 从 `options` 中单选
+    Select,
+    /// 复选框（布尔值）
+    Checkbox,
+}
+
+/// 表单字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+pub struct FormField {
+    #[schemars(description = "Key of the answer in the result object")]
+    pub name: String,
+    #[serde(default)]
+    #[schemars(description = "Label shown to the user (defaults to name)")]
+    pub label: Option<String>,
+    #[schemars(description = "Field type: text, multiline, number, select or checkbox")]
+    pub kind: FormFieldKind,
+    #[serde(default)]
+    #[schemars(description = "Whether the user must fill in the field")]
+    pub required: bool,
+    #[serde(default)]
+    #[schemars(description = "Choices for select fields")]
+    pub options: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Initial value")]
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    #[schemars(description = "Help text shown under the field")]
+    pub description: Option<String>,
+}
+
+impl FormField {
+    /// 校验并规范化用户填写的值，空值返回 Ok(None)
+    pub fn normalize(&self, value: Option<&serde_json::Value>) -> Result<Option<serde_json::Value>, String> {
+        let value = match value {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) if s.trim().is_empty() => None,
+            Some(v) => Some(v.clone()),
+        };
+        let Some(value) = value else {
+            if self.kind == FormFieldKind::Checkbox {
+                return Ok(Some(Value::Bool(false)));
+            }
+            return if self.required { Err(format!("字段 {} 为必填项", self.name)) } else { Ok(None) };
+        };
+        
+        let normalized = match (self.kind, &value) {
+            (FormFieldKind::Text | FormFieldKind::Multiline, Value::String(_)) => value,
+            (FormFieldKind::Number, Value::Number(_)) => value,
+            (FormFieldKind::Number, Value::String(s)) => parse_number(s.trim())
+                .map(Value::Number)
+                .ok_or_else(|| format!("字段 {} 必须是数字", self.name))?,
+            (FormFieldKind::Select, Value::String(s)) if self.options.contains(s) => value,
+            (FormFieldKind::Checkbox, Value::Bool(_)) => value,
+            _ => return Err(format!("字段 {} 的值无效", self.name)),
+        };
+        Ok(Some(normalized))
+    }
+}
+
+/// 解析表单中填写的数字，整数保持为整数（"8080" 得到 8080 而不是 8080.0）
+fn parse_number(s: &str) -> Option<serde_json::Number> {
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(n.into());
+    }
+    if let Ok(n) = s.parse::<u64>() {
+        return Some(n.into());
+    }
+    s.parse::<f64>().ok().and_then(serde_json::Number::from_f64)
+}
+
+/// 确认对话框的“是”选项文本
+pub const CONFIRM_YES_OPTION: &str = "是";
+/// 确认对话框的“否”选项文本
+pub const CONFIRM_NO_OPTION: &str = "否";
+
+/// 结构化预定义选项（与 `predefined_options` 按下标一一对应）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub struct OptionDetail {
+    /// 机器可读的选项 ID，返回给 AI
+    pub id: String,
+    /// 显示文本
+    pub label: String,
+    /// 选项说明（鼠标悬停显示）
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 互斥选项：选中后取消其他选项
+    #[serde(default)]
+    pub exclusive: bool,
+}
+
+/// 发起请求的 Agent（MCP 客户端）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct ClientInfo {
+    /// 客户端名称（如 `claude-code`、`cursor`）
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// 数字快捷键的数量（1..9）
+pub const MAX_OPTION_HOTKEYS: usize = 9;
+
+/// 选项的数字快捷键：按下后直接以该选项作答
+///
+/// 由服务端按请求生成，所有前端使用同一映射
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+pub struct OptionHotkey {
+    /// 按键（"1".."9"）
+    pub key: String,
+    /// 对应的选项文本
+    pub option: String,
+}
+
+/// AI 附带的单个图片的最大大小（base64 编码后）
+pub const MAX_AGENT_IMAGE_BYTES: usize = 8 * 1024 * 1024;
+
+/// AI 附带的附件，显示在问题下方（如改动的截图、diff）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentAttachment {
+    /// 图片
+    Image {
+        #[schemars(description = "Base64-encoded image data (without the data: URL prefix)")]
+        data: String,
+        #[schemars(description = "MIME type, e.g. 'image/png'")]
+        mime_type: String,
+        #[serde(default)]
+        #[schemars(description = "Optional caption shown below the image")]
+        caption: Option<String>,
+    },
+    /// 代码片段
+    Code {
+        #[schemars(description = "The code or diff to show")]
+        code: String,
+        #[serde(default)]
+        #[schemars(description = "Language for syntax highlighting, e.g. 'rust' or 'diff'")]
+        language: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Optional title, e.g. a file name")]
+        title: Option<String>,
+    },
+}
+
+impl AgentAttachment {
+    /// 检查附件是否可以显示
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            AgentAttachment::Image { data, mime_type, .. } => {
+                if !mime_type.starts_with("image/") {
+                    return Err(format!("unsupported image type '{}'", mime_type));
+                }
+                if data.len() > MAX_AGENT_IMAGE_BYTES {
+                    return Err(format!("image exceeds {} MB", MAX_AGENT_IMAGE_BYTES / 1024 / 1024));
+                }
+                use base64::Engine;
+                base64::engine::general_purpose::STANDARD
+                    .decode(data.trim())
+                    .map(|_| ())
+                    .map_err(|e| format!("invalid base64 image data: {}", e))
+            }
+            AgentAttachment::Code { code, .. } if code.trim().is_empty() => Err("code snippet is empty".to_string()),
+            AgentAttachment::Code { .. } => Ok(()),
+        }
+    }
+}
+
+/// 弹窗中按请求语言显示的文本（未提供时 GUI 使用默认的中文文本）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub struct PopupStrings {
+    pub input_placeholder: String,
+    pub submit: String,
+    pub cancel: String,
+    pub previous_answer: String,
+    pub thread_history: String,
+}
+
+/// 自动提交设置：用户在指定时间内未响应时自动选择默认选项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub struct AutoSubmit {
+    /// 默认选项的显示文本
+    pub option: String,
+    /// 倒计时秒数
+    #[ts(type = "number")]
+    pub after_secs: u64,
+}
+
+/// 自动截图设置：弹窗打开后倒计时截取当前显示器，作为预填的上下文图片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub struct ContextCapture {
+    /// 截图前的倒计时秒数
+    #[ts(type = "number")]
+    pub countdown_secs: u64,
+}
 
 /// Popup request sent to the GUI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[ts(export)]
 pub struct PopupRequest {
     pub id: String,
+    /// 格式版本（见 [`POPUP_SCHEMA_VERSION`]）
+    #[serde(default)]
+    pub schema_version: u32,
+    /// 发送方能兼容的最低格式版本（旧版本不发送，视为 0）
+    #[serde(default)]
+    pub min_schema_version: u32,
+    /// 发送方的程序版本
+    #[serde(default)]
+    pub app_version: Option<String>,
+    #[serde(default)]
+    pub kind: PopupKind,
+    /// 窗口标题（确认对话框使用）
+    #[serde(default)]
+    pub title: Option<String>,
+    /// 是否显示“记住我的选择”（确认对话框使用）
+    #[serde(default)]
+    pub allow_remember: bool,
+    /// 文件选择器选项（文件选择模式使用）
+    #[serde(default)]
+    pub file_picker: Option<FilePickerOptions>,
     pub message: Option<String>,
     pub full_response: Option<String>,
     pub predefined_options: Option<Vec<String>>,
+    /// 预定义选项的结构化信息（ID、说明、互斥）
+    #[serde(default)]
+    pub option_details: Vec<OptionDetail>,
+    /// 用户自定义的快捷选项（已与 AI 提供的选项合并去重）
+    #[serde(default)]
+    pub quick_options: Vec<CustomOption>,
+    /// 超时自动提交默认选项
+    #[serde(default)]
+    pub auto_submit: Option<AutoSubmit>,
+    /// 优先级
+    #[serde(default)]
+    pub priority: Priority,
+    /// 发起请求的项目名称（显示在窗口标题中）
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// 发起请求的工作目录
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// 期望的响应截止时间（RFC 3339）
+    #[serde(default)]
+    pub respond_by: Option<String>,
+    /// 相同问题的上次回答（供用户一键复用）
+    #[serde(default)]
+    pub previous_answer: Option<SessionSummary>,
+    /// 所属会话线程（Agent 指定，关联同一话题的多次提问）
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    /// 同一线程中之前的问答（按时间顺序，显示在问题上方）
+    #[serde(default)]
+    pub thread_history: Vec<SessionSummary>,
+    /// AI 附带的图片和代码片段
+    #[serde(default)]
+    pub agent_attachments: Vec<AgentAttachment>,
+    /// Agent 请求的自动截图（用户在设置中开启后才会携带）
+    #[serde(default)]
+    pub capture_context: Option<ContextCapture>,
+    /// Agent 提示的语言（BCP 47，如 `en-US`）
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// 按 `locale` 本地化的弹窗文本
+    #[serde(default)]
+    pub strings: Option<PopupStrings>,
+    /// 表单字段（表单模式使用）
+    #[serde(default)]
+    pub form_fields: Vec<FormField>,
+    /// 多轮对话会话 ID：同一会话的连续请求复用同一个弹窗
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// 操作类别（审查请求显示修改意见编辑器）
+    #[serde(default)]
+    pub action_class: ActionClass,
+    /// 预定义选项的数字快捷键（见 [`PopupRequest::assign_hotkeys`]）
+    #[serde(default)]
+    pub hotkeys: Vec<OptionHotkey>,
+    /// 发起请求的 MCP 客户端（弹窗显示“某某正在询问”）
+    #[serde(default)]
+    pub client_info: Option<ClientInfo>,
+    /// 发起请求的工具调用 ID（JSON-RPC 请求 ID），用于在日志中关联弹窗和 Agent 会话
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+    /// 接收响应的本地 socket（Unix 域套接字路径或 Windows 命名管道名），为空时写入响应文件
+    #[serde(default)]
+    pub response_endpoint: Option<String>,
+    /// 截图附件的编码格式（GUI 提交时将 PNG 截图转换为该格式）
+    #[serde(default)]
+    pub attachment_format: AttachmentFormat,
+    /// 临时文件密钥（Base64），只通过 stdin 管道/本地 socket 传递或在 GUI 读取请求文件后填入，不写入请求文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_key: Option<String>,
     pub created_at: String,
 }
 
@@ -36,61 +522,957 @@ impl PopupRequest {
     pub fn new(message: Option<String>, full_response: Option<String>, predefined_options: Option<Vec<String>>) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
+            schema_version: POPUP_SCHEMA_VERSION,
+            min_schema_version: POPUP_MIN_SCHEMA_VERSION,
+            app_version: Some(APP_VERSION.to_string()),
+            kind: PopupKind::Feedback,
+            title: None,
+            allow_remember: false,
+            file_picker: None,
             message,
             full_response,
             predefined_options,
+            option_details: Vec::new(),
+            quick_options: Vec::new(),
+            auto_submit: None,
+            priority: Priority::Normal,
+            project_name: None,
+            working_dir: None,
+            respond_by: None,
+            previous_answer: None,
+            thread_id: None,
+            thread_history: Vec::new(),
+            agent_attachments: Vec::new(),
+            capture_context: None,
+            locale: None,
+            strings: None,
+            form_fields: Vec::new(),
+            session_id: None,
+            action_class: ActionClass::General,
+            hotkeys: Vec::new(),
+            client_info: None,
+            tool_call_id: None,
+            response_endpoint: None,
+            attachment_format: AttachmentFormat::Png,
+            file_key: None,
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
+
+    /// 创建是/否确认请求
+    ///
+    /// 同时提供“是/否”两个预定义选项，不支持确认模式的旧版 GUI 仍可正常作答
+    pub fn confirm(title: Option<String>, message: String, allow_remember: bool) -> Self {
+        let mut request = Self::new(
+            Some(message),
+            None,
+            Some(vec![CONFIRM_YES_OPTION.to_string(), CONFIRM_NO_OPTION.to_string()]),
+        );
+        request.kind = PopupKind::Confirm;
+        request.title = title;
+        request.allow_remember = allow_remember;
+        request
+    }
+
+    /// 创建文件选择请求
+    pub fn select_file(title: Option<String>, message: Option<String>, options: FilePickerOptions) -> Self {
+        let mut request = Self::new(message, None, None);
+        request.kind = PopupKind::SelectFile;
+        request.title = title;
+        request.file_picker = Some(options);
+        request
+    }
+
+    /// 创建表单请求
+    pub fn form(title: Option<String>, message: Option<String>, fields: Vec<FormField>) -> Self {
+        let mut request = Self::new(message, None, None);
+        request.kind = PopupKind::Form;
+        request.title = title;
+        request.form_fields = fields;
+        request
+    }
+
+    /// 设置结构化预定义选项，同时填充 `predefined_options` 以兼容旧版 GUI
+    pub fn with_option_details(mut self, details: Vec<OptionDetail>) -> Self {
+        self.predefined_options = Some(details.iter().map(|d| d.label.clone()).collect());
+        self.option_details = details;
+        self
+    }
+
+    /// 项目名称：优先使用请求中的名称，否则取工作目录名
+    pub fn project_label(&self) -> Option<String> {
+        self.project_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                let dir = self.working_dir.as_deref()?;
+                let name = std::path::Path::new(dir.trim()).file_name()?;
+                Some(name.to_string_lossy().to_string())
+            })
+    }
+
+    /// 窗口标题：`<标题> — <项目>`，未指定标题时使用 `default_title`
+    pub fn window_title(&self, default_title: &str) -> String {
+        let title = self.title.as_deref().map(str::trim).filter(|t| !t.is_empty()).unwrap_or(default_title);
+        match self.project_label() {
+            Some(project) => format!("{} — {}", title, project),
+            None => title.to_string(),
+        }
+    }
+
+    /// 将 GUI 返回的选项文本映射为选项 ID（未知文本原样返回）
+    pub fn selected_option_ids(&self, selected: &[String]) -> Vec<String> {
+        selected.iter()
+            .map(|label| {
+                self.option_details.iter()
+                    .find(|d| &d.label == label)
+                    .map(|d| d.id.clone())
+                    .unwrap_or_else(|| label.clone())
+            })
+            .collect()
+    }
+
+    /// 记录发起请求的 MCP 客户端和工具调用 ID
+    pub fn with_agent(mut self, client_info: Option<ClientInfo>, tool_call_id: Option<String>) -> Self {
+        self.client_info = client_info;
+        self.tool_call_id = tool_call_id;
+        self
+    }
+
+    /// 为前 9 个预定义选项依次分配数字快捷键 1..9
+    ///
+    /// 只覆盖请求最初的选项，弹窗打开后 Agent 追加的选项没有快捷键
+    pub fn assign_hotkeys(&mut self) {
+        self.hotkeys = self.predefined_options.iter().flatten()
+            .take(MAX_OPTION_HOTKEYS)
+            .enumerate()
+            .map(|(index, option)| OptionHotkey { key: (index + 1).to_string(), option: option.clone() })
+            .collect();
+    }
+
+    /// 快捷键对应的选项
+    pub fn hotkey_option(&self, key: &str) -> Option<&str> {
+        self.hotkeys.iter().find(|h| h.key == key).map(|h| h.option.as_str())
+    }
+
+    /// 合并用户自定义快捷选项
+    ///
+    /// 与 AI 提供的 predefined_options 文本相同的快捷选项会被跳过，避免重复显示
+    pub fn with_quick_options(mut self, options: &[CustomOption]) -> Self {
+        let agent_options = self.predefined_options.as_deref().unwrap_or(&[]);
+        let mut merged: Vec<CustomOption> = Vec::new();
+        for option in options {
+            let text = option.text.trim();
+            if text.is_empty()
+                || agent_options.iter().any(|o| o.trim() == text)
+                || merged.iter().any(|o| o.text.trim() == text)
+            {
+                continue;
+            }
+            merged.push(option.clone());
+        }
+        self.quick_options = merged;
+        self
+    }
+}
+
+/// GUI 进程的结果，`exit_app` 以对应的退出码退出
+///
+/// 服务端没有收到响应（响应通道和响应文件都没有）时按退出码构造响应：
+///
+/// | 退出码 | 结果 |
+/// |--------|------|
+/// | 0  | 已提交；没有响应时视为用户直接关闭了窗口（取消） |
+/// | 10 | 用户取消 |
+/// | 11 | 超时 |
+/// | 12 | GUI 出错（如无法读取请求文件），按崩溃处理并重试 |
+/// | 13 | GUI 与服务端的格式版本不兼容，不再重试 |
+/// | 14 | 自动提交倒计时结束且用户没有操作，选择默认选项 |
+///
+/// 其他非零退出码或被信号终止同样视为崩溃。不使用 1、2（通用错误和命令行参数错误）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PopupOutcome {
+    #[default]
+    Submitted,
+    Cancelled,
+    TimedOut,
+    Error,
+    Incompatible,
+    AutoSelected,
+}
+
+impl PopupOutcome {
+    /// 对应的进程退出码
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            PopupOutcome::Submitted => 0,
+            PopupOutcome::Cancelled => 10,
+            PopupOutcome::TimedOut => 11,
+            PopupOutcome::Error => 12,
+            PopupOutcome::Incompatible => 13,
+            PopupOutcome::AutoSelected => 14,
+        }
+    }
+
+    /// 从进程退出码解析
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        [Self::Submitted, Self::Cancelled, Self::TimedOut, Self::Error, Self::Incompatible, Self::AutoSelected]
+            .into_iter()
+            .find(|outcome| outcome.exit_code() == code)
+    }
 }
 
 /// Response from the popup GUI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[ts(export)]
 pub struct PopupResponse {
     pub request_id: String,
+    /// 格式版本（见 [`POPUP_SCHEMA_VERSION`]）
+    #[serde(default)]
+    pub schema_version: u32,
+    /// 发送方能兼容的最低格式版本（旧版本不发送，视为 0）
+    #[serde(default)]
+    pub min_schema_version: u32,
+    /// 发送方的程序版本
+    #[serde(default)]
+    pub app_version: Option<String>,
     pub user_input: Option<String>,
     pub selected_options: Vec<String>,
     pub images: Vec<ImageData>,
     #[serde(default)]
     pub file_references: Vec<FileReferenceData>,
     pub cancelled: bool,
+    /// 确认对话框结果
+    #[serde(default)]
+    pub confirmed: Option<bool>,
+    /// 用户是否勾选了“记住我的选择”
+    #[serde(default)]
+    pub remember_choice: bool,
+    /// 是否因超时而自动取消
+    #[serde(default)]
+    pub timed_out: bool,
+    /// 是否因用户未响应而自动选择了默认选项
+    #[serde(default)]
+    pub auto_selected: bool,
+    /// 表单填写结果（表单模式使用）
+    #[serde(default)]
+    pub form_values: Option<serde_json::Map<String, serde_json::Value>>,
+    /// 是否因 MCP 客户端断开连接而关闭了弹窗
+    #[serde(default)]
+    pub client_disconnected: bool,
+    /// 用户作答时看到的请求修订号（请求未更新过或旧版 GUI 时为 None）
+    #[serde(default)]
+    pub revision: Option<u32>,
+    /// 审查请求中用户逐条填写的修改意见
+    #[serde(default)]
+    pub change_requests: Vec<ChangeRequest>,
+    /// 用户作答时按下的选项快捷键
+    #[serde(default)]
+    pub hotkey: Option<String>,
 }
 
-/// Image data in response
+impl PopupResponse {
+    /// 创建取消状态的响应
+    pub fn cancelled(request_id: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            schema_version: POPUP_SCHEMA_VERSION,
+            min_schema_version: POPUP_MIN_SCHEMA_VERSION,
+            app_version: Some(APP_VERSION.to_string()),
+            user_input: None,
+            selected_options: vec![],
+            images: vec![],
+            file_references: vec![],
+            cancelled: true,
+            confirmed: None,
+            remember_choice: false,
+            timed_out: false,
+            auto_selected: false,
+            form_values: None,
+            client_disconnected: false,
+            revision: None,
+            change_requests: Vec::new(),
+            hotkey: None,
+        }
+    }
+
+    /// 创建超时状态的响应
+    pub fn timed_out(request_id: &str) -> Self {
+        Self {
+            timed_out: true,
+            ..Self::cancelled(request_id)
+        }
+    }
+
+    /// 创建客户端断开连接的响应（弹窗已被关闭，结果无人接收）
+    pub fn client_disconnected(request_id: &str) -> Self {
+        Self {
+            client_disconnected: true,
+            ..Self::cancelled(request_id)
+        }
+    }
+
+    /// 创建自动选择默认选项的响应（GUI 倒计时结束但未能发送响应时由服务端生成）
+    pub fn auto_selected(request_id: &str, option: &str) -> Self {
+        Self {
+            selected_options: vec![option.to_string()],
+            cancelled: false,
+            auto_selected: true,
+            ..Self::cancelled(request_id)
+        }
+    }
+
+    /// 解析确认结果（兼容仅返回选项的旧版 GUI）
+    pub fn confirmation(&self) -> Option<bool> {
+        if self.cancelled {
+            return None;
+        }
+        self.confirmed.or_else(|| {
+            if self.selected_options.iter().any(|o| o == CONFIRM_YES_OPTION) {
+                Some(true)
+            } else if self.selected_options.iter().any(|o| o == CONFIRM_NO_OPTION) {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// 解析请求，兼容其他版本的 MCP Server
+///
+/// 先严格解析；失败时：缺少的必填字段补默认值，无法识别的枚举值（如新版本的弹窗类型）回退为默认值，
+/// 列表中无法识别的元素（如新的附件类型）被丢弃。未知字段本来就会被忽略
+pub fn decode_request(json: &str) -> Result<PopupRequest> {
+    decode_compat(json, "请求", "MCP Server", upgrade_request)
+}
+
+/// 解析响应，兼容其他版本的 GUI（规则同 [`decode_request`]）
+pub fn decode_response(json: &str) -> Result<PopupResponse> {
+    decode_compat(json, "响应", "GUI", upgrade_response)
+}
+
+/// 解析服务端消息，其中的请求按 [`decode_request`] 兼容处理
+pub fn decode_parent_message(json: &str) -> Result<ParentMessage> {
+    decode_compat(json, "服务端消息", "MCP Server", |object| {
+        if object.get("type").and_then(Value::as_str) == Some("request") {
+            upgrade_request(object);
+        }
+    })
+}
+
+/// 对方的格式版本超出兼容范围
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "{peer}（版本 {peer_version}，格式版本 {peer_schema}）与当前程序（版本 {app}，格式版本 {schema}）不兼容，\
+     请将 MCP Server 和 GUI 更新到同一版本",
+    app = APP_VERSION,
+    schema = POPUP_SCHEMA_VERSION
+)]
+pub struct VersionMismatch {
+    pub peer: &'static str,
+    pub peer_version: String,
+    pub peer_schema: u32,
+}
+
+/// 检查对方的版本：格式版本不兼容时返回 [`VersionMismatch`]，程序版本不同时记录日志
+fn check_peer_version(object: &Map<String, Value>, peer: &'static str) -> Result<(), VersionMismatch> {
+    let number = |field: &str| object.get(field).and_then(Value::as_u64).unwrap_or(0);
+    let peer_schema = number("schema_version") as u32;
+    let peer_min_schema = number("min_schema_version") as u32;
+    let peer_version = object.get("app_version").and_then(Value::as_str);
+
+    // 旧版本不发送格式版本（0），交给兼容解析处理
+    if (peer_schema > 0 && peer_schema < POPUP_MIN_SCHEMA_VERSION) || peer_min_schema > POPUP_SCHEMA_VERSION {
+        return Err(VersionMismatch {
+            peer,
+            peer_version: peer_version.unwrap_or("未知").to_string(),
+            peer_schema,
+        });
+    }
+    if let Some(version) = peer_version.filter(|v| *v != APP_VERSION) {
+        if !VERSION_MISMATCH_LOGGED.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "[popup] {} 版本 {}（格式版本 {}）与当前版本 {}（格式版本 {}）不同，可能只升级了其中一个",
+                peer, version, peer_schema, APP_VERSION, POPUP_SCHEMA_VERSION
+            );
+        }
+    }
+    Ok(())
+}
+
+fn decode_compat<T: DeserializeOwned>(
+    json: &str,
+    what: &str,
+    peer: &'static str,
+    upgrade: fn(&mut Map<String, Value>),
+) -> Result<T> {
+    let mut value: Value = serde_json::from_str(json)?;
+    let strict_error = match value.as_object() {
+        Some(object) => {
+            check_peer_version(object, peer)?;
+            match T::deserialize(&value) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => e,
+            }
+        }
+        None => return Ok(serde_json::from_value(value)?),
+    };
+    let Some(object) = value.as_object_mut() else {
+        return Err(strict_error.into());
+    };
+    let version = object.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    upgrade(object);
+    let decoded = serde_json::from_value(value).map_err(|_| anyhow!(strict_error))?;
+    log::warn!(
+        "[popup] {}格式版本 {} 与当前版本 {} 不一致，已按兼容模式解析",
+        what, version, POPUP_SCHEMA_VERSION
+    );
+    Ok(decoded)
+}
+
+fn upgrade_request(object: &mut Map<String, Value>) {
+    object.entry("created_at").or_insert_with(|| Value::String(chrono::Utc::now().to_rfc3339()));
+    default_if_invalid::<PopupKind>(object, "kind");
+    default_if_invalid::<Priority>(object, "priority");
+    default_if_invalid::<AttachmentFormat>(object, "attachment_format");
+    default_if_invalid::<ActionClass>(object, "action_class");
+    remove_if_invalid::<FilePickerOptions>(object, "file_picker");
+    remove_if_invalid::<AutoSubmit>(object, "auto_submit");
+    remove_if_invalid::<ContextCapture>(object, "capture_context");
+    remove_if_invalid::<PopupStrings>(object, "strings");
+    remove_if_invalid::<SessionSummary>(object, "previous_answer");
+    retain_valid::<OptionDetail>(object, "option_details");
+    retain_valid::<CustomOption>(object, "quick_options");
+    retain_valid::<SessionSummary>(object, "thread_history");
+    retain_valid::<AgentAttachment>(object, "agent_attachments");
+    retain_valid::<FormField>(object, "form_fields");
+    retain_valid::<OptionHotkey>(object, "hotkeys");
+    remove_if_invalid::<ClientInfo>(object, "client_info");
+    remove_if_invalid::<String>(object, "tool_call_id");
+}
+
+fn upgrade_response(object: &mut Map<String, Value>) {
+    object.entry("selected_options").or_insert_with(|| Value::Array(Vec::new()));
+    object.entry("images").or_insert_with(|| Value::Array(Vec::new()));
+    object.entry("cancelled").or_insert(Value::Bool(false));
+    retain_valid::<ImageData>(object, "images");
+    retain_valid::<FileReferenceData>(object, "file_references");
+    retain_valid::<ChangeRequest>(object, "change_requests");
+    remove_if_invalid::<String>(object, "hotkey");
+    remove_if_invalid::<Map<String, Value>>(object, "form_values");
+}
+
+/// 字段值无法解析时改为默认值
+fn default_if_invalid<T: DeserializeOwned + Serialize + Default>(object: &mut Map<String, Value>, key: &str) {
+    if let Some(value) = object.get_mut(key) {
+        if T::deserialize(&*value).is_err() {
+            *value = serde_json::to_value(T::default()).unwrap_or(Value::Null);
+        }
+    }
+}
+
+/// 可选字段的值无法解析时去掉该字段
+fn remove_if_invalid<T: DeserializeOwned>(object: &mut Map<String, Value>, key: &str) {
+    if object.get(key).is_some_and(|value| Option::<T>::deserialize(value).is_err()) {
+        object.remove(key);
+    }
+}
+
+/// 去掉列表中无法解析的元素
+fn retain_valid<T: DeserializeOwned>(object: &mut Map<String, Value>, key: &str) {
+    match object.get_mut(key) {
+        Some(Value::Array(items)) => items.retain(|item| T::deserialize(item).is_ok()),
+        Some(_) => {
+            object.remove(key);
+        }
+        None => {}
+    }
+}
+
+/// 已打开弹窗的更新（Agent 在用户作答前补充了信息）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopupUpdate {
+    pub request_id: String,
+    /// 更新后的修订号（初始请求为 1）
+    pub revision: u32,
+    /// 追加显示的补充说明
+    #[serde(default)]
+    pub clarification: Option<String>,
+    /// 新增的选项
+    #[serde(default)]
+    pub add_options: Vec<OptionDetail>,
+}
+
+/// MCP Server 通过 stdin 发送给 GUI 的消息（每行一个 JSON）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParentMessage {
+    /// 已打开弹窗的更新
+    Update(PopupUpdate),
+    /// 多轮会话中的下一个问题（请求文件已写入）
+    Request(PopupRequest),
+}
+
+/// 多轮会话中的下一个问题，转发给前端的 `mcp-session-request` 事件
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRequestEvent {
+    pub request_file: String,
+    pub request: PopupRequest,
+}
+
+/// 解析 GUI 输出的响应通知，返回请求 ID
+pub fn parse_response_ready(line: &str) -> Option<&str> {
+    line.trim().strip_prefix(SESSION_RESPONSE_READY_PREFIX)
+}
+
+/// Image data in response
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct ImageData {
-    pub data: String,  // base64 encoded
+    /// Base64 数据，只引用附件时为空
+    #[serde(default)]
+    pub data: String,
     pub mime_type: String,
+    /// 附件存储中的 ID（见 [`crate::attachments`]），需要数据时再解析
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_id: Option<String>,
 }
 
 /// File reference data in response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct FileReferenceData {
     pub display_name: String,
     pub path: String,
     pub is_directory: bool,
 }
 
+/// Get the request file path for a request
+pub fn get_request_file_path(request_id: &str) -> PathBuf {
+    let temp_dir = ipc_dir();
+    let file_name = format!("{}{}.json", MCP_REQUEST_FILE_PREFIX, request_id);
+    temp_dir.join(file_name)
+}
+
 /// Create a temporary request file for the popup
+///
+/// 请求已登记（[`InFlightGuard`]）时使用其密钥加密，文件只允许当前用户读写
 pub async fn create_request_file(request: &PopupRequest) -> Result<PathBuf> {
-    let temp_dir = std::env::temp_dir();
-    let file_name = format!("{}{}.json", MCP_REQUEST_FILE_PREFIX, request.id);
-    let file_path = temp_dir.join(file_name);
+    use tokio::io::AsyncWriteExt;
+    let file_path = get_request_file_path(&request.id);
     
-    let content = serde_json::to_string_pretty(request)?;
-    tokio::fs::write(&file_path, content).await?;
+    let mut request = request.clone();
+    request.file_key = None;
+    let content = ipc_crypto::seal(request_file_key(&request.id).as_ref(), &serde_json::to_string_pretty(&request)?)?;
+    let mut file = ipc_crypto::create_private(&file_path).await?;
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await?;
     
     log::info!("Created MCP request file: {:?}", file_path);
     Ok(file_path)
 }
 
+/// 解析请求文件内容（GUI 使用）
+///
+/// 使用 MCP Server 通过环境变量传入的密钥解密，并把密钥填入请求，提交响应时用于加密响应文件
+pub fn parse_request_file(content: &str) -> Result<PopupRequest> {
+    let key = FileKey::from_env();
+    let mut request = decode_request(&ipc_crypto::open(key.as_ref(), content)?)?;
+    request.file_key = key.map(|key| key.encode());
+    Ok(request)
+}
+
+/// 同步读取请求文件（GUI 启动时使用）
+///
+/// 与服务端的格式版本不兼容时把错误输出到 stderr（服务端会附在错误信息中），
+/// 并以 [`PopupOutcome::Incompatible`] 退出，服务端不再重试
+pub fn load_request_file(request_file: &str) -> Option<PopupRequest> {
+    let content = std::fs::read_to_string(request_file).ok()?;
+    match parse_request_file(&content) {
+        Ok(request) => Some(request),
+        Err(e) if e.is::<VersionMismatch>() => {
+            eprintln!("{}", e);
+            std::process::exit(PopupOutcome::Incompatible.exit_code());
+        }
+        Err(e) => {
+            log::warn!("[load_request_file] 无法读取请求文件: {}", e);
+            None
+        }
+    }
+}
+
+/// 监视 MCP Server 通过 stdin 发送的消息（GUI 使用）
+///
+/// 每行一个 [`ParentMessage`]，由 [`forward_parent_message`] 转发给前端；
+/// 读到 EOF（MCP Server 退出、管道关闭）时退出应用，避免弹窗残留
+pub fn watch_parent_channel(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        use std::io::BufRead;
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match decode_parent_message(&line) {
+                Ok(message) => forward_parent_message(&app_handle, message),
+                Err(e) => log::warn!("[watch_parent_channel] 无法解析服务端消息: {}", e),
+            }
+        }
+        log::warn!("[watch_parent_channel] MCP 服务端已退出，关闭弹窗");
+        app_handle.exit(0);
+    });
+}
+
+/// GUI 收到的更新（前端注册监听之前到达的更新由前端通过 [`received_updates`] 补取）
+static RECEIVED_UPDATES: Mutex<Vec<PopupUpdate>> = Mutex::new(Vec::new());
+
+/// 已收到的某个请求的更新（按到达顺序）
+pub fn received_updates(request_id: &str) -> Vec<PopupUpdate> {
+    RECEIVED_UPDATES.lock().unwrap().iter().filter(|u| u.request_id == request_id).cloned().collect()
+}
+
+/// 将服务端消息转发给前端（GUI 使用）
+///
+/// 更新转发给 `mcp-request-update` 事件，下一个问题转发给 `mcp-session-request` 事件
+pub(crate) fn forward_parent_message(app_handle: &tauri::AppHandle, message: ParentMessage) {
+    use tauri::Emitter;
+    match message {
+        ParentMessage::Update(update) => {
+            log::info!("[forward_parent_message] 请求已更新，修订号: {}", update.revision);
+            RECEIVED_UPDATES.lock().unwrap().push(update.clone());
+            if let Err(e) = app_handle.emit("mcp-request-update", &update) {
+                log::error!("[forward_parent_message] 发送更新事件失败: {}", e);
+            }
+        }
+        ParentMessage::Request(request) => {
+            log::info!("[forward_parent_message] 收到下一个问题: {}", request.id);
+            RECEIVED_UPDATES.lock().unwrap().retain(|u| u.request_id == request.id);
+            let event = SessionRequestEvent {
+                request_file: get_request_file_path(&request.id).to_string_lossy().into_owned(),
+                request,
+            };
+            if let Err(e) = app_handle.emit("mcp-session-request", &event) {
+                log::error!("[forward_parent_message] 发送会话请求事件失败: {}", e);
+            }
+        }
+    }
+}
+
 /// Get the response file path for a request
 pub fn get_response_file_path(request_id: &str) -> PathBuf {
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = ipc_dir();
     let file_name = format!("{}{}.json", MCP_RESPONSE_FILE_PREFIX, request_id);
     temp_dir.join(file_name)
 }
 
+/// 响应文件格式版本，格式不兼容地变化时递增
+pub const RESPONSE_FILE_SCHEMA_VERSION: u32 = 1;
+
+/// 响应文件内容：序列化后的响应及其 SHA-256 校验和
+#[derive(Debug, Serialize, Deserialize)]
+struct ResponseFile {
+    schema_version: u32,
+    sha256: String,
+    payload: String,
+}
+
+impl ResponseFile {
+    /// 有密钥时加密响应，校验和针对写入文件的内容
+    fn new(response: &PopupResponse, key: Option<&FileKey>) -> Result<Self> {
+        let payload = ipc_crypto::seal(key, &serde_json::to_string(response)?)?;
+        Ok(Self {
+            schema_version: RESPONSE_FILE_SCHEMA_VERSION,
+            sha256: sha256_hex(payload.as_bytes()),
+            payload,
+        })
+    }
+
+    /// 校验格式版本和校验和，解密后解析响应
+    fn into_response(self, key: Option<&FileKey>) -> Result<PopupResponse> {
+        if self.schema_version != RESPONSE_FILE_SCHEMA_VERSION {
+            return Err(anyhow!(
+                "Unsupported response file schema version {} (expected {})",
+                self.schema_version, RESPONSE_FILE_SCHEMA_VERSION
+            ));
+        }
+        if sha256_hex(self.payload.as_bytes()) != self.sha256 {
+            return Err(anyhow!("Response file checksum mismatch"));
+        }
+        decode_response(&ipc_crypto::open(key, &self.payload)?)
+            .map_err(|e| anyhow!("Failed to parse response JSON: {}", e))
+    }
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// GUI 收到服务端确认前等待的最长时间，超时后改为写入响应文件
+const RESPONSE_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+/// 诊断崩溃时保留的 stderr 行数
+const STDERR_TAIL_LINES: usize = 20;
+/// GUI 退出后等待 stderr 读取完成的时间
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// 收到响应后等待 GUI 进程自行退出的时间
+const POPUP_EXIT_GRACE: Duration = Duration::from_secs(5);
+/// 服务端确认收到响应时回复的内容
+const RESPONSE_ACK: &str = "ok";
+
+/// 请求对应的本地 socket 地址
+fn response_endpoint(request_id: &str) -> String {
+    #[cfg(unix)]
+    {
+        // Unix 域套接字路径有长度限制（macOS 为 104 字节），使用较短的文件名
+        ipc_dir().join(format!("whale_{}.sock", request_id)).to_string_lossy().into_owned()
+    }
+    #[cfg(windows)]
+    {
+        format!(r"\\.\pipe\whale_mcp_{}", request_id)
+    }
+}
+
+/// 服务端接收 GUI 响应的本地 socket
+///
+/// 后台任务等待 GUI 连接并读取一行响应 JSON，回复确认后通过 oneshot 交给等待方；
+/// 丢弃时停止监听并删除 socket 文件
+pub(crate) struct ResponseChannel {
+    endpoint: String,
+    response: Option<tokio::sync::oneshot::Receiver<PopupResponse>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ResponseChannel {
+    /// 为请求创建 socket，失败时返回 None（回退到响应文件）
+    pub(crate) fn bind(request_id: &str) -> Option<Self> {
+        let endpoint = response_endpoint(request_id);
+        match Self::listen(&endpoint, request_id.to_string()) {
+            Ok((response, task)) => {
+                log::info!("[ResponseChannel] 监听响应通道: {}", endpoint);
+                Some(Self { endpoint, response: Some(response), task })
+            }
+            Err(e) => {
+                log::warn!("[ResponseChannel] 无法创建响应通道（{}），使用响应文件", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn listen(
+        endpoint: &str,
+        request_id: String,
+    ) -> Result<(tokio::sync::oneshot::Receiver<PopupResponse>, tokio::task::JoinHandle<()>)> {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::remove_file(endpoint);
+        let listener = tokio::net::UnixListener::bind(endpoint)?;
+        // 临时目录可能被其他用户共享，只允许当前用户连接
+        std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        log::warn!("[ResponseChannel] 接受连接失败: {}", e);
+                        return;
+                    }
+                };
+                if let Some((response, stream)) = receive_response(stream, &request_id).await {
+                    // 先交出响应再确认，GUI 收到确认后退出时响应一定已经可取
+                    let _ = tx.send(response);
+                    acknowledge(stream).await;
+                    return;
+                }
+            }
+        });
+        Ok((rx, task))
+    }
+
+    #[cfg(windows)]
+    fn listen(
+        endpoint: &str,
+        request_id: String,
+    ) -> Result<(tokio::sync::oneshot::Receiver<PopupResponse>, tokio::task::JoinHandle<()>)> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        let mut server = ServerOptions::new().first_pipe_instance(true).create(endpoint)?;
+        let endpoint = endpoint.to_string();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let task = tokio::spawn(async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    log::warn!("[ResponseChannel] 等待命名管道连接失败: {}", e);
+                    return;
+                }
+                // 为下一次连接创建新的管道实例，当前实例用于读取响应
+                let connected = match ServerOptions::new().create(&endpoint) {
+                    Ok(next) => std::mem::replace(&mut server, next),
+                    Err(e) => {
+                        log::warn!("[ResponseChannel] 创建命名管道实例失败: {}", e);
+                        return;
+                    }
+                };
+                if let Some((response, connected)) = receive_response(connected, &request_id).await {
+                    let _ = tx.send(response);
+                    acknowledge(connected).await;
+                    return;
+                }
+            }
+        });
+        Ok((rx, task))
+    }
+
+    /// socket 地址（写入请求，供 GUI 连接）
+    pub(crate) fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+impl Drop for ResponseChannel {
+    fn drop(&mut self) {
+        self.task.abort();
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.endpoint);
+    }
+}
+
+/// 读取一个连接发送的响应；内容不属于该请求时忽略
+async fn receive_response<S>(stream: S, request_id: &str) -> Option<(PopupResponse, tokio::io::BufReader<S>)>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncBufReadExt;
+    let mut stream = tokio::io::BufReader::new(stream);
+    let mut line = String::new();
+    if let Err(e) = stream.read_line(&mut line).await {
+        log::warn!("[ResponseChannel] 读取响应失败: {}", e);
+        return None;
+    }
+    let response = match decode_response(&line) {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("[ResponseChannel] 无法解析响应: {}", e);
+            return None;
+        }
+    };
+    if response.request_id != request_id {
+        log::warn!("[ResponseChannel] 忽略其他请求的响应: {}", response.request_id);
+        return None;
+    }
+    log::info!("[ResponseChannel] 已通过响应通道收到响应: {}", request_id);
+    Some((response, stream))
+}
+
+/// 回复确认，GUI 收到后不再写入响应文件
+async fn acknowledge<S>(mut stream: tokio::io::BufReader<S>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+    let mut ack = RESPONSE_ACK.to_string();
+    ack.push('\n');
+    let _ = stream.get_mut().write_all(ack.as_bytes()).await;
+    let _ = stream.get_mut().flush().await;
+}
+
+/// 取出响应通道已收到的响应（不等待）
+pub(crate) fn take_channel_response(channel: &mut Option<ResponseChannel>) -> Option<PopupResponse> {
+    let response = channel.as_mut()?.response.as_mut()?.try_recv().ok()?;
+    channel.as_mut()?.response = None;
+    Some(response)
+}
+
+/// 等待响应通道收到响应（没有通道或通道已关闭时一直等待）
+pub(crate) async fn next_channel_response(channel: &mut Option<ResponseChannel>) -> PopupResponse {
+    let receiver = match channel.as_mut().and_then(|channel| channel.response.as_mut()) {
+        Some(receiver) => receiver,
+        None => return std::future::pending().await,
+    };
+    let result = receiver.await;
+    if let Some(channel) = channel.as_mut() {
+        channel.response = None;
+    }
+    match result {
+        Ok(response) => response,
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// 通过响应通道发送响应（GUI 使用），需要服务端确认
+async fn send_via_channel(endpoint: &str, response: &PopupResponse) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(endpoint).await?;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(endpoint)?;
+
+    let mut stream = tokio::io::BufReader::new(stream);
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    stream.get_mut().write_all(line.as_bytes()).await?;
+    stream.get_mut().flush().await?;
+
+    let mut ack = String::new();
+    tokio::time::timeout(RESPONSE_ACK_TIMEOUT, stream.read_line(&mut ack))
+        .await
+        .map_err(|_| anyhow!("等待服务端确认超时"))??;
+    if ack.trim() != RESPONSE_ACK {
+        return Err(anyhow!("服务端未确认响应"));
+    }
+    Ok(())
+}
+
+/// 将响应交给 MCP Server（GUI 使用）
+///
+/// 请求带有 `response_endpoint` 时优先通过本地 socket 发送，失败时写入响应文件（有密钥时加密）
+pub async fn deliver_response(
+    endpoint: Option<&str>,
+    response_path: &std::path::Path,
+    response: &PopupResponse,
+    key: Option<&FileKey>,
+) -> Result<()> {
+    if let Some(endpoint) = endpoint {
+        match send_via_channel(endpoint, response).await {
+            Ok(()) => {
+                log::info!("[deliver_response] 已通过响应通道发送: {}", response.request_id);
+                return Ok(());
+            }
+            Err(e) => log::warn!("[deliver_response] 响应通道不可用（{}），写入响应文件", e),
+        }
+    }
+    write_response_file_atomically(response_path, response, key).await?;
+    log::info!("[deliver_response] 已写入响应文件: {:?}", response_path);
+    Ok(())
+}
+
+/// 原子写入响应文件：先写入同目录的临时文件并落盘，再重命名为最终文件名，
+/// 服务端不会读到写了一半的文件
+async fn write_response_file_atomically(
+    response_path: &std::path::Path,
+    response: &PopupResponse,
+    key: Option<&FileKey>,
+) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let content = serde_json::to_string_pretty(&ResponseFile::new(response, key)?)?;
+    let mut temp_name = response_path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let written = async {
+        let mut file = ipc_crypto::create_private(&temp_path).await?;
+        file.write_all(content.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&temp_path, response_path).await
+    }.await;
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    Ok(written?)
+}
+
 /// Find the UI executable path
 /// 优先查找与 mcp-server 同目录的 app，支持环境变量 WHALE_UI_PATH 覆盖
 pub fn find_ui_executable() -> Result<PathBuf> {
@@ -171,6 +1553,10 @@ pub async fn launch_popup(request: &PopupRequest) -> Result<tokio::process::Chil
     log::info!("[launch_popup] ========================================");
     log::info!("[launch_popup] 启动 GUI 弹窗");
     log::info!("[launch_popup] 请求 ID: {}", request.id);
+    log::info!(
+        "[launch_popup] 客户端: {:?}，工具调用 ID: {:?}",
+        request.client_info.as_ref().map(|c| c.name.as_str()), request.tool_call_id
+    );
     log::info!("[launch_popup] 请求文件: {:?}", request_file);
     log::info!("[launch_popup] UI 可执行文件: {:?}", ui_exe);
     log::info!("[launch_popup] 消息: {:?}", request.message.as_ref().map(|s| {
@@ -188,11 +1574,21 @@ pub async fn launch_popup(request: &PopupRequest) -> Result<tokio::process::Chil
     command
         .arg("--mcp-request")
         .arg(&request_file)
-        .stdin(std::process::Stdio::null())
+        // GUI 监视 stdin 管道：服务端进程退出（包括被强制结束）时管道关闭，GUI 随之退出
+        .arg("--watch-parent")
+        .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())  // 捕获输出用于调试
-        .stderr(std::process::Stdio::piped());
+        .stderr(std::process::Stdio::piped())
+        // 等待被中止（如服务端关闭时丢弃了未完成的请求）时终止 GUI 进程
+        .kill_on_drop(true);
+    // GUI 与服务端使用相同的临时文件目录
+    command.env(IPC_DIR_ENV, ipc_dir());
+    // 请求文件和响应文件的密钥
+    if let Some(key) = request_file_key(&request.id) {
+        command.env(ipc_crypto::FILE_KEY_ENV, key.encode());
+    }
     
-    log::info!("[launch_popup] 执行命令: {:?} --mcp-request {:?}", ui_exe, request_file);
+    log::info!("[launch_popup] 执行命令: {:?} --mcp-request {:?} --watch-parent", ui_exe, request_file);
     
     match command.spawn() {
         Ok(child) => {
@@ -206,13 +1602,10 @@ pub async fn launch_popup(request: &PopupRequest) -> Result<tokio::process::Chil
     }
 }
 
-/// 读取响应文件
-async fn read_response_file(request_id: &str) -> Result<PopupResponse> {
+/// 读取响应文件，校验格式版本和校验和后解密
+pub(crate) async fn read_response_file(request_id: &str) -> Result<PopupResponse> {
     let response_path = get_response_file_path(request_id);
     
-    // 等待文件写入完成（短暂延迟）
-    tokio::time::sleep(Duration::from_millis(50)).await;
-    
     log::info!("[read_response_file] 读取响应文件: {:?}", response_path);
     
     let content = tokio::fs::read_to_string(&response_path).await
@@ -220,8 +1613,9 @@ async fn read_response_file(request_id: &str) -> Result<PopupResponse> {
     
     log::debug!("[read_response_file] 响应内容: {}", content);
     
-    let response: PopupResponse = serde_json::from_str(&content)
-        .map_err(|e| anyhow!("Failed to parse response JSON: {}", e))?;
+    let response = serde_json::from_str::<ResponseFile>(&content)
+        .map_err(|e| anyhow!("Failed to parse response JSON: {}", e))?
+        .into_response(request_file_key(request_id).as_ref())?;
     
     // Clean up response file
     if let Err(e) = tokio::fs::remove_file(&response_path).await {
@@ -235,7 +1629,21 @@ async fn read_response_file(request_id: &str) -> Result<PopupResponse> {
 /// Launch popup and wait for user response
 /// 使用同步阻塞方式等待子进程，类似 Python 的 subprocess.run()
 /// 这种方式更简单可靠，休眠恢复后能正常继续
-pub async fn launch_popup_and_wait(request: &PopupRequest) -> Result<PopupResponse> {
+/// 
+/// 指定 `timeout` 时，超时后终止 GUI 进程并返回超时响应；
+/// `cancelled` 完成时（如 MCP 客户端取消了调用）终止 GUI 进程并返回取消响应；
+/// 等待期间从 `updates` 收到的更新通过 stdin 转发给 GUI。
+///
+/// GUI 启动失败或异常退出（非零退出码、被信号终止）且没有响应时，按 `retry` 退避后重新启动，
+/// 用完重试次数后返回错误；正常退出且没有响应才视为用户取消。
+/// 每次启动都重新查找 GUI 可执行文件：刚更新完时文件可能暂时缺失或被杀毒软件锁定
+pub async fn launch_popup_and_wait(
+    request: &PopupRequest,
+    timeout: Option<Duration>,
+    cancelled: impl std::future::Future<Output = ()>,
+    mut updates: Option<tokio::sync::mpsc::UnboundedReceiver<PopupUpdate>>,
+    retry: &LaunchRetryConfig,
+) -> Result<PopupResponse> {
     let request_id = request.id.clone();
     let response_path = get_response_file_path(&request_id);
     
@@ -244,17 +1652,115 @@ pub async fn launch_popup_and_wait(request: &PopupRequest) -> Result<PopupRespon
     log::info!("[launch_popup_and_wait] 响应文件路径: {:?}", response_path);
     
     let start_time = std::time::Instant::now();
+    let _in_flight = InFlightGuard::new(&request_id);
     
-    // 启动 GUI 进程
-    let mut child = launch_popup(request).await?;
+    // 优先通过本地 socket 接收响应，创建失败时 GUI 写入响应文件
+    let mut channel = ResponseChannel::bind(&request_id);
+    let mut request = request.clone();
+    request.response_endpoint = channel.as_ref().map(|channel| channel.endpoint().to_string());
     
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+    tokio::pin!(cancelled);
+    
+    let max_attempts = retry.max_attempts.max(1);
+    let mut backoff = Duration::from_millis(retry.initial_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        // 启动 GUI 进程
+        let failure = match launch_popup(&request).await {
+            Ok(child) => {
+                let exit = wait_for_popup_exit(
+                    &request, child, deadline.as_mut(), cancelled.as_mut(), &mut updates, &mut channel, start_time,
+                ).await?;
+                match exit {
+                    PopupExit::Finished(response) => return Ok(response),
+                    PopupExit::Crashed(description) => description,
+                }
+            }
+            Err(e) => format!("启动失败: {}", e),
+        };
+        
+        if attempt >= max_attempts {
+            log::error!("[launch_popup_and_wait] GUI 进程异常（已尝试 {} 次）: {}", attempt, failure);
+            return Err(anyhow!("GUI 无法启动或异常退出（已尝试 {} 次）: {}", attempt, failure));
+        }
+        log::warn!("[launch_popup_and_wait] GUI 进程异常（第 {} 次）: {}，{:?} 后重试", attempt, failure, backoff);
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = &mut deadline => return Ok(PopupResponse::timed_out(&request_id)),
+            _ = &mut cancelled => return Ok(PopupResponse::cancelled(&request_id)),
+        }
+        attempt += 1;
+        backoff *= 2;
+    }
+}
+
+/// 一次启动的 GUI 进程的结果
+enum PopupExit {
+    /// 得到了响应（包括超时、取消和用户关闭窗口）
+    Finished(PopupResponse),
+    /// GUI 进程异常退出且没有响应
+    Crashed(String),
+}
+
+/// 等待已启动的 GUI 进程给出响应或退出
+async fn wait_for_popup_exit<D, C>(
+    request: &PopupRequest,
+    mut child: tokio::process::Child,
+    mut deadline: std::pin::Pin<&mut D>,
+    mut cancelled: std::pin::Pin<&mut C>,
+    updates: &mut Option<tokio::sync::mpsc::UnboundedReceiver<PopupUpdate>>,
+    channel: &mut Option<ResponseChannel>,
+    start_time: std::time::Instant,
+) -> Result<PopupExit>
+where
+    D: std::future::Future<Output = ()>,
+    C: std::future::Future<Output = ()>,
+{
+    let request_id = &request.id;
+    let response_path = get_response_file_path(request_id);
     log::info!("[launch_popup_and_wait] 等待用户响应（同步阻塞模式）...");
     
+    // stdin 需要保持打开，关闭后 GUI 会认为 MCP Server 已退出
+    let mut stdin = child.stdin.take();
+    let stderr_tail = collect_stderr_tail(child.stderr.take());
+    
     // 同步阻塞等待子进程结束
     // 这种方式类似 Python 的 subprocess.run()，更简单可靠
     // 休眠时进程被挂起，恢复后继续等待
-    let exit_status = child.wait().await
-        .map_err(|e| anyhow!("等待 GUI 进程失败: {}", e))?;
+    let exit_status = loop {
+        tokio::select! {
+            status = child.wait() => break status.map_err(|e| anyhow!("等待 GUI 进程失败: {}", e))?,
+            _ = deadline.as_mut() => {
+                log::warn!("[launch_popup_and_wait] 等待用户响应超时，终止 GUI 进程");
+                terminate_popup(&mut child, &response_path).await;
+                return Ok(PopupExit::Finished(PopupResponse::timed_out(request_id)));
+            }
+            _ = cancelled.as_mut() => {
+                log::warn!("[launch_popup_and_wait] 请求已被客户端取消，终止 GUI 进程");
+                terminate_popup(&mut child, &response_path).await;
+                return Ok(PopupExit::Finished(PopupResponse::cancelled(request_id)));
+            }
+            update = next_update(updates) => match update {
+                Some(update) => send_update(stdin.as_mut(), &update).await,
+                None => *updates = None,
+            },
+            response = next_channel_response(channel) => {
+                // 给 GUI 一点时间自行退出（保存历史等），超时后随 child 丢弃终止
+                if tokio::time::timeout(POPUP_EXIT_GRACE, child.wait()).await.is_err() {
+                    log::warn!("[launch_popup_and_wait] 已收到响应但 GUI 进程未退出，终止进程");
+                }
+                log::info!("[launch_popup_and_wait] 通过响应通道收到响应，耗时: {:?}", start_time.elapsed());
+                return Ok(PopupExit::Finished(response));
+            }
+        }
+    };
     
     log::info!("[launch_popup_and_wait] GUI 进程退出，状态: {:?}, 耗时: {:?}", 
               exit_status, start_time.elapsed());
@@ -262,27 +1768,151 @@ pub async fn launch_popup_and_wait(request: &PopupRequest) -> Result<PopupRespon
     // 等待文件写入完成
     tokio::time::sleep(Duration::from_millis(100)).await;
     
+    // GUI 通过响应通道发送后立即退出时，响应可能与进程退出同时到达
+    if let Some(response) = take_channel_response(channel) {
+        return Ok(PopupExit::Finished(response));
+    }
+    
     // 检查响应文件
     if response_path.exists() {
-        read_response_file(&request_id).await
-    } else {
-        log::warn!("[launch_popup_and_wait] 进程退出但未找到响应文件，返回取消状态");
-        Ok(PopupResponse {
-            request_id: request_id.to_string(),
-            user_input: None,
-            selected_options: vec![],
-            images: vec![],
-            file_references: vec![],
-            cancelled: true,
-        })
+        return read_response_file(request_id).await.map(PopupExit::Finished);
+    }
+    
+    // 没有响应时按 GUI 的退出码构造响应
+    match exit_status.code().and_then(PopupOutcome::from_exit_code) {
+        Some(PopupOutcome::Cancelled) => {
+            log::info!("[launch_popup_and_wait] GUI 以取消状态退出且没有响应，返回取消状态");
+            return Ok(PopupExit::Finished(PopupResponse::cancelled(request_id)));
+        }
+        Some(PopupOutcome::TimedOut) => {
+            log::info!("[launch_popup_and_wait] GUI 以超时状态退出且没有响应，返回超时状态");
+            return Ok(PopupExit::Finished(PopupResponse::timed_out(request_id)));
+        }
+        Some(PopupOutcome::AutoSelected) => {
+            if let Some(auto_submit) = &request.auto_submit {
+                log::info!("[launch_popup_and_wait] GUI 自动提交倒计时结束且没有响应，选择默认选项");
+                return Ok(PopupExit::Finished(PopupResponse::auto_selected(request_id, &auto_submit.option)));
+            }
+        }
+        _ => {}
+    }
+    
+    let stderr_tail = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, stderr_tail)
+        .await
+        .ok()
+        .and_then(|tail| tail.ok())
+        .unwrap_or_default();
+    // 版本不兼容时重试也不会成功，直接返回 GUI 输出的错误
+    if exit_status.code().and_then(PopupOutcome::from_exit_code) == Some(PopupOutcome::Incompatible) {
+        log::error!("[launch_popup_and_wait] GUI 与 MCP Server 版本不兼容: {}", stderr_tail.trim());
+        return Err(anyhow!("GUI 与 MCP Server 版本不兼容: {}", stderr_tail.trim()));
+    }
+    match describe_crash(exit_status, &stderr_tail) {
+        Some(description) => Ok(PopupExit::Crashed(description)),
+        None => {
+            log::warn!("[launch_popup_and_wait] 进程正常退出但未找到响应文件，返回取消状态");
+            Ok(PopupExit::Finished(PopupResponse::cancelled(request_id)))
+        }
+    }
+}
+
+/// 判断没有响应的 GUI 退出是否为异常退出，返回异常描述
+///
+/// 用户关闭窗口时 GUI 正常退出（退出码 0）；非零退出码（包括 GUI 报告的错误）或被信号终止视为崩溃
+fn describe_crash(status: std::process::ExitStatus, stderr_tail: &str) -> Option<String> {
+    if status.success() {
+        return None;
+    }
+    let mut description = match status.code() {
+        Some(code) if PopupOutcome::from_exit_code(code) == Some(PopupOutcome::Error) => {
+            format!("GUI 报告错误（退出码 {}）", code)
+        }
+        Some(code) => format!("退出码 {}", code),
+        None => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::ExitStatusExt;
+                match status.signal() {
+                    Some(signal) => format!("被信号 {} 终止", signal),
+                    None => "异常终止".to_string(),
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                "异常终止".to_string()
+            }
+        }
+    };
+    if !stderr_tail.trim().is_empty() {
+        description.push_str(&format!("，stderr: {}", stderr_tail.trim()));
+    }
+    Some(description)
+}
+
+/// 持续读取 GUI 的 stderr，只保留最后几行
+///
+/// 及时读取可避免 GUI 日志写满管道后阻塞，保留的内容用于诊断崩溃
+pub(crate) fn collect_stderr_tail(stderr: Option<tokio::process::ChildStderr>) -> tokio::task::JoinHandle<String> {
+    tokio::spawn(async move {
+        use tokio::io::AsyncBufReadExt;
+        let Some(stderr) = stderr else { return String::new() };
+        let mut reader = tokio::io::BufReader::new(stderr);
+        let mut tail = std::collections::VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line).await, Ok(n) if n > 0) {
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+            line.clear();
+        }
+        Vec::from(tail).join("\n")
+    })
+}
+
+/// 等待下一个更新（没有更新通道时一直等待）
+pub(crate) async fn next_update(
+    updates: &mut Option<tokio::sync::mpsc::UnboundedReceiver<PopupUpdate>>,
+) -> Option<PopupUpdate> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 通过 stdin 将更新发送给 GUI
+pub(crate) async fn send_update(stdin: Option<&mut tokio::process::ChildStdin>, update: &PopupUpdate) {
+    let Some(stdin) = stdin else { return };
+    match send_message(stdin, &ParentMessage::Update(update.clone())).await {
+        Ok(()) => log::info!("[send_update] 已发送更新，修订号: {}", update.revision),
+        Err(e) => log::warn!("[send_update] 发送更新失败: {}", e),
+    }
+}
+
+/// 向 GUI 发送一行消息（stdin 或常驻 GUI 的连接）
+pub(crate) async fn send_message<W>(writer: &mut W, message: &ParentMessage) -> Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// 终止 GUI 进程并清理残留的响应文件（用户可能恰好在终止前提交）
+pub(crate) async fn terminate_popup(child: &mut tokio::process::Child, response_path: &std::path::Path) {
+    if let Err(e) = child.kill().await {
+        log::error!("[terminate_popup] 终止 GUI 进程失败: {}", e);
     }
+    let _ = tokio::fs::remove_file(response_path).await;
 }
 
 /// Clean up request file after response
 pub async fn cleanup_request_file(request_id: &str) -> Result<()> {
-    let temp_dir = std::env::temp_dir();
-    let file_name = format!("{}{}.json", MCP_REQUEST_FILE_PREFIX, request_id);
-    let file_path = temp_dir.join(file_name);
+    let file_path = get_request_file_path(request_id);
     
     if file_path.exists() {
         tokio::fs::remove_file(&file_path).await?;
@@ -294,9 +1924,396 @@ pub async fn cleanup_request_file(request_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// 清理仍在等待响应的请求的临时文件（MCP Server 退出前调用）
+///
+/// # Returns
+/// * 清理的请求数
+pub async fn cleanup_in_flight_files() -> usize {
+    let request_ids: Vec<String> = IN_FLIGHT.lock().unwrap().iter().map(|(id, _)| id.clone()).collect();
+    for request_id in &request_ids {
+        let _ = cleanup_request_file(request_id).await;
+        let _ = tokio::fs::remove_file(get_response_file_path(request_id)).await;
+    }
+    request_ids.len()
+}
+
+/// 清理崩溃等原因残留的 MCP 请求/响应临时文件
+///
+/// 只删除修改时间早于 `max_age` 的文件，仍在等待响应的请求的文件不受影响
+///
+/// # Returns
+/// * 删除的文件数和字节数
+pub async fn sweep_orphaned_temp_files(max_age: Duration) -> (usize, u64) {
+    let in_flight: Vec<String> = IN_FLIGHT.lock().unwrap().iter().map(|(id, _)| id.clone()).collect();
+    let removed = tokio::task::spawn_blocking(move || {
+        sweep_temp_dir(&ipc_dir(), max_age, &in_flight, std::time::SystemTime::now())
+    })
+    .await
+    .unwrap_or_default();
+    if removed.0 > 0 {
+        log::info!("[sweep_orphaned_temp_files] 已清理 {} 个残留的临时文件", removed.0);
+    }
+    removed
+}
+
+fn sweep_temp_dir(dir: &std::path::Path, max_age: Duration, in_flight: &[String], now: std::time::SystemTime) -> (usize, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    let mut removed = 0;
+    let mut freed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(rest) = name.strip_prefix(MCP_REQUEST_FILE_PREFIX)
+            .or_else(|| name.strip_prefix(MCP_RESPONSE_FILE_PREFIX)) else {
+            continue;
+        };
+        if in_flight.iter().any(|id| rest.starts_with(&format!("{}.", id))) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let expired = metadata.modified()
+            .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age >= max_age));
+        if expired && std::fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+            freed += metadata.len();
+        }
+    }
+    (removed, freed)
+}
+
 /// 检查 GUI 是否可用（用于诊断）
 pub fn check_ui_availability() -> Result<PathBuf> {
     let exe_path = find_ui_executable()?;
     log::info!("[check_ui_availability] UI 可执行文件可用: {:?}", exe_path);
     Ok(exe_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(name: &str, kind: FormFieldKind, required: bool) -> FormField {
+        FormField {
+            name: name.to_string(),
+            label: None,
+            kind,
+            required,
+            options: vec!["dev".to_string(), "prod".to_string()],
+            default: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_form_field_normalize() {
+        let port = field("port", FormFieldKind::Number, true);
+        assert_eq!(port.normalize(Some(&json!("8080"))).unwrap(), Some(json!(8080)));
+        assert_eq!(port.normalize(Some(&json!(" -1 "))).unwrap(), Some(json!(-1)));
+        assert_eq!(port.normalize(Some(&json!("18446744073709551615"))).unwrap(), Some(json!(u64::MAX)));
+        assert_eq!(port.normalize(Some(&json!("0.5"))).unwrap(), Some(json!(0.5)));
+        assert_eq!(port.normalize(Some(&json!(8080))).unwrap(), Some(json!(8080)));
+        assert!(port.normalize(Some(&json!("abc"))).is_err());
+        assert!(port.normalize(None).is_err());
+
+        let env = field("env", FormFieldKind::Select, false);
+        assert_eq!(env.normalize(Some(&json!("prod"))).unwrap(), Some(json!("prod")));
+        assert!(env.normalize(Some(&json!("staging"))).is_err());
+        assert_eq!(env.normalize(Some(&json!(" "))).unwrap(), None);
+
+        let overwrite = field("overwrite", FormFieldKind::Checkbox, false);
+        assert_eq!(overwrite.normalize(None).unwrap(), Some(json!(false)));
+        assert!(overwrite.normalize(Some(&json!("yes"))).is_err());
+    }
+
+    #[test]
+    fn test_agent_attachment_validate() {
+        let image = AgentAttachment::Image { data: "aGVsbG8=".to_string(), mime_type: "image/png".to_string(), caption: None };
+        assert!(image.validate().is_ok());
+        let not_image = AgentAttachment::Image { data: "aGVsbG8=".to_string(), mime_type: "text/html".to_string(), caption: None };
+        assert!(not_image.validate().is_err());
+        let bad_data = AgentAttachment::Image { data: "not base64!".to_string(), mime_type: "image/png".to_string(), caption: None };
+        assert!(bad_data.validate().is_err());
+        let empty = AgentAttachment::Code { code: " ".to_string(), language: None, title: None };
+        assert!(empty.validate().is_err());
+
+        let parsed: AgentAttachment = serde_json::from_value(json!({ "type": "code", "code": "fn main() {}", "language": "rust" })).unwrap();
+        assert!(matches!(parsed, AgentAttachment::Code { language: Some(ref l), .. } if l == "rust"));
+    }
+
+    #[test]
+    fn test_client_disconnected_response() {
+        let response = PopupResponse::client_disconnected("req-1");
+        assert!(response.cancelled && response.client_disconnected);
+        assert_eq!(response.confirmation(), None);
+
+        // 旧版 GUI 写入的响应没有该字段
+        let legacy: PopupResponse = serde_json::from_value(json!({
+            "request_id": "req-1",
+            "user_input": "ok",
+            "selected_options": [],
+            "images": [],
+            "cancelled": false,
+        }))
+        .unwrap();
+        assert!(!legacy.client_disconnected);
+    }
+
+    #[test]
+    fn test_parent_message_roundtrip() {
+        let update = ParentMessage::Update(PopupUpdate {
+            request_id: "req-1".to_string(),
+            revision: 2,
+            clarification: Some("more".to_string()),
+            add_options: Vec::new(),
+        });
+        let line = serde_json::to_string(&update).unwrap();
+        assert!(line.contains(r#""type":"update""#));
+        assert!(matches!(serde_json::from_str(&line).unwrap(), ParentMessage::Update(u) if u.revision == 2));
+
+        let mut request = PopupRequest::new(Some("next?".to_string()), None, None);
+        request.session_id = Some("s-1".to_string());
+        let line = serde_json::to_string(&ParentMessage::Request(request.clone())).unwrap();
+        assert!(matches!(serde_json::from_str(&line).unwrap(), ParentMessage::Request(r) if r.id == request.id));
+
+        let ready = format!("{}{}\n", SESSION_RESPONSE_READY_PREFIX, request.id);
+        assert_eq!(parse_response_ready(&ready), Some(request.id.as_str()));
+        assert_eq!(parse_response_ready("some other output"), None);
+    }
+
+    #[test]
+    fn test_decode_request_from_newer_server() {
+        let request = PopupRequest::new(Some("continue?".to_string()), None, Some(vec!["yes".to_string()]));
+        assert_eq!(request.schema_version, POPUP_SCHEMA_VERSION);
+        let decoded = decode_request(&serde_json::to_string(&request).unwrap()).unwrap();
+        assert_eq!(decoded.id, request.id);
+
+        // 新版本的弹窗类型、附件类型和字段
+        let newer = json!({
+            "id": "req-1",
+            "schema_version": POPUP_SCHEMA_VERSION + 1,
+            "kind": "wizard",
+            "priority": "urgent",
+            "message": "continue?",
+            "predefined_options": ["yes"],
+            "agent_attachments": [
+                { "type": "video", "url": "https://example.com/a.mp4" },
+                { "type": "code", "code": "fn main() {}" }
+            ],
+            "auto_submit": { "option": "yes", "after_ms": 1000 },
+            "wizard_steps": [],
+            "created_at": "2024-01-01T00:00:00Z"
+        });
+        assert!(serde_json::from_value::<PopupRequest>(newer.clone()).is_err());
+        let decoded = decode_request(&newer.to_string()).unwrap();
+        assert_eq!(decoded.kind, PopupKind::Feedback);
+        assert_eq!(decoded.priority, Priority::Normal);
+        assert_eq!(decoded.agent_attachments.len(), 1);
+        assert!(decoded.auto_submit.is_none());
+        assert_eq!(decoded.predefined_options, Some(vec!["yes".to_string()]));
+
+        let message = json!({ "type": "request", "id": "req-2", "kind": "wizard" });
+        assert!(matches!(decode_parent_message(&message.to_string()).unwrap(), ParentMessage::Request(r) if r.id == "req-2"));
+        assert!(decode_request(r#"{"kind": "feedback"}"#).is_err());
+    }
+
+    #[test]
+    fn test_decode_response_from_older_gui() {
+        // 早期 GUI 只写入这些字段
+        let legacy = json!({ "request_id": "req-1", "user_input": "ok" });
+        let decoded = decode_response(&legacy.to_string()).unwrap();
+        assert_eq!(decoded.schema_version, 0);
+        assert_eq!(decoded.user_input.as_deref(), Some("ok"));
+        assert!(!decoded.cancelled && decoded.images.is_empty());
+
+        let newer = json!({
+            "request_id": "req-1",
+            "schema_version": POPUP_SCHEMA_VERSION + 1,
+            "user_input": null,
+            "selected_options": ["yes"],
+            "images": [{ "data": "", "media_type": "image/png" }],
+            "cancelled": false,
+            "form_values": ["not", "a", "map"]
+        });
+        let decoded = decode_response(&newer.to_string()).unwrap();
+        assert_eq!(decoded.selected_options, vec!["yes".to_string()]);
+        assert!(decoded.images.is_empty() && decoded.form_values.is_none());
+    }
+
+    #[test]
+    fn test_incompatible_peer_version() {
+        let request = PopupRequest::new(Some("continue?".to_string()), None, None);
+        assert_eq!(request.app_version.as_deref(), Some(APP_VERSION));
+        assert_eq!(request.min_schema_version, POPUP_MIN_SCHEMA_VERSION);
+
+        // 对方要求的最低格式版本高于当前版本
+        let newer = json!({
+            "id": "req-1",
+            "schema_version": POPUP_SCHEMA_VERSION + 1,
+            "min_schema_version": POPUP_SCHEMA_VERSION + 1,
+            "app_version": "99.0.0"
+        });
+        let err = decode_request(&newer.to_string()).unwrap_err();
+        let mismatch = err.downcast_ref::<VersionMismatch>().unwrap();
+        assert_eq!(mismatch.peer_version, "99.0.0");
+        assert_eq!(mismatch.peer_schema, POPUP_SCHEMA_VERSION + 1);
+
+        let response = json!({ "request_id": "req-1", "min_schema_version": POPUP_SCHEMA_VERSION + 1 });
+        assert!(decode_response(&response.to_string()).unwrap_err().is::<VersionMismatch>());
+
+        // 只有程序版本不同仍可解析
+        let other = json!({ "request_id": "req-1", "app_version": "0.0.1", "user_input": "ok" });
+        assert_eq!(decode_response(&other.to_string()).unwrap().user_input.as_deref(), Some("ok"));
+    }
+
+    #[test]
+    fn test_assign_hotkeys() {
+        let options: Vec<String> = (1..=11).map(|i| format!("option {}", i)).collect();
+        let mut request = PopupRequest::new(Some("pick".to_string()), None, Some(options));
+        request.assign_hotkeys();
+        assert_eq!(request.hotkeys.len(), MAX_OPTION_HOTKEYS);
+        assert_eq!(request.hotkey_option("1"), Some("option 1"));
+        assert_eq!(request.hotkey_option("9"), Some("option 9"));
+        assert_eq!(request.hotkey_option("0"), None);
+
+        let mut request = PopupRequest::new(Some("free text".to_string()), None, None);
+        request.assign_hotkeys();
+        assert!(request.hotkeys.is_empty());
+    }
+
+    #[test]
+    fn test_decode_change_requests() {
+        let response = json!({
+            "request_id": "req-1",
+            "selected_options": [],
+            "images": [],
+            "cancelled": false,
+            "change_requests": [
+                { "file": "src/lib.rs", "location": "42", "comment": "Handle the error", "severity": "blocker" },
+                { "file": "src/main.rs", "comment": "Rename" },
+                { "comment": "missing file" }
+            ]
+        });
+        let decoded = decode_response(&response.to_string()).unwrap();
+        assert_eq!(decoded.change_requests.len(), 2);
+        assert_eq!(decoded.change_requests[0].severity, ChangeSeverity::Blocker);
+        assert_eq!(decoded.change_requests[1].location, None);
+        assert_eq!(decoded.change_requests[1].severity, ChangeSeverity::Minor);
+
+        let request = json!({ "id": "req-1", "action_class": "deploy", "created_at": "2024-01-01T00:00:00Z" });
+        assert_eq!(decode_request(&request.to_string()).unwrap().action_class, ActionClass::General);
+    }
+
+    #[tokio::test]
+    async fn test_response_channel_delivery() {
+        let request_id = Uuid::new_v4().to_string();
+        let mut channel = ResponseChannel::bind(&request_id);
+        let endpoint = channel.as_ref().map(|c| c.endpoint().to_string());
+        assert!(endpoint.is_some());
+
+        let response = PopupResponse::cancelled(&request_id);
+        let response_path = get_response_file_path(&request_id);
+        deliver_response(endpoint.as_deref(), &response_path, &response, None).await.unwrap();
+        assert_eq!(next_channel_response(&mut channel).await.request_id, request_id);
+        // 通过通道送达时不写入响应文件
+        assert!(!response_path.exists());
+
+        // 通道关闭后回退到响应文件
+        drop(channel);
+        deliver_response(endpoint.as_deref(), &response_path, &response, None).await.unwrap();
+        assert!(response_path.exists());
+        let _ = std::fs::remove_file(response_path);
+    }
+
+    #[test]
+    fn test_response_file_validation() {
+        let response = PopupResponse::cancelled("req-1");
+        let file = ResponseFile::new(&response, None).unwrap();
+        let content = serde_json::to_string(&file).unwrap();
+        let parsed = serde_json::from_str::<ResponseFile>(&content).unwrap().into_response(None).unwrap();
+        assert_eq!(parsed.request_id, "req-1");
+
+        let mut tampered = ResponseFile::new(&response, None).unwrap();
+        tampered.payload = tampered.payload.replace("req-1", "req-2");
+        assert!(tampered.into_response(None).is_err());
+
+        let mut future = ResponseFile::new(&response, None).unwrap();
+        future.schema_version = RESPONSE_FILE_SCHEMA_VERSION + 1;
+        assert!(future.into_response(None).is_err());
+
+        // 加密的响应需要同一个密钥
+        let key = FileKey::generate();
+        let sealed = ResponseFile::new(&response, Some(&key)).unwrap();
+        assert!(!sealed.payload.contains("req-1"));
+        assert!(ResponseFile::new(&response, Some(&key)).unwrap().into_response(None).is_err());
+        assert_eq!(sealed.into_response(Some(&key)).unwrap().request_id, "req-1");
+
+        // 写了一半的文件无法解析
+        assert!(serde_json::from_str::<ResponseFile>(&content[..content.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_sweep_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "whale_mcp_request_old.json",
+            "whale_mcp_response_old.json.tmp",
+            "whale_mcp_request_busy.json",
+            "unrelated.json",
+        ] {
+            std::fs::write(dir.path().join(name), "{}").unwrap();
+        }
+        let max_age = Duration::from_secs(3600);
+        let in_flight = vec!["busy".to_string()];
+
+        // 文件刚创建，未超过保留时间
+        assert_eq!(sweep_temp_dir(dir.path(), max_age, &in_flight, std::time::SystemTime::now()), (0, 0));
+
+        let later = std::time::SystemTime::now() + Duration::from_secs(2 * 3600);
+        assert_eq!(sweep_temp_dir(dir.path(), max_age, &in_flight, later), (2, 4));
+        assert!(dir.path().join("whale_mcp_request_busy.json").exists());
+        assert!(dir.path().join("unrelated.json").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_describe_crash() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        // 正常退出且没有响应：用户关闭了窗口
+        assert_eq!(describe_crash(ExitStatus::from_raw(0), "log line"), None);
+        // 退出码 101（Rust panic）
+        let crash = describe_crash(ExitStatus::from_raw(101 << 8), "thread 'main' panicked\n").unwrap();
+        assert!(crash.contains("101"));
+        assert!(crash.contains("panicked"));
+        // 被 SIGSEGV 终止
+        assert!(describe_crash(ExitStatus::from_raw(11), "").unwrap().contains("11"));
+        // GUI 报告的错误
+        let code = PopupOutcome::Error.exit_code();
+        assert!(describe_crash(ExitStatus::from_raw(code << 8), "").unwrap().contains("GUI 报告错误"));
+    }
+
+    #[test]
+    fn test_popup_outcome_exit_codes() {
+        for outcome in [
+            PopupOutcome::Submitted,
+            PopupOutcome::Cancelled,
+            PopupOutcome::TimedOut,
+            PopupOutcome::Error,
+            PopupOutcome::Incompatible,
+            PopupOutcome::AutoSelected,
+        ] {
+            assert_eq!(PopupOutcome::from_exit_code(outcome.exit_code()), Some(outcome));
+        }
+        assert_eq!(PopupOutcome::exit_code(PopupOutcome::Submitted), 0);
+        // 通用错误、命令行参数错误和 panic 不属于语义退出码
+        assert_eq!(PopupOutcome::from_exit_code(1), None);
+        assert_eq!(PopupOutcome::from_exit_code(2), None);
+        assert_eq!(PopupOutcome::from_exit_code(101), None);
+        assert_eq!(serde_json::to_value(PopupOutcome::TimedOut).unwrap(), json!("timed_out"));
+    }
+}
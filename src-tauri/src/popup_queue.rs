@@ -0,0 +1,148 @@
+//! 弹窗请求队列
+//!
+//! 多个 Agent 会话同时调用工具时，如果同时启动多个 GUI 进程会互相抢占焦点。
+//! 这里按先后顺序排队，同一时间只显示一个弹窗；排队中的请求可以获得当前位置。
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct QueueState {
+    tickets: Mutex<VecDeque<u64>>,
+    next_ticket: AtomicU64,
+    changed: Notify,
+}
+
+/// 弹窗队列（克隆后共享同一队列）
+#[derive(Debug, Clone, Default)]
+pub struct PopupQueue {
+    state: Arc<QueueState>,
+}
+
+/// 队列中的位置凭证，释放时自动出队（包括请求被取消的情况）
+pub struct QueueTicket {
+    state: Arc<QueueState>,
+    ticket: u64,
+}
+
+impl PopupQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入队列
+    pub fn join(&self) -> QueueTicket {
+        let ticket = self.state.next_ticket.fetch_add(1, Ordering::SeqCst);
+        self.state.tickets.lock().unwrap().push_back(ticket);
+        QueueTicket {
+            state: self.state.clone(),
+            ticket,
+        }
+    }
+
+    /// 是否没有正在显示或排队中的弹窗
+    pub fn is_empty(&self) -> bool {
+        self.state.tickets.lock().unwrap().is_empty()
+    }
+}
+
+impl QueueTicket {
+    /// 排在前面的请求数（0 表示轮到当前请求）
+    pub fn position(&self) -> usize {
+        self.state
+            .tickets
+            .lock()
+            .unwrap()
+            .iter()
+            .position(|&t| t == self.ticket)
+            .unwrap_or(0)
+    }
+
+    /// 等待轮到当前请求，位置变化时调用 `on_position`
+    pub async fn wait_turn<F, Fut>(&self, mut on_position: F)
+    where
+        F: FnMut(usize) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        loop {
+            // 先创建通知再读取位置，避免错过两者之间的出队通知
+            let changed = self.state.changed.notified();
+            let ahead = self.position();
+            if ahead == 0 {
+                return;
+            }
+            on_position(ahead).await;
+            changed.await;
+        }
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        self.state.tickets.lock().unwrap().retain(|&t| t != self.ticket);
+        self.state.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_first_ticket_runs_immediately() {
+        let queue = PopupQueue::new();
+        let ticket = queue.join();
+
+        ticket.wait_turn(|_| async {}).await;
+        assert_eq!(ticket.position(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_waits_until_previous_released() {
+        let queue = PopupQueue::new();
+        let first = queue.join();
+        let second = queue.join();
+        assert_eq!(second.position(), 1);
+
+        let handle = tokio::spawn(async move {
+            let mut reported = Vec::new();
+            second.wait_turn(|ahead| {
+                reported.push(ahead);
+                async {}
+            }).await;
+            reported
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!handle.is_finished());
+
+        drop(first);
+        let reported = tokio::time::timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+        assert_eq!(reported, vec![1]);
+        assert_eq!(queue.join().position(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_leaves_queue() {
+        let queue = PopupQueue::new();
+        let first = queue.join();
+        let second = queue.join();
+        let third = queue.join();
+        assert_eq!(third.position(), 2);
+
+        // 中间的请求被取消，后面的请求位置前移
+        drop(second);
+        assert_eq!(third.position(), 1);
+
+        drop(first);
+        assert_eq!(third.position(), 0);
+
+        assert!(!queue.is_empty());
+        drop(third);
+        assert!(queue.is_empty());
+    }
+}
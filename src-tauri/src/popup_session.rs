@@ -0,0 +1,198 @@
+//! 多轮对话弹窗会话
+//!
+//! Agent 用同一个 `session_id` 连续调用反馈工具时复用同一个 GUI 进程，而不是每个问题启动、关闭一次弹窗。
+//! 服务端通过 GUI 的 stdin 发送下一个问题（[`ParentMessage::Request`]），
+//! GUI 通过响应通道发送响应（或写入响应文件后在 stdout 输出 [`SESSION_RESPONSE_READY_PREFIX`] 通知），
+//! 弹窗保持打开等待下一个问题。
+//! 用户关闭弹窗、等待超时或请求被取消时结束会话，下一次调用重新启动 GUI。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::daemon;
+use crate::gui_limit::{self, GuiSlot};
+use crate::popup::{
+    self, InFlightGuard, ParentMessage, PopupOutcome, PopupRequest, PopupResponse, PopupUpdate, ResponseChannel,
+    SESSION_RESPONSE_READY_PREFIX,
+};
+
+/// 运行中的会话弹窗
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    /// 弹窗数量上限的槽位，会话结束时释放
+    _gui_slot: Option<GuiSlot>,
+}
+
+impl Session {
+    /// 启动新的 GUI 进程显示第一个问题
+    async fn spawn(request: &PopupRequest, gui_slot: Option<GuiSlot>) -> Result<Self> {
+        let mut child = popup::launch_popup(request).await?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("GUI stdin 不可用"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("GUI stdout 不可用"))?;
+        // 及时读取 stderr，避免 GUI 日志写满管道后阻塞
+        popup::collect_stderr_tail(child.stderr.take());
+        Ok(Self { child, stdin, stdout: BufReader::new(stdout).lines(), _gui_slot: gui_slot })
+    }
+
+    /// 在已打开的弹窗中显示下一个问题
+    async fn ask(&mut self, request: &PopupRequest) -> Result<()> {
+        if self.child.try_wait()?.is_some() {
+            return Err(anyhow!("GUI 进程已退出"));
+        }
+        popup::create_request_file(request).await?;
+        popup::send_message(&mut self.stdin, &ParentMessage::Request(popup::with_file_key(request))).await
+    }
+}
+
+/// 会话槽位：同一会话的请求依次使用，弹窗关闭后为 None
+type Slot = Arc<tokio::sync::Mutex<Option<Session>>>;
+
+/// 多轮对话会话（克隆后共享）
+#[derive(Clone, Default)]
+pub struct PopupSessions {
+    sessions: Arc<Mutex<HashMap<String, Slot>>>,
+}
+
+impl PopupSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 显示请求并等待响应
+    ///
+    /// 请求带有 `session_id` 时复用该会话的弹窗，否则与 [`daemon::launch_and_wait`] 相同
+    pub async fn launch_and_wait(
+        &self,
+        request: &PopupRequest,
+        timeout: Option<Duration>,
+        cancelled: impl Future<Output = ()>,
+        mut updates: Option<UnboundedReceiver<PopupUpdate>>,
+    ) -> Result<PopupResponse> {
+        let Some(session_id) = request.session_id.as_deref() else {
+            return daemon::launch_and_wait(request, timeout, cancelled, updates).await;
+        };
+
+        let slot = self.sessions.lock().unwrap()
+            .entry(session_id.to_string())
+            .or_default()
+            .clone();
+        // 同一会话的问题依次显示
+        let mut slot = slot.lock().await;
+        let _in_flight = InFlightGuard::new(&request.id);
+        
+        let mut channel = ResponseChannel::bind(&request.id);
+        let mut request = request.clone();
+        request.response_endpoint = channel.as_ref().map(|channel| channel.endpoint().to_string());
+        let request = &request;
+
+        let reused = match slot.as_mut() {
+            Some(session) => match session.ask(request).await {
+                Ok(()) => true,
+                Err(e) => {
+                    log::info!("[session] 会话 {} 的弹窗不可用（{}），重新启动", session_id, e);
+                    false
+                }
+            },
+            None => false,
+        };
+        tokio::pin!(cancelled);
+        if !reused {
+            // 独立弹窗数量达到上限时等待其他弹窗关闭
+            let gui_limit = crate::config::load_config_direct().await.unwrap_or_default().gui_limit;
+            let gui_slot = tokio::select! {
+                gui_slot = gui_limit::acquire(&gui_limit) => gui_slot,
+                _ = &mut cancelled => return Ok(PopupResponse::cancelled(&request.id)),
+            };
+            *slot = Some(Session::spawn(request, gui_slot).await?);
+        }
+        log::info!("[session] 会话 {} 显示问题 {}（复用弹窗: {}）", session_id, request.id, reused);
+        let session = slot.as_mut().expect("会话弹窗已启动");
+
+        let response_path = popup::get_response_file_path(&request.id);
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                line = session.stdout.next_line() => match line {
+                    Ok(Some(line)) => {
+                        // 通过响应通道发送时没有响应文件，由通道分支返回
+                        if popup::parse_response_ready(&line) == Some(request.id.as_str()) && response_path.exists() {
+                            return popup::read_response_file(&request.id).await;
+                        }
+                    }
+                    // stdout 关闭：用户关闭了弹窗，会话结束
+                    Ok(None) | Err(_) => {
+                        let status = session.child.wait().await.ok();
+                        *slot = None;
+                        log::info!("[session] 会话 {} 的弹窗已关闭", session_id);
+                        if let Some(response) = popup::take_channel_response(&mut channel) {
+                            return Ok(response);
+                        }
+                        if response_path.exists() {
+                            return popup::read_response_file(&request.id).await;
+                        }
+                        // GUI 报告自动提交倒计时结束但没能发送响应
+                        let outcome = status.and_then(|status| status.code()).and_then(PopupOutcome::from_exit_code);
+                        return Ok(match (outcome, &request.auto_submit) {
+                            (Some(PopupOutcome::AutoSelected), Some(auto_submit)) => {
+                                PopupResponse::auto_selected(&request.id, &auto_submit.option)
+                            }
+                            _ => PopupResponse::cancelled(&request.id),
+                        });
+                    }
+                },
+                _ = &mut deadline => {
+                    log::warn!("[session] 等待用户响应超时 ({:?})，关闭会话 {}", timeout, session_id);
+                    popup::terminate_popup(&mut session.child, &response_path).await;
+                    *slot = None;
+                    return Ok(PopupResponse::timed_out(&request.id));
+                }
+                _ = &mut cancelled => {
+                    log::warn!("[session] 请求已被取消，关闭会话 {}", session_id);
+                    popup::terminate_popup(&mut session.child, &response_path).await;
+                    *slot = None;
+                    return Ok(PopupResponse::cancelled(&request.id));
+                }
+                response = popup::next_channel_response(&mut channel) => return Ok(response),
+                update = popup::next_update(&mut updates) => match update {
+                    Some(update) => popup::send_update(Some(&mut session.stdin), &update).await,
+                    None => updates = None,
+                },
+            }
+        }
+    }
+
+    /// 关闭所有会话弹窗（MCP Server 退出前调用）
+    ///
+    /// # Returns
+    /// * 关闭的会话数
+    pub async fn close_all(&self) -> usize {
+        let slots: Vec<Slot> = self.sessions.lock().unwrap().drain().map(|(_, slot)| slot).collect();
+        let mut closed = 0;
+        for slot in slots {
+            // 正在等待回答的会话由断开信号终止，这里只关闭空闲的会话
+            let Ok(mut slot) = slot.try_lock() else { continue };
+            if let Some(mut session) = slot.take() {
+                let _ = session.child.kill().await;
+                closed += 1;
+            }
+        }
+        closed
+    }
+}
+
@@ -0,0 +1,220 @@
+//! 已打开弹窗的更新
+//!
+//! Agent 在用户作答前获得了新信息时，可以用 `supersede` 参数再次调用反馈工具，
+//! 更新同一客户端仍在等待回答的弹窗（追加说明、增加选项），而不是等用户回答过时的问题。
+//! 更新通过 GUI 进程的 stdin 发送，弹窗原地刷新；用户的回答交给最后一次更新的调用返回，
+//! 之前的调用返回“已被更新”。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::popup::{OptionDetail, PopupUpdate};
+
+/// 交给更新方的结果
+#[derive(Debug)]
+pub enum Handoff<T> {
+    /// 用户的回答（或弹窗的最终状态）
+    Answered(T),
+    /// 又被更新的调用取代
+    Superseded,
+}
+
+struct Entry<T> {
+    owner: String,
+    seq: u64,
+    /// 各修订的问题文本（第一个为原始问题）
+    revisions: Vec<String>,
+    updates: mpsc::UnboundedSender<PopupUpdate>,
+    /// 弹窗启动时取走
+    receiver: Option<mpsc::UnboundedReceiver<PopupUpdate>>,
+    /// 最后一次更新的调用
+    successor: Option<oneshot::Sender<Handoff<T>>>,
+}
+
+struct State<T> {
+    entries: HashMap<String, Entry<T>>,
+    next_seq: u64,
+}
+
+/// 等待回答的弹窗（克隆后共享状态）
+pub struct OpenPopups<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Clone for OpenPopups<T> {
+    fn clone(&self) -> Self {
+        Self { state: self.state.clone() }
+    }
+}
+
+impl<T> Default for OpenPopups<T> {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State { entries: HashMap::new(), next_seq: 0 })),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for OpenPopups<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let open = self.state.lock().unwrap().entries.len();
+        f.debug_struct("OpenPopups").field("open", &open).finish()
+    }
+}
+
+/// 弹窗登记凭证，释放时移除登记
+pub struct Registration<T> {
+    popups: OpenPopups<T>,
+    request_id: String,
+}
+
+impl<T> OpenPopups<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记等待回答的弹窗
+    ///
+    /// # Arguments
+    /// * `owner` - 发起请求的客户端，只有同一客户端的调用可以更新该弹窗
+    pub fn register(&self, request_id: &str, owner: &str, message: &str) -> Registration<T> {
+        let (updates, receiver) = mpsc::unbounded_channel();
+        let mut state = self.state.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.entries.insert(request_id.to_string(), Entry {
+            owner: owner.to_string(),
+            seq,
+            revisions: vec![message.to_string()],
+            updates,
+            receiver: Some(receiver),
+            successor: None,
+        });
+        Registration { popups: self.clone(), request_id: request_id.to_string() }
+    }
+
+    /// 取出弹窗的更新通道（启动 GUI 时使用）
+    pub fn take_updates(&self, request_id: &str) -> Option<mpsc::UnboundedReceiver<PopupUpdate>> {
+        self.state.lock().unwrap().entries.get_mut(request_id)?.receiver.take()
+    }
+
+    /// 更新该客户端最近打开的弹窗
+    ///
+    /// # Returns
+    /// * 接收最终结果的通道；该客户端没有等待回答的弹窗时返回 None
+    pub fn supersede(
+        &self,
+        owner: &str,
+        message: &str,
+        add_options: Vec<OptionDetail>,
+    ) -> Option<oneshot::Receiver<Handoff<T>>> {
+        let mut state = self.state.lock().unwrap();
+        let (request_id, entry) = state
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| entry.owner == owner)
+            .max_by_key(|(_, entry)| entry.seq)?;
+
+        entry.revisions.push(message.to_string());
+        let update = PopupUpdate {
+            request_id: request_id.clone(),
+            revision: entry.revisions.len() as u32,
+            clarification: Some(message.to_string()),
+            add_options,
+        };
+        // 弹窗已关闭时发送失败，回答仍会交给本次调用
+        let _ = entry.updates.send(update);
+
+        let (successor, handoff) = oneshot::channel();
+        if let Some(previous) = entry.successor.replace(successor) {
+            let _ = previous.send(Handoff::Superseded);
+        }
+        Some(handoff)
+    }
+}
+
+impl<T> Registration<T> {
+    /// 各修订的问题文本
+    pub fn revisions(&self) -> Vec<String> {
+        self.popups
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .get(&self.request_id)
+            .map(|entry| entry.revisions.clone())
+            .unwrap_or_default()
+    }
+
+    /// 把结果交给最后一次更新的调用并移除登记
+    ///
+    /// # Returns
+    /// * 没有等待中的更新方时原样返回结果
+    pub fn hand_off(&self, value: T) -> Result<(), T> {
+        let entry = self.popups.state.lock().unwrap().entries.remove(&self.request_id);
+        match entry.and_then(|entry| entry.successor) {
+            Some(successor) => match successor.send(Handoff::Answered(value)) {
+                Err(Handoff::Answered(value)) => Err(value),
+                _ => Ok(()),
+            },
+            None => Err(value),
+        }
+    }
+}
+
+impl<T> Drop for Registration<T> {
+    fn drop(&mut self) {
+        self.popups.state.lock().unwrap().entries.remove(&self.request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supersede_forwards_update_and_hands_off() {
+        let popups: OpenPopups<&str> = OpenPopups::new();
+        let registration = popups.register("req-1", "cursor", "Deploy?");
+        let mut updates = popups.take_updates("req-1").unwrap();
+
+        // 其他客户端不能更新该弹窗
+        assert!(popups.supersede("claude", "Deploy to prod?", Vec::new()).is_none());
+
+        let mut handoff = popups.supersede("cursor", "Deploy to prod?", Vec::new()).unwrap();
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.request_id, "req-1");
+        assert_eq!(update.revision, 2);
+        assert_eq!(registration.revisions(), vec!["Deploy?", "Deploy to prod?"]);
+
+        assert!(registration.hand_off("yes").is_ok());
+        assert!(matches!(handoff.try_recv(), Ok(Handoff::Answered("yes"))));
+        assert!(popups.supersede("cursor", "again", Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_later_update_supersedes_earlier_one() {
+        let popups: OpenPopups<&str> = OpenPopups::new();
+        let registration = popups.register("req-1", "cursor", "v1");
+        let mut first = popups.supersede("cursor", "v2", Vec::new()).unwrap();
+        let mut second = popups.supersede("cursor", "v3", Vec::new()).unwrap();
+
+        assert!(matches!(first.try_recv(), Ok(Handoff::Superseded)));
+        assert!(registration.hand_off("ok").is_ok());
+        assert!(matches!(second.try_recv(), Ok(Handoff::Answered("ok"))));
+    }
+
+    #[test]
+    fn test_hand_off_without_successor_returns_value() {
+        let popups: OpenPopups<&str> = OpenPopups::new();
+        let registration = popups.register("req-1", "cursor", "v1");
+        assert_eq!(registration.hand_off("ok"), Err("ok"));
+
+        // 登记释放后等待中的更新方收到关闭信号
+        let registration = popups.register("req-2", "cursor", "v1");
+        let mut handoff = popups.supersede("cursor", "v2", Vec::new()).unwrap();
+        drop(registration);
+        assert!(handoff.try_recv().is_err());
+    }
+}
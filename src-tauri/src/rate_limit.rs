@@ -0,0 +1,304 @@
+//! 工具调用限流和熔断
+//!
+//! 出错的 Agent 可能在循环中反复调用反馈工具，每次都弹出窗口打断用户。
+//! 这里按客户端限制调用频率（令牌桶），并在连续多个请求都没有得到用户回答时熔断：
+//! 之后的调用直接返回 “user overwhelmed” 错误，直到用户在界面中重置。
+//! GUI 和 MCP Server 是不同的进程，熔断状态和重置时间通过本地存储共享。
+//!
+//! 文本优化工具会消耗 LLM API 配额，另外按每分钟预算和最大并发数限制。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::result_language::{fill, ResultLanguage};
+use crate::types::{FloodProtectionConfig, OptimizeRateLimitConfig};
+
+/// 存储中记录最近一次熔断时间的键
+pub const BREAKER_TRIPPED_AT_KEY: &str = "circuit_breaker.tripped_at";
+/// 存储中记录用户最近一次重置熔断的键
+pub const BREAKER_RESET_AT_KEY: &str = "circuit_breaker.reset_at";
+
+/// 达到最大并发数时建议的重试等待时间
+const CONCURRENCY_RETRY_AFTER_SECS: u64 = 5;
+
+/// 令牌桶：容量为每分钟预算，按预算匀速补充
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// 每分钟最多 `budget` 次（允许短时间内突发用完）
+    pub fn per_minute(budget: u32) -> Self {
+        let capacity = f64::from(budget.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            updated: Instant::now(),
+        }
+    }
+
+    /// 取一个令牌，不足时返回需要等待的时间
+    pub fn try_take(&mut self) -> Result<(), Duration> {
+        self.try_take_at(Instant::now())
+    }
+
+    fn try_take_at(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+/// 被拒绝的调用（作为结构化错误返回给 Agent）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum Rejection {
+    /// 调用过于频繁
+    RateLimited { retry_after_secs: u64 },
+    /// 连续多个请求未得到回答，已熔断，需要用户重置
+    UserOverwhelmed { unanswered: u32, tripped_at: String },
+    /// 同时打开的弹窗已达上限
+    GuiBusy { running: u32, retry_after_secs: u64 },
+}
+
+impl Rejection {
+    /// 返回给 Agent 的错误信息
+    pub fn describe(&self, language: ResultLanguage) -> String {
+        let labels = language.text();
+        match self {
+            Rejection::RateLimited { retry_after_secs } => fill(labels.rate_limited, &[retry_after_secs]),
+            Rejection::UserOverwhelmed { unanswered, .. } => fill(labels.user_overwhelmed, &[unanswered]),
+            Rejection::GuiBusy { running, retry_after_secs } => fill(labels.gui_busy, &[running, retry_after_secs]),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClientState {
+    bucket: TokenBucket,
+    unanswered: u32,
+    tripped_at: Option<DateTime<Utc>>,
+}
+
+/// 按客户端的限流和熔断状态（克隆后共享）
+#[derive(Debug, Clone, Default)]
+pub struct FloodGuard {
+    clients: Arc<Mutex<HashMap<String, ClientState>>>,
+}
+
+impl FloodGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 检查是否允许客户端发起新的调用
+    ///
+    /// `reset_at` 为用户最近一次重置熔断的时间，晚于熔断时间时恢复调用
+    pub fn admit(
+        &self,
+        owner: &str,
+        config: &FloodProtectionConfig,
+        reset_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Rejection> {
+        if !config.enabled {
+            return Ok(());
+        }
+        let mut clients = self.clients.lock().unwrap();
+        let state = clients.entry(owner.to_string()).or_insert_with(|| ClientState {
+            bucket: TokenBucket::per_minute(config.max_calls_per_minute),
+            unanswered: 0,
+            tripped_at: None,
+        });
+
+        if let Some(tripped_at) = state.tripped_at {
+            if reset_at.is_some_and(|reset_at| reset_at > tripped_at) {
+                log::info!("[rate_limit] 用户已重置熔断，恢复客户端 {} 的调用", owner);
+                state.tripped_at = None;
+                state.unanswered = 0;
+            } else {
+                return Err(state.overwhelmed());
+            }
+        }
+
+        if config.max_unanswered > 0 && state.unanswered >= config.max_unanswered {
+            log::warn!("[rate_limit] 客户端 {} 已有 {} 个请求未得到回答，熔断", owner, state.unanswered);
+            state.tripped_at = Some(Utc::now());
+            return Err(state.overwhelmed());
+        }
+
+        state.bucket.try_take().map_err(|wait| {
+            log::warn!("[rate_limit] 客户端 {} 调用过于频繁", owner);
+            Rejection::RateLimited { retry_after_secs: wait.as_secs().max(1) }
+        })
+    }
+
+    /// 记录用户回答了请求（包括主动取消），清零未回答计数
+    pub fn record_answered(&self, owner: &str) {
+        if let Some(state) = self.clients.lock().unwrap().get_mut(owner) {
+            state.unanswered = 0;
+        }
+    }
+
+    /// 记录请求未得到用户回答（超时、被客户端取消等）
+    pub fn record_unanswered(&self, owner: &str) {
+        if let Some(state) = self.clients.lock().unwrap().get_mut(owner) {
+            state.unanswered += 1;
+        }
+    }
+}
+
+/// 所有客户端共享的调用限流：每分钟预算和最大并发数（克隆后共享）
+#[derive(Debug, Clone, Default)]
+pub struct CallLimiter {
+    /// 当前预算和对应的令牌桶（配置的预算变化时重建）
+    bucket: Arc<Mutex<Option<(u32, TokenBucket)>>>,
+    running: Arc<AtomicUsize>,
+}
+
+/// 正在进行的调用，释放时减少并发计数
+#[derive(Debug)]
+pub struct CallPermit {
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for CallPermit {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl CallLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始一次调用，超出并发数或每分钟预算时拒绝
+    pub fn acquire(&self, config: &OptimizeRateLimitConfig) -> Result<CallPermit, Rejection> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst);
+        let permit = CallPermit { running: self.running.clone() };
+        if config.max_concurrent > 0 && running >= config.max_concurrent as usize {
+            log::warn!("[rate_limit] 文本优化并发数已达上限 {}", config.max_concurrent);
+            return Err(Rejection::RateLimited { retry_after_secs: CONCURRENCY_RETRY_AFTER_SECS });
+        }
+        if config.calls_per_minute == 0 {
+            return Ok(permit);
+        }
+
+        let mut current = self.bucket.lock().unwrap();
+        if current.as_ref().map(|(budget, _)| *budget) != Some(config.calls_per_minute) {
+            *current = Some((config.calls_per_minute, TokenBucket::per_minute(config.calls_per_minute)));
+        }
+        let (_, bucket) = current.as_mut().expect("令牌桶已创建");
+        bucket.try_take().map_err(|wait| {
+            log::warn!("[rate_limit] 文本优化超出每分钟预算 {}", config.calls_per_minute);
+            Rejection::RateLimited { retry_after_secs: wait.as_secs().max(1) }
+        })?;
+        Ok(permit)
+    }
+}
+
+impl ClientState {
+    fn overwhelmed(&self) -> Rejection {
+        Rejection::UserOverwhelmed {
+            unanswered: self.unanswered,
+            tripped_at: self.tripped_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_calls_per_minute: u32, max_unanswered: u32) -> FloodProtectionConfig {
+        FloodProtectionConfig { enabled: true, max_calls_per_minute, max_unanswered }
+    }
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(2);
+        bucket.updated = start;
+        assert!(bucket.try_take_at(start).is_ok());
+        assert!(bucket.try_take_at(start).is_ok());
+        let wait = bucket.try_take_at(start).unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+        assert!(bucket.try_take_at(start + Duration::from_secs(31)).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limited_per_client() {
+        let guard = FloodGuard::new();
+        let config = config(1, 0);
+        assert!(guard.admit("a", &config, None).is_ok());
+        assert!(matches!(guard.admit("a", &config, None), Err(Rejection::RateLimited { .. })));
+        assert!(guard.admit("b", &config, None).is_ok());
+    }
+
+    #[test]
+    fn test_breaker_trips_until_reset() {
+        let guard = FloodGuard::new();
+        let config = config(100, 2);
+        for _ in 0..2 {
+            guard.admit("agent", &config, None).unwrap();
+            guard.record_unanswered("agent");
+        }
+        let Err(Rejection::UserOverwhelmed { unanswered, .. }) = guard.admit("agent", &config, None) else {
+            panic!("expected the breaker to trip");
+        };
+        assert_eq!(unanswered, 2);
+
+        // 熔断前的重置不生效
+        let stale_reset = Utc::now() - chrono::Duration::hours(1);
+        assert!(guard.admit("agent", &config, Some(stale_reset)).is_err());
+
+        let reset = Utc::now() + chrono::Duration::seconds(1);
+        assert!(guard.admit("agent", &config, Some(reset)).is_ok());
+    }
+
+    #[test]
+    fn test_call_limiter_concurrency_and_budget() {
+        let limiter = CallLimiter::new();
+        let config = OptimizeRateLimitConfig { calls_per_minute: 3, max_concurrent: 1 };
+
+        let first = limiter.acquire(&config).unwrap();
+        assert_eq!(
+            limiter.acquire(&config).unwrap_err(),
+            Rejection::RateLimited { retry_after_secs: CONCURRENCY_RETRY_AFTER_SECS }
+        );
+        drop(first);
+
+        // 并发被拒绝的调用不消耗预算
+        limiter.acquire(&config).unwrap();
+        limiter.acquire(&config).unwrap();
+        assert!(matches!(limiter.acquire(&config), Err(Rejection::RateLimited { retry_after_secs }) if retry_after_secs > 1));
+    }
+
+    #[test]
+    fn test_answer_clears_unanswered() {
+        let guard = FloodGuard::new();
+        let config = config(100, 2);
+        guard.admit("agent", &config, None).unwrap();
+        guard.record_unanswered("agent");
+        guard.record_answered("agent");
+        guard.admit("agent", &config, None).unwrap();
+        guard.record_unanswered("agent");
+        assert!(guard.admit("agent", &config, None).is_ok());
+    }
+}
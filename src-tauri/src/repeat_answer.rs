@@ -0,0 +1,180 @@
+//! 重复问题记忆模块
+//!
+//! Agent 近期重复提出相同（或高度相似）的问题时，根据 `repeat_answer_policy`
+//! 在弹窗中提示上次的回答，或直接自动使用上次的回答而不再打扰用户。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::session::SessionSummary;
+use crate::storage::{Storage, StorageResult};
+
+/// 每次最多比较的历史会话数
+const MAX_CANDIDATES: usize = 200;
+
+/// 重复问题的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum RepeatAnswerMode {
+    /// 总是询问
+    #[default]
+    AlwaysAsk,
+    /// 在弹窗中提示上次的回答
+    Suggest,
+    /// 直接使用上次的回答
+    AutoApply,
+}
+
+/// 重复问题策略
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatAnswerPolicy {
+    #[serde(default)]
+    pub mode: RepeatAnswerMode,
+    /// 只匹配最近多少分钟内的回答
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: u32,
+    /// 相似度阈值（0~1，1 表示完全相同）
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f64,
+}
+
+fn default_window_minutes() -> u32 {
+    30
+}
+
+fn default_min_similarity() -> f64 {
+    0.9
+}
+
+impl Default for RepeatAnswerPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RepeatAnswerMode::default(),
+            window_minutes: default_window_minutes(),
+            min_similarity: default_min_similarity(),
+        }
+    }
+}
+
+/// 归一化文本：小写并合并空白
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// 字符三元组集合（短文本按整体处理）
+fn trigrams(text: &str) -> HashSet<Vec<char>> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() < 3 {
+        return HashSet::from([chars]);
+    }
+    chars.windows(3).map(|w| w.to_vec()).collect()
+}
+
+/// 两段文本的相似度（字符三元组 Dice 系数，0~1）
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    if a == b {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (a, b) = (trigrams(&a), trigrams(&b));
+    let common = a.intersection(&b).count();
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+/// 查找策略时间窗口内与问题最相似的历史回答
+///
+/// 只使用选中的选项都在 `options`（本次请求的选项）中的回答，选项已变化的旧回答不再适用
+pub fn find_previous_answer(
+    storage: &Storage,
+    message: &str,
+    options: &[String],
+    policy: &RepeatAnswerPolicy,
+) -> StorageResult<Option<SessionSummary>> {
+    if policy.mode == RepeatAnswerMode::AlwaysAsk {
+        return Ok(None);
+    }
+    let since = (chrono::Utc::now() - chrono::Duration::minutes(policy.window_minutes as i64)).to_rfc3339();
+    let candidates = storage.recent_answers(&since, MAX_CANDIDATES)?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|s| s.user_input.as_deref().is_some_and(|i| !i.trim().is_empty()) || !s.selected_options.is_empty())
+        .filter(|s| s.selected_options.iter().all(|o| options.contains(o)))
+        .map(|s| (similarity(message, s.message.as_deref().unwrap_or_default()), s))
+        .filter(|(score, _)| *score >= policy.min_similarity)
+        // 相似度相同时保留最近的回答（候选按时间倒序）
+        .fold(None, |best: Option<(f64, SessionSummary)>, (score, s)| match best {
+            Some((best_score, _)) if best_score >= score => best,
+            _ => Some((score, s)),
+        })
+        .map(|(_, s)| s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::{PopupRequest, PopupResponse};
+    use crate::session::SessionRecord;
+
+    fn answered(message: &str, input: &str) -> SessionRecord {
+        let request = PopupRequest::new(Some(message.to_string()), None, None);
+        let mut response = PopupResponse::cancelled(&request.id);
+        response.cancelled = false;
+        response.user_input = Some(input.to_string());
+        SessionRecord::new(&request, &response)
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Run the  tests?", "run the tests?"), 1.0);
+        assert!(similarity("Should I run the full test suite now?", "Should I run the full test suite now") > 0.9);
+        assert!(similarity("Should I run the tests?", "Delete the build directory?") < 0.3);
+        assert_eq!(similarity("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn test_find_previous_answer_respects_mode_and_threshold() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.save_session(&answered("Should I run the full test suite now?", "yes, run it")).unwrap();
+        storage.save_session(&answered("Deploy to staging?", "not yet")).unwrap();
+
+        let suggest = RepeatAnswerPolicy { mode: RepeatAnswerMode::Suggest, ..Default::default() };
+        let found = find_previous_answer(&storage, "should I run the full test suite now", &[], &suggest).unwrap();
+        assert_eq!(found.unwrap().user_input.as_deref(), Some("yes, run it"));
+
+        assert!(find_previous_answer(&storage, "Refactor the parser?", &[], &suggest).unwrap().is_none());
+
+        let always_ask = RepeatAnswerPolicy::default();
+        assert!(find_previous_answer(&storage, "Deploy to staging?", &[], &always_ask).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_previous_answer_skips_automatic_and_stale_answers() {
+        let storage = Storage::open_in_memory().unwrap();
+        let auto_apply = RepeatAnswerPolicy { mode: RepeatAnswerMode::AutoApply, ..Default::default() };
+        let options = vec!["Yes".to_string(), "No".to_string()];
+
+        // 倒计时结束自动选择的默认选项不是用户的回答
+        let request = PopupRequest::new(Some("Deploy to staging?".to_string()), None, None);
+        storage.save_session(&SessionRecord::new(&request, &PopupResponse::auto_selected(&request.id, "Yes"))).unwrap();
+        assert!(find_previous_answer(&storage, "Deploy to staging?", &options, &auto_apply).unwrap().is_none());
+
+        // 用户选择的选项已不在本次请求中
+        let mut record = answered("Deploy to staging?", "");
+        record.id = "stale".to_string();
+        record.response.selected_options = vec!["Later".to_string()];
+        storage.save_session(&record).unwrap();
+        assert!(find_previous_answer(&storage, "Deploy to staging?", &options, &auto_apply).unwrap().is_none());
+
+        let mut record = answered("Deploy to staging?", "");
+        record.id = "current".to_string();
+        record.response.selected_options = vec!["No".to_string()];
+        storage.save_session(&record).unwrap();
+        let found = find_previous_answer(&storage, "Deploy to staging?", &options, &auto_apply).unwrap();
+        assert_eq!(found.unwrap().selected_options, ["No"]);
+    }
+}
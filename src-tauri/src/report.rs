@@ -0,0 +1,230 @@
+//! 会话报告导出模块
+//!
+//! 将反馈会话（AI 请求消息、用户响应、附件图片、时间戳）渲染为
+//! Markdown 或自包含 HTML（图片以 data URI 内嵌），便于设计评审和审计留档。
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::SessionRecord;
+
+/// 报告格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// 文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// 按指定格式渲染报告
+pub fn render_report(record: &SessionRecord, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Markdown => render_markdown(record),
+        ReportFormat::Html => render_html(record),
+    }
+}
+
+/// 渲染 Markdown 报告
+pub fn render_markdown(record: &SessionRecord) -> String {
+    let request = &record.request;
+    let response = &record.response;
+    let mut out = String::new();
+
+    out.push_str(&format!("# Feedback Session {}\n\n", record.id));
+    out.push_str(&format!("- **Requested at:** {}\n", request.created_at));
+    out.push_str(&format!("- **Responded at:** {}\n\n", record.responded_at));
+
+    out.push_str("## Request\n\n");
+    if let Some(message) = request.message.as_deref() {
+        out.push_str(message.trim_end());
+        out.push_str("\n\n");
+    }
+    if let Some(full) = request.full_response.as_deref().filter(|s| !s.is_empty()) {
+        out.push_str("<details>\n<summary>Full response</summary>\n\n");
+        out.push_str(full.trim_end());
+        out.push_str("\n\n</details>\n\n");
+    }
+    if let Some(options) = request.predefined_options.as_ref().filter(|o| !o.is_empty()) {
+        out.push_str("**Options:**\n\n");
+        for option in options {
+            let mark = if response.selected_options.contains(option) { "x" } else { " " };
+            out.push_str(&format!("- [{}] {}\n", mark, option));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## Response\n\n");
+    if response.cancelled {
+        out.push_str(if response.timed_out { "_Timed out._\n\n" } else { "_Cancelled._\n\n" });
+    }
+    if response.auto_selected {
+        out.push_str("_Auto-selected (no response)._\n\n");
+    }
+    if !response.selected_options.is_empty() {
+        out.push_str(&format!("**Selected:** {}\n\n", response.selected_options.join(", ")));
+    }
+    if let Some(input) = response.user_input.as_deref().filter(|s| !s.is_empty()) {
+        out.push_str(input.trim_end());
+        out.push_str("\n\n");
+    }
+    if !response.file_references.is_empty() {
+        out.push_str("**Files:**\n\n");
+        for file in &response.file_references {
+            out.push_str(&format!("- `{}`\n", file.path));
+        }
+        out.push('\n');
+    }
+    for (i, image) in response.images.iter().enumerate() {
+        out.push_str(&format!("![Image {}](data:{};base64,{})\n\n", i + 1, image.mime_type, image.data));
+    }
+
+    out
+}
+
+/// 渲染自包含 HTML 报告
+pub fn render_html(record: &SessionRecord) -> String {
+    let request = &record.request;
+    let response = &record.response;
+    let mut body = String::new();
+
+    body.push_str(&format!("<h1>Feedback Session {}</h1>\n", escape_html(&record.id)));
+    body.push_str(&format!(
+        "<p class=\"meta\">Requested at {}<br>Responded at {}</p>\n",
+        escape_html(&request.created_at),
+        escape_html(&record.responded_at)
+    ));
+
+    body.push_str("<h2>Request</h2>\n");
+    if let Some(message) = request.message.as_deref() {
+        body.push_str(&format!("<pre>{}</pre>\n", escape_html(message.trim_end())));
+    }
+    if let Some(full) = request.full_response.as_deref().filter(|s| !s.is_empty()) {
+        body.push_str(&format!(
+            "<details><summary>Full response</summary><pre>{}</pre></details>\n",
+            escape_html(full.trim_end())
+        ));
+    }
+    if let Some(options) = request.predefined_options.as_ref().filter(|o| !o.is_empty()) {
+        body.push_str("<ul class=\"options\">\n");
+        for option in options {
+            let class = if response.selected_options.contains(option) { " class=\"selected\"" } else { "" };
+            body.push_str(&format!("<li{}>{}</li>\n", class, escape_html(option)));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    body.push_str("<h2>Response</h2>\n");
+    if response.cancelled {
+        body.push_str(if response.timed_out { "<p><em>Timed out.</em></p>\n" } else { "<p><em>Cancelled.</em></p>\n" });
+    }
+    if response.auto_selected {
+        body.push_str("<p><em>Auto-selected (no response).</em></p>\n");
+    }
+    if !response.selected_options.is_empty() {
+        body.push_str(&format!(
+            "<p><strong>Selected:</strong> {}</p>\n",
+            escape_html(&response.selected_options.join(", "))
+        ));
+    }
+    if let Some(input) = response.user_input.as_deref().filter(|s| !s.is_empty()) {
+        body.push_str(&format!("<pre>{}</pre>\n", escape_html(input.trim_end())));
+    }
+    if !response.file_references.is_empty() {
+        body.push_str("<ul class=\"files\">\n");
+        for file in &response.file_references {
+            body.push_str(&format!("<li><code>{}</code></li>\n", escape_html(&file.path)));
+        }
+        body.push_str("</ul>\n");
+    }
+    for (i, image) in response.images.iter().enumerate() {
+        body.push_str(&format!(
+            "<img alt=\"Image {}\" src=\"data:{};base64,{}\">\n",
+            i + 1,
+            escape_html(&image.mime_type),
+            escape_html(&image.data)
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Feedback Session {}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&record.id),
+        REPORT_CSS,
+        body
+    )
+}
+
+const REPORT_CSS: &str = "body { font-family: -apple-system, 'PingFang SC', 'Microsoft YaHei', sans-serif; max-width: 880px; margin: 2em auto; padding: 0 1em; color: #222; }
+pre { white-space: pre-wrap; background: #f6f8fa; padding: 1em; border-radius: 6px; }
+.meta { color: #666; }
+.options .selected { font-weight: bold; color: #0969da; }
+img { max-width: 100%; border: 1px solid #ddd; margin: 0.5em 0; }
+";
+
+/// HTML 转义
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::{ImageData, PopupRequest, PopupResponse};
+
+    fn sample_record() -> SessionRecord {
+        let request = PopupRequest::new(
+            Some("Please review <b>layout</b>".to_string()),
+            None,
+            Some(vec!["Approve".to_string(), "Reject".to_string()]),
+        );
+        let mut response = PopupResponse::cancelled(&request.id);
+        response.cancelled = false;
+        response.selected_options = vec!["Approve".to_string()];
+        response.user_input = Some("看起来不错".to_string());
+        response.images = vec![ImageData {
+            data: "iVBORw0KGgo=".to_string(),
+            mime_type: "image/png".to_string(),
+            attachment_id: None,
+        }];
+        SessionRecord::new(&request, &response)
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let report = render_markdown(&sample_record());
+
+        assert!(report.contains("- [x] Approve"));
+        assert!(report.contains("- [ ] Reject"));
+        assert!(report.contains("看起来不错"));
+        assert!(report.contains("](data:image/png;base64,iVBORw0KGgo=)"));
+    }
+
+    #[test]
+    fn test_html_report_escapes_and_embeds() {
+        let report = render_html(&sample_record());
+
+        assert!(report.contains("Please review &lt;b&gt;layout&lt;/b&gt;"));
+        assert!(!report.contains("<b>layout</b>"));
+        assert!(report.contains("<li class=\"selected\">Approve</li>"));
+        assert!(report.contains("src=\"data:image/png;base64,iVBORw0KGgo=\""));
+    }
+}
@@ -0,0 +1,198 @@
+//! 回答质量检查
+//!
+//! 提交 MCP 回答前在本地检查：Agent 的问题分成多个部分但回答为空、
+//! Agent 明确提出的问题在回答中没有回应、回答里残留 TODO 之类的待办标记。
+//! 只返回警告，由前端提示用户，用户可以忽略后继续提交。
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// 警告类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LintKind {
+    /// 多部分问题的回答为空
+    EmptyAnswer,
+    /// 有问题没有回应
+    UnansweredQuestions,
+    /// 回答中残留待办标记
+    TodoMarker,
+}
+
+/// 一条警告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintWarning {
+    pub kind: LintKind,
+    pub message: String,
+    /// 相关的问题或标记
+    pub details: Vec<String>,
+}
+
+fn question_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // 句子以问号结尾，不跨越句号、感叹号和换行
+    RE.get_or_init(|| Regex::new(r"[^.!?。！？\n]*[?？]").unwrap())
+}
+
+fn list_item_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(?:[-*+]|\d+[.)、]|[（(]\d+[)）])\s+").unwrap())
+}
+
+fn answer_number_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?m)^\s*(?:[Qq]|问题)?\s*(\d+)\s*[.)、:：]").unwrap())
+}
+
+fn todo_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(?:TODO|FIXME|TBD|XXX)\b|待定|待补充").unwrap())
+}
+
+/// 提取消息中明确的问题（跳过代码块和引用行）
+pub fn extract_questions(message: &str) -> Vec<String> {
+    let mut questions = Vec::new();
+    let mut in_code = false;
+    for line in message.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || trimmed.starts_with('>') {
+            continue;
+        }
+        let line = list_item_regex().replace(line, "");
+        for m in question_regex().find_iter(&line) {
+            let question = m.as_str().trim().trim_start_matches(['*', '_', ' ']).trim();
+            // 过滤掉“?”之类没有内容的片段
+            if question.chars().filter(|c| c.is_alphanumeric()).count() >= 2 {
+                questions.push(question.to_string());
+            }
+        }
+    }
+    questions
+}
+
+/// 问题的关键词：长度至少 4 的英文单词，以及中日韩文字的二元组
+fn keywords(text: &str) -> Vec<String> {
+    const STOP_WORDS: &[&str] = &[
+        "should", "would", "could", "which", "what", "when", "where", "there", "these", "those", "that", "this",
+        "with", "from", "have", "your", "does", "want", "like", "into", "about", "they", "them", "will",
+    ];
+    let lower = text.to_lowercase();
+    let mut words: Vec<String> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 4 && !STOP_WORDS.contains(w))
+        .map(str::to_string)
+        .collect();
+    let cjk: Vec<char> = lower.chars().filter(|c| crate::llm::is_cjk(*c) && c.is_alphanumeric()).collect();
+    words.extend(cjk.windows(2).map(|pair| pair.iter().collect::<String>()));
+    words
+}
+
+/// 检查回答
+pub fn lint(message: &str, answer: &str, selected_options: &[String]) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let questions = extract_questions(message);
+    let answer = answer.trim();
+
+    let list_items = message.lines().filter(|line| list_item_regex().is_match(line)).count();
+    let parts = questions.len().max(if questions.is_empty() { 0 } else { list_items });
+    if answer.is_empty() && selected_options.is_empty() && parts > 1 {
+        warnings.push(LintWarning {
+            kind: LintKind::EmptyAnswer,
+            message: format!("问题包含 {} 个部分，回答为空", parts),
+            details: questions.clone(),
+        });
+    }
+
+    // 只有一个问题时任何回答都算回应；按行逐条回答时视为都已回应
+    let answer_lines = answer.lines().filter(|line| !line.trim().is_empty()).count();
+    if !answer.is_empty() && questions.len() > 1 && answer_lines < questions.len() {
+        let answered_numbers: Vec<usize> = answer_number_regex()
+            .captures_iter(answer)
+            .filter_map(|c| c[1].parse().ok())
+            .collect();
+        let answer_lower = format!("{} {}", answer, selected_options.join(" ")).to_lowercase();
+        let unanswered: Vec<String> = questions
+            .iter()
+            .enumerate()
+            .filter(|(index, question)| {
+                !answered_numbers.contains(&(index + 1))
+                    && !keywords(question).iter().any(|keyword| answer_lower.contains(keyword.as_str()))
+            })
+            .map(|(_, question)| question.clone())
+            .collect();
+        // 回答只覆盖了一个问题时，无法判断它回应的是哪个，不提示
+        if !unanswered.is_empty() && unanswered.len() < questions.len() {
+            warnings.push(LintWarning {
+                kind: LintKind::UnansweredQuestions,
+                message: format!("{} 个问题可能没有回应", unanswered.len()),
+                details: unanswered,
+            });
+        }
+    }
+
+    let mut markers: Vec<String> = todo_regex().find_iter(answer).map(|m| m.as_str().to_string()).collect();
+    markers.dedup();
+    if !markers.is_empty() {
+        warnings.push(LintWarning {
+            kind: LintKind::TodoMarker,
+            message: format!("回答中有未完成的标记：{}", markers.join("、")),
+            details: markers,
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "I finished the refactor.\n\
+                           1. Should I keep the legacy parser?\n\
+                           2. Which database migration should run first?\n\
+                           ```\nfn main() { let x = a?; }\n```\n\
+                           > Is this quoted?";
+
+    #[test]
+    fn test_extract_questions() {
+        assert_eq!(
+            extract_questions(MESSAGE),
+            vec!["Should I keep the legacy parser?", "Which database migration should run first?"]
+        );
+        assert_eq!(extract_questions("要保留旧接口吗？还是直接删除？"), vec!["要保留旧接口吗？", "还是直接删除？"]);
+        assert!(extract_questions("Done. No questions here.").is_empty());
+    }
+
+    #[test]
+    fn test_lint_empty_and_unanswered() {
+        let warnings = lint(MESSAGE, "", &[]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, LintKind::EmptyAnswer);
+        // 选了选项不算空回答
+        assert!(lint(MESSAGE, "", &["Continue".to_string()]).is_empty());
+
+        let warnings = lint(MESSAGE, "Keep the parser for now", &[]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, LintKind::UnansweredQuestions);
+        assert_eq!(warnings[0].details, vec!["Which database migration should run first?"]);
+
+        // 编号回应和逐行回答
+        assert!(lint(MESSAGE, "1. yes 2) users first", &[]).is_empty());
+        assert!(lint(MESSAGE, "yes\nusers first", &[]).is_empty());
+        // 单个问题任何回答都算回应
+        assert!(lint("Ship it?", "ok", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_lint_todo_markers() {
+        let warnings = lint("Ship it?", "Yes, but TODO: update the changelog; docs 待补充", &[]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].details, vec!["TODO", "待补充"]);
+        assert!(lint("Ship it?", "todos are done", &[]).is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! 工具结果的语言
+//!
+//! 弹窗界面是中文，但读取工具结果的 Agent 可能更习惯英文。反馈结果中的标签
+//! （“Selected Options”“User Feedback”等）和错误信息按 `language` 参数或配置的默认语言输出。
+
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+use crate::popup::PopupStrings;
+
+/// 工具结果使用的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema, ts_rs::TS)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultLanguage {
+    #[default]
+    En,
+    Zh,
+}
+
+/// 结果文本模板，`{}` 按顺序替换为参数
+#[derive(Debug)]
+pub struct ResultText {
+    pub selected_options: &'static str,
+    pub selected_option_ids: &'static str,
+    pub user_feedback: &'static str,
+    pub attached_images: &'static str,
+    pub attached_files: &'static str,
+    pub change_requests: &'static str,
+    pub auto_selected: &'static str,
+    pub no_feedback: &'static str,
+    pub user_cancelled: &'static str,
+    pub timed_out: &'static str,
+    pub client_cancelled: &'static str,
+    pub client_disconnected: &'static str,
+    pub superseded: &'static str,
+    pub user_unavailable: &'static str,
+    pub escalated_via_webhook: &'static str,
+    pub in_meeting_until: &'static str,
+    pub outside_working_hours_until: &'static str,
+    pub busy: &'static str,
+    pub workspace_auto_reply: &'static str,
+    pub previous_answer_applied: &'static str,
+    pub revisions_answered: &'static str,
+    pub revisions_updated: &'static str,
+    pub sla_met: &'static str,
+    pub sla_missed: &'static str,
+    pub user_notes: &'static str,
+    pub question: &'static str,
+    pub invalid_attachment: &'static str,
+    pub unknown_options_pack: &'static str,
+    pub feedback_failed: &'static str,
+    pub empty_param: &'static str,
+    pub invalid_mode: &'static str,
+    pub custom_prompt_required: &'static str,
+    pub config_load_failed: &'static str,
+    pub sampling_unsupported: &'static str,
+    pub optimize_failed: &'static str,
+    pub rate_limited: &'static str,
+    pub user_overwhelmed: &'static str,
+    pub gui_busy: &'static str,
+    pub request_cancelled: &'static str,
+    pub confirm_failed: &'static str,
+    pub file_picker_failed: &'static str,
+    pub form_failed: &'static str,
+    pub invalid_form_response: &'static str,
+    pub form_field_name_empty: &'static str,
+    pub form_field_duplicate: &'static str,
+    pub form_select_options_required: &'static str,
+    pub serialize_failed: &'static str,
+}
+
+const EN: ResultText = ResultText {
+    selected_options: "**Selected Options:** {}",
+    selected_option_ids: "**Selected Option IDs:** {}",
+    user_feedback: "**User Feedback:**\n{}",
+    attached_images: "**Attached Images:** {} image(s)",
+    attached_files: "**Attached Files:**\n{}",
+    change_requests: "**Change Requests:** {} item(s), apply them in order\n```json\n{}\n```",
+    auto_selected: "**Auto-selected:** user did not respond within {} seconds, the default option was submitted automatically",
+    no_feedback: "No feedback provided by user.",
+    user_cancelled: "[User cancelled or provided no feedback]",
+    timed_out: "[Timed out: user did not respond within {} seconds]",
+    client_cancelled: "[Request cancelled by client]",
+    client_disconnected: "[Client disconnected]",
+    superseded: "[Superseded: the request was updated by a later call, which receives the user's answer]",
+    user_unavailable: "[User unavailable: {}; the question was not shown. Ask again later or continue without an answer]",
+    escalated_via_webhook: "The request was forwarded to the user's escalation webhook.",
+    in_meeting_until: "in a meeting until {}",
+    outside_working_hours_until: "outside working hours until {}",
+    busy: "busy",
+    workspace_auto_reply: "[Auto-replied by workspace settings; the user was not prompted]",
+    previous_answer_applied: "[Auto-applied the user's previous answer to the same question from {}; the user was not prompted]",
+    revisions_answered: "**Revisions:** the question was updated while the popup was open; the user answered revision {} of {}",
+    revisions_updated: "**Revisions:** the question was updated {} time(s) while the popup was open",
+    sla_met: "**SLA:** met (answered {}s before the deadline)",
+    sla_missed: "**SLA:** missed (answered {}s after the deadline)",
+    user_notes: "**Notes from the user:**\n{}",
+    question: "**Question:**\n{}",
+    invalid_attachment: "Error: Invalid attachment #{} - {}",
+    unknown_options_pack: "Error: Unknown options_pack '{}'. Available packs: {}",
+    feedback_failed: "Error: Failed to get user feedback - {}",
+    empty_param: "Error: '{}' must not be empty",
+    invalid_mode: "Error: 'mode' must be 'optimize' or 'enhance'",
+    custom_prompt_required: "Error: 'custom_prompt' is required when mode is 'enhance'",
+    config_load_failed: "Error: Failed to load config - {}",
+    sampling_unsupported: "Error: the client does not support MCP sampling, cannot optimize with the client's model",
+    optimize_failed: "Error: Optimization failed - {}",
+    rate_limited: "Error: Rate limited - too many requests, retry after {} seconds",
+    user_overwhelmed: "Error: User overwhelmed - {} requests in a row went unanswered. Further requests are rejected until the user resets the circuit breaker in the app settings; continue without asking or stop",
+    gui_busy: "Error: Busy - {} feedback popups are already open, retry after {} seconds",
+    request_cancelled: "Error: Request cancelled by client",
+    confirm_failed: "Error: Failed to get user confirmation - {}",
+    file_picker_failed: "Error: Failed to open file picker - {}",
+    form_failed: "Error: Failed to open form - {}",
+    invalid_form_response: "Error: Invalid form response - {}",
+    form_field_name_empty: "Error: form field 'name' must not be empty",
+    form_field_duplicate: "Error: duplicate form field name: {}",
+    form_select_options_required: "Error: select field {} requires 'options'",
+    serialize_failed: "Error: Failed to serialize result - {}",
+};
+
+const ZH: ResultText = ResultText {
+    selected_options: "**已选选项：** {}",
+    selected_option_ids: "**已选选项 ID：** {}",
+    user_feedback: "**用户反馈：**\n{}",
+    attached_images: "**附加图片：** {} 张",
+    attached_files: "**附加文件：**\n{}",
+    change_requests: "**修改意见：** {} 条，请逐条处理\n```json\n{}\n```",
+    auto_selected: "**自动选择：** 用户 {} 秒内未响应，已自动提交默认选项",
+    no_feedback: "用户未提供反馈。",
+    user_cancelled: "[用户取消或未提供反馈]",
+    timed_out: "[超时：用户 {} 秒内未响应]",
+    client_cancelled: "[客户端已取消请求]",
+    client_disconnected: "[客户端已断开]",
+    superseded: "[已被更新：请求被之后的调用更新，用户的回答交给该调用]",
+    user_unavailable: "[用户暂时不方便：{}，问题未显示。请稍后再问，或不等待回答继续工作]",
+    escalated_via_webhook: "请求已通过 Webhook 转发给用户。",
+    in_meeting_until: "正在开会，预计 {} 结束",
+    outside_working_hours_until: "不在工作时间，{} 开始工作",
+    busy: "忙碌",
+    workspace_auto_reply: "[按工作区设置自动回复，未询问用户]",
+    previous_answer_applied: "[自动使用了用户在 {} 对相同问题的回答，未询问用户]",
+    revisions_answered: "**修订：** 弹窗打开期间问题被更新过，用户回答的是第 {} 版（共 {} 版）",
+    revisions_updated: "**修订：** 弹窗打开期间问题被更新了 {} 次",
+    sla_met: "**SLA：** 已达成（在截止时间前 {} 秒回答）",
+    sla_missed: "**SLA：** 未达成（在截止时间后 {} 秒回答）",
+    user_notes: "**用户留言：**\n{}",
+    question: "**问题：**\n{}",
+    invalid_attachment: "Error: 第 {} 个附件无效 - {}",
+    unknown_options_pack: "Error: 未知的选项组 '{}'，可用的选项组：{}",
+    feedback_failed: "Error: 获取用户反馈失败 - {}",
+    empty_param: "Error: '{}' 参数不能为空",
+    invalid_mode: "Error: 'mode' 参数必须是 'optimize' 或 'enhance'",
+    custom_prompt_required: "Error: 当 mode 为 'enhance' 时，必须提供 'custom_prompt' 参数",
+    config_load_failed: "Error: 加载配置失败: {}",
+    sampling_unsupported: "Error: 客户端不支持 MCP sampling，无法使用客户端的模型优化",
+    optimize_failed: "Error: 优化失败: {}",
+    rate_limited: "Error: 调用过于频繁，请在 {} 秒后重试",
+    user_overwhelmed: "Error: 用户无暇回应 - 连续 {} 个请求未得到回答，在用户于应用设置中重置前将拒绝新的请求；请不再询问继续工作，或停止",
+    gui_busy: "Error: 忙 - 已有 {} 个反馈弹窗打开，请在 {} 秒后重试",
+    request_cancelled: "Error: 请求已被客户端取消",
+    confirm_failed: "Error: 获取用户确认失败 - {}",
+    file_picker_failed: "Error: 打开文件选择器失败 - {}",
+    form_failed: "Error: 打开表单失败 - {}",
+    invalid_form_response: "Error: 表单结果无效 - {}",
+    form_field_name_empty: "Error: 字段 name 不能为空",
+    form_field_duplicate: "Error: 字段 name 重复: {}",
+    form_select_options_required: "Error: select 字段 {} 必须提供 options",
+    serialize_failed: "Error: 序列化结果失败 - {}",
+};
+
+impl ResultLanguage {
+    /// 该语言的结果文本
+    pub fn text(self) -> &'static ResultText {
+        match self {
+            ResultLanguage::En => &EN,
+            ResultLanguage::Zh => &ZH,
+        }
+    }
+
+    /// 按 BCP 47 语言标签选择语言（如 `en-US`、`zh_CN`），不支持的语言返回 None
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let primary = locale.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(ResultLanguage::En),
+            "zh" => Some(ResultLanguage::Zh),
+            _ => None,
+        }
+    }
+
+    /// 该语言的弹窗文本
+    pub fn popup_strings(self) -> PopupStrings {
+        let (input_placeholder, submit, cancel, previous_answer, thread_history) = match self {
+            ResultLanguage::En => (
+                "Type your feedback... (Enter to send, Shift+Enter for a new line)",
+                "Submit",
+                "Cancel",
+                "Previous answer",
+                "Earlier in this thread",
+            ),
+            ResultLanguage::Zh => (
+                "输入您的反馈... (Enter 发送, Shift+Enter 换行)",
+                "提交",
+                "取消",
+                "上次回答",
+                "之前的问答",
+            ),
+        };
+        PopupStrings {
+            input_placeholder: input_placeholder.to_string(),
+            submit: submit.to_string(),
+            cancel: cancel.to_string(),
+            previous_answer: previous_answer.to_string(),
+            thread_history: thread_history.to_string(),
+        }
+    }
+}
+
+/// 按顺序用参数替换模板中的 `{}`
+pub fn fill(template: &str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{}") {
+        result.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => result.push_str(&arg.to_string()),
+            None => result.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_templates() {
+        let text = ResultLanguage::En.text();
+        assert_eq!(fill(text.selected_options, &[&"A, B"]), "**Selected Options:** A, B");
+        assert_eq!(
+            fill(ResultLanguage::Zh.text().revisions_answered, &[&2, &3]),
+            "**修订：** 弹窗打开期间问题被更新过，用户回答的是第 2 版（共 3 版）"
+        );
+        // 参数中的 `{}` 不再替换
+        assert_eq!(fill("{} and {}", &[&"{}", &"x"]), "{} and x");
+    }
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(ResultLanguage::from_locale("en-US"), Some(ResultLanguage::En));
+        assert_eq!(ResultLanguage::from_locale("zh_Hans_CN"), Some(ResultLanguage::Zh));
+        assert_eq!(ResultLanguage::from_locale("ZH"), Some(ResultLanguage::Zh));
+        assert_eq!(ResultLanguage::from_locale("fr-FR"), None);
+        assert_eq!(ResultLanguage::from_locale(""), None);
+    }
+
+    #[test]
+    fn test_language_serde() {
+        assert_eq!(serde_json::from_str::<ResultLanguage>("\"zh\"").unwrap(), ResultLanguage::Zh);
+        assert_eq!(serde_json::to_string(&ResultLanguage::En).unwrap(), "\"en\"");
+    }
+}
@@ -0,0 +1,123 @@
+//! 反馈结果模板
+//!
+//! `whale_interactive_feedback` 默认输出固定格式的 Markdown（“**Selected Options:** ...”）。
+//! 配置 `feedbackResultTemplate` 后改用 Handlebars 模板渲染，团队可以输出自家 Agent
+//! 最容易解析的格式（如 XML 标签、YAML）。模板渲染失败时回退到默认格式。
+
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::mcp_server::ImageRef;
+use crate::popup::{ChangeRequest, ChangeSeverity, Priority};
+use crate::result_language::ResultLanguage;
+
+/// 模板中的附加文件
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateFile {
+    pub path: String,
+    pub is_directory: bool,
+    /// 内嵌的文本内容（文件过大、二进制或为目录时为空）
+    pub content: Option<String>,
+    /// 代码块语言（文件扩展名）
+    pub language: String,
+}
+
+/// 请求的元信息
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateMetadata {
+    pub request_id: String,
+    pub project_name: Option<String>,
+    pub priority: Priority,
+    pub responded_at: String,
+    pub language: ResultLanguage,
+    /// 用户作答时看到的修订号
+    pub revision: Option<u32>,
+    /// 用户作答时按下的选项快捷键
+    pub hotkey: Option<String>,
+}
+
+/// 模板可用的字段
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateData {
+    pub selected_options: Vec<String>,
+    pub selected_option_ids: Vec<String>,
+    pub feedback: Option<String>,
+    pub images: Vec<ImageRef>,
+    pub image_count: usize,
+    pub files: Vec<TemplateFile>,
+    /// 审查请求中的修改意见
+    pub change_requests: Vec<ChangeRequest>,
+    /// 默认选项是否由倒计时自动提交
+    pub auto_selected: bool,
+    /// 截止时间的达成情况（未设置截止时间时为空）
+    pub sla: Option<String>,
+    pub metadata: TemplateMetadata,
+}
+
+/// 渲染结果模板
+///
+/// 输出的是 Markdown/纯文本，不做 HTML 转义
+pub fn render(template: &str, data: &TemplateData) -> Result<String, String> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .render_template(template, data)
+        .map(|text| text.trim().to_string())
+        .map_err(|e| format!("结果模板渲染失败: {}", e))
+}
+
+/// 示例数据（用于在设置中预览模板）
+pub fn sample_data() -> TemplateData {
+    TemplateData {
+        selected_options: vec!["Approve".to_string()],
+        selected_option_ids: vec!["approve".to_string()],
+        feedback: Some("Looks good, but rename the helper.".to_string()),
+        images: vec![ImageRef { index: 0, mime_type: "image/png".to_string(), size_bytes: 20480 }],
+        image_count: 1,
+        files: vec![TemplateFile {
+            path: "/project/src/main.rs".to_string(),
+            is_directory: false,
+            content: Some("fn main() {}".to_string()),
+            language: "rs".to_string(),
+        }],
+        change_requests: vec![ChangeRequest {
+            file: "src/main.rs".to_string(),
+            location: Some("12-18".to_string()),
+            comment: "Extract the retry loop into a helper".to_string(),
+            severity: ChangeSeverity::Major,
+        }],
+        auto_selected: false,
+        sla: None,
+        metadata: TemplateMetadata {
+            request_id: "00000000-0000-0000-0000-000000000000".to_string(),
+            project_name: Some("project".to_string()),
+            priority: Priority::Normal,
+            responded_at: chrono::Utc::now().to_rfc3339(),
+            language: ResultLanguage::En,
+            revision: None,
+            hotkey: Some("1".to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let template = "<options>{{#each selected_option_ids}}{{this}};{{/each}}</options>\n\
+                        {{#if feedback}}<feedback>{{feedback}}</feedback>{{/if}}\n\
+                        {{#each files}}{{path}}{{#if content}}:{{language}}{{/if}}{{/each}} ({{image_count}} images)";
+        let text = render(template, &sample_data()).unwrap();
+        assert_eq!(
+            text,
+            "<options>approve;</options>\n<feedback>Looks good, but rename the helper.</feedback>\n/project/src/main.rs:rs (1 images)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        assert!(render("{{#each selected_options}}", &sample_data()).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! 数据保留与清除模块
+//!
+//! 按配置的保留策略（天数 / 条数 / 占用空间）自动清理历史记录、导出的报告、
+//! 截图和归档等本地数据，并提供一键清除全部用户数据的能力。
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+use crate::config;
+use crate::popup::{MCP_REQUEST_FILE_PREFIX, MCP_RESPONSE_FILE_PREFIX};
+use crate::storage::{encryption, Storage};
+use crate::types::AppConfig;
+
+/// 应用数据目录中由保留策略管理的文件目录
+const MANAGED_DATA_DIRS: &[&str] = &["reports", "screenshots", "logs", "drafts"];
+
+/// 清除确认令牌有效期
+const PURGE_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// 数据保留配置（各项为 0 表示不限制）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionConfig {
+    /// 保留天数
+    #[serde(default)]
+    pub max_age_days: u32,
+    /// 每类数据最多保留的条数
+    #[serde(default)]
+    pub max_entries: u32,
+    /// 每类数据最多占用的空间（MB）
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub max_disk_mb: u64,
+}
+
+impl RetentionConfig {
+    /// 是否配置了任何限制
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age_days == 0 && self.max_entries == 0 && self.max_disk_mb == 0
+    }
+
+    fn max_bytes(&self) -> u64 {
+        self.max_disk_mb * 1024 * 1024
+    }
+}
+
+/// 清理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub sessions_removed: usize,
+    pub files_removed: usize,
+    /// 删除的文件占用的空间（字节，不含数据库中的历史记录）
+    #[serde(default)]
+    pub bytes_freed: u64,
+}
+
+/// 按配置清理历史记录、报告、截图、日志、草稿和归档
+pub fn apply_retention(app_config: &AppConfig) -> RetentionReport {
+    let retention = &app_config.retention;
+    let mut report = RetentionReport::default();
+    if retention.is_unlimited() {
+        return report;
+    }
+
+    match Storage::open_default() {
+        Ok(storage) => {
+            match storage.prune_sessions(retention.max_age_days, retention.max_entries, retention.max_bytes()) {
+                Ok(removed) => report.sessions_removed = removed,
+                Err(e) => log::warn!("[retention] 清理历史记录失败: {}", e),
+            }
+        }
+        Err(e) => log::warn!("[retention] 打开本地存储失败: {}", e),
+    }
+
+    let mut dirs: Vec<PathBuf> = match config::get_default_data_dir() {
+        Ok(data_dir) => MANAGED_DATA_DIRS.iter().map(|d| data_dir.join(d)).collect(),
+        Err(_) => Vec::new(),
+    };
+    if let Some(archive_dir) = app_config.archive.archive_dir() {
+        dirs.push(archive_dir);
+    }
+    for dir in dirs.iter().filter(|d| d.exists()) {
+        let (files, bytes) = prune_directory(dir, retention);
+        report.files_removed += files;
+        report.bytes_freed += bytes;
+    }
+
+    if report.sessions_removed + report.files_removed > 0 {
+        log::info!(
+            "[retention] 已清理 {} 条历史记录，{} 个文件",
+            report.sessions_removed,
+            report.files_removed
+        );
+    }
+    report
+}
+
+/// 按保留策略清理目录中的文件，返回删除的文件数和字节数
+fn prune_directory(dir: &Path, retention: &RetentionConfig) -> (usize, u64) {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files);
+    // 最新的文件在前
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let cutoff = (retention.max_age_days > 0)
+        .then(|| SystemTime::now() - Duration::from_secs(retention.max_age_days as u64 * 24 * 60 * 60));
+    let max_bytes = retention.max_bytes();
+
+    let mut removed = 0;
+    let mut freed = 0;
+    let mut total_bytes = 0;
+    for (index, (path, modified, size)) in files.iter().enumerate() {
+        total_bytes += size;
+        let expired = cutoff.is_some_and(|cutoff| *modified < cutoff)
+            || (retention.max_entries > 0 && index >= retention.max_entries as usize)
+            || (max_bytes > 0 && total_bytes > max_bytes);
+        if expired {
+            match std::fs::remove_file(path) {
+                Ok(()) => {
+                    removed += 1;
+                    freed += size;
+                }
+                Err(e) => log::warn!("[retention] 删除文件失败 {:?}: {}", path, e),
+            }
+        }
+    }
+
+    remove_empty_dirs(dir);
+    (removed, freed)
+}
+
+/// 递归收集文件（路径、修改时间、大小）
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, SystemTime, u64)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files);
+        } else {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((entry.path(), modified, metadata.len()));
+        }
+    }
+}
+
+/// 删除空的子目录（保留根目录）
+fn remove_empty_dirs(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            remove_empty_dirs(&path);
+            // 非空目录删除失败，忽略
+            let _ = std::fs::remove_dir(&path);
+        }
+    }
+}
+
+/// 清除全部用户数据的确认令牌（两步确认，防止误操作）
+#[derive(Default)]
+pub struct PurgeGuard {
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl PurgeGuard {
+    /// 生成新的确认令牌（旧令牌失效）
+    pub fn issue(&self) -> String {
+        let token = Uuid::new_v4().to_string();
+        *self.token.lock().unwrap() = Some((token.clone(), Instant::now()));
+        token
+    }
+
+    /// 校验并消费令牌
+    pub fn consume(&self, token: &str) -> bool {
+        match self.token.lock().unwrap().take() {
+            Some((expected, issued)) => expected == token && issued.elapsed() <= PURGE_TOKEN_TTL,
+            None => false,
+        }
+    }
+}
+
+/// 清除全部用户数据：应用数据目录（配置、历史、报告等）、归档目录、钥匙串中的数据库密钥和临时请求文件
+///
+/// 返回未能删除的路径
+pub fn purge_all_user_data(app_config: &AppConfig) -> Vec<String> {
+    let mut failed = Vec::new();
+    let mut remove_dir = |path: &Path| {
+        if path.exists() {
+            if let Err(e) = std::fs::remove_dir_all(path) {
+                log::error!("[retention] 删除目录失败 {:?}: {}", path, e);
+                failed.push(path.to_string_lossy().to_string());
+            }
+        }
+    };
+
+    if let Some(archive_dir) = app_config.archive.archive_dir() {
+        remove_dir(&archive_dir);
+    }
+    if let Ok(data_dir) = config::get_default_data_dir() {
+        remove_dir(&data_dir);
+    }
+    encryption::delete_keychain_key();
+
+    if let Ok(entries) = std::fs::read_dir(crate::popup::ipc_dir()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(MCP_REQUEST_FILE_PREFIX) || name.starts_with(MCP_RESPONSE_FILE_PREFIX) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    log::warn!("[retention] 已清除全部用户数据");
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_directory_by_entries_and_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("2024-01-01");
+        std::fs::create_dir_all(&nested).unwrap();
+        for i in 0..4 {
+            std::fs::write(nested.join(format!("{}.md", i)), vec![b'x'; 1024]).unwrap();
+        }
+
+        let by_entries = RetentionConfig { max_entries: 3, ..Default::default() };
+        assert_eq!(prune_directory(dir.path(), &by_entries), (1, 1024));
+
+        let by_size = RetentionConfig { max_disk_mb: 1, ..Default::default() };
+        assert_eq!(prune_directory(dir.path(), &by_size), (0, 0));
+    }
+
+    #[test]
+    fn test_prune_removes_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("old");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("a.png"), b"png").unwrap();
+
+        let keep_none = RetentionConfig { max_entries: 1, ..Default::default() };
+        std::fs::write(dir.path().join("b.png"), b"png").unwrap();
+        prune_directory(dir.path(), &keep_none);
+
+        let mut remaining = Vec::new();
+        collect_files(dir.path(), &mut remaining);
+        assert_eq!(remaining.len(), 1);
+        // 文件被删除后所在的子目录也应被删除
+        assert!(!nested.exists() || nested.join("a.png").exists());
+    }
+
+    #[test]
+    fn test_purge_token_is_single_use() {
+        let guard = PurgeGuard::default();
+        assert!(!guard.consume("anything"));
+
+        let token = guard.issue();
+        assert!(!guard.consume("wrong"));
+        // 错误的令牌也会使已签发的令牌失效
+        assert!(!guard.consume(&token));
+
+        let token = guard.issue();
+        assert!(guard.consume(&token));
+        assert!(!guard.consume(&token));
+    }
+}
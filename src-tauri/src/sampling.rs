@@ -0,0 +1,96 @@
+//! MCP sampling 支持
+//!
+//! `whale_optimize_user_input` 可以通过 `sampling/createMessage` 请求客户端使用宿主 Agent 的模型
+//! 完成文本优化，未配置 LLM API 密钥的用户也能使用该工具。
+//!
+//! 本模块负责 sampling 请求参数的构造和结果文本的提取。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// sampling 请求的最大输出 token 数
+const MAX_TOKENS: u32 = 2048;
+
+/// 文本优化使用 MCP sampling 的时机
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum SamplingMode {
+    /// 只使用本地配置的 API 密钥
+    Off,
+    /// 未配置 API 密钥且客户端支持 sampling 时使用
+    #[default]
+    Fallback,
+    /// 始终使用客户端的模型
+    Always,
+}
+
+impl SamplingMode {
+    /// 本次优化是否使用 sampling
+    ///
+    /// # Arguments
+    /// * `has_api_keys` - 是否配置了 LLM API 密钥
+    /// * `client_supports` - 客户端是否声明支持 sampling
+    pub fn should_sample(self, has_api_keys: bool, client_supports: bool) -> bool {
+        match self {
+            SamplingMode::Off => false,
+            SamplingMode::Fallback => !has_api_keys && client_supports,
+            SamplingMode::Always => true,
+        }
+    }
+
+    /// 是否能够通过 sampling 优化（用于决定是否暴露优化工具）
+    pub fn available(self, client_supports: bool) -> bool {
+        self != SamplingMode::Off && client_supports
+    }
+}
+
+/// `sampling/createMessage` 请求参数
+pub fn request_param(system_prompt: &str, text: &str) -> Value {
+    json!({
+        "messages": [{
+            "role": "user",
+            "content": { "type": "text", "text": text },
+        }],
+        "systemPrompt": system_prompt,
+        "includeContext": "none",
+        "maxTokens": MAX_TOKENS,
+    })
+}
+
+/// 从客户端返回的消息中取出文本（非文本内容返回 None）
+pub fn response_text(message: &Value) -> Option<String> {
+    let content = message.get("content")?;
+    (content.get("type")?.as_str()? == "text")
+        .then(|| content.get("text")?.as_str())
+        .flatten()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_sample() {
+        assert!(!SamplingMode::Off.should_sample(false, true));
+        assert!(SamplingMode::Fallback.should_sample(false, true));
+        assert!(!SamplingMode::Fallback.should_sample(true, true));
+        assert!(!SamplingMode::Fallback.should_sample(false, false));
+        assert!(SamplingMode::Always.should_sample(true, false));
+        assert!(!SamplingMode::Always.available(false));
+    }
+
+    #[test]
+    fn test_request_and_response() {
+        let param = request_param("optimize", "fix teh bug");
+        assert_eq!(param["messages"][0]["content"]["text"], "fix teh bug");
+        assert_eq!(param["systemPrompt"], "optimize");
+
+        let message = json!({ "role": "assistant", "content": { "type": "text", "text": " Fix the bug.\n" } });
+        assert_eq!(response_text(&message).as_deref(), Some("Fix the bug."));
+        let image = json!({ "role": "assistant", "content": { "type": "image", "data": "", "mimeType": "image/png" } });
+        assert_eq!(response_text(&image), None);
+    }
+}
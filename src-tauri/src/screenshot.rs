@@ -4,9 +4,12 @@
 //! 
 //! 提供屏幕捕获和区域截图功能
 
-use crate::image_processor::ImageProcessor;
-use image::{DynamicImage, ImageEncoder, RgbaImage};
-use xcap::Monitor;
+use crate::color_profile::{self, ColorManagementConfig};
+use crate::image_processor::{ImageMetrics, ImageProcessor, PngCompression};
+use crate::types::{PrivacyBlurConfig, ScalePreset, ScreenRegion};
+use image::{DynamicImage, RgbaImage};
+use mouse_position::mouse_position::Mouse;
+use xcap::{Monitor, Window};
 
 /// 截图错误类型
 #[derive(Debug, thiserror::Error)]
@@ -34,13 +37,17 @@ pub struct ScreenshotRegion {
 }
 
 /// 截图结果
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS)]
+#[ts(export)]
 pub struct ScreenshotResult {
     pub data: String,      // Base64 编码的图片数据
     pub mime_type: String,
     pub width: u32,
     pub height: u32,
     pub size: usize,
+    /// 处理度量（原始分辨率、压缩比、耗时等）
+    #[serde(default)]
+    pub metrics: Option<ImageMetrics>,
 }
 
 /// 显示器信息
@@ -55,6 +62,50 @@ pub struct MonitorInfo {
     pub is_primary: bool,
 }
 
+/// 区域选择吸附目标（窗口矩形）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SnapTarget {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+    pub app_name: String,
+}
+
+impl SnapTarget {
+    /// 是否与指定区域相交
+    pub fn intersects(&self, region: &ScreenshotRegion) -> bool {
+        let (ax1, ay1) = (self.x as i64, self.y as i64);
+        let (ax2, ay2) = (ax1 + self.width as i64, ay1 + self.height as i64);
+        let (bx1, by1) = (region.x as i64, region.y as i64);
+        let (bx2, by2) = (bx1 + region.width as i64, by1 + region.height as i64);
+        
+        ax1 < bx2 && bx1 < ax2 && ay1 < by2 && by1 < ay2
+    }
+}
+
+/// 判断点是否位于矩形 (x, y, width, height) 内
+fn contains_point(bounds: (i32, i32, u32, u32), point: (i32, i32)) -> bool {
+    let (x, y, width, height) = bounds;
+    let (px, py) = (point.0 as i64, point.1 as i64);
+    px >= x as i64 && px < x as i64 + width as i64 &&
+    py >= y as i64 && py < y as i64 + height as i64
+}
+
+/// 截图处理选项
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// 缩放预设
+    pub scale: ScalePreset,
+    /// 隐私模糊配置
+    pub privacy: PrivacyBlurConfig,
+    /// 色彩管理配置
+    pub color: ColorManagementConfig,
+    /// PNG 压缩级别
+    pub png: PngCompression,
+}
+
 /// 截图管理器
 pub struct ScreenshotManager;
 
@@ -96,10 +147,54 @@ impl ScreenshotManager {
         Ok(result)
     }
     
+    /// 获取区域选择的吸附目标
+    /// 
+    /// 枚举当前可见的窗口矩形，区域选择时可吸附到窗口边缘。
+    /// 传入 `region` 时只返回与该区域相交的窗口。
+    pub fn get_snap_targets(region: Option<&ScreenshotRegion>) -> Result<Vec<SnapTarget>, ScreenshotError> {
+        let windows = Window::all()
+            .map_err(|e| ScreenshotError::MonitorError(e.to_string()))?;
+        
+        let targets = windows.iter()
+            .filter(|w| !w.is_minimized().unwrap_or(false))
+            .filter_map(|w| {
+                Some(SnapTarget {
+                    x: w.x().ok()?,
+                    y: w.y().ok()?,
+                    width: w.width().ok()?,
+                    height: w.height().ok()?,
+                    title: w.title().unwrap_or_default(),
+                    app_name: w.app_name().unwrap_or_default(),
+                })
+            })
+            .filter(|t| t.width > 0 && t.height > 0)
+            .filter(|t| match region {
+                Some(r) => t.intersects(r),
+                None => true,
+            })
+            .collect();
+        
+        Ok(targets)
+    }
+    
+    /// 获取鼠标光标位置（全局屏幕坐标）
+    /// 
+    /// 平台不支持或获取失败时返回 None
+    pub fn get_cursor_position() -> Option<(i32, i32)> {
+        match Mouse::get_mouse_position() {
+            Mouse::Position { x, y } => Some((x, y)),
+            Mouse::Error => {
+                log::debug!("[screenshot] 获取鼠标位置失败");
+                None
+            }
+        }
+    }
+    
     /// 捕获整个屏幕
     /// 
     /// Requirement 8.4: 实现屏幕捕获
-    pub fn capture_full_screen(monitor_id: Option<u32>) -> Result<ScreenshotResult, ScreenshotError> {
+    /// 未指定显示器时截取鼠标所在的显示器
+    pub fn capture_full_screen(monitor_id: Option<u32>, options: &CaptureOptions) -> Result<ScreenshotResult, ScreenshotError> {
         let monitors = Monitor::all()
             .map_err(|e| ScreenshotError::MonitorError(e.to_string()))?;
         
@@ -108,11 +203,22 @@ impl ScreenshotManager {
             monitors.get(id as usize)
                 .ok_or_else(|| ScreenshotError::MonitorError(format!("Monitor {} not found", id)))?
         } else {
-            // 默认使用主显示器
-            let primary = monitors.iter()
+            // 默认使用鼠标所在的显示器，获取失败时回退到主显示器
+            let under_cursor = Self::get_cursor_position().and_then(|point| {
+                monitors.iter().find(|m| {
+                    let bounds = (
+                        m.x().unwrap_or(0),
+                        m.y().unwrap_or(0),
+                        m.width().unwrap_or(0),
+                        m.height().unwrap_or(0),
+                    );
+                    contains_point(bounds, point)
+                })
+            });
+            let primary = || monitors.iter()
                 .find(|m| m.is_primary().unwrap_or(false));
             
-            primary.or_else(|| monitors.first())
+            under_cursor.or_else(primary).or_else(|| monitors.first())
                 .ok_or_else(|| ScreenshotError::MonitorError("No monitors found".to_string()))?
         };
         
@@ -120,13 +226,73 @@ impl ScreenshotManager {
         let image = monitor.capture_image()
             .map_err(|e| ScreenshotError::CaptureError(e.to_string()))?;
         
-        Self::process_captured_image(image)
+        let origin = (monitor.x().unwrap_or(0), monitor.y().unwrap_or(0));
+        let icc_profile = Self::monitor_profile(monitor, &options.color);
+        Self::process_captured_image(image, origin, icc_profile.as_deref(), options)
     }
     
+    /// 捕获所有显示器
+    ///
+    /// 每个显示器在独立的阻塞任务中并发截图，结果按显示器 ID 排列。
+    /// 任一显示器截图失败时返回错误
+    pub async fn capture_all_screens(options: &CaptureOptions) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
+        let options = options.clone();
+        Self::for_each_monitor(move |id| Self::capture_full_screen(Some(id), &options))
+            .await?
+            .into_iter()
+            .collect()
+    }
+
+    /// 逐个显示器试截图（不做后续处理），用于自检
+    ///
+    /// 返回按显示器 ID 排列的结果，单个显示器失败不影响其他显示器
+    pub async fn probe_all_monitors() -> Result<Vec<Result<(), ScreenshotError>>, ScreenshotError> {
+        Self::for_each_monitor(|id| {
+            let monitors = Monitor::all()
+                .map_err(|e| ScreenshotError::MonitorError(e.to_string()))?;
+            let monitor = monitors.get(id as usize)
+                .ok_or_else(|| ScreenshotError::MonitorError(format!("Monitor {} not found", id)))?;
+            monitor.capture_image()
+                .map(|_| ())
+                .map_err(|e| ScreenshotError::CaptureError(e.to_string()))
+        }).await
+    }
+
+    /// 为每个显示器启动一个阻塞任务并发执行 `task`，等待全部完成
+    async fn for_each_monitor<T, F>(task: F) -> Result<Vec<Result<T, ScreenshotError>>, ScreenshotError>
+    where
+        T: Send + 'static,
+        F: Fn(u32) -> Result<T, ScreenshotError> + Send + Sync + 'static,
+    {
+        let count = tokio::task::spawn_blocking(|| Monitor::all().map(|m| m.len()))
+            .await
+            .map_err(|e| ScreenshotError::MonitorError(e.to_string()))?
+            .map_err(|e| ScreenshotError::MonitorError(e.to_string()))?;
+        if count == 0 {
+            return Err(ScreenshotError::MonitorError("No monitors found".to_string()));
+        }
+
+        let task = std::sync::Arc::new(task);
+        let handles: Vec<_> = (0..count as u32)
+            .map(|id| {
+                let task = task.clone();
+                tokio::task::spawn_blocking(move || (*task)(id))
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(handles.len());
+        for (id, handle) in handles.into_iter().enumerate() {
+            results.push(handle.await.unwrap_or_else(|e| {
+                Err(ScreenshotError::CaptureError(format!("Monitor {} capture task failed: {}", id, e)))
+            }));
+        }
+        Ok(results)
+    }
+
     /// 捕获指定区域
-    /// 
+    ///
     /// Requirement 8.2, 8.3: 矩形选择和实时预览
-    pub fn capture_region(region: ScreenshotRegion) -> Result<ScreenshotResult, ScreenshotError> {
+    pub fn capture_region(region: ScreenshotRegion, options: &CaptureOptions) -> Result<ScreenshotResult, ScreenshotError> {
         // 验证区域
         if region.width == 0 || region.height == 0 {
             return Err(ScreenshotError::InvalidRegion("Width and height must be greater than 0".to_string()));
@@ -163,7 +329,9 @@ impl ScreenshotManager {
         // 裁剪区域
         let cropped = Self::crop_image(&full_image, rel_x, rel_y, region.width, region.height)?;
         
-        Self::process_captured_image(cropped)
+        let origin = (monitor_x + rel_x as i32, monitor_y + rel_y as i32);
+        let icc_profile = Self::monitor_profile(monitor, &options.color);
+        Self::process_captured_image(cropped, origin, icc_profile.as_deref(), options)
     }
     
     /// 裁剪图片
@@ -193,20 +361,138 @@ impl ScreenshotManager {
         Ok(cropped.to_rgba8())
     }
     
+    /// 按缩放预设缩放图片
+    fn apply_scale(image: RgbaImage, scale: ScalePreset) -> RgbaImage {
+        let (width, height) = scale.target_size(image.width(), image.height());
+        if width == image.width() && height == image.height() {
+            return image;
+        }
+        
+        image::imageops::resize(&image, width, height, image::imageops::FilterType::Lanczos3)
+    }
+    
+    /// 收集需要模糊的屏幕区域（配置的固定区域 + 标题匹配的窗口）
+    fn collect_privacy_regions(privacy: &PrivacyBlurConfig) -> Vec<ScreenRegion> {
+        let mut regions = privacy.regions.clone();
+        
+        let keywords: Vec<String> = privacy.window_titles.iter()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if keywords.is_empty() {
+            return regions;
+        }
+        
+        match Window::all() {
+            Ok(windows) => {
+                for window in &windows {
+                    let title = window.title().unwrap_or_default().to_lowercase();
+                    if !keywords.iter().any(|k| title.contains(k.as_str())) {
+                        continue;
+                    }
+                    if let (Ok(x), Ok(y), Ok(width), Ok(height)) =
+                        (window.x(), window.y(), window.width(), window.height())
+                    {
+                        regions.push(ScreenRegion { x, y, width, height });
+                    }
+                }
+            }
+            Err(e) => log::warn!("[privacy_blur] 枚举窗口失败: {}", e),
+        }
+        
+        regions
+    }
+    
+    /// 对图片中的指定区域进行像素化
+    /// 
+    /// `origin` 为图片左上角在全局屏幕坐标中的位置
+    fn pixelate_regions(image: &mut RgbaImage, origin: (i32, i32), regions: &[ScreenRegion], block_size: u32) {
+        let block_size = block_size.max(2);
+        let (img_w, img_h) = (image.width() as i64, image.height() as i64);
+        
+        for region in regions {
+            // 转换为图片坐标并裁剪到图片范围内
+            let x1 = (region.x as i64 - origin.0 as i64).clamp(0, img_w);
+            let y1 = (region.y as i64 - origin.1 as i64).clamp(0, img_h);
+            let x2 = (region.x as i64 + region.width as i64 - origin.0 as i64).clamp(0, img_w);
+            let y2 = (region.y as i64 + region.height as i64 - origin.1 as i64).clamp(0, img_h);
+            if x1 >= x2 || y1 >= y2 {
+                continue;
+            }
+            
+            let (x1, y1, x2, y2) = (x1 as u32, y1 as u32, x2 as u32, y2 as u32);
+            let mut by = y1;
+            while by < y2 {
+                let bh = block_size.min(y2 - by);
+                let mut bx = x1;
+                while bx < x2 {
+                    let bw = block_size.min(x2 - bx);
+                    
+                    // 计算块内平均颜色
+                    let mut sum = [0u64; 4];
+                    for y in by..by + bh {
+                        for x in bx..bx + bw {
+                            for (total, value) in sum.iter_mut().zip(image.get_pixel(x, y).0) {
+                                *total += value as u64;
+                            }
+                        }
+                    }
+                    let count = (bw * bh) as u64;
+                    let avg = image::Rgba([
+                        (sum[0] / count) as u8,
+                        (sum[1] / count) as u8,
+                        (sum[2] / count) as u8,
+                        (sum[3] / count) as u8,
+                    ]);
+                    
+                    for y in by..by + bh {
+                        for x in bx..bx + bw {
+                            image.put_pixel(x, y, avg);
+                        }
+                    }
+                    bx += bw;
+                }
+                by += bh;
+            }
+        }
+    }
+    
+    /// 获取显示器的 ICC 配置文件
+    fn monitor_profile(monitor: &Monitor, color: &ColorManagementConfig) -> Option<Vec<u8>> {
+        let name = monitor.name().unwrap_or_default();
+        let id = monitor.id().unwrap_or(0);
+        color.resolve_profile(&name, id)
+    }
+    
     /// 处理捕获的图片
-    fn process_captured_image(image: RgbaImage) -> Result<ScreenshotResult, ScreenshotError> {
+    /// 
+    /// 依次执行色彩转换、隐私模糊、缩放和 PNG 编码，确保敏感区域不会离开后端
+    fn process_captured_image(
+        mut image: RgbaImage,
+        origin: (i32, i32),
+        icc_profile: Option<&[u8]>,
+        options: &CaptureOptions,
+    ) -> Result<ScreenshotResult, ScreenshotError> {
+        let started = std::time::Instant::now();
+        let original_dimensions = image.dimensions();
+        let original_bytes = image.as_raw().len();
+        
+        if let Some(profile) = icc_profile {
+            color_profile::convert_to_srgb(&mut image, profile);
+        }
+        
+        if !options.privacy.is_empty() {
+            let regions = Self::collect_privacy_regions(&options.privacy);
+            Self::pixelate_regions(&mut image, origin, &regions, options.privacy.block_size);
+        }
+        
+        let image = Self::apply_scale(image, options.scale);
         let width = image.width();
         let height = image.height();
         
         // 转换为 PNG 格式
-        let mut buffer = Vec::new();
-        let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-        encoder.write_image(
-            image.as_raw(),
-            width,
-            height,
-            image::ExtendedColorType::Rgba8,
-        ).map_err(|e| ScreenshotError::ProcessError(e.to_string()))?;
+        let buffer = ImageProcessor::encode_png(&image, options.png)
+            .map_err(|e| ScreenshotError::ProcessError(e.to_string()))?;
         
         // Base64 编码
         let base64_data = ImageProcessor::encode_base64(&buffer);
@@ -217,6 +503,7 @@ impl ScreenshotManager {
             width,
             height,
             size: buffer.len(),
+            metrics: Some(ImageMetrics::new(original_dimensions, original_bytes, buffer.len(), None, started)),
         })
     }
 }
@@ -242,7 +529,74 @@ mod tests {
             height: 0,
         };
         
-        let result = ScreenshotManager::capture_region(region);
+        let result = ScreenshotManager::capture_region(region, &CaptureOptions::default());
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_contains_point() {
+        // 主显示器右侧的副显示器
+        let secondary = (1920, 0, 2560, 1440);
+        assert!(contains_point(secondary, (1920, 0)));
+        assert!(contains_point(secondary, (3000, 700)));
+        assert!(!contains_point(secondary, (1919, 700)));
+        assert!(!contains_point(secondary, (4480, 700)));
+    }
+    
+    #[test]
+    fn test_snap_target_intersects() {
+        let target = SnapTarget {
+            x: 100,
+            y: 100,
+            width: 200,
+            height: 100,
+            title: String::new(),
+            app_name: String::new(),
+        };
+        let inside = ScreenshotRegion { x: 150, y: 150, width: 10, height: 10 };
+        let outside = ScreenshotRegion { x: 300, y: 100, width: 10, height: 10 };
+        
+        assert!(target.intersects(&inside));
+        assert!(!target.intersects(&outside));
+    }
+    
+    #[test]
+    fn test_scale_presets() {
+        assert_eq!(ScalePreset::Original.target_size(3840, 2160), (3840, 2160));
+        assert_eq!(ScalePreset::Half.target_size(3840, 2160), (1920, 1080));
+        assert_eq!(ScalePreset::Fit1280.target_size(3840, 2160), (1280, 720));
+        // 小图不放大
+        assert_eq!(ScalePreset::Fit1280.target_size(800, 600), (800, 600));
+        
+        let (w, h) = ScalePreset::FitTokenBudget { tokens: 1600 }.target_size(3840, 2160);
+        assert!((w as f64 * h as f64) / ScalePreset::PIXELS_PER_TOKEN <= 1601.0);
+    }
+    
+    #[test]
+    fn test_apply_scale_resizes_image() {
+        let image = RgbaImage::new(200, 100);
+        let scaled = ScreenshotManager::apply_scale(image, ScalePreset::Half);
+        assert_eq!((scaled.width(), scaled.height()), (100, 50));
+    }
+    
+    #[test]
+    fn test_pixelate_regions_only_touches_region() {
+        // 棋盘格图片，像素化后区域内颜色应一致
+        let mut image = RgbaImage::from_fn(8, 8, |x, y| {
+            if (x + y) % 2 == 0 {
+                image::Rgba([255, 255, 255, 255])
+            } else {
+                image::Rgba([0, 0, 0, 255])
+            }
+        });
+        let original = image.clone();
+        // 屏幕坐标 (100, 100) 对应图片左上角
+        let region = ScreenRegion { x: 100, y: 100, width: 4, height: 4 };
+        
+        ScreenshotManager::pixelate_regions(&mut image, (100, 100), &[region], 4);
+        
+        let first = *image.get_pixel(0, 0);
+        assert!((0..4).all(|x| (0..4).all(|y| *image.get_pixel(x, y) == first)));
+        assert_eq!(image.get_pixel(5, 5), original.get_pixel(5, 5));
+    }
 }
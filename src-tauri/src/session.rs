@@ -0,0 +1,44 @@
+//! 反馈会话记录模块
+//!
+//! MCP 请求完成后，将请求与用户响应保存为会话记录（存储在本地 SQLite 数据库中），
+//! 供历史搜索、导出报告、归档等功能使用。
+
+use serde::{Deserialize, Serialize};
+
+use crate::popup::{PopupRequest, PopupResponse};
+
+/// 一次完整的反馈会话（请求 + 响应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// 会话 ID（与请求 ID 相同）
+    pub id: String,
+    pub request: PopupRequest,
+    pub response: PopupResponse,
+    /// 响应时间（RFC 3339）
+    pub responded_at: String,
+}
+
+impl SessionRecord {
+    pub fn new(request: &PopupRequest, response: &PopupResponse) -> Self {
+        Self {
+            id: request.id.clone(),
+            request: request.clone(),
+            response: response.clone(),
+            responded_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// 会话摘要（历史列表和搜索结果使用，不含图片数据）
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub id: String,
+    pub kind: String,
+    pub message: Option<String>,
+    pub user_input: Option<String>,
+    pub selected_options: Vec<String>,
+    pub cancelled: bool,
+    pub created_at: String,
+    pub responded_at: String,
+}
@@ -0,0 +1,158 @@
+//! 响应时限（SLA）模块
+//!
+//! Agent 可以声明期望的响应截止时间（`respond_by`）。GUI 在截止时间临近和超时时
+//! 逐级提醒用户（状态事件、提示音、窗口置顶和任务栏闪烁），
+//! MCP 结果中会注明是否在截止时间前完成响应。
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, UserAttentionType};
+
+use crate::audio::AudioNotifier;
+use crate::config;
+use crate::popup::PopupRequest;
+use crate::result_language::{fill, ResultLanguage};
+use crate::types::AppConfig;
+
+/// SLA 状态事件名
+pub const SLA_STATE_EVENT: &str = "sla-state";
+
+/// 已用时间达到此比例时进入警告状态
+const WARNING_FRACTION: f64 = 0.8;
+
+/// SLA 状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaState {
+    /// 时间充裕
+    OnTrack,
+    /// 即将到期
+    Warning,
+    /// 已超时
+    Overdue,
+}
+
+/// SLA 状态事件载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlaStatePayload {
+    pub state: SlaState,
+    /// 截止时间（RFC 3339）
+    pub deadline: String,
+}
+
+/// 解析截止时间（RFC 3339）
+pub fn parse_deadline(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// 进入警告状态的时间
+fn warning_at(created: DateTime<Utc>, deadline: DateTime<Utc>) -> DateTime<Utc> {
+    let window = (deadline - created).num_milliseconds().max(0) as f64;
+    created + ChronoDuration::milliseconds((window * WARNING_FRACTION) as i64)
+}
+
+/// 指定时刻的 SLA 状态
+pub fn state_at(now: DateTime<Utc>, created: DateTime<Utc>, deadline: DateTime<Utc>) -> SlaState {
+    if now >= deadline {
+        SlaState::Overdue
+    } else if now >= warning_at(created, deadline) {
+        SlaState::Warning
+    } else {
+        SlaState::OnTrack
+    }
+}
+
+/// 结果中的 SLA 说明
+pub fn describe_outcome(deadline: DateTime<Utc>, responded_at: DateTime<Utc>, language: ResultLanguage) -> String {
+    let text = language.text();
+    let diff = (deadline - responded_at).num_seconds();
+    if diff >= 0 {
+        fill(text.sla_met, &[&diff])
+    } else {
+        fill(text.sla_missed, &[&-diff])
+    }
+}
+
+/// GUI 端的逐级提醒：警告时播放提示音，超时时置顶窗口、请求用户注意并播放紧急提示音
+pub async fn run_escalation(app_handle: AppHandle, request: PopupRequest) {
+    let Some(deadline) = request.respond_by.as_deref().and_then(parse_deadline) else {
+        return;
+    };
+    let created = parse_deadline(&request.created_at).unwrap_or_else(Utc::now);
+    let app_config = config::load_config(&app_handle).await.unwrap_or_default();
+
+    let mut last_state = None;
+    loop {
+        let state = state_at(Utc::now(), created, deadline);
+        if last_state != Some(state) {
+            last_state = Some(state);
+            log::info!("[sla] 状态变化: {:?}", state);
+            let payload = SlaStatePayload { state, deadline: deadline.to_rfc3339() };
+            if let Err(e) = app_handle.emit(SLA_STATE_EVENT, &payload) {
+                log::warn!("[sla] 发送状态事件失败: {}", e);
+            }
+            escalate(&app_handle, state, &app_config);
+        }
+
+        let next = match state {
+            SlaState::OnTrack => warning_at(created, deadline),
+            SlaState::Warning => deadline,
+            SlaState::Overdue => return,
+        };
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+    }
+}
+
+fn escalate(app_handle: &AppHandle, state: SlaState, app_config: &AppConfig) {
+    let sound = app_config.audio_file.as_deref();
+    match state {
+        SlaState::OnTrack => {}
+        SlaState::Warning => {
+            if app_config.audio_enabled {
+                AudioNotifier::play_notification_async(sound);
+            }
+        }
+        SlaState::Overdue => {
+            if app_config.audio_enabled {
+                AudioNotifier::play_alert_async(sound);
+            }
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = window.set_always_on_top(true);
+                let _ = window.request_user_attention(Some(UserAttentionType::Critical));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_transitions() {
+        let created = parse_deadline("2024-01-01T10:00:00Z").unwrap();
+        let deadline = parse_deadline("2024-01-01T10:10:00Z").unwrap();
+        let at = |s: &str| parse_deadline(s).unwrap();
+
+        assert_eq!(state_at(at("2024-01-01T10:07:59Z"), created, deadline), SlaState::OnTrack);
+        assert_eq!(state_at(at("2024-01-01T10:08:00Z"), created, deadline), SlaState::Warning);
+        assert_eq!(state_at(at("2024-01-01T10:10:00Z"), created, deadline), SlaState::Overdue);
+        // 截止时间早于创建时间时直接超时
+        assert_eq!(state_at(created, deadline, created), SlaState::Overdue);
+    }
+
+    #[test]
+    fn test_describe_outcome() {
+        let deadline = parse_deadline("2024-01-01T10:10:00+08:00").unwrap();
+        let early = parse_deadline("2024-01-01T02:09:18Z").unwrap();
+        let late = parse_deadline("2024-01-01T02:10:05Z").unwrap();
+
+        assert_eq!(describe_outcome(deadline, early, ResultLanguage::En), "**SLA:** met (answered 42s before the deadline)");
+        assert_eq!(describe_outcome(deadline, late, ResultLanguage::En), "**SLA:** missed (answered 5s after the deadline)");
+        assert_eq!(describe_outcome(deadline, late, ResultLanguage::Zh), "**SLA：** 未达成（在截止时间后 5 秒回答）");
+    }
+}
@@ -0,0 +1,276 @@
+//! 数据库静态加密（SQLCipher）
+//!
+//! 历史反馈中经常包含专有代码和匆忙粘贴的凭据，可选择对本地数据库整体加密。
+//! 密钥来源：
+//! - 系统钥匙串（默认）：首次启用时生成随机密钥并保存在钥匙串中
+//! - 口令：从环境变量 `WHALE_STORAGE_PASSPHRASE` 读取，由 SQLCipher 派生密钥
+//!
+//! 启用或关闭加密时，已有数据库会自动转换。转换需要独占数据库锁文件：
+//! 打开数据库的连接都持有共享锁，其他进程（GUI 或其他 MCP Server）正在使用数据库时
+//! 暂不转换，按文件的实际状态打开，等下次没有其他连接时再转换。
+
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+
+use super::{StorageError, StorageResult};
+
+/// 钥匙串服务名
+const KEYCHAIN_SERVICE: &str = "com.whale-interactive-feedback.app";
+/// 钥匙串账户名
+const KEYCHAIN_ACCOUNT: &str = "storage-key";
+/// 口令环境变量
+pub const PASSPHRASE_ENV: &str = "WHALE_STORAGE_PASSPHRASE";
+
+/// 未加密 SQLite 文件头
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// 密钥来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    #[default]
+    Keychain,
+    Passphrase,
+}
+
+/// 存储加密配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageEncryptionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub key_source: KeySource,
+}
+
+/// 获取配置对应的 SQLCipher 密钥（`PRAGMA key` 的值）
+///
+/// 钥匙串模式下密钥不存在时会自动生成
+pub fn resolve_key(config: &StorageEncryptionConfig) -> StorageResult<String> {
+    match config.key_source {
+        KeySource::Keychain => match keychain_key()? {
+            Some(key) => Ok(key),
+            None => {
+                let key = raw_key(&rand_key());
+                keychain_entry()?.set_password(&key).map_err(keychain_error)?;
+                log::info!("[storage] 已在系统钥匙串中生成数据库密钥");
+                Ok(key)
+            }
+        },
+        KeySource::Passphrase => passphrase_key().ok_or(StorageError::MissingKey),
+    }
+}
+
+/// 查找已有的密钥（不生成新密钥），用于关闭加密时解密已有数据库
+fn existing_key() -> Option<String> {
+    passphrase_key().or_else(|| keychain_key().ok().flatten())
+}
+
+fn passphrase_key() -> Option<String> {
+    std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+fn keychain_entry() -> StorageResult<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(keychain_error)
+}
+
+fn keychain_key() -> StorageResult<Option<String>> {
+    match keychain_entry()?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keychain_error(e)),
+    }
+}
+
+/// 删除钥匙串中的数据库密钥（清除全部用户数据时使用）
+pub fn delete_keychain_key() {
+    if let Ok(entry) = keychain_entry() {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => log::warn!("[storage] 删除钥匙串密钥失败: {}", e),
+        }
+    }
+}
+
+fn keychain_error(e: keyring::Error) -> StorageError {
+    StorageError::Keychain(e.to_string())
+}
+
+/// 从操作系统 CSPRNG 生成 32 字节随机密钥
+fn rand_key() -> [u8; 32] {
+    ChaCha20Poly1305::generate_key(&mut OsRng).into()
+}
+
+/// SQLCipher 原始密钥格式：x'<64 位十六进制>'，跳过口令派生
+fn raw_key(key: &[u8]) -> String {
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("x'{}'", hex)
+}
+
+/// 数据库文件是否为未加密的 SQLite 文件
+fn is_plaintext(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(header == SQLITE_HEADER),
+        // 空文件视为未加密
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// 打开数据库的锁文件（与数据库同目录）
+pub fn lock_file(path: &Path) -> StorageResult<File> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(sibling_path(path, "-lock"))?;
+    Ok(file)
+}
+
+/// 使已有数据库文件与配置一致：启用加密时加密明文库，关闭加密时解密密文库
+///
+/// 只在取得 `lock` 的独占锁（没有其他连接）时转换，返回前在 `lock` 上持有共享锁，
+/// 调用方在连接期间保持 `lock` 打开
+///
+/// # Returns
+/// * 打开数据库应使用的密钥（按文件的实际加密状态）
+pub fn prepare(path: &Path, config: &StorageEncryptionConfig, lock: &File) -> StorageResult<Option<String>> {
+    if path.exists() && is_plaintext(path)? == config.enabled {
+        match lock.try_lock() {
+            Ok(()) => {
+                let converted = convert_to(path, config);
+                lock.unlock()?;
+                converted?;
+            }
+            Err(TryLockError::WouldBlock) => {
+                log::warn!("[storage] 数据库正被其他进程使用，暂不转换加密状态");
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+
+    lock.lock_shared()?;
+    let encrypted = if path.exists() { !is_plaintext(path)? } else { config.enabled };
+    match (encrypted, config.enabled) {
+        (false, _) => Ok(None),
+        (true, true) => resolve_key(config).map(Some),
+        (true, false) => existing_key().map(Some).ok_or(StorageError::MissingKey),
+    }
+}
+
+/// 持有独占锁时转换数据库（重新检查状态：其他进程可能刚完成了转换）
+fn convert_to(path: &Path, config: &StorageEncryptionConfig) -> StorageResult<()> {
+    let plaintext = is_plaintext(path)?;
+    if config.enabled && plaintext {
+        let key = resolve_key(config)?;
+        log::info!("[storage] 加密已有数据库");
+        convert(path, None, Some(&key))?;
+    } else if !config.enabled && !plaintext {
+        let key = existing_key().ok_or(StorageError::MissingKey)?;
+        log::info!("[storage] 加密已关闭，解密已有数据库");
+        convert(path, Some(&key), None)?;
+    }
+    Ok(())
+}
+
+/// 使用 sqlcipher_export 将数据库复制为新密钥（None 表示不加密），完成后替换原文件
+fn convert(path: &Path, from_key: Option<&str>, to_key: Option<&str>) -> StorageResult<()> {
+    let temp_path = sibling_path(path, "converting");
+    let _ = std::fs::remove_file(&temp_path);
+
+    {
+        let conn = Connection::open(path)?;
+        if let Some(key) = from_key {
+            conn.pragma_update(None, "key", key)?;
+        }
+        // 合并 WAL，确保导出完整数据
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        conn.execute(
+            "ATTACH DATABASE ?1 AS converted KEY ?2",
+            rusqlite::params![temp_path.to_string_lossy(), to_key.unwrap_or("")],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+        // sqlcipher_export 不会复制 user_version（迁移版本）
+        conn.execute_batch(&format!("PRAGMA converted.user_version = {}", version))?;
+        conn.execute_batch("DETACH DATABASE converted")?;
+    }
+
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling_path(path, suffix));
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+
+    #[test]
+    fn test_raw_key_format() {
+        let key = raw_key(&[0xab; 32]);
+        assert!(key.starts_with("x'abab"));
+        assert_eq!(key.len(), 64 + 3);
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_existing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("whale.db");
+        let key = raw_key(&rand_key());
+
+        let storage = Storage::open(&path, None).unwrap();
+        storage.record_usage("whale_confirm", "ok", std::time::Duration::from_millis(5)).unwrap();
+        drop(storage);
+        assert!(is_plaintext(&path).unwrap());
+
+        convert(&path, None, Some(&key)).unwrap();
+        assert!(!is_plaintext(&path).unwrap());
+        assert!(Storage::open(&path, None).is_err());
+
+        let storage = Storage::open(&path, Some(&key)).unwrap();
+        assert_eq!(storage.usage_stats().unwrap()[0].calls, 1);
+        drop(storage);
+
+        convert(&path, Some(&key), None).unwrap();
+        assert!(is_plaintext(&path).unwrap());
+        assert_eq!(Storage::open(&path, None).unwrap().usage_stats().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_prepare_skips_conversion_while_database_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("whale.db");
+        drop(Storage::open(&path, None).unwrap());
+
+        // 其他进程的连接持有共享锁时不转换，按明文打开
+        let other = lock_file(&path).unwrap();
+        other.lock_shared().unwrap();
+        let config = StorageEncryptionConfig { enabled: true, key_source: KeySource::Passphrase };
+        let lock = lock_file(&path).unwrap();
+        assert_eq!(prepare(&path, &config, &lock).unwrap(), None);
+        assert!(is_plaintext(&path).unwrap());
+
+        // 与配置一致时不需要转换
+        let disabled = StorageEncryptionConfig::default();
+        assert_eq!(prepare(&path, &disabled, &lock_file(&path).unwrap()).unwrap(), None);
+    }
+}
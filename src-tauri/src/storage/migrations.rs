@@ -0,0 +1,112 @@
+//! 数据库迁移
+//!
+//! 迁移按顺序执行，已执行的版本记录在 `PRAGMA user_version` 中。
+//! 只能追加新的迁移，不能修改已发布的迁移。
+
+use rusqlite::Connection;
+
+/// 按版本顺序排列的迁移脚本（版本号 = 下标 + 1）
+const MIGRATIONS: &[&str] = &[
+    // v1: 反馈历史、审计日志、使用统计
+    r#"
+    CREATE TABLE sessions (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        message TEXT,
+        user_input TEXT,
+        selected_options TEXT NOT NULL DEFAULT '[]',
+        cancelled INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL,
+        responded_at TEXT NOT NULL,
+        record TEXT NOT NULL
+    );
+    CREATE INDEX idx_sessions_responded_at ON sessions(responded_at DESC);
+    CREATE INDEX idx_sessions_kind ON sessions(kind, responded_at DESC);
+
+    CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at TEXT NOT NULL,
+        tool TEXT NOT NULL,
+        params TEXT,
+        summary TEXT,
+        outcome TEXT NOT NULL,
+        latency_ms INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_audit_log_created_at ON audit_log(created_at DESC);
+    CREATE INDEX idx_audit_log_tool ON audit_log(tool, created_at DESC);
+
+    CREATE TABLE usage_stats (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at TEXT NOT NULL,
+        tool TEXT NOT NULL,
+        outcome TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_usage_stats_tool ON usage_stats(tool, created_at DESC);
+    "#,
+    // v2: 全文搜索索引（trigram 分词，支持中文子串匹配），并回填已有历史
+    r#"
+    CREATE VIRTUAL TABLE search_index USING fts5(
+        source UNINDEXED,
+        ref_id UNINDEXED,
+        content,
+        created_at UNINDEXED,
+        tokenize = 'trigram'
+    );
+
+    INSERT INTO search_index (source, ref_id, content, created_at)
+        SELECT 'agent_message', id,
+               trim(coalesce(message, '') || char(10) || coalesce(json_extract(record, '$.request.full_response'), '')),
+               responded_at
+        FROM sessions
+        WHERE coalesce(message, '') != '' OR coalesce(json_extract(record, '$.request.full_response'), '') != '';
+
+    INSERT INTO search_index (source, ref_id, content, created_at)
+        SELECT 'feedback', id, user_input, responded_at
+        FROM sessions
+        WHERE coalesce(user_input, '') != '';
+    "#,
+    // v3: 会话线程
+    r#"
+    ALTER TABLE sessions ADD COLUMN thread_id TEXT;
+    UPDATE sessions SET thread_id = json_extract(record, '$.request.thread_id');
+    CREATE INDEX idx_sessions_thread ON sessions(thread_id, responded_at DESC);
+    "#,
+    // v4: 用户主动留给 Agent 的消息
+    r#"
+    CREATE TABLE user_notes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        message TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        delivered_at TEXT
+    );
+    CREATE INDEX idx_user_notes_pending ON user_notes(delivered_at, id);
+    "#,
+    // v5: GUI 和 MCP Server 共享的运行状态（如熔断和重置时间）
+    r#"
+    CREATE TABLE app_state (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    "#,
+];
+
+/// 当前数据库结构版本
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// 执行尚未执行的迁移
+pub fn run(conn: &mut Connection) -> rusqlite::Result<()> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as u32 + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        log::info!("[storage] 数据库迁移到版本 {}", version);
+    }
+
+    Ok(())
+}
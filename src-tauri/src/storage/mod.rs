@@ -0,0 +1,532 @@
+//! 本地存储模块
+//!
+//! 使用嵌入式 SQLite 保存反馈历史、审计日志和使用统计，
+//! 替代随数据量增长而变慢的 JSON 文件，并提供全文搜索。
+//!
+//! MCP 服务器进程和 GUI 进程会同时访问数据库，使用 WAL 模式和 busy timeout 避免锁冲突。
+
+pub mod encryption;
+mod migrations;
+mod search;
+
+pub use encryption::StorageEncryptionConfig;
+pub use search::{SearchFilters, SearchResult};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::{self, ConfigError};
+use crate::session::{SessionRecord, SessionSummary};
+
+/// 数据库文件名
+const DATABASE_FILE_NAME: &str = "whale.db";
+
+/// 存储错误类型
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("Keychain error: {0}")]
+    Keychain(String),
+    #[error("Database is encrypted but no key is available (set {} or enable storage encryption)", encryption::PASSPHRASE_ENV)]
+    MissingKey,
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// 单个工具的使用统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolUsageStats {
+    pub tool: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub avg_duration_ms: f64,
+}
+
+/// 用户主动留给 Agent 的消息，随下一次工具结果交给 Agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserNote {
+    pub id: i64,
+    pub message: String,
+    pub created_at: String,
+}
+
+/// SQLite 存储
+#[derive(Debug)]
+pub struct Storage {
+    conn: Mutex<Connection>,
+    /// 数据库锁文件上的共享锁，持有期间其他进程不会转换数据库的加密状态
+    _lock: Option<std::fs::File>,
+}
+
+impl Storage {
+    /// 打开（或创建）数据库并执行迁移
+    ///
+    /// `key` 为 SQLCipher 密钥，None 表示不加密
+    pub fn open(path: &Path, key: Option<&str>) -> StorageResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        if let Some(key) = key {
+            // 必须在任何其他操作之前设置密钥
+            conn.pragma_update(None, "key", key)?;
+        }
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    /// 打开应用数据目录下的默认数据库（按配置加密或解密已有数据库）
+    ///
+    /// 配置文件无法解析时返回错误，不按默认配置解密已加密的数据库
+    pub fn open_default() -> StorageResult<Self> {
+        let encryption = config::load_config_direct_blocking()?.storage_encryption;
+        let path = config::get_default_data_dir()?.join(DATABASE_FILE_NAME);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let lock = encryption::lock_file(&path)?;
+        let key = encryption::prepare(&path, &encryption, &lock)?;
+        let mut storage = Self::open(&path, key.as_deref())?;
+        storage._lock = Some(lock);
+        Ok(storage)
+    }
+
+    /// 打开内存数据库（测试使用）
+    #[cfg(test)]
+    pub fn open_in_memory() -> StorageResult<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> StorageResult<Self> {
+        conn.busy_timeout(Duration::from_secs(5))?;
+        migrations::run(&mut conn)?;
+        log::debug!("[storage] 数据库已就绪 (schema v{})", migrations::SCHEMA_VERSION);
+        Ok(Self { conn: Mutex::new(conn), _lock: None })
+    }
+
+    /// 保存会话记录（相同 ID 覆盖）
+    pub fn save_session(&self, record: &SessionRecord) -> StorageResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO sessions
+                (id, kind, message, user_input, selected_options, cancelled, created_at, responded_at, record, thread_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                record.id,
+                record.request.kind.as_str(),
+                record.request.message,
+                record.response.user_input,
+                serde_json::to_string(&record.response.selected_options)?,
+                record.response.cancelled,
+                record.request.created_at,
+                record.responded_at,
+                serde_json::to_string(record)?,
+                record.request.thread_id,
+            ],
+        )?;
+        search::index_session(&tx, record)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 读取会话记录
+    pub fn load_session(&self, id: &str) -> StorageResult<Option<SessionRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let record: Option<String> = conn
+            .query_row("SELECT record FROM sessions WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(record.map(|r| serde_json::from_str(&r)).transpose()?)
+    }
+
+    /// 会话记录中引用的全部附件 ID（清理孤立附件时使用）
+    pub fn referenced_attachment_ids(&self) -> StorageResult<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT record FROM sessions WHERE record LIKE '%\"attachment_id\"%'")?;
+        let mut ids = HashSet::new();
+        for record in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let record: SessionRecord = serde_json::from_str(&record?)?;
+            ids.extend(record.response.images.into_iter().filter_map(|image| image.attachment_id));
+        }
+        Ok(ids)
+    }
+
+    /// 按时间倒序列出会话，`query` 非空时按请求消息和用户输入过滤
+    pub fn search_sessions(&self, query: &str, limit: usize, offset: usize) -> StorageResult<Vec<SessionSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let pattern = format!("%{}%", escape_like(query.trim()));
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, message, user_input, selected_options, cancelled, created_at, responded_at
+             FROM sessions
+             WHERE ?1 = '%%' OR message LIKE ?1 ESCAPE '\\' OR user_input LIKE ?1 ESCAPE '\\'
+             ORDER BY responded_at DESC
+             LIMIT ?2 OFFSET ?3",
+        )?;
+        let rows = stmt.query_map(params![pattern, limit as i64, offset as i64], summary_from_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// 列出 `since`（RFC 3339）之后用户实际作答的反馈会话，按时间倒序
+    ///
+    /// 倒计时结束自动选择默认选项和超时的响应不是用户的回答，不包括在内
+    pub fn recent_answers(&self, since: &str, limit: usize) -> StorageResult<Vec<SessionSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, message, user_input, selected_options, cancelled, created_at, responded_at
+             FROM sessions
+             WHERE kind = 'feedback' AND cancelled = 0 AND responded_at >= ?1
+               AND coalesce(json_extract(record, '$.response.auto_selected'), 0) = 0
+               AND coalesce(json_extract(record, '$.response.timed_out'), 0) = 0
+             ORDER BY responded_at DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![since, limit as i64], summary_from_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// 同一线程中最近的 `limit` 次问答，按时间顺序排列
+    pub fn thread_sessions(&self, thread_id: &str, limit: usize) -> StorageResult<Vec<SessionSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, message, user_input, selected_options, cancelled, created_at, responded_at
+             FROM sessions
+             WHERE thread_id = ?1
+             ORDER BY responded_at DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![thread_id, limit as i64], summary_from_row)?;
+        let mut sessions: Vec<SessionSummary> = rows.collect::<Result<_, _>>()?;
+        sessions.reverse();
+        Ok(sessions)
+    }
+
+    /// 按保留策略删除旧会话（0 表示不限制），返回删除的会话数
+    /// 
+    /// 依次按时间、条数、占用空间（会话记录 JSON 大小之和）淘汰最旧的会话
+    pub fn prune_sessions(&self, max_age_days: u32, max_entries: u32, max_bytes: u64) -> StorageResult<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut removed = 0;
+        
+        if max_age_days > 0 {
+            let cutoff = (chrono::Utc::now() - chrono::Duration::days(max_age_days as i64)).to_rfc3339();
+            tx.execute(
+                "DELETE FROM search_index WHERE source != 'canned_response'
+                   AND ref_id IN (SELECT id FROM sessions WHERE responded_at < ?1)",
+                [&cutoff],
+            )?;
+            removed += tx.execute("DELETE FROM sessions WHERE responded_at < ?1", [&cutoff])?;
+        }
+        
+        // 从最新往前累计条数和大小，超出限制的旧会话全部删除
+        let mut expired = Vec::new();
+        {
+            let mut stmt = tx.prepare("SELECT id, length(record) FROM sessions ORDER BY responded_at DESC")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+            let mut total_bytes: u64 = 0;
+            for (index, row) in rows.enumerate() {
+                let (id, size) = row?;
+                total_bytes += size as u64;
+                if (max_entries > 0 && index >= max_entries as usize) || (max_bytes > 0 && total_bytes > max_bytes) {
+                    expired.push(id);
+                }
+            }
+        }
+        for id in &expired {
+            tx.execute("DELETE FROM search_index WHERE ref_id = ?1 AND source != 'canned_response'", [id])?;
+            removed += tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+        }
+        
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// 记录一次工具调用
+    pub fn record_usage(&self, tool: &str, outcome: &str, duration: Duration) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO usage_stats (created_at, tool, outcome, duration_ms) VALUES (?1, ?2, ?3, ?4)",
+            params![chrono::Utc::now().to_rfc3339(), tool, outcome, duration.as_millis() as i64],
+        )?;
+        Ok(())
+    }
+
+    /// 按工具汇总使用统计
+    pub fn usage_stats(&self) -> StorageResult<Vec<ToolUsageStats>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT tool, COUNT(*), SUM(outcome != 'ok'), AVG(duration_ms)
+             FROM usage_stats GROUP BY tool ORDER BY COUNT(*) DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ToolUsageStats {
+                tool: row.get(0)?,
+                calls: row.get::<_, i64>(1)? as u64,
+                errors: row.get::<_, i64>(2)? as u64,
+                avg_duration_ms: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// 保存留给 Agent 的消息
+    pub fn add_user_note(&self, message: &str) -> StorageResult<UserNote> {
+        let conn = self.conn.lock().unwrap();
+        let created_at = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO user_notes (message, created_at) VALUES (?1, ?2)",
+            params![message, created_at],
+        )?;
+        Ok(UserNote { id: conn.last_insert_rowid(), message: message.to_string(), created_at })
+    }
+
+    /// 尚未交给 Agent 的消息（按保存顺序）
+    pub fn pending_user_notes(&self) -> StorageResult<Vec<UserNote>> {
+        let conn = self.conn.lock().unwrap();
+        pending_notes(&conn)
+    }
+
+    /// 取出尚未交给 Agent 的消息并标记为已送达
+    pub fn take_user_notes(&self) -> StorageResult<Vec<UserNote>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let notes = pending_notes(&tx)?;
+        if !notes.is_empty() {
+            tx.execute(
+                "UPDATE user_notes SET delivered_at = ?1 WHERE delivered_at IS NULL AND id <= ?2",
+                params![chrono::Utc::now().to_rfc3339(), notes[notes.len() - 1].id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(notes)
+    }
+
+    /// 删除尚未送达的消息，返回是否删除
+    pub fn delete_user_note(&self, id: i64) -> StorageResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute("DELETE FROM user_notes WHERE id = ?1 AND delivered_at IS NULL", params![id])?;
+        Ok(removed > 0)
+    }
+
+    /// 写入共享的运行状态
+    pub fn set_state(&self, key: &str, value: &str) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO app_state (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![key, value, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// 读取共享的运行状态
+    pub fn get_state(&self, key: &str) -> StorageResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn
+            .query_row("SELECT value FROM app_state WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+}
+
+fn pending_notes(conn: &Connection) -> StorageResult<Vec<UserNote>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, message, created_at FROM user_notes WHERE delivered_at IS NULL ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(UserNote { id: row.get(0)?, message: row.get(1)?, created_at: row.get(2)? })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// 读取 `id, kind, message, user_input, selected_options, cancelled, created_at, responded_at` 列
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<SessionSummary> {
+    let selected: String = row.get(4)?;
+    Ok(SessionSummary {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        message: row.get(2)?,
+        user_input: row.get(3)?,
+        selected_options: serde_json::from_str(&selected).unwrap_or_default(),
+        cancelled: row.get(5)?,
+        created_at: row.get(6)?,
+        responded_at: row.get(7)?,
+    })
+}
+
+/// 转义 LIKE 模式中的通配符
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::{PopupRequest, PopupResponse};
+
+    fn sample_record(message: &str, input: &str) -> SessionRecord {
+        let request = PopupRequest::new(Some(message.to_string()), None, None);
+        let mut response = PopupResponse::cancelled(&request.id);
+        response.cancelled = false;
+        response.user_input = Some(input.to_string());
+        SessionRecord::new(&request, &response)
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        drop(Storage::open(&path, None).unwrap());
+        let storage = Storage::open(&path, None).unwrap();
+
+        let version: u32 = storage.conn.lock().unwrap()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, migrations::SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_save_load_and_search_sessions() {
+        let storage = Storage::open_in_memory().unwrap();
+        let first = sample_record("Review the login page", "looks good");
+        let second = sample_record("Refactor 100% of utils", "请拆分成小函数");
+        storage.save_session(&first).unwrap();
+        storage.save_session(&second).unwrap();
+
+        let loaded = storage.load_session(&first.id).unwrap().unwrap();
+        assert_eq!(loaded.request.message.as_deref(), Some("Review the login page"));
+        assert!(storage.load_session("missing").unwrap().is_none());
+
+        assert_eq!(storage.search_sessions("", 10, 0).unwrap().len(), 2);
+        let hits = storage.search_sessions("拆分", 10, 0).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, second.id);
+        // 通配符按字面匹配
+        assert_eq!(storage.search_sessions("100%", 10, 0).unwrap().len(), 1);
+        assert!(storage.search_sessions("_", 10, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_referenced_attachment_ids() {
+        let storage = Storage::open_in_memory().unwrap();
+        let mut record = sample_record("Check the screenshot", "see image");
+        record.response.images = vec![crate::popup::ImageData {
+            data: String::new(),
+            mime_type: "image/png".to_string(),
+            attachment_id: Some("abc".to_string()),
+        }];
+        storage.save_session(&record).unwrap();
+        storage.save_session(&sample_record("No images", "ok")).unwrap();
+
+        let ids = storage.referenced_attachment_ids().unwrap();
+        assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec!["abc".to_string()]);
+    }
+
+    #[test]
+    fn test_thread_sessions_oldest_first() {
+        let storage = Storage::open_in_memory().unwrap();
+        for i in 0..4 {
+            let mut record = sample_record(&format!("step {}", i), "ok");
+            record.request.thread_id = Some("task-1".to_string());
+            record.responded_at = format!("2024-01-0{}T00:00:00+00:00", i + 1);
+            storage.save_session(&record).unwrap();
+        }
+        storage.save_session(&sample_record("unrelated", "ok")).unwrap();
+
+        let history = storage.thread_sessions("task-1", 3).unwrap();
+        let messages: Vec<_> = history.iter().map(|s| s.message.as_deref().unwrap()).collect();
+        assert_eq!(messages, vec!["step 1", "step 2", "step 3"]);
+        assert!(storage.thread_sessions("task-2", 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_user_notes_delivered_once() {
+        let storage = Storage::open_in_memory().unwrap();
+        let first = storage.add_user_note("use the staging database").unwrap();
+        let second = storage.add_user_note("skip the e2e tests").unwrap();
+        assert!(storage.delete_user_note(second.id).unwrap());
+        storage.add_user_note("ping me before deploying").unwrap();
+
+        assert_eq!(storage.pending_user_notes().unwrap().len(), 2);
+        let notes = storage.take_user_notes().unwrap();
+        assert_eq!(notes[0].id, first.id);
+        assert_eq!(notes[1].message, "ping me before deploying");
+
+        assert!(storage.take_user_notes().unwrap().is_empty());
+        // 已送达的消息不能再删除
+        assert!(!storage.delete_user_note(first.id).unwrap());
+    }
+
+    #[test]
+    fn test_app_state_upsert() {
+        let storage = Storage::open_in_memory().unwrap();
+        assert_eq!(storage.get_state("circuit_breaker.reset_at").unwrap(), None);
+        storage.set_state("circuit_breaker.reset_at", "2024-01-01T00:00:00+00:00").unwrap();
+        storage.set_state("circuit_breaker.reset_at", "2024-01-02T00:00:00+00:00").unwrap();
+        assert_eq!(
+            storage.get_state("circuit_breaker.reset_at").unwrap().as_deref(),
+            Some("2024-01-02T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_prune_sessions_by_entries_and_size() {
+        let storage = Storage::open_in_memory().unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let mut record = sample_record(&format!("message {}", i), "ok");
+            record.responded_at = format!("2024-01-0{}T00:00:00+00:00", i + 1);
+            storage.save_session(&record).unwrap();
+            ids.push(record.id);
+        }
+
+        assert_eq!(storage.prune_sessions(0, 3, 0).unwrap(), 2);
+        assert!(storage.load_session(&ids[0]).unwrap().is_none());
+        assert!(storage.load_session(&ids[4]).unwrap().is_some());
+
+        // 只保留一条记录的空间
+        let size: i64 = storage.conn.lock().unwrap()
+            .query_row("SELECT max(length(record)) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(storage.prune_sessions(0, 0, size as u64).unwrap(), 2);
+        assert_eq!(storage.search_sessions("", 10, 0).unwrap().len(), 1);
+
+        // 按时间淘汰（测试数据都早于保留期）
+        assert_eq!(storage.prune_sessions(30, 0, 0).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.record_usage("whale_confirm", "ok", Duration::from_millis(100)).unwrap();
+        storage.record_usage("whale_confirm", "error", Duration::from_millis(300)).unwrap();
+
+        let stats = storage.usage_stats().unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].calls, 2);
+        assert_eq!(stats[0].errors, 1);
+        assert_eq!(stats[0].avg_duration_ms, 200.0);
+    }
+}
@@ -0,0 +1,312 @@
+//! 全文搜索
+//!
+//! 基于 FTS5（trigram 分词）统一搜索反馈历史、AI 请求消息和常用语，
+//! 结果按相关度排序并带有高亮片段。
+
+use rusqlite::{params, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+
+use super::{Storage, StorageResult};
+use crate::session::SessionRecord;
+use crate::types::CannedResponse;
+
+/// 高亮片段起始标记（STX），前端按标记拆分后安全渲染，避免把内容当作 HTML
+pub const HIGHLIGHT_START: &str = "\u{2}";
+/// 高亮片段结束标记（ETX）
+pub const HIGHLIGHT_END: &str = "\u{3}";
+
+/// trigram 分词至少需要 3 个字符，更短的关键词使用 LIKE 匹配
+const MIN_FTS_QUERY_CHARS: usize = 3;
+
+/// 片段前后保留的字符数（LIKE 匹配时使用）
+const SNIPPET_CONTEXT_CHARS: usize = 24;
+
+/// 搜索来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    /// 用户反馈内容
+    Feedback,
+    /// AI 请求消息
+    AgentMessage,
+    /// 常用语
+    CannedResponse,
+}
+
+impl SearchSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchSource::Feedback => "feedback",
+            SearchSource::AgentMessage => "agent_message",
+            SearchSource::CannedResponse => "canned_response",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "feedback" => Some(SearchSource::Feedback),
+            "agent_message" => Some(SearchSource::AgentMessage),
+            "canned_response" => Some(SearchSource::CannedResponse),
+            _ => None,
+        }
+    }
+}
+
+/// 搜索过滤条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFilters {
+    /// 限定来源（为空表示全部）
+    #[serde(default)]
+    pub sources: Vec<SearchSource>,
+    /// 起始时间（RFC 3339，仅作用于历史记录）
+    #[serde(default)]
+    pub since: Option<String>,
+    /// 结束时间（RFC 3339，仅作用于历史记录）
+    #[serde(default)]
+    pub until: Option<String>,
+    /// 返回条数（默认 50）
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub source: SearchSource,
+    /// 会话 ID 或常用语 ID
+    pub ref_id: String,
+    /// 带高亮标记的片段
+    pub snippet: String,
+    /// 相关度（越小越相关）
+    pub rank: f64,
+    pub created_at: String,
+}
+
+/// 将会话写入搜索索引（覆盖该会话已有的索引）
+pub(super) fn index_session(conn: &Connection, record: &SessionRecord) -> StorageResult<()> {
+    conn.execute(
+        "DELETE FROM search_index WHERE ref_id = ?1 AND source != 'canned_response'",
+        [&record.id],
+    )?;
+
+    let agent_message = [record.request.message.as_deref(), record.request.full_response.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let feedback = record.response.user_input.as_deref().unwrap_or_default();
+
+    for (source, content) in [
+        (SearchSource::AgentMessage, agent_message.as_str()),
+        (SearchSource::Feedback, feedback),
+    ] {
+        if content.trim().is_empty() {
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO search_index (source, ref_id, content, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![source.as_str(), record.id, content, record.responded_at],
+        )?;
+    }
+    Ok(())
+}
+
+impl Storage {
+    /// 用最新的常用语列表替换索引中的常用语
+    pub fn index_canned_responses(&self, responses: &[CannedResponse]) -> StorageResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM search_index WHERE source = 'canned_response'", [])?;
+        for response in responses {
+            tx.execute(
+                "INSERT INTO search_index (source, ref_id, content, created_at) VALUES ('canned_response', ?1, ?2, '')",
+                params![response.id, response.text],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 全文搜索历史记录和常用语
+    pub fn search_all(&self, query: &str, filters: &SearchFilters) -> StorageResult<Vec<SearchResult>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sources = if filters.sources.is_empty() {
+            None
+        } else {
+            let list: Vec<&str> = filters.sources.iter().map(|s| s.as_str()).collect();
+            Some(format!(",{},", list.join(",")))
+        };
+        let limit = filters.limit.unwrap_or(50) as i64;
+        let use_fts = query.chars().count() >= MIN_FTS_QUERY_CHARS;
+
+        let sql = if use_fts {
+            "SELECT source, ref_id, snippet(search_index, 2, ?6, ?7, '…', 16), bm25(search_index), created_at
+             FROM search_index
+             WHERE search_index MATCH ?1"
+        } else {
+            "SELECT source, ref_id, content, 0.0, created_at
+             FROM search_index
+             WHERE content LIKE '%' || ?1 || '%' ESCAPE '\\'"
+        };
+        let sql = format!(
+            "{}
+               AND (?2 IS NULL OR instr(?2, ',' || source || ',') > 0)
+               AND (source = 'canned_response' OR ?3 IS NULL OR created_at >= ?3)
+               AND (source = 'canned_response' OR ?4 IS NULL OR created_at <= ?4)
+             ORDER BY 4, created_at DESC
+             LIMIT ?5",
+            sql
+        );
+        let pattern = if use_fts {
+            // 作为短语匹配，避免用户输入被解析为 FTS 语法
+            format!("\"{}\"", query.replace('"', "\"\""))
+        } else {
+            super::escape_like(query)
+        };
+
+        let mut bindings: Vec<&dyn ToSql> = vec![&pattern, &sources, &filters.since, &filters.until, &limit];
+        if use_fts {
+            bindings.push(&HIGHLIGHT_START);
+            bindings.push(&HIGHLIGHT_END);
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            bindings.as_slice(),
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let (source, ref_id, text, rank, created_at) = row?;
+            let Some(source) = SearchSource::parse(&source) else {
+                continue;
+            };
+            let snippet = if use_fts { text } else { highlight_snippet(&text, query) };
+            results.push(SearchResult { source, ref_id, snippet, rank, created_at });
+        }
+        Ok(results)
+    }
+}
+
+/// 截取关键词附近的片段并加上高亮标记（LIKE 匹配时使用）
+fn highlight_snippet(content: &str, query: &str) -> String {
+    let lower_content = content.to_lowercase();
+    let Some(byte_index) = lower_content.find(&query.to_lowercase()) else {
+        return content.chars().take(SNIPPET_CONTEXT_CHARS * 2).collect();
+    };
+    // 小写转换可能改变字节长度，按字符位置定位原文
+    let start_char = lower_content[..byte_index].chars().count();
+    let query_chars = query.chars().count();
+
+    let chars: Vec<char> = content.chars().collect();
+    let from = start_char.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let match_end = (start_char + query_chars).min(chars.len());
+    let to = (match_end + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[from..start_char]);
+    snippet.push_str(HIGHLIGHT_START);
+    snippet.extend(&chars[start_char..match_end]);
+    snippet.push_str(HIGHLIGHT_END);
+    snippet.extend(&chars[match_end..to]);
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::popup::{PopupRequest, PopupResponse};
+
+    fn sample_record(message: &str, input: &str) -> SessionRecord {
+        let request = PopupRequest::new(Some(message.to_string()), None, None);
+        let mut response = PopupResponse::cancelled(&request.id);
+        response.cancelled = false;
+        response.user_input = Some(input.to_string());
+        SessionRecord::new(&request, &response)
+    }
+
+    fn setup() -> Storage {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.save_session(&sample_record("Please review the login page", "按钮颜色需要调整")).unwrap();
+        storage.save_session(&sample_record("Refactor the parser", "looks good to me")).unwrap();
+        storage.index_canned_responses(&[CannedResponse {
+            id: "c1".to_string(),
+            text: "Please add unit tests for the login flow".to_string(),
+            order: 0,
+        }]).unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_search_all_sources_with_highlight() {
+        let storage = setup();
+
+        let results = storage.search_all("login", &SearchFilters::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().any(|r| r.source == SearchSource::AgentMessage));
+        assert!(results.iter().any(|r| r.source == SearchSource::CannedResponse));
+        assert!(results.iter().all(|r| r.snippet.contains(&format!("{}login{}", HIGHLIGHT_START, HIGHLIGHT_END))));
+    }
+
+    #[test]
+    fn test_search_filters_and_cjk() {
+        let storage = setup();
+
+        let filters = SearchFilters {
+            sources: vec![SearchSource::CannedResponse],
+            ..SearchFilters::default()
+        };
+        let results = storage.search_all("login", &filters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].ref_id, "c1");
+
+        // 三个及以上中文字符走 FTS，两个字符走 LIKE
+        assert_eq!(storage.search_all("按钮颜色", &SearchFilters::default()).unwrap().len(), 1);
+        let short = storage.search_all("颜色", &SearchFilters::default()).unwrap();
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].source, SearchSource::Feedback);
+        assert!(short[0].snippet.contains(&format!("{}颜色{}", HIGHLIGHT_START, HIGHLIGHT_END)));
+    }
+
+    #[test]
+    fn test_search_query_is_not_fts_syntax() {
+        let storage = setup();
+        assert!(storage.search_all("login\" OR \"parser", &SearchFilters::default()).unwrap().is_empty());
+        assert!(storage.search_all("   ", &SearchFilters::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resaving_session_replaces_index() {
+        let storage = Storage::open_in_memory().unwrap();
+        let mut record = sample_record("Check the navbar", "first answer");
+        storage.save_session(&record).unwrap();
+        record.response.user_input = Some("second answer".to_string());
+        storage.save_session(&record).unwrap();
+
+        assert!(storage.search_all("first", &SearchFilters::default()).unwrap().is_empty());
+        assert_eq!(storage.search_all("second", &SearchFilters::default()).unwrap().len(), 1);
+    }
+}
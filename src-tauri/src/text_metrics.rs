@@ -0,0 +1,104 @@
+//! 文本统计模块
+//!
+//! 统计输入文本的字符数、词数、估算 token 数和主要语言。
+//! 前端编写反馈时实时调用，MCP 服务器也会将最终输入的统计写入审计日志，保证两边口径一致。
+
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{estimate_tokens, is_cjk};
+
+/// 文本统计结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextMetrics {
+    /// 字符数（Unicode 标量值）
+    pub chars: usize,
+    /// 词数：中日文字符每个计为一个词，其余按空白和标点分隔
+    pub words: usize,
+    pub estimated_tokens: usize,
+    /// 主要语言（zh / ja / ko / en / other），空文本为 None
+    pub language: Option<String>,
+}
+
+/// 统计文本
+pub fn text_metrics(text: &str) -> TextMetrics {
+    let mut words = 0usize;
+    let mut in_word = false;
+    let (mut han, mut kana, mut hangul, mut latin, mut other) = (0usize, 0usize, 0usize, 0usize, 0usize);
+
+    for c in text.chars() {
+        match c as u32 {
+            0x3040..=0x30FF => kana += 1,
+            0xAC00..=0xD7AF => hangul += 1,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => han += 1,
+            _ if c.is_ascii_alphabetic() => latin += 1,
+            _ if c.is_alphabetic() => other += 1,
+            _ => {}
+        }
+
+        // 韩文以空格分词，按普通单词处理
+        let hangul_syllable = (0xAC00..=0xD7AF).contains(&(c as u32));
+        if is_cjk(c) && !hangul_syllable {
+            // CJK 标点不计为词
+            if c.is_alphanumeric() {
+                words += 1;
+            }
+            in_word = false;
+        } else if c.is_alphanumeric() || c == '\'' || c == '_' {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else {
+            in_word = false;
+        }
+    }
+
+    // 含假名的按日文计（日文同时使用汉字）
+    let language = if kana > 0 && kana + han >= latin {
+        Some("ja")
+    } else if hangul > 0 && hangul >= latin {
+        Some("ko")
+    } else if han > 0 && han >= latin {
+        Some("zh")
+    } else if latin > 0 && latin >= other {
+        Some("en")
+    } else if other > 0 {
+        Some("other")
+    } else {
+        None
+    };
+
+    TextMetrics {
+        chars: text.chars().count(),
+        words,
+        estimated_tokens: estimate_tokens(text),
+        language: language.map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_metrics() {
+        let empty = text_metrics("");
+        assert_eq!(empty, TextMetrics::default());
+
+        let english = text_metrics("Don't run the tests yet, please.");
+        assert_eq!(english.words, 6);
+        assert_eq!(english.chars, 32);
+        assert_eq!(english.language.as_deref(), Some("en"));
+
+        let chinese = text_metrics("先不要运行测试，谢谢 CI");
+        assert_eq!(chinese.words, 10);
+        assert_eq!(chinese.estimated_tokens, 11);
+        assert_eq!(chinese.language.as_deref(), Some("zh"));
+
+        assert_eq!(text_metrics("テストを実行して").language.as_deref(), Some("ja"));
+        let korean = text_metrics("테스트 실행");
+        assert_eq!(korean.words, 2);
+        assert_eq!(korean.language.as_deref(), Some("ko"));
+    }
+}
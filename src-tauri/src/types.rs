@@ -1,7 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::annotation::AnnotationConfig;
+use crate::answer_macro::AnswerMacroConfig;
+use crate::archive::ArchiveConfig;
+use crate::audit::AuditLogConfig;
+use crate::color_profile::ColorManagementConfig;
+use crate::image_processor::{ImageEncoderConfig, ImageMetrics};
+use crate::mcp_tools::McpToolsConfig;
+use crate::option_packs::OptionPack;
+use crate::pii::PiiScanConfig;
+use crate::repeat_answer::RepeatAnswerPolicy;
+use crate::result_language::ResultLanguage;
+use crate::retention::RetentionConfig;
+use crate::sampling::SamplingMode;
+use crate::storage::StorageEncryptionConfig;
+use crate::workspace::WorkspaceConfig;
 
 /// 主题色
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, ts_rs::TS)]
 #[serde(rename_all = "lowercase")]
 pub enum ThemeColor {
     #[default]
@@ -14,7 +31,7 @@ pub enum ThemeColor {
 }
 
 /// 字体大小配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct FontSizeConfig {
     pub display: u32,
     pub options: u32,
@@ -32,8 +49,9 @@ impl Default for FontSizeConfig {
 }
 
 /// 应用配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 #[serde(rename_all = "camelCase")]
+#[ts(export)]
 pub struct AppConfig {
     pub theme: Theme,
     #[serde(default)]
@@ -60,23 +78,577 @@ pub struct AppConfig {
     #[serde(default)]
     pub custom_options_enabled: bool,
     #[serde(default = "default_custom_options")]
-    pub custom_options: Vec<String>,
+    pub custom_options: Vec<CustomOption>,
     /// 文本优化类型配置
     #[serde(default = "default_optimization_types")]
     pub optimization_types: Vec<OptimizationTypeConfig>,
+    /// 是否在下拉列表中显示系统预设优化类型
+    #[serde(default = "default_true")]
+    pub show_system_optimization_types: bool,
+    /// 优化类型系统提示词的 token 预算（超出时保存会给出警告）
+    #[serde(default = "default_prompt_token_budget")]
+    pub prompt_token_budget: usize,
+    /// 截图默认缩放预设（单次截图可覆盖）
+    #[serde(default)]
+    pub screenshot_scale: ScalePreset,
+    /// 截图隐私模糊配置
+    #[serde(default)]
+    pub privacy_blur: PrivacyBlurConfig,
+    /// 附加文件内嵌到 MCP 结果中的最大字节数（0 表示不内嵌）
+    #[serde(default = "default_embed_file_max_bytes")]
+    #[ts(type = "number")]
+    pub embed_file_max_bytes: u64,
+    /// 截图色彩管理（ICC 转换到 sRGB）
+    #[serde(default)]
+    pub color_management: ColorManagementConfig,
+    /// 截图标注文字设置
+    #[serde(default)]
+    pub annotation: AnnotationConfig,
+    /// MCP 反馈请求默认超时时间（秒，0 表示一直等待）
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub feedback_timeout_secs: u64,
+    /// 已完成请求的自动归档设置
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    /// 本地数据保留策略
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 本地数据库静态加密
+    #[serde(default)]
+    pub storage_encryption: StorageEncryptionConfig,
+    /// 按 Agent 区分的工作区设置
+    #[serde(default)]
+    pub workspaces: Vec<WorkspaceConfig>,
+    /// 重复问题的处理策略
+    #[serde(default)]
+    pub repeat_answer_policy: RepeatAnswerPolicy,
+    /// 选中选项时自动执行的回答宏
+    #[serde(default)]
+    pub answer_macros: AnswerMacroConfig,
+    /// MCP 工具调用审计日志
+    #[serde(default)]
+    pub audit_log: AuditLogConfig,
+    /// 文本优化发送前的敏感信息扫描
+    #[serde(default)]
+    pub pii_scan: PiiScanConfig,
+    /// 客户端支持 MCP elicitation 时优先由客户端收集输入（不支持时仍使用弹窗）
+    #[serde(default)]
+    pub prefer_elicitation: bool,
+    /// MCP 工具前缀、重命名和启用设置
+    #[serde(default)]
+    pub mcp_tools: McpToolsConfig,
+    /// 文本优化何时改用 MCP 客户端的模型（sampling）
+    #[serde(default)]
+    pub optimize_sampling: SamplingMode,
+    /// MCP 模式下弹窗的激活和置顶行为
+    #[serde(default)]
+    pub mcp_window_behavior: McpWindowBehavior,
+    /// 弹窗位置、大小和首选显示器
+    #[serde(default)]
+    pub window_geometry: WindowGeometryConfig,
+    /// MCP 工具结果标签和错误信息的默认语言（可被调用参数覆盖）
+    #[serde(default)]
+    pub result_language: ResultLanguage,
+    /// 反馈结果的 Handlebars 模板（为空时使用默认的 Markdown 格式）
+    #[serde(default)]
+    pub feedback_result_template: Option<String>,
+    /// 在反馈结果中回显原始问题，与回答配对
+    #[serde(default)]
+    pub echo_request_in_result: bool,
+    /// 提交 MCP 回答前检查回答质量并提示警告
+    #[serde(default)]
+    pub response_lint_enabled: bool,
+    /// Agent 请求时自动截取当前显示器作为上下文
+    #[serde(default)]
+    pub context_capture: ContextCaptureConfig,
+    /// 工具调用限流和未回答请求熔断
+    #[serde(default)]
+    pub flood_protection: FloodProtectionConfig,
+    /// 文本优化工具的限流（避免 Agent 循环调用耗尽 API 配额）
+    #[serde(default)]
+    pub optimize_rate_limit: OptimizeRateLimitConfig,
+    /// 截图和图片压缩使用的编码器
+    #[serde(default)]
+    pub image_encoder: ImageEncoderConfig,
+    /// 常驻 GUI 模式（请求交给后台常驻的窗口显示）
+    #[serde(default)]
+    pub daemon_mode: DaemonModeConfig,
+    /// 同时运行的独立弹窗数量上限
+    #[serde(default)]
+    pub gui_limit: GuiLimitConfig,
+    /// GUI 启动失败或崩溃时的重试
+    #[serde(default)]
+    pub launch_retry: LaunchRetryConfig,
+    /// 残留的 MCP 请求/响应临时文件超过多少小时后清理（0 表示不清理）
+    #[serde(default = "default_temp_file_max_age_hours")]
+    #[ts(type = "number")]
+    pub temp_file_max_age_hours: u64,
+    /// 请求/响应临时文件目录（为空时使用系统临时目录，环境变量 `WHALE_IPC_DIR` 优先）
+    #[serde(default)]
+    pub ipc_dir: Option<String>,
+    /// 命名的预定义选项组，Agent 通过 `options_pack` 引用
+    #[serde(default)]
+    pub option_packs: Vec<OptionPack>,
+    /// 通过 `whale_get_user_preferences` 提供给 Agent 的偏好
+    #[serde(default)]
+    pub user_preferences: UserPreferencesConfig,
+    /// 工作时间和日历：不方便时延后弹窗或通过 Webhook 升级
+    #[serde(default)]
+    pub availability: AvailabilityConfig,
+}
+
+fn default_embed_file_max_bytes() -> u64 {
+    32 * 1024
+}
+
+fn default_temp_file_max_age_hours() -> u64 {
+    24
+}
+
+fn default_prompt_token_budget() -> usize {
+    500
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 弹窗出现时如何引起注意
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum FocusPolicy {
+    /// 激活窗口并抢占键盘焦点
+    #[default]
+    Steal,
+    /// 只显示窗口并闪烁任务栏/Dock 图标，不打断其他应用中的输入
+    Flash,
+}
+
+/// MCP 模式下弹窗的激活和置顶行为
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct McpWindowBehavior {
+    /// 窗口创建后等待多久再激活（毫秒），较慢的机器上可适当调大
+    #[serde(default = "default_activation_delay_ms")]
+    #[ts(type = "number")]
+    pub activation_delay_ms: u64,
+    /// 是否抢占焦点（低优先级请求始终不抢占）
+    #[serde(default)]
+    pub focus: FocusPolicy,
+    /// 激活时是否置顶窗口（紧急请求始终保持置顶）
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    /// 置顶多久后取消（毫秒，0 表示一直置顶）
+    #[serde(default = "default_pin_duration_ms")]
+    #[ts(type = "number")]
+    pub pin_duration_ms: u64,
+}
+
+impl Default for McpWindowBehavior {
+    fn default() -> Self {
+        Self {
+            activation_delay_ms: default_activation_delay_ms(),
+            focus: FocusPolicy::default(),
+            always_on_top: true,
+            pin_duration_ms: default_pin_duration_ms(),
+        }
+    }
+}
+
+/// 弹窗位置和大小（关闭弹窗时自动记录，见 [`crate::window_geometry`]）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometryConfig {
+    /// 是否记录并恢复窗口位置和大小
+    #[serde(default = "default_true")]
+    pub remember: bool,
+    /// 首选显示器名称：设置后弹窗总是出现在该显示器上
+    #[serde(default)]
+    pub preferred_monitor: Option<String>,
+    /// 上次所在的显示器名称
+    #[serde(default)]
+    pub monitor: Option<String>,
+    /// 相对显示器左上角的位置（逻辑像素）
+    #[serde(default)]
+    pub x: Option<f64>,
+    #[serde(default)]
+    pub y: Option<f64>,
+    /// 窗口大小（逻辑像素）
+    #[serde(default)]
+    pub width: Option<f64>,
+    #[serde(default)]
+    pub height: Option<f64>,
+}
+
+impl Default for WindowGeometryConfig {
+    fn default() -> Self {
+        Self {
+            remember: true,
+            preferred_monitor: None,
+            monitor: None,
+            x: None,
+            y: None,
+            width: None,
+            height: None,
+        }
+    }
+}
+
+/// 自动截图上下文设置（默认关闭，需用户开启）
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextCaptureConfig {
+    /// 是否允许 Agent 请求自动截图
+    #[serde(default)]
+    pub enabled: bool,
+    /// 截图前的倒计时（秒），用户可在此期间取消
+    #[serde(default = "default_context_capture_countdown_secs")]
+    #[ts(type = "number")]
+    pub countdown_secs: u64,
+}
+
+impl Default for ContextCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            countdown_secs: default_context_capture_countdown_secs(),
+        }
+    }
+}
+
+fn default_context_capture_countdown_secs() -> u64 {
+    3
+}
+
+/// 弹窗类工具的限流和熔断设置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct FloodProtectionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 每个客户端每分钟最多发起的弹窗请求数
+    #[serde(default = "default_max_calls_per_minute")]
+    pub max_calls_per_minute: u32,
+    /// 连续多少个请求未得到回答后熔断（0 表示不熔断）
+    #[serde(default = "default_max_unanswered")]
+    pub max_unanswered: u32,
+}
+
+impl Default for FloodProtectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_calls_per_minute: default_max_calls_per_minute(),
+            max_unanswered: default_max_unanswered(),
+        }
+    }
+}
+
+/// 文本优化工具的限流设置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeRateLimitConfig {
+    /// 每分钟最多调用次数（0 表示不限制）
+    #[serde(default = "default_optimize_calls_per_minute")]
+    pub calls_per_minute: u32,
+    /// 同时进行的最大 LLM 调用数（0 表示不限制）
+    #[serde(default = "default_optimize_max_concurrent")]
+    pub max_concurrent: u32,
+}
+
+impl Default for OptimizeRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            calls_per_minute: default_optimize_calls_per_minute(),
+            max_concurrent: default_optimize_max_concurrent(),
+        }
+    }
+}
+
+fn default_optimize_calls_per_minute() -> u32 {
+    20
+}
+
+fn default_optimize_max_concurrent() -> u32 {
+    2
+}
+
+/// 常驻 GUI 模式设置
+///
+/// 启用后 GUI 在后台常驻，MCP Server 通过本地 socket 发送请求，省去每次启动 webview 的等待
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonModeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 常驻进程未运行时，启动后等待其就绪的最长时间（秒）
+    #[serde(default = "default_daemon_start_timeout_secs")]
+    #[ts(type = "number")]
+    pub start_timeout_secs: u64,
+}
+
+impl Default for DaemonModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_timeout_secs: default_daemon_start_timeout_secs(),
+        }
+    }
+}
+
+fn default_daemon_start_timeout_secs() -> u64 {
+    15
+}
+
+/// 独立弹窗数量达到上限时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub enum GuiLimitAction {
+    /// 排队等待其他弹窗关闭
+    #[default]
+    Queue,
+    /// 立即返回“忙，稍后重试”的错误
+    Reject,
+}
+
+/// 同时运行的独立弹窗进程数量上限（所有 MCP Server 进程合计，常驻模式不受限制）
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct GuiLimitConfig {
+    /// 最多同时运行的弹窗数（0 表示不限制）
+    #[serde(default)]
+    pub max_concurrent: u32,
+    #[serde(default)]
+    pub when_full: GuiLimitAction,
+    /// 拒绝时建议 Agent 等待的秒数
+    #[serde(default = "default_gui_retry_after_secs")]
+    #[ts(type = "number")]
+    pub retry_after_secs: u64,
+}
+
+impl Default for GuiLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 0,
+            when_full: GuiLimitAction::Queue,
+            retry_after_secs: default_gui_retry_after_secs(),
+        }
+    }
+}
+
+fn default_gui_retry_after_secs() -> u64 {
+    30
+}
+
+/// 提供给 Agent 的用户偏好，Agent 可据此调整提问方式而不需要用户在每个会话中重复说明
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPreferencesConfig {
+    /// 希望 Agent 使用的语言（BCP 47，如 `zh-CN`），为空时按结果语言
+    #[serde(default)]
+    pub preferred_language: Option<String>,
+    /// 回答风格说明（如“尽量给出选项，少问开放式问题”）
+    #[serde(default)]
+    pub style_notes: Option<String>,
+    /// 工作时间说明（如“工作日 09:00-18:00，UTC+8”）
+    #[serde(default)]
+    pub working_hours: Option<String>,
+}
+
+/// 工作时间段（本地时间）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingHours {
+    /// 适用的星期（`mon`、`tue` ... `sun`）
+    pub days: Vec<String>,
+    /// 开始时间（`HH:MM`）
+    pub start: String,
+    /// 结束时间（`HH:MM`，早于开始时间表示跨过午夜）
+    pub end: String,
+}
+
+/// 用户不方便时收到反馈请求的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableAction {
+    /// 照常弹窗
+    #[default]
+    Popup,
+    /// 等到用户有空时再弹窗（不超过请求的超时时间）
+    Defer,
+    /// 发送到 Webhook 并立即告知 Agent 用户暂时无法回答
+    Webhook,
+}
+
+/// 用户可用时间设置（见 [`crate::availability`]）
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityConfig {
+    /// 工作时间段，为空表示不限制
+    #[serde(default)]
+    pub working_hours: Vec<WorkingHours>,
+    /// ICS 日历地址（http(s)://、webcal:// 或本地文件路径），其中的事件视为会议
+    #[serde(default)]
+    pub calendar_url: Option<String>,
+    /// 日历刷新间隔（分钟）
+    #[serde(default = "default_calendar_poll_minutes")]
+    #[ts(type = "number")]
+    pub calendar_poll_minutes: u64,
+    /// 会议期间收到请求时的处理方式
+    #[serde(default)]
+    pub during_meeting: UnavailableAction,
+    /// 工作时间以外收到请求时的处理方式
+    #[serde(default)]
+    pub outside_working_hours: UnavailableAction,
+    /// `webhook` 处理方式使用的地址（未设置时照常弹窗）
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+fn default_calendar_poll_minutes() -> u64 {
+    15
+}
+
+impl Default for AvailabilityConfig {
+    fn default() -> Self {
+        Self {
+            working_hours: Vec::new(),
+            calendar_url: None,
+            calendar_poll_minutes: default_calendar_poll_minutes(),
+            during_meeting: UnavailableAction::Popup,
+            outside_working_hours: UnavailableAction::Popup,
+            webhook_url: None,
+        }
+    }
+}
+
+/// GUI 启动失败或崩溃（没有响应且非正常退出）时的重试设置
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchRetryConfig {
+    /// 最多启动次数（包括第一次）
+    #[serde(default = "default_launch_max_attempts")]
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间（毫秒），之后每次翻倍
+    #[serde(default = "default_launch_initial_backoff_ms")]
+    #[ts(type = "number")]
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for LaunchRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_launch_max_attempts(),
+            initial_backoff_ms: default_launch_initial_backoff_ms(),
+        }
+    }
+}
+
+fn default_launch_max_attempts() -> u32 {
+    3
+}
+
+fn default_launch_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_calls_per_minute() -> u32 {
+    10
+}
+
+fn default_max_unanswered() -> u32 {
+    5
+}
+
+fn default_activation_delay_ms() -> u64 {
+    100
+}
+
+fn default_pin_duration_ms() -> u64 {
+    500
 }
 
 /// 默认自定义选项
-fn default_custom_options() -> Vec<String> {
+fn default_custom_options() -> Vec<CustomOption> {
     vec![
-        "好的，我明白了".to_string(),
-        "请继续".to_string(),
-        "需要更多信息".to_string(),
-        "返回上一步".to_string(),
-        "暂停，让我思考一下".to_string(),
+        CustomOption::from_text("好的，我明白了"),
+        CustomOption::from_text("请继续"),
+        CustomOption::from_text("需要更多信息"),
+        CustomOption::from_text("返回上一步"),
+        CustomOption::from_text("暂停，让我思考一下"),
     ]
 }
 
+/// 自定义选项插入方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, ts_rs::TS)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertPosition {
+    /// 替换输入框内容
+    Replace,
+    /// 追加到输入框末尾
+    #[default]
+    Append,
+}
+
+/// 自定义快捷选项
+///
+/// 兼容旧版配置：`customOptions` 为纯字符串数组时自动迁移为结构化条目
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
+#[serde(rename_all = "camelCase", from = "CustomOptionRepr")]
+pub struct CustomOption {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub insert_position: InsertPosition,
+}
+
+impl CustomOption {
+    /// 从纯文本创建选项（无图标、无颜色、追加插入）
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            icon: None,
+            color: None,
+            insert_position: InsertPosition::default(),
+        }
+    }
+}
+
+/// 自定义选项的反序列化表示（旧版字符串 / 新版结构体）
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CustomOptionRepr {
+    Text(String),
+    #[serde(rename_all = "camelCase")]
+    Full {
+        text: String,
+        #[serde(default)]
+        icon: Option<String>,
+        #[serde(default)]
+        color: Option<String>,
+        #[serde(default)]
+        insert_position: InsertPosition,
+    },
+}
+
+impl From<CustomOptionRepr> for CustomOption {
+    fn from(repr: CustomOptionRepr) -> Self {
+        match repr {
+            CustomOptionRepr::Text(text) => Self::from_text(text),
+            CustomOptionRepr::Full { text, icon, color, insert_position } => Self {
+                text,
+                icon,
+                color,
+                insert_position,
+            },
+        }
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -99,41 +671,260 @@ impl Default for AppConfig {
             custom_options_enabled: false,
             custom_options: default_custom_options(),
             optimization_types: default_optimization_types(),
+            show_system_optimization_types: true,
+            prompt_token_budget: default_prompt_token_budget(),
+            screenshot_scale: ScalePreset::default(),
+            privacy_blur: PrivacyBlurConfig::default(),
+            embed_file_max_bytes: default_embed_file_max_bytes(),
+            color_management: ColorManagementConfig::default(),
+            annotation: AnnotationConfig::default(),
+            feedback_timeout_secs: 0,
+            archive: ArchiveConfig::default(),
+            retention: RetentionConfig::default(),
+            storage_encryption: StorageEncryptionConfig::default(),
+            workspaces: Vec::new(),
+            repeat_answer_policy: RepeatAnswerPolicy::default(),
+            answer_macros: AnswerMacroConfig::default(),
+            audit_log: AuditLogConfig::default(),
+            pii_scan: PiiScanConfig::default(),
+            prefer_elicitation: false,
+            mcp_tools: McpToolsConfig::default(),
+            optimize_sampling: SamplingMode::default(),
+            mcp_window_behavior: McpWindowBehavior::default(),
+            window_geometry: WindowGeometryConfig::default(),
+            result_language: ResultLanguage::default(),
+            feedback_result_template: None,
+            echo_request_in_result: false,
+            response_lint_enabled: false,
+            context_capture: ContextCaptureConfig::default(),
+            flood_protection: FloodProtectionConfig::default(),
+            optimize_rate_limit: OptimizeRateLimitConfig::default(),
+            image_encoder: ImageEncoderConfig::default(),
+            daemon_mode: DaemonModeConfig::default(),
+            gui_limit: GuiLimitConfig::default(),
+            launch_retry: LaunchRetryConfig::default(),
+            temp_file_max_age_hours: default_temp_file_max_age_hours(),
+            ipc_dir: None,
+            option_packs: Vec::new(),
+            user_preferences: UserPreferencesConfig::default(),
+            availability: AvailabilityConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl AppConfig {
+    /// 获取下拉列表中可见的优化类型
+    ///
+    /// 过滤未启用的类型（以及隐藏时的系统预设类型），置顶项优先，其余按 order 排序
+    pub fn visible_optimization_types(&self) -> Vec<OptimizationTypeConfig> {
+        let mut types: Vec<OptimizationTypeConfig> = self.optimization_types.iter()
+            .filter(|t| t.enabled)
+            .filter(|t| self.show_system_optimization_types || !t.is_system || t.pinned)
+            .cloned()
+            .collect();
+        // sort_by_key 为稳定排序，order 相同时保持配置中的原有顺序
+        types.sort_by_key(|t| (!t.pinned, t.order));
+        types
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
 #[serde(rename_all = "lowercase")]
 pub enum Theme {
     Dark,
     Light,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
 #[serde(rename_all = "lowercase")]
 pub enum Layout {
     Vertical,
     Horizontal,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
 #[serde(rename_all = "lowercase")]
 pub enum DisplayMode {
     Simple,
     Full,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ts_rs::TS)]
 pub struct ApiKeys {
     pub openai: Option<String>,
     pub gemini: Option<String>,
     pub deepseek: Option<String>,
     pub volcengine: Option<String>,
+    #[serde(default)]
+    pub anthropic: Option<String>,
+    /// Azure OpenAI 的终结点、部署和（混淆后的）API 密钥
+    #[serde(default)]
+    pub azure: AzureOpenAiConfig,
+    /// 本地模型（不需要 API 密钥）
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    /// 用户添加的 OpenAI 兼容提供商
+    #[serde(default)]
+    pub custom: Vec<CustomProvider>,
+}
+
+impl ApiKeys {
+    /// 内置的提供商（默认优先级顺序）
+    pub const PROVIDERS: [&'static str; 7] = ["openai", "gemini", "deepseek", "volcengine", "anthropic", "azure", "ollama"];
+
+    /// 是否配置了任意一个提供商
+    pub fn any_configured(&self) -> bool {
+        !self.configured_providers().is_empty()
+    }
+
+    /// 已配置的提供商：已设置 API 密钥的内置提供商和已启用的本地模型（按默认优先级排列），之后是自定义提供商
+    pub fn configured_providers(&self) -> Vec<&str> {
+        Self::PROVIDERS
+            .into_iter()
+            .filter(|provider| match *provider {
+                "ollama" => self.ollama.is_configured(),
+                "azure" => self.azure.is_configured() && self.get(provider).is_some(),
+                _ => self.get(provider).is_some(),
+            })
+            .chain(self.custom.iter().map(|custom| custom.name.as_str()))
+            .collect()
+    }
+
+    /// 指定提供商的（混淆后的）API 密钥，未配置或为空时返回 None
+    pub fn get(&self, provider: &str) -> Option<&str> {
+        let key = match provider {
+            "openai" => &self.openai,
+            "gemini" => &self.gemini,
+            "deepseek" => &self.deepseek,
+            "volcengine" => &self.volcengine,
+            "anthropic" => &self.anthropic,
+            "azure" => &self.azure.api_key,
+            _ => &self.custom_provider(provider)?.api_key,
+        };
+        key.as_deref().filter(|k| !k.is_empty())
+    }
+
+    /// 指定提供商的（混淆后的）API 密钥字段，未知的提供商返回 None
+    pub fn get_mut(&mut self, provider: &str) -> Option<&mut Option<String>> {
+        match provider {
+            "openai" => Some(&mut self.openai),
+            "gemini" => Some(&mut self.gemini),
+            "deepseek" => Some(&mut self.deepseek),
+            "volcengine" => Some(&mut self.volcengine),
+            "anthropic" => Some(&mut self.anthropic),
+            "azure" => Some(&mut self.azure.api_key),
+            _ => self.custom.iter_mut().find(|custom| custom.name == provider).map(|custom| &mut custom.api_key),
+        }
+    }
+
+    /// 按名称查找自定义提供商
+    pub fn custom_provider(&self, name: &str) -> Option<&CustomProvider> {
+        self.custom.iter().find(|custom| custom.name == name)
+    }
+}
+
+/// Azure OpenAI：请求发送到 `{endpoint}/openai/deployments/{deployment}`，使用 `api-key` 请求头
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureOpenAiConfig {
+    /// 资源终结点，如 `https://my-resource.openai.azure.com`
+    #[serde(default)]
+    pub endpoint: String,
+    /// 部署名称（Azure 按部署选择模型）
+    #[serde(default)]
+    pub deployment: String,
+    /// `api-version` 查询参数
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+    /// 混淆后的 API 密钥
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl AzureOpenAiConfig {
+    /// 默认 API 版本
+    pub const DEFAULT_API_VERSION: &'static str = "2024-10-21";
+
+    /// 已填写终结点和部署名称
+    pub fn is_configured(&self) -> bool {
+        !self.endpoint.trim().is_empty() && !self.deployment.trim().is_empty()
+    }
+}
+
+impl Default for AzureOpenAiConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            deployment: String::new(),
+            api_version: default_azure_api_version(),
+            api_key: None,
+        }
+    }
+}
+
+fn default_azure_api_version() -> String {
+    AzureOpenAiConfig::DEFAULT_API_VERSION.to_string()
+}
+
+/// 本地模型提供商（Ollama），使用其 OpenAI 兼容 API，不需要 API 密钥
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// OpenAI 兼容 API 地址
+    #[serde(default = "default_ollama_base_url")]
+    pub base_url: String,
+    /// 模型名称（本地已下载的模型见 `GET /api/tags`）
+    #[serde(default)]
+    pub model: String,
+}
+
+impl OllamaConfig {
+    /// 默认 API 地址
+    pub const DEFAULT_BASE_URL: &'static str = "http://localhost:11434/v1";
+
+    /// 已启用并选择了模型
+    pub fn is_configured(&self) -> bool {
+        self.enabled && !self.model.trim().is_empty()
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_url: default_ollama_base_url(),
+            model: String::new(),
+        }
+    }
+}
+
+fn default_ollama_base_url() -> String {
+    OllamaConfig::DEFAULT_BASE_URL.to_string()
+}
+
+/// 自定义的 OpenAI 兼容提供商（LiteLLM、one-api、自建网关等）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomProvider {
+    /// 名称，同时作为提供商 ID（不能与内置提供商重名）
+    pub name: String,
+    /// API 基础 URL（请求发送到 `{base_url}/chat/completions`）
+    pub base_url: String,
+    /// 模型名称
+    pub model: String,
+    /// 额外的请求头
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 混淆后的 API 密钥（网关不需要密钥时为空）
+    #[serde(default)]
+    pub api_key: Option<String>,
 }
 
 /// API 测试状态
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ts_rs::TS)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiTestStatus {
     #[serde(default)]
@@ -144,19 +935,71 @@ pub struct ApiTestStatus {
     pub deepseek: bool,
     #[serde(default)]
     pub volcengine: bool,
+    #[serde(default)]
+    pub anthropic: bool,
+    #[serde(default)]
+    pub azure: bool,
+    #[serde(default)]
+    pub ollama: bool,
+    /// 自定义提供商（按名称）
+    #[serde(default)]
+    pub custom: BTreeMap<String, bool>,
+}
+
+impl ApiTestStatus {
+    /// 指定提供商是否测试通过
+    pub fn get(&self, provider: &str) -> bool {
+        match provider {
+            "openai" => self.openai,
+            "gemini" => self.gemini,
+            "deepseek" => self.deepseek,
+            "volcengine" => self.volcengine,
+            "anthropic" => self.anthropic,
+            "azure" => self.azure,
+            "ollama" => self.ollama,
+            _ => self.custom.get(provider).copied().unwrap_or(false),
+        }
+    }
+
+    /// 记录指定提供商的测试结果
+    pub fn set(&mut self, provider: &str, tested: bool) {
+        match provider {
+            "openai" => self.openai = tested,
+            "gemini" => self.gemini = tested,
+            "deepseek" => self.deepseek = tested,
+            "volcengine" => self.volcengine = tested,
+            "anthropic" => self.anthropic = tested,
+            "azure" => self.azure = tested,
+            "ollama" => self.ollama = tested,
+            _ if tested => {
+                self.custom.insert(provider.to_string(), true);
+            }
+            _ => {
+                self.custom.remove(provider);
+            }
+        }
+    }
 }
 
 /// 反馈内容
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum FeedbackContent {
     Text { text: String },
-    Image { data: String, mime_type: String },
+    /// 图片内容，`attachment_id` 引用附件存储中的图片时 `data` 可以为空
+    Image {
+        #[serde(default)]
+        data: String,
+        mime_type: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attachment_id: Option<String>,
+    },
     FileReference { display_name: String, path: String },
 }
 
 /// 反馈数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[ts(export)]
 pub struct FeedbackData {
     pub content: Vec<FeedbackContent>,
 }
@@ -177,10 +1020,62 @@ pub struct ProcessedImage {
     pub width: u32,
     pub height: u32,
     pub size: usize,
+    /// 处理度量（原图信息、压缩比、耗时等）
+    #[serde(default)]
+    pub metrics: Option<ImageMetrics>,
+    /// 附件存储中的 ID（内容哈希）
+    #[serde(default)]
+    pub attachment_id: Option<String>,
+}
+
+/// 截图缩放预设
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, ts_rs::TS)]
+#[serde(tag = "preset", rename_all = "kebab-case")]
+pub enum ScalePreset {
+    /// 原始分辨率
+    #[default]
+    Original,
+    /// 缩放至 50%
+    Half,
+    /// 长边不超过 1280 像素
+    Fit1280,
+    /// 按图片 token 预算缩放（约 750 像素/token）
+    FitTokenBudget { tokens: u32 },
+}
+
+impl ScalePreset {
+    /// 每个图片 token 大约对应的像素数
+    pub const PIXELS_PER_TOKEN: f64 = 750.0;
+
+    /// 计算缩放后的尺寸（只缩小不放大，保持宽高比）
+    pub fn target_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let ratio = match *self {
+            ScalePreset::Original => 1.0,
+            ScalePreset::Half => 0.5,
+            ScalePreset::Fit1280 => {
+                let long_side = width.max(height) as f64;
+                (1280.0 / long_side).min(1.0)
+            }
+            ScalePreset::FitTokenBudget { tokens } => {
+                let max_pixels = tokens.max(1) as f64 * Self::PIXELS_PER_TOKEN;
+                let pixels = width as f64 * height as f64;
+                (max_pixels / pixels).sqrt().min(1.0)
+            }
+        };
+        
+        if ratio >= 1.0 {
+            return (width, height);
+        }
+        
+        (
+            ((width as f64 * ratio).round() as u32).max(1),
+            ((height as f64 * ratio).round() as u32).max(1),
+        )
+    }
 }
 
 /// 截图区域
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct ScreenRegion {
     pub x: i32,
     pub y: i32,
@@ -188,8 +1083,46 @@ pub struct ScreenRegion {
     pub height: u32,
 }
 
+/// 截图隐私模糊配置
+/// 
+/// 在截图离开后端之前对指定屏幕区域和窗口进行像素化处理
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyBlurConfig {
+    /// 始终模糊的屏幕区域（全局屏幕坐标）
+    #[serde(default)]
+    pub regions: Vec<ScreenRegion>,
+    /// 窗口标题包含这些关键字时模糊整个窗口（不区分大小写）
+    #[serde(default)]
+    pub window_titles: Vec<String>,
+    /// 像素化块大小（像素）
+    #[serde(default = "default_blur_block_size")]
+    pub block_size: u32,
+}
+
+fn default_blur_block_size() -> u32 {
+    16
+}
+
+impl Default for PrivacyBlurConfig {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            window_titles: Vec::new(),
+            block_size: default_blur_block_size(),
+        }
+    }
+}
+
+impl PrivacyBlurConfig {
+    /// 是否配置了任何模糊规则
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty() && self.window_titles.iter().all(|t| t.trim().is_empty())
+    }
+}
+
 /// 文本优化类型配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 #[serde(rename_all = "camelCase")]
 pub struct OptimizationTypeConfig {
     pub id: String,
@@ -199,6 +1132,47 @@ pub struct OptimizationTypeConfig {
     pub prompt: String,
     pub is_system: bool,
     pub enabled: bool,
+    /// 是否置顶（置顶项始终显示在下拉列表最前面）
+    #[serde(default)]
+    pub pinned: bool,
+    /// 排序权重（越小越靠前）
+    #[serde(default)]
+    pub order: i32,
+}
+
+/// 优化类型保存校验结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizationTypeValidation {
+    /// 估算的系统提示词 token 数
+    pub estimated_tokens: usize,
+    /// 当前配置的 token 预算
+    pub token_budget: usize,
+    /// 是否超出预算
+    pub exceeds_budget: bool,
+    /// 警告信息
+    pub warnings: Vec<String>,
+}
+
+impl OptimizationTypeValidation {
+    /// 根据提示词和预算生成校验结果
+    pub fn check(prompt: &str, token_budget: usize) -> Self {
+        let estimated_tokens = crate::llm::estimate_tokens(prompt);
+        let exceeds_budget = token_budget > 0 && estimated_tokens > token_budget;
+        let mut warnings = Vec::new();
+        if exceeds_budget {
+            warnings.push(format!(
+                "提示词约 {} tokens，超出预算 {} tokens，每次优化调用都会增加成本",
+                estimated_tokens, token_budget
+            ));
+        }
+        Self {
+            estimated_tokens,
+            token_budget,
+            exceeds_budget,
+            warnings,
+        }
+    }
 }
 
 /// 默认提示词类型
@@ -213,6 +1187,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
             prompt: "你是一个专业的文本优化助手。请将用户的输入文本改写为结构化、逻辑清晰的指令。只需要输出优化后的文本，不要包含任何技术参数、函数定义或元数据信息。".to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 0,
         },
         OptimizationTypeConfig {
             id: "prompt-enhance".to_string(),
@@ -228,6 +1204,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 只输出改写结果，不要包含任何技术信息".to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 1,
         },
         // ===== 代码类 =====
         OptimizationTypeConfig {
@@ -250,6 +1228,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 在指出问题的同时，也指出代码优点"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 2,
         },
         OptimizationTypeConfig {
             id: "code-explainer".to_string(),
@@ -273,6 +1253,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 使用通俗语言解释专业术语"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 3,
         },
         OptimizationTypeConfig {
             id: "code-refactor".to_string(),
@@ -295,6 +1277,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 避免过度重构，保持代码简洁和可理解"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 4,
         },
         // ===== 专家类 =====
         OptimizationTypeConfig {
@@ -322,6 +1306,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 代码附加必要注释及依赖说明"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 5,
         },
         OptimizationTypeConfig {
             id: "frontend-expert".to_string(),
@@ -344,6 +1330,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 保持模块化结构，避免嵌套混乱与硬编码"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 6,
         },
         OptimizationTypeConfig {
             id: "backend-expert".to_string(),
@@ -367,6 +1355,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 遇到不明确的需求主动询问"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 7,
         },
         OptimizationTypeConfig {
             id: "mobile-expert".to_string(),
@@ -390,6 +1380,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 兼顾初学者和高级开发者"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 8,
         },
         OptimizationTypeConfig {
             id: "architect".to_string(),
@@ -413,6 +1405,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 - 如需图示结构，使用 Mermaid 格式生成架构图"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 9,
         },
         OptimizationTypeConfig {
             id: "tech-doc".to_string(),
@@ -437,6 +1431,8 @@ fn default_optimization_types() -> Vec<OptimizationTypeConfig> {
 输出专业、规范的技术文档。"#.to_string(),
             is_system: true,
             enabled: true,
+            pinned: false,
+            order: 10,
         },
     ]
 }
@@ -0,0 +1,216 @@
+//! 弹窗窗口位置和大小
+//!
+//! 关闭弹窗时记录窗口大小、所在显示器以及相对该显示器左上角的位置，下次打开 MCP 弹窗时恢复。
+//! 设置了首选显示器时窗口总是出现在该显示器上；目标显示器已断开时使用默认位置（居中）。
+
+use tauri::{AppHandle, LogicalSize, Manager, PhysicalPosition, WebviewWindow};
+
+use crate::config;
+use crate::types::WindowGeometryConfig;
+
+/// 默认窗口大小（逻辑像素）
+pub const DEFAULT_WIDTH: f64 = 1024.0;
+pub const DEFAULT_HEIGHT: f64 = 800.0;
+/// 最小窗口大小（逻辑像素）
+pub const MIN_WIDTH: f64 = 400.0;
+pub const MIN_HEIGHT: f64 = 300.0;
+
+/// 显示器区域（逻辑像素）
+#[derive(Debug, Clone, PartialEq)]
+struct MonitorArea {
+    name: String,
+    width: f64,
+    height: f64,
+}
+
+/// 计算出的窗口位置：目标显示器下标、相对显示器左上角的位置和窗口大小（逻辑像素）
+#[derive(Debug, Clone, PartialEq)]
+struct Placement {
+    monitor: usize,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// 按配置计算窗口位置，没有可用的目标显示器时返回 None
+fn place(config: &WindowGeometryConfig, monitors: &[MonitorArea]) -> Option<Placement> {
+    let find = |name: &Option<String>| name.as_ref().and_then(|name| monitors.iter().position(|m| &m.name == name));
+    let saved_monitor = if config.remember { find(&config.monitor) } else { None };
+    let monitor = find(&config.preferred_monitor).or(saved_monitor)?;
+    let area = &monitors[monitor];
+
+    let (width, height) = match (config.remember, config.width, config.height) {
+        (true, Some(width), Some(height)) => (width, height),
+        _ => (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+    };
+    let width = width.max(MIN_WIDTH).min(area.width);
+    let height = height.max(MIN_HEIGHT).min(area.height);
+
+    // 只有上次就在这个显示器上时才恢复位置，否则在目标显示器上居中
+    let (x, y) = match (saved_monitor == Some(monitor), config.x, config.y) {
+        (true, Some(x), Some(y)) => (
+            x.clamp(0.0, (area.width - width).max(0.0)),
+            y.clamp(0.0, (area.height - height).max(0.0)),
+        ),
+        _ => ((area.width - width) / 2.0, (area.height - height) / 2.0),
+    };
+    Some(Placement { monitor, x, y, width, height })
+}
+
+/// 按配置恢复窗口位置和大小（创建窗口后、显示之前调用）
+pub fn restore(window: &WebviewWindow, config: &WindowGeometryConfig) {
+    let Ok(monitors) = window.available_monitors() else { return };
+    let areas: Vec<MonitorArea> = monitors
+        .iter()
+        .map(|m| {
+            let size = m.size().to_logical::<f64>(m.scale_factor());
+            MonitorArea {
+                name: m.name().cloned().unwrap_or_default(),
+                width: size.width,
+                height: size.height,
+            }
+        })
+        .collect();
+    let Some(placement) = place(config, &areas) else { return };
+
+    let monitor = &monitors[placement.monitor];
+    let scale = monitor.scale_factor();
+    let position = PhysicalPosition::new(
+        monitor.position().x + (placement.x * scale).round() as i32,
+        monitor.position().y + (placement.y * scale).round() as i32,
+    );
+    let _ = window.set_size(LogicalSize::new(placement.width, placement.height));
+    let _ = window.set_position(position);
+    log::info!(
+        "[window_geometry] 窗口已放置到显示器 {}（{}x{}）",
+        areas[placement.monitor].name, placement.width, placement.height
+    );
+}
+
+/// 读取窗口当前的位置和大小；最小化、最大化或无法确定所在显示器时返回 None
+pub fn capture(window: &WebviewWindow) -> Option<WindowGeometryConfig> {
+    if window.is_minimized().unwrap_or(false) || window.is_maximized().unwrap_or(false) {
+        return None;
+    }
+    let monitor = window.current_monitor().ok()??;
+    let scale = monitor.scale_factor();
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?.to_logical::<f64>(window.scale_factor().ok()?);
+    Some(WindowGeometryConfig {
+        monitor: monitor.name().cloned(),
+        x: Some(f64::from(position.x - monitor.position().x) / scale),
+        y: Some(f64::from(position.y - monitor.position().y) / scale),
+        width: Some(size.width),
+        height: Some(size.height),
+        ..WindowGeometryConfig::default()
+    })
+}
+
+/// 记录主窗口当前的位置和大小（关闭窗口前调用）
+pub async fn save_main_window(app_handle: &AppHandle) {
+    let Some(window) = app_handle.get_webview_window("main") else { return };
+    if let Some(geometry) = capture(&window) {
+        save(app_handle, geometry).await;
+    }
+}
+
+/// 保存 [`capture`] 读取的位置和大小（保留首选显示器设置）
+pub async fn save(app_handle: &AppHandle, current: WindowGeometryConfig) {
+    let mut app_config = match config::load_config(app_handle).await {
+        Ok(app_config) => app_config,
+        Err(e) => {
+            log::warn!("[window_geometry] 读取配置失败: {}", e);
+            return;
+        }
+    };
+    let geometry = &mut app_config.window_geometry;
+    if !geometry.remember {
+        return;
+    }
+    let updated = WindowGeometryConfig {
+        remember: true,
+        preferred_monitor: geometry.preferred_monitor.clone(),
+        ..current
+    };
+    if *geometry == updated {
+        return;
+    }
+    *geometry = updated;
+    if let Err(e) = config::save_config(app_handle, &app_config).await {
+        log::warn!("[window_geometry] 保存窗口位置失败: {}", e);
+    }
+}
+
+/// 可选的显示器名称
+pub fn monitor_names(window: &WebviewWindow) -> Vec<String> {
+    window
+        .available_monitors()
+        .map(|monitors| monitors.iter().filter_map(|m| m.name().cloned()).collect())
+        .unwrap_or_default()
+}
+
+/// 窗口当前所在的显示器名称
+pub fn current_monitor_name(window: &WebviewWindow) -> Option<String> {
+    window.current_monitor().ok()??.name().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitors() -> Vec<MonitorArea> {
+        vec![
+            MonitorArea { name: "built-in".to_string(), width: 1440.0, height: 900.0 },
+            MonitorArea { name: "external".to_string(), width: 2560.0, height: 1440.0 },
+        ]
+    }
+
+    fn saved(monitor: &str) -> WindowGeometryConfig {
+        WindowGeometryConfig {
+            monitor: Some(monitor.to_string()),
+            x: Some(100.0),
+            y: Some(50.0),
+            width: Some(800.0),
+            height: Some(600.0),
+            ..WindowGeometryConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_place_restores_saved_geometry() {
+        let placement = place(&saved("external"), &monitors()).unwrap();
+        assert_eq!(placement, Placement { monitor: 1, x: 100.0, y: 50.0, width: 800.0, height: 600.0 });
+
+        // 显示器已断开
+        assert_eq!(place(&saved("projector"), &monitors()), None);
+        // 关闭记录
+        let config = WindowGeometryConfig { remember: false, ..saved("external") };
+        assert_eq!(place(&config, &monitors()), None);
+    }
+
+    #[test]
+    fn test_place_on_preferred_monitor() {
+        // 上次在其他显示器上：在首选显示器上居中
+        let config = WindowGeometryConfig { preferred_monitor: Some("built-in".to_string()), ..saved("external") };
+        let placement = place(&config, &monitors()).unwrap();
+        assert_eq!(placement, Placement { monitor: 0, x: 320.0, y: 150.0, width: 800.0, height: 600.0 });
+
+        // 首选显示器已断开时使用上次的显示器
+        let config = WindowGeometryConfig { preferred_monitor: Some("projector".to_string()), ..saved("external") };
+        assert_eq!(place(&config, &monitors()).unwrap().monitor, 1);
+    }
+
+    #[test]
+    fn test_place_keeps_window_on_screen() {
+        let config = WindowGeometryConfig {
+            x: Some(1400.0),
+            y: Some(-20.0),
+            width: Some(3000.0),
+            height: Some(100.0),
+            ..saved("built-in")
+        };
+        let placement = place(&config, &monitors()).unwrap();
+        assert_eq!(placement, Placement { monitor: 0, x: 0.0, y: 0.0, width: 1440.0, height: MIN_HEIGHT });
+    }
+}
@@ -0,0 +1,100 @@
+//! 窗口隐藏看门狗模块
+//! 
+//! `capture_screen_hidden` 隐藏窗口后依赖前端调用 `show_window` 恢复，
+//! 如果前端出错，窗口将一直不可见。看门狗在超时后自动恢复窗口。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 窗口隐藏后自动恢复的默认超时时间
+pub const DEFAULT_HIDDEN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 窗口隐藏看门狗
+/// 
+/// 每次隐藏窗口时 `arm` 得到一个令牌，恢复窗口时 `disarm` 使所有旧令牌失效。
+/// 超时任务只有在令牌仍然有效时才会恢复窗口。
+#[derive(Clone)]
+pub struct HiddenWindowWatchdog {
+    generation: Arc<AtomicU64>,
+    armed: Arc<AtomicU64>,
+    timeout: Duration,
+}
+
+impl Default for HiddenWindowWatchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_HIDDEN_TIMEOUT)
+    }
+}
+
+impl HiddenWindowWatchdog {
+    /// 创建指定超时时间的看门狗
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            generation: Arc::new(AtomicU64::new(0)),
+            armed: Arc::new(AtomicU64::new(0)),
+            timeout,
+        }
+    }
+
+    /// 超时时间
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// 窗口已隐藏，启动看门狗并返回令牌
+    pub fn arm(&self) -> u64 {
+        let token = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.armed.store(token, Ordering::SeqCst);
+        token
+    }
+
+    /// 窗口已恢复，取消所有未到期的看门狗
+    pub fn disarm(&self) {
+        self.armed.store(0, Ordering::SeqCst);
+    }
+
+    /// 等待超时，返回是否需要由看门狗恢复窗口
+    /// 
+    /// 令牌仍有效时会同时解除看门狗，保证窗口只被恢复一次
+    pub async fn expire(&self, token: u64) -> bool {
+        tokio::time::sleep(self.timeout).await;
+        self.armed
+            .compare_exchange(token, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_expire_restores_when_frontend_never_shows() {
+        let watchdog = HiddenWindowWatchdog::new(Duration::from_millis(10));
+        let token = watchdog.arm();
+        
+        assert!(watchdog.expire(token).await);
+        // 只恢复一次
+        assert!(!watchdog.expire(token).await);
+    }
+
+    #[tokio::test]
+    async fn test_rearm_invalidates_previous_token() {
+        let watchdog = HiddenWindowWatchdog::new(Duration::from_millis(10));
+        let first = watchdog.arm();
+        let second = watchdog.arm();
+        
+        assert!(!watchdog.expire(first).await);
+        assert!(watchdog.expire(second).await);
+    }
+
+    #[tokio::test]
+    async fn test_expire_skipped_after_show() {
+        let watchdog = HiddenWindowWatchdog::new(Duration::from_millis(10));
+        let token = watchdog.arm();
+        watchdog.disarm();
+        
+        assert!(!watchdog.expire(token).await);
+    }
+}
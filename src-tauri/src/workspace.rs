@@ -0,0 +1,112 @@
+//! 工作区设置模块
+//!
+//! 按 MCP 客户端（Agent）名称区分偏好设置，例如 Cursor 中的 Agent 和 CI 中的 Agent
+//! 使用同一个服务器时，可以拥有不同的快捷选项、超时时间和自动回复规则。
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_processor::AttachmentFormat;
+use crate::popup::Priority;
+use crate::types::{AppConfig, CustomOption};
+
+/// 单个工作区的设置（未设置的项沿用全局配置）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceConfig {
+    /// 匹配的 Agent 名称（MCP 客户端名称，不区分大小写，支持 `*` 结尾的前缀匹配）
+    pub agent: String,
+    #[serde(default)]
+    pub custom_options_enabled: Option<bool>,
+    #[serde(default)]
+    pub custom_options: Option<Vec<CustomOption>>,
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub feedback_timeout_secs: Option<u64>,
+    /// 默认优先级（请求未指定时使用）
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// 自动回复：设置后不再弹窗，直接以该文本作为用户反馈返回
+    #[serde(default)]
+    pub auto_reply: Option<String>,
+    /// 截图附件格式（客户端无法显示 WebP/AVIF 时设为 PNG）
+    #[serde(default)]
+    pub attachment_format: Option<AttachmentFormat>,
+}
+
+impl WorkspaceConfig {
+    /// 是否匹配指定的 Agent 名称
+    pub fn matches(&self, agent: &str) -> bool {
+        let pattern = self.agent.trim().to_lowercase();
+        let agent = agent.trim().to_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => agent.starts_with(prefix),
+            None => !pattern.is_empty() && pattern == agent,
+        }
+    }
+
+    /// 用工作区设置覆盖全局配置
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(enabled) = self.custom_options_enabled {
+            config.custom_options_enabled = enabled;
+        }
+        if let Some(options) = &self.custom_options {
+            config.custom_options = options.clone();
+        }
+        if let Some(timeout) = self.feedback_timeout_secs {
+            config.feedback_timeout_secs = timeout;
+        }
+        if let Some(format) = self.attachment_format {
+            config.image_encoder.attachment = format;
+        }
+    }
+}
+
+/// 查找与 Agent 名称匹配的工作区（精确匹配优先于前缀匹配）
+pub fn find_workspace<'a>(workspaces: &'a [WorkspaceConfig], agent: &str) -> Option<&'a WorkspaceConfig> {
+    workspaces
+        .iter()
+        .filter(|w| w.matches(agent))
+        .max_by_key(|w| !w.agent.trim().ends_with('*'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(agent: &str) -> WorkspaceConfig {
+        WorkspaceConfig {
+            agent: agent.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_workspace_prefers_exact_match() {
+        let workspaces = vec![workspace("cursor*"), workspace("Cursor-Agent"), workspace("ci-runner")];
+
+        assert_eq!(find_workspace(&workspaces, "cursor-agent").unwrap().agent, "Cursor-Agent");
+        assert_eq!(find_workspace(&workspaces, "cursor-vscode").unwrap().agent, "cursor*");
+        assert_eq!(find_workspace(&workspaces, "CI-Runner").unwrap().agent, "ci-runner");
+        assert!(find_workspace(&workspaces, "claude-desktop").is_none());
+        assert!(find_workspace(&[workspace("")], "anything").is_none());
+    }
+
+    #[test]
+    fn test_apply_overrides_only_set_fields() {
+        let mut config = AppConfig::default();
+        config.image_encoder.attachment = AttachmentFormat::Webp;
+        let original_options = config.custom_options.len();
+        let ci = WorkspaceConfig {
+            feedback_timeout_secs: Some(30),
+            custom_options_enabled: Some(true),
+            attachment_format: Some(AttachmentFormat::Png),
+            ..workspace("ci-runner")
+        };
+
+        ci.apply(&mut config);
+        assert_eq!(config.feedback_timeout_secs, 30);
+        assert!(config.custom_options_enabled);
+        assert_eq!(config.custom_options.len(), original_options);
+        assert_eq!(config.image_encoder.attachment, AttachmentFormat::Png);
+    }
+}
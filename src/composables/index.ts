@@ -17,7 +17,9 @@ export {
   useTextOptimization,
   type OptimizationType,
   type TextOptimizationResult,
-  type OptimizationHistoryItem
+  type OptimizationHistoryItem,
+  type TextRange,
+  type SelectionReplacement
 } from './useTextOptimization'
 export { 
   useApiKeys, 
@@ -62,3 +64,7 @@ export {
   type DragDropCallbacks,
   type DragDropReturn
 } from './useDragDrop'
+export {
+  useTextMetrics,
+  type TextMetrics
+} from './useTextMetrics'
@@ -9,9 +9,13 @@
 
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
+import type { AzureOpenAiConfig, CustomProvider, OllamaConfig } from '@/types'
 
-// 支持的 AI 提供商
-export type ApiProvider = 'openai' | 'gemini' | 'deepseek' | 'volcengine'
+// 内置的 AI 提供商
+export type ApiProvider = 'openai' | 'gemini' | 'deepseek' | 'volcengine' | 'anthropic'
+
+// 提供商 ID：内置提供商、Azure OpenAI、本地模型或自定义提供商名称
+export type ProviderId = ApiProvider | 'azure' | 'ollama' | string
 
 // 提供商配置信息
 export interface ProviderInfo {
@@ -47,6 +51,12 @@ export const PROVIDERS: ProviderInfo[] = [
     description: '豆包大模型等',
     placeholder: 'your-api-key',
   },
+  {
+    id: 'anthropic',
+    name: 'Anthropic Claude',
+    description: 'Claude Haiku, Claude Sonnet 等模型',
+    placeholder: 'sk-ant-...',
+  },
 ]
 
 /**
@@ -58,6 +68,9 @@ export function useApiKeys() {
   const error = ref<string | null>(null)
   const configuredProviders = ref<string[]>([])
   const maskedKeys = ref<Record<string, string>>({})
+  const customProviders = ref<CustomProvider[]>([])
+  const azureConfig = ref<AzureOpenAiConfig | null>(null)
+  const ollamaConfig = ref<OllamaConfig | null>(null)
 
   /**
    * 保存 API 密钥
@@ -65,7 +78,7 @@ export function useApiKeys() {
    * @param provider - 提供商 ID
    * @param apiKey - API 密钥
    */
-  async function saveApiKey(provider: ApiProvider, apiKey: string): Promise<void> {
+  async function saveApiKey(provider: ProviderId, apiKey: string): Promise<void> {
     isLoading.value = true
     error.value = null
     
@@ -88,7 +101,7 @@ export function useApiKeys() {
    * @param provider - 提供商 ID
    * @returns API 密钥或 null
    */
-  async function getApiKey(provider: ApiProvider): Promise<string | null> {
+  async function getApiKey(provider: ProviderId): Promise<string | null> {
     isLoading.value = true
     error.value = null
     
@@ -108,7 +121,7 @@ export function useApiKeys() {
    * 
    * @param provider - 提供商 ID
    */
-  async function deleteApiKey(provider: ApiProvider): Promise<void> {
+  async function deleteApiKey(provider: ProviderId): Promise<void> {
     isLoading.value = true
     error.value = null
     
@@ -131,7 +144,7 @@ export function useApiKeys() {
    * @param provider - 提供商 ID
    * @returns 是否存在
    */
-  async function hasApiKey(provider: ApiProvider): Promise<boolean> {
+  async function hasApiKey(provider: ProviderId): Promise<boolean> {
     try {
       return await invoke<boolean>('has_api_key', { provider })
     } catch (e) {
@@ -157,7 +170,7 @@ export function useApiKeys() {
    * 
    * @param provider - 提供商 ID
    */
-  async function refreshMaskedKey(provider: ApiProvider): Promise<void> {
+  async function refreshMaskedKey(provider: ProviderId): Promise<void> {
     try {
       const masked = await invoke<string | null>('get_masked_api_key', { provider })
       if (masked) {
@@ -185,19 +198,22 @@ export function useApiKeys() {
   async function initialize(): Promise<void> {
     await refreshConfiguredProviders()
     await refreshAllMaskedKeys()
+    await refreshCustomProviders()
+    await refreshAzureConfig()
+    await refreshOllamaConfig()
   }
 
   /**
    * 检查提供商是否已配置
    */
-  function isProviderConfigured(provider: ApiProvider): boolean {
+  function isProviderConfigured(provider: ProviderId): boolean {
     return configuredProviders.value.includes(provider)
   }
 
   /**
    * 获取提供商的掩码密钥
    */
-  function getMaskedKey(provider: ApiProvider): string | undefined {
+  function getMaskedKey(provider: ProviderId): string | undefined {
     return maskedKeys.value[provider]
   }
 
@@ -207,7 +223,7 @@ export function useApiKeys() {
    * @param provider - 提供商 ID
    * @returns 测试结果消息
    */
-  async function testConnection(provider: ApiProvider): Promise<string> {
+  async function testConnection(provider: ProviderId): Promise<string> {
     isLoading.value = true
     error.value = null
     
@@ -223,12 +239,144 @@ export function useApiKeys() {
     }
   }
 
+  /**
+   * 获取自定义提供商列表（密钥为掩码版本）
+   */
+  async function refreshCustomProviders(): Promise<void> {
+    try {
+      customProviders.value = await invoke<CustomProvider[]>('get_custom_providers')
+    } catch (e) {
+      console.error('Failed to get custom providers:', e)
+      customProviders.value = []
+    }
+  }
+
+  /**
+   * 添加或更新自定义提供商
+   * 
+   * @param provider - 提供商配置，apiKey 为空时保留已保存的密钥
+   */
+  async function saveCustomProvider(provider: CustomProvider): Promise<void> {
+    isLoading.value = true
+    error.value = null
+    
+    try {
+      await invoke('save_custom_provider', { provider })
+      await refreshCustomProviders()
+      await refreshConfiguredProviders()
+    } catch (e) {
+      error.value = e instanceof Error ? e.message : String(e)
+      throw e
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  /**
+   * 删除自定义提供商
+   * 
+   * @param name - 提供商名称
+   */
+  async function deleteCustomProvider(name: string): Promise<void> {
+    isLoading.value = true
+    error.value = null
+    
+    try {
+      await invoke('delete_custom_provider', { name })
+      await refreshCustomProviders()
+      await refreshConfiguredProviders()
+    } catch (e) {
+      error.value = e instanceof Error ? e.message : String(e)
+      throw e
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  /**
+   * 获取 Azure OpenAI 设置（密钥为掩码版本）
+   */
+  async function refreshAzureConfig(): Promise<void> {
+    try {
+      azureConfig.value = await invoke<AzureOpenAiConfig>('get_azure_openai_config')
+    } catch (e) {
+      console.error('Failed to get Azure OpenAI config:', e)
+      azureConfig.value = null
+    }
+  }
+
+  /**
+   * 保存 Azure OpenAI 设置
+   * 
+   * @param azure - 终结点、部署名称和 API 版本，apiKey 为空时保留已保存的密钥
+   */
+  async function saveAzureConfig(azure: AzureOpenAiConfig): Promise<void> {
+    isLoading.value = true
+    error.value = null
+    
+    try {
+      await invoke('save_azure_openai_config', { azure })
+      await refreshAzureConfig()
+      await refreshConfiguredProviders()
+    } catch (e) {
+      error.value = e instanceof Error ? e.message : String(e)
+      throw e
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  /**
+   * 获取本地模型（Ollama）设置
+   */
+  async function refreshOllamaConfig(): Promise<void> {
+    try {
+      ollamaConfig.value = await invoke<OllamaConfig>('get_ollama_config')
+    } catch (e) {
+      console.error('Failed to get local model config:', e)
+      ollamaConfig.value = null
+    }
+  }
+
+  /**
+   * 保存本地模型（Ollama）设置
+   * 
+   * @param ollama - 本地模型设置，baseUrl 为空时使用默认地址
+   */
+  async function saveOllamaConfig(ollama: OllamaConfig): Promise<void> {
+    isLoading.value = true
+    error.value = null
+    
+    try {
+      await invoke('save_ollama_config', { ollama })
+      await refreshOllamaConfig()
+      await refreshConfiguredProviders()
+    } catch (e) {
+      error.value = e instanceof Error ? e.message : String(e)
+      throw e
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  /**
+   * 获取本地已下载的模型列表
+   * 
+   * @param baseUrl - API 地址，省略时使用已保存的地址
+   */
+  async function listOllamaModels(baseUrl?: string): Promise<string[]> {
+    return await invoke<string[]>('list_ollama_models', { baseUrl: baseUrl ?? null })
+  }
+
   return {
     // 状态
     isLoading,
     error,
     configuredProviders,
     maskedKeys,
+    customProviders,
+    azureConfig,
+    ollamaConfig,
     
     // 方法
     saveApiKey,
@@ -242,6 +390,14 @@ export function useApiKeys() {
     isProviderConfigured,
     getMaskedKey,
     testConnection,
+    refreshCustomProviders,
+    saveCustomProvider,
+    deleteCustomProvider,
+    refreshAzureConfig,
+    saveAzureConfig,
+    refreshOllamaConfig,
+    saveOllamaConfig,
+    listOllamaModels,
     
     // 常量
     PROVIDERS,
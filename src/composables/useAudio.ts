@@ -1,6 +1,7 @@
 import { computed, ref } from 'vue'
 import { useConfigStore } from '@/stores/config'
 import { invoke } from '@tauri-apps/api/core'
+import type { Priority } from '@/types'
 
 /**
  * 音频通知 Composable
@@ -29,8 +30,10 @@ export function useAudio() {
    * 
    * Requirement 12.1: WHEN the Feedback_Window opens THEN the Audio_Notifier SHALL play a notification sound
    * Requirement 12.4: IF audio playback fails THEN the Audio_Notifier SHALL silently continue without interrupting the workflow
+   *
+   * @param priority MCP 请求优先级，critical 时提高音量并重复播放
    */
-  async function playNotification(): Promise<void> {
+  async function playNotification(priority?: Priority): Promise<void> {
     // 如果音频被禁用，直接返回
     if (!audioEnabled.value) {
       return
@@ -41,9 +44,10 @@ export function useAudio() {
 
     try {
       // 调用 Tauri 命令播放音频
-      // 如果 audioFile 为空或 undefined，后端会使用默认音频
+      // 如果 audioFile 为空，后端会使用默认音频
       await invoke('play_notification_sound', { 
-        soundPath: audioFile.value || null 
+        soundPath: audioFile.value || null,
+        priority: priority ?? null
       })
     } catch (e) {
       // Requirement 12.4: 静默处理错误，不中断工作流
@@ -68,7 +72,7 @@ export function useAudio() {
    * 
    * Requirement 12.3: WHEN in the settings page THEN the Config_Manager SHALL allow selecting a custom audio file
    */
-  function setAudioFile(path: string | undefined): void {
+  function setAudioFile(path: string | null): void {
     configStore.setAudioFile(path)
   }
 
@@ -107,7 +111,7 @@ export function useAudio() {
    * 
    * 用于设置页面测试音频效果
    */
-  async function testPlaySound(path?: string): Promise<{ success: boolean; error?: string }> {
+  async function testPlaySound(path?: string | null): Promise<{ success: boolean; error?: string }> {
     isPlaying.value = true
     lastError.value = null
 
@@ -129,7 +133,7 @@ export function useAudio() {
    * 清除自定义音频文件（使用默认音频）
    */
   function clearAudioFile(): void {
-    configStore.setAudioFile(undefined)
+    configStore.setAudioFile(null)
   }
 
   return {
@@ -1,7 +1,7 @@
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
 import { readFile } from '@tauri-apps/plugin-fs'
-import type { ImagePreviewData } from '@/types'
+import type { ImageMetrics, ImagePreviewData } from '@/types'
 
 // 支持的图片 MIME 类型
 const SUPPORTED_IMAGE_TYPES = [
@@ -152,15 +152,26 @@ export function useImageHandler() {
    * 处理图片数据（调用后端进行压缩和转换）
    * Requirement 3.6, 3.7, 3.8
    */
-  async function processImage(imageData: number[]): Promise<{ data: string; mimeType: string; size: number }> {
+  async function processImage(
+    imageData: number[]
+  ): Promise<{ data: string; mimeType: string; size: number; metrics: ImageMetrics | null; attachmentId: string | null }> {
     try {
-      const result = await invoke<{ data: string; mime_type: string; size: number }>('process_image', {
-        imageData
-      })
+      const result = await invoke<{
+        data: string
+        mime_type: string
+        size: number
+        metrics: ImageMetrics | null
+        attachment_id: string | null
+      }>(
+        'process_image',
+        { imageData }
+      )
       return {
         data: result.data,
         mimeType: result.mime_type,
-        size: result.size
+        size: result.size,
+        metrics: result.metrics,
+        attachmentId: result.attachment_id
       }
     } catch (err) {
       // 如果后端处理失败，返回原始数据
@@ -194,7 +205,9 @@ export function useImageHandler() {
         mimeType: processed.mimeType,
         width: dimensions.width,
         height: dimensions.height,
-        size: processed.size
+        size: processed.size,
+        metrics: processed.metrics,
+        attachmentId: processed.attachmentId
       }
     } catch {
       // 后端处理失败，使用前端处理
@@ -249,7 +262,9 @@ export function useImageHandler() {
         mimeType: processed.mimeType,
         width: dimensions.width,
         height: dimensions.height,
-        size: processed.size
+        size: processed.size,
+        metrics: processed.metrics,
+        attachmentId: processed.attachmentId
       }
     } catch {
       // 后端处理失败，使用前端处理
@@ -383,7 +398,9 @@ export function useImageHandler() {
         mimeType: processed.mimeType,
         width: dimensions.width,
         height: dimensions.height,
-        size: processed.size
+        size: processed.size,
+        metrics: processed.metrics,
+        attachmentId: processed.attachmentId
       }
     } catch {
       // 后端处理失败，使用前端处理
@@ -1,48 +1,55 @@
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
+import type { ChangeRequest, FileReferenceData, ImageData, JsonValue, OptionDetail, PopupRequest, PopupResponse } from '@/types'
 
-// MCP 请求类型
-export interface PopupRequest {
-  id: string
-  message: string | null
-  full_response: string | null
-  predefined_options: string[] | null
-  created_at: string
-}
+// 与 Rust 对应的弹窗类型由 ts-rs 生成，从 @/types 重新导出
+export type {
+  ActionClass,
+  AgentAttachment,
+  ChangeRequest,
+  ChangeSeverity,
+  ClientInfo,
+  FileReferenceData,
+  ImageData,
+  OptionHotkey,
+  PopupRequest,
+  PopupResponse,
+  PopupStrings
+} from '@/types'
 
-// MCP 响应类型
-export interface PopupResponse {
-  request_id: string
-  user_input: string | null
-  selected_options: string[]
-  images: ImageData[]
-  file_references: FileReferenceData[]
-  cancelled: boolean
-}
+// 前端发送的响应：后端带 #[serde(default)] 的字段可以省略，写入响应时补全
+export type PopupResponseDraft = Pick<PopupResponse, 'request_id' | 'user_input' | 'selected_options' | 'images' | 'cancelled'> &
+  Partial<PopupResponse>
 
-// 图片数据类型
-export interface ImageData {
-  data: string
-  mime_type: string
+// 已打开弹窗的更新（Agent 在用户作答前补充了信息）
+export interface PopupUpdate {
+  request_id: string
+  revision: number
+  clarification: string | null
+  add_options: OptionDetail[]
 }
 
-// 文件引用数据类型
-export interface FileReferenceData {
-  display_name: string
-  path: string
-  is_directory: boolean
-}
+// GUI 进程结果，决定 exit_app 的退出码（服务端没有收到响应时据此构造结果）
+export type PopupOutcome = 'submitted' | 'cancelled' | 'timed_out' | 'error' | 'incompatible' | 'auto_selected'
 
 // CLI 参数类型
 export interface CliArgs {
   mcp_request_file: string | null
   mcp_mode: boolean
+  watch_parent?: boolean
+  daemon?: boolean
 }
 
 // MCP handler 状态
 const mcpRequest = ref<PopupRequest | null>(null)
 const isMcpMode = ref(false)
 const mcpRequestFile = ref<string | null>(null)
+// 常驻模式：窗口在后台等待服务端发送请求
+const isDaemonMode = ref(false)
+// 当前显示的请求修订号（请求未更新过时为 null）
+const mcpRevision = ref<number | null>(null)
+// 请求更新的处理函数（补取监听注册前到达的更新时使用）
+let requestUpdateHandler: ((update: PopupUpdate) => void) | null = null
 
 /**
  * MCP 请求处理 composable
@@ -55,6 +62,7 @@ export function useMcpHandler() {
     try {
       const args = await invoke<CliArgs>('get_cli_args')
       isMcpMode.value = args.mcp_mode || !!args.mcp_request_file
+      isDaemonMode.value = !!args.daemon
       mcpRequestFile.value = args.mcp_request_file || null
       console.log('MCP mode:', isMcpMode.value, 'Request file:', mcpRequestFile.value)
       return isMcpMode.value
@@ -93,22 +101,97 @@ export function useMcpHandler() {
     userInput: string,
     selectedOptions: string[],
     images: ImageData[],
-    fileReferences: FileReferenceData[] = []
-  ): PopupResponse {
+    fileReferences: FileReferenceData[] = [],
+    autoSelected = false,
+    changeRequests: ChangeRequest[] = [],
+    hotkey: string | null = null
+  ): PopupResponseDraft {
     return {
       request_id: mcpRequest.value?.id || '',
       user_input: userInput.trim() || null,
       selected_options: selectedOptions,
       images,
       file_references: fileReferences,
-      cancelled: false
+      cancelled: false,
+      auto_selected: autoSelected,
+      revision: mcpRevision.value,
+      change_requests: changeRequests,
+      hotkey
+    }
+  }
+
+  /**
+   * 监听 MCP 服务端发来的请求更新，返回取消监听的函数
+   */
+  async function onRequestUpdate(handler: (update: PopupUpdate) => void): Promise<() => void> {
+    const { listen } = await import('@tauri-apps/api/event')
+    requestUpdateHandler = handler
+    const unlisten = await listen<PopupUpdate>('mcp-request-update', event => applyUpdate(event.payload))
+    await replayMissedUpdates()
+    return () => {
+      requestUpdateHandler = null
+      unlisten()
+    }
+  }
+
+  /**
+   * 应用当前请求的更新，已应用过的修订号不再重复应用
+   */
+  function applyUpdate(update: PopupUpdate) {
+    if (!mcpRequest.value || update.request_id !== mcpRequest.value.id) return
+    if (mcpRevision.value !== null && update.revision <= mcpRevision.value) return
+    mcpRevision.value = update.revision
+    console.log('[MCP] 请求已更新，修订号:', update.revision)
+    requestUpdateHandler?.(update)
+  }
+
+  /**
+   * 补取前端开始监听之前到达的更新（GUI 启动或收到新请求期间发送的更新不会触发事件）
+   */
+  async function replayMissedUpdates(): Promise<void> {
+    if (!mcpRequest.value || !requestUpdateHandler) return
+    try {
+      const updates = await invoke<PopupUpdate[]>('get_request_updates', { requestId: mcpRequest.value.id })
+      updates.forEach(applyUpdate)
+    } catch (error) {
+      console.error('[MCP] 获取请求更新失败:', error)
     }
   }
 
+  /**
+   * 监听多轮会话的下一个问题（弹窗保持打开时由服务端发送），返回取消监听的函数
+   */
+  async function onSessionRequest(handler: (request: PopupRequest) => void | Promise<void>): Promise<() => void> {
+    const { listen } = await import('@tauri-apps/api/event')
+    return listen<{ request_file: string; request: PopupRequest }>('mcp-session-request', event => {
+      mcpRequest.value = event.payload.request
+      mcpRequestFile.value = event.payload.request_file
+      mcpRevision.value = null
+      console.log('[MCP] 会话的下一个问题:', event.payload.request.id)
+      // 显示新请求后补取它的更新
+      void Promise.resolve(handler(event.payload.request)).then(replayMissedUpdates)
+    })
+  }
+
+  /**
+   * 监听常驻模式下请求的结束（服务端超时、取消或窗口被关闭），返回取消监听的函数
+   */
+  async function onRequestDismissed(handler: () => void): Promise<() => void> {
+    const { listen } = await import('@tauri-apps/api/event')
+    return listen<string>('mcp-request-dismissed', event => {
+      if (mcpRequest.value?.id !== event.payload) return
+      console.log('[MCP] 请求已结束:', event.payload)
+      mcpRequest.value = null
+      mcpRequestFile.value = null
+      mcpRevision.value = null
+      handler()
+    })
+  }
+
   /**
    * 发送 MCP 响应
    */
-  async function sendResponse(response: PopupResponse): Promise<void> {
+  async function sendResponse(response: PopupResponseDraft): Promise<void> {
     if (!mcpRequestFile.value || !mcpRequest.value) {
       throw new Error('No MCP request active')
     }
@@ -122,7 +205,10 @@ export function useMcpHandler() {
     try {
       await invoke('write_response_file', {
         filePath: responsePath,
-        response
+        response,
+        endpoint: mcpRequest.value.response_endpoint ?? null,
+        attachmentFormat: mcpRequest.value.attachment_format ?? null,
+        fileKey: mcpRequest.value.file_key ?? null
       })
       console.log('[MCP] 响应文件写入成功:', responsePath)
       
@@ -141,11 +227,26 @@ export function useMcpHandler() {
     userInput: string,
     selectedOptions: string[],
     images: ImageData[],
-    fileReferences: FileReferenceData[] = []
+    fileReferences: FileReferenceData[] = [],
+    autoSelected = false,
+    changeRequests: ChangeRequest[] = [],
+    hotkey: string | null = null
   ): Promise<void> {
-    const response = buildResponse(userInput, selectedOptions, images, fileReferences)
+    const response = buildResponse(userInput, selectedOptions, images, fileReferences, autoSelected, changeRequests, hotkey)
     await sendResponse(response)
-    await closePopup()
+    await finishTurn()
+  }
+
+  /**
+   * 提交表单并关闭
+   */
+  async function submitForm(values: { [key in string]?: JsonValue }): Promise<void> {
+    const response: PopupResponseDraft = {
+      ...buildResponse('', [], []),
+      form_values: values
+    }
+    await sendResponse(response)
+    await finishTurn()
   }
 
   /**
@@ -153,7 +254,7 @@ export function useMcpHandler() {
    */
   async function cancelRequest(): Promise<void> {
     if (mcpRequest.value && mcpRequestFile.value) {
-      const response: PopupResponse = {
+      const response: PopupResponseDraft = {
         request_id: mcpRequest.value.id,
         user_input: null,
         selected_options: [],
@@ -163,18 +264,35 @@ export function useMcpHandler() {
       }
       await sendResponse(response)
     }
-    await closePopup()
+    await finishTurn('cancelled')
+  }
+
+  /**
+   * 完成当前问题：多轮会话中通知服务端并保持弹窗打开，否则关闭弹窗
+   */
+  async function finishTurn(outcome: PopupOutcome = 'submitted'): Promise<void> {
+    const request = mcpRequest.value
+    if (!request?.session_id) {
+      await closePopup(outcome)
+      return
+    }
+    try {
+      await invoke('finish_session_turn', { requestId: request.id })
+    } catch (error) {
+      console.error('[MCP] 通知服务端失败，关闭弹窗:', error)
+      await closePopup(outcome)
+    }
   }
 
   /**
-   * 关闭弹窗
+   * 关闭弹窗，以对应结果的退出码退出
    */
-  async function closePopup(): Promise<void> {
+  async function closePopup(outcome: PopupOutcome = 'submitted'): Promise<void> {
     console.log('[MCP] 准备关闭弹窗...')
     try {
       // 短暂延迟确保响应文件已写入
       await new Promise(resolve => setTimeout(resolve, 50))
-      await invoke('exit_app')
+      await invoke('exit_app', { outcome })
       console.log('[MCP] 退出命令已发送')
     } catch (error) {
       console.error('[MCP] 关闭弹窗失败:', error)
@@ -188,13 +306,19 @@ export function useMcpHandler() {
     mcpRequest,
     isMcpMode,
     mcpRequestFile,
+    mcpRevision,
+    isDaemonMode,
 
     // 方法
     checkMcpMode,
     loadMcpRequest,
     buildResponse,
+    onRequestUpdate,
+    onSessionRequest,
+    onRequestDismissed,
     sendResponse,
     submitFeedback,
+    submitForm,
     cancelRequest,
     closePopup
   }
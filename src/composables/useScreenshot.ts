@@ -8,15 +8,9 @@
 import { ref } from 'vue'
 import { getCurrentWindow } from '@tauri-apps/api/window'
 import { platform } from '@tauri-apps/plugin-os'
+import type { ScreenshotResult } from '@/types'
 
-// 截图结果类型
-export interface ScreenshotResult {
-  data: string      // Base64 编码的图片数据
-  mime_type: string
-  width: number
-  height: number
-  size: number
-}
+export type { ScreenshotResult } from '@/types'
 
 // 选区类型
 export interface SelectionRect {
@@ -102,7 +96,8 @@ export function useScreenshot() {
         mime_type: 'image/png',
         width,
         height,
-        size: fileData.byteLength
+        size: fileData.byteLength,
+        metrics: null
       }
     } catch (e) {
       const errorMsg = e instanceof Error ? e.message : String(e)
@@ -178,7 +173,8 @@ export function useScreenshot() {
       mime_type: 'image/png',
       width,
       height,
-      size: fileData.byteLength
+      size: fileData.byteLength,
+      metrics: null
     }
   }
 
@@ -205,7 +201,8 @@ export function useScreenshot() {
       mime_type: 'image/png',
       width,
       height,
-      size: fileData.byteLength
+      size: fileData.byteLength,
+      metrics: null
     }
   }
 
@@ -284,7 +281,8 @@ export function useScreenshot() {
           mime_type: 'image/png',
           width: canvas.width,
           height: canvas.height,
-          size: Math.round(base64.length * 0.75)
+          size: Math.round(base64.length * 0.75),
+          metrics: null
         })
       }
       img.onerror = () => reject(new Error('图片加载失败'))
@@ -0,0 +1,38 @@
+import { ref, watch, type Ref } from 'vue'
+import { invoke } from '@tauri-apps/api/core'
+
+// 文本统计（与后端 text_metrics 模块一致）
+export interface TextMetrics {
+  chars: number
+  words: number
+  estimatedTokens: number
+  language: string | null
+}
+
+// 输入停顿多久后重新统计
+const DEBOUNCE_MS = 150
+
+/**
+ * 文本统计 composable
+ *
+ * 统一由后端计算字符数、词数、估算 token 数和主要语言，输入时防抖更新
+ */
+export function useTextMetrics(text: Readonly<Ref<string>>) {
+  const metrics = ref<TextMetrics | null>(null)
+  let timer: ReturnType<typeof setTimeout> | null = null
+
+  async function refresh() {
+    try {
+      metrics.value = await invoke<TextMetrics>('get_text_metrics', { text: text.value })
+    } catch (error) {
+      console.error('Failed to get text metrics:', error)
+    }
+  }
+
+  watch(text, () => {
+    if (timer) clearTimeout(timer)
+    timer = setTimeout(refresh, DEBOUNCE_MS)
+  }, { immediate: true })
+
+  return { metrics }
+}
@@ -1,5 +1,6 @@
 import { ref } from 'vue'
-import { invoke } from '@tauri-apps/api/core'
+import { Channel, invoke } from '@tauri-apps/api/core'
+import { ask } from '@tauri-apps/plugin-dialog'
 
 // 支持系统预设和自定义类型
 export type OptimizationType = string
@@ -11,6 +12,57 @@ export interface TextOptimizationResult {
   timestamp: Date
 }
 
+// 选区（UTF-16 偏移，与 textarea 的 selectionStart/selectionEnd 一致）
+export interface TextRange {
+  start: number
+  end: number
+}
+
+// 选区优化结果
+export interface SelectionReplacement {
+  range: TextRange
+  original: string
+  replacement: string
+}
+
+// 流式优化的进度（到目前为止生成的完整文本）
+export interface OptimizeStreamChunk {
+  text: string
+}
+
+// 敏感信息扫描结果
+export interface PiiScanReport {
+  action: 'off' | 'warn' | 'mask'
+  matches: { kind: 'email' | 'phone' | 'idNumber' | 'cardNumber'; start: number; end: number; text: string }[]
+}
+
+const PII_LABELS: Record<PiiScanReport['matches'][number]['kind'], string> = {
+  email: '邮箱',
+  phone: '手机号',
+  idNumber: '证件号',
+  cardNumber: '银行卡号'
+}
+
+/**
+ * 发送给 LLM 前检查敏感信息，提示模式下由用户确认
+ * @returns 用户已确认发送
+ */
+async function confirmPii(text: string): Promise<boolean> {
+  const report = await invoke<PiiScanReport>('scan_pii', { text })
+  if (report.action !== 'warn' || report.matches.length === 0) {
+    return false
+  }
+  const found = report.matches.map(m => `${PII_LABELS[m.kind]}: ${m.text}`).join('\n')
+  const confirmed = await ask(`文本中可能包含敏感信息，将发送给第三方 AI 服务：\n\n${found}\n\n仍要继续吗？`, {
+    title: '敏感信息提示',
+    kind: 'warning'
+  })
+  if (!confirmed) {
+    throw new Error('已取消：文本包含敏感信息')
+  }
+  return true
+}
+
 export interface OptimizationHistoryItem extends TextOptimizationResult {
   id: string
 }
@@ -20,6 +72,8 @@ export function useTextOptimization() {
   const lastResult = ref<TextOptimizationResult | null>(null)
   const error = ref<string | null>(null)
   const history = ref<OptimizationHistoryItem[]>([])
+  // 流式优化时已生成的文本
+  const streamingText = ref('')
 
   /**
    * 优化文本
@@ -33,12 +87,20 @@ export function useTextOptimization() {
 
     isOptimizing.value = true
     error.value = null
+    streamingText.value = ''
 
     try {
-      // 传递原始文本和类型 ID，后端负责获取提示词模板并替换 {text}
-      const result = await invoke<string>('optimize_text', {
+      const allowPii = await confirmPii(text)
+      // 传递原始文本和类型 ID，后端负责获取提示词模板并替换 {text}；结果边生成边显示
+      const onChunk = new Channel<OptimizeStreamChunk>()
+      onChunk.onmessage = (chunk) => {
+        streamingText.value = chunk.text
+      }
+      const result = await invoke<string>('optimize_text_stream', {
         text,
-        optimizationType: type
+        optimizationType: type,
+        allowPii,
+        onChunk
       })
 
       const optimizationResult: TextOptimizationResult = {
@@ -58,6 +120,51 @@ export function useTextOptimization() {
       const errorMessage = e instanceof Error ? e.message : '优化失败，请重试'
       error.value = errorMessage
       throw new Error(errorMessage)
+    } finally {
+      isOptimizing.value = false
+      streamingText.value = ''
+    }
+  }
+
+  /**
+   * 只优化选中的部分文本
+   * @param text 完整文本
+   * @param range 选区
+   * @param type 优化类型 ID
+   * @returns 替换选区后的完整文本
+   */
+  async function optimizeSelection(text: string, range: TextRange, type: string): Promise<string> {
+    if (!text.slice(range.start, range.end).trim()) {
+      throw new Error('选中的文本不能为空')
+    }
+
+    isOptimizing.value = true
+    error.value = null
+
+    try {
+      const allowPii = await confirmPii(text.slice(range.start, range.end))
+      const result = await invoke<SelectionReplacement>('optimize_selection', {
+        text,
+        range,
+        optimizationType: type,
+        allowPii
+      })
+      const optimized = text.slice(0, result.range.start) + result.replacement + text.slice(result.range.end)
+
+      const optimizationResult: TextOptimizationResult = {
+        original: text,
+        optimized,
+        type,
+        timestamp: new Date()
+      }
+      lastResult.value = optimizationResult
+      addToHistory(optimizationResult)
+
+      return optimized
+    } catch (e) {
+      const errorMessage = e instanceof Error ? e.message : '优化失败，请重试'
+      error.value = errorMessage
+      throw new Error(errorMessage)
     } finally {
       isOptimizing.value = false
     }
@@ -129,10 +236,12 @@ export function useTextOptimization() {
 
   return {
     isOptimizing,
+    streamingText,
     lastResult,
     error,
     history,
     optimizeText,
+    optimizeSelection,
     clearResult,
     clearHistory,
     restoreFromHistory,
@@ -1,7 +1,7 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import type { AppConfig, ThemeColor, FontSizeConfig, OptimizationTypeConfig } from '@/types'
+import type { AppConfig, ThemeColor, FontSizeConfig, OptimizationTypeConfig, CustomOption } from '@/types'
 
 // 默认配置 - optimization_types 从后端获取
 const defaultConfig: AppConfig = {
@@ -15,21 +15,84 @@ const defaultConfig: AppConfig = {
   layout: 'horizontal',   // 默认水平布局
   displayMode: 'full',    // 默认完整模式
   audioEnabled: true,
-  audioFile: undefined,
+  audioFile: null,
   windowPinned: false,
   autoMinimize: false,
   splitterPosition: 50,
-  apiKeys: {},
-  apiTestStatus: {},
+  apiKeys: {
+    openai: null,
+    gemini: null,
+    deepseek: null,
+    volcengine: null,
+    anthropic: null,
+    azure: { endpoint: '', deployment: '', apiVersion: '2024-10-21', apiKey: null },
+    ollama: { enabled: false, baseUrl: 'http://localhost:11434/v1', model: '' },
+    custom: [],
+  },
+  apiTestStatus: {
+    openai: false,
+    gemini: false,
+    deepseek: false,
+    volcengine: false,
+    anthropic: false,
+    azure: false,
+    ollama: false,
+    custom: {},
+  },
   providerOrder: [],
   selectedProvider: 'openai',
   optimizePrompt: '',
   enhancePrompt: '',
   // 自定义选项
   customOptionsEnabled: false,
-  customOptions: ['好的，我明白了', '请继续', '需要更多信息', '返回上一步', '暂停，让我思考一下'],
+  customOptions: ['好的，我明白了', '请继续', '需要更多信息', '返回上一步', '暂停，让我思考一下']
+    .map(text => ({ text, icon: null, color: null, insertPosition: 'append' as const })),
   // 文本优化类型 - 从后端获取，这里设为空数组
   optimizationTypes: [],
+  showSystemOptimizationTypes: true,
+  promptTokenBudget: 500,
+  screenshotScale: { preset: 'original' },
+  privacyBlur: { regions: [], windowTitles: [], blockSize: 16 },
+  embedFileMaxBytes: 32 * 1024,
+  colorManagement: { enabled: true, profileOverrides: {} },
+  annotation: { fontPath: null, fontSize: 18, color: '#FF3B30' },
+  feedbackTimeoutSecs: 0,
+  archive: { enabled: false, directory: null, retentionDays: 0 },
+  retention: { maxAgeDays: 0, maxEntries: 0, maxDiskMb: 0 },
+  storageEncryption: { enabled: false, keySource: 'keychain' },
+  workspaces: [],
+  answerMacros: { enabled: false, allowedLogFiles: [], macros: [] },
+  repeatAnswerPolicy: { mode: 'alwaysAsk', windowMinutes: 30, minSimilarity: 0.9 },
+  auditLog: { enabled: false, path: null },
+  piiScan: { action: 'warn' },
+  preferElicitation: false,
+  mcpTools: { prefix: null, rename: {}, disabled: [], hideOptimizeWithoutApiKeys: true },
+  optimizeSampling: 'fallback',
+  mcpWindowBehavior: { activationDelayMs: 100, focus: 'steal', alwaysOnTop: true, pinDurationMs: 500 },
+  windowGeometry: { remember: true, preferredMonitor: null, monitor: null, x: null, y: null, width: null, height: null },
+  resultLanguage: 'en',
+  feedbackResultTemplate: null,
+  echoRequestInResult: false,
+  responseLintEnabled: false,
+  contextCapture: { enabled: false, countdownSecs: 3 },
+  floodProtection: { enabled: true, maxCallsPerMinute: 10, maxUnanswered: 5 },
+  optimizeRateLimit: { callsPerMinute: 20, maxConcurrent: 2 },
+  imageEncoder: { jpeg: 'image', png: 'default', attachment: 'png' },
+  daemonMode: { enabled: false, startTimeoutSecs: 15 },
+  guiLimit: { maxConcurrent: 0, whenFull: 'queue', retryAfterSecs: 30 },
+  launchRetry: { maxAttempts: 3, initialBackoffMs: 500 },
+  tempFileMaxAgeHours: 24,
+  ipcDir: null,
+  optionPacks: [],
+  userPreferences: { preferredLanguage: null, styleNotes: null, workingHours: null },
+  availability: {
+    workingHours: [],
+    calendarUrl: null,
+    calendarPollMinutes: 15,
+    duringMeeting: 'popup',
+    outsideWorkingHours: 'popup',
+    webhookUrl: null,
+  },
 }
 
 /**
@@ -168,7 +231,7 @@ export const useConfigStore = defineStore('config', () => {
   /**
    * 设置音频文件路径
    */
-  function setAudioFile(path: string | undefined): void {
+  function setAudioFile(path: string | null): void {
     config.value.audioFile = path
     saveConfig()
   }
@@ -336,10 +399,26 @@ export const useConfigStore = defineStore('config', () => {
     saveConfig()
   }
 
+  /**
+   * 设置是否允许 Agent 请求自动截图
+   */
+  function setContextCaptureEnabled(enabled: boolean): void {
+    config.value.contextCapture.enabled = enabled
+    saveConfig()
+  }
+
+  /**
+   * 设置是否启用常驻 GUI 模式
+   */
+  function setDaemonModeEnabled(enabled: boolean): void {
+    config.value.daemonMode.enabled = enabled
+    saveConfig()
+  }
+
   /**
    * 设置自定义选项列表
    */
-  function setCustomOptions(options: string[]): void {
+  function setCustomOptions(options: CustomOption[]): void {
     config.value.customOptions = options
     saveConfig()
   }
@@ -348,8 +427,9 @@ export const useConfigStore = defineStore('config', () => {
    * 添加自定义选项
    */
   function addCustomOption(option: string): void {
-    if (option.trim() && !config.value.customOptions.includes(option.trim())) {
-      config.value.customOptions.push(option.trim())
+    const text = option.trim()
+    if (text && !config.value.customOptions.some(o => o.text === text)) {
+      config.value.customOptions.push({ text, icon: null, color: null, insertPosition: 'append' })
       saveConfig()
     }
   }
@@ -369,7 +449,17 @@ export const useConfigStore = defineStore('config', () => {
    */
   function updateCustomOption(index: number, value: string): void {
     if (index >= 0 && index < config.value.customOptions.length) {
-      config.value.customOptions[index] = value.trim()
+      config.value.customOptions[index] = { ...config.value.customOptions[index], text: value.trim() }
+      saveConfig()
+    }
+  }
+
+  /**
+   * 更新自定义选项的图标、颜色或插入方式
+   */
+  function updateCustomOptionStyle(index: number, updates: Partial<Omit<CustomOption, 'text'>>): void {
+    if (index >= 0 && index < config.value.customOptions.length) {
+      config.value.customOptions[index] = { ...config.value.customOptions[index], ...updates }
       saveConfig()
     }
   }
@@ -386,11 +476,13 @@ export const useConfigStore = defineStore('config', () => {
   /**
    * 添加自定义优化类型
    */
-  function addOptimizationType(typeConfig: Omit<OptimizationTypeConfig, 'id' | 'isSystem'>): void {
+  function addOptimizationType(typeConfig: Omit<OptimizationTypeConfig, 'id' | 'isSystem' | 'pinned' | 'order'>): void {
     const newType: OptimizationTypeConfig = {
       ...typeConfig,
       id: `custom_${Date.now()}_${Math.random().toString(36).slice(2, 11)}`,
       isSystem: false,
+      pinned: false,
+      order: 0,
     }
     config.value.optimizationTypes.push(newType)
     saveConfig()
@@ -599,10 +691,13 @@ export const useConfigStore = defineStore('config', () => {
     setOptimizePrompt,
     setEnhancePrompt,
     setCustomOptionsEnabled,
+    setContextCaptureEnabled,
+    setDaemonModeEnabled,
     setCustomOptions,
     addCustomOption,
     removeCustomOption,
     updateCustomOption,
+    updateCustomOptionStyle,
     // 优化类型管理
     getOptimizationTypes,
     addOptimizationType,
@@ -1,7 +1,7 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import type { FeedbackContent, FeedbackData, ImagePreviewData, FileReference } from '@/types'
+import type { FeedbackContent, FeedbackData, ImagePreviewData, FileReference, OptionDetail } from '@/types'
 
 export const useFeedbackStore = defineStore('feedback', () => {
   // 状态
@@ -9,6 +9,7 @@ export const useFeedbackStore = defineStore('feedback', () => {
   const images = ref<ImagePreviewData[]>([])
   const fileReferences = ref<FileReference[]>([])
   const predefinedOptions = ref<string[]>([])
+  const optionDetails = ref<OptionDetail[]>([])
   const selectedOptions = ref<Set<number>>(new Set())
   const displayContent = ref('')
   const isSubmitting = ref(false)
@@ -43,10 +44,12 @@ export const useFeedbackStore = defineStore('feedback', () => {
 
     // 添加图片
     for (const img of images.value) {
+      // 已存入附件存储的图片只传递附件 ID
       content.push({
         type: 'image',
-        data: img.data,
-        mimeType: img.mimeType,
+        data: img.attachmentId ? '' : img.data,
+        mime_type: img.mimeType,
+        attachment_id: img.attachmentId ?? null,
       })
     }
 
@@ -89,17 +92,38 @@ export const useFeedbackStore = defineStore('feedback', () => {
     }
   }
 
-  function setPredefinedOptions(options: string[]) {
+  function setPredefinedOptions(options: string[], details: OptionDetail[] = []) {
     predefinedOptions.value = options
+    optionDetails.value = details
     selectedOptions.value.clear()
   }
 
+  // 追加选项（保留已选中的选项）
+  function addPredefinedOptions(options: string[], details: OptionDetail[] = []) {
+    predefinedOptions.value = [...predefinedOptions.value, ...options]
+    optionDetails.value = [...optionDetails.value, ...details]
+  }
+
+  function isExclusiveOption(index: number) {
+    return optionDetails.value[index]?.exclusive === true
+  }
+
   function toggleOption(index: number) {
     if (selectedOptions.value.has(index)) {
       selectedOptions.value.delete(index)
+      return
+    }
+    // 互斥选项与其他选项不能同时选中
+    if (isExclusiveOption(index)) {
+      selectedOptions.value.clear()
     } else {
-      selectedOptions.value.add(index)
+      for (const selected of Array.from(selectedOptions.value)) {
+        if (isExclusiveOption(selected)) {
+          selectedOptions.value.delete(selected)
+        }
+      }
     }
+    selectedOptions.value.add(index)
   }
 
   function setDisplayContent(content: string) {
@@ -128,6 +152,7 @@ export const useFeedbackStore = defineStore('feedback', () => {
     images.value = []
     fileReferences.value = []
     predefinedOptions.value = []
+    optionDetails.value = []
     selectedOptions.value.clear()
     displayContent.value = ''
     originalText.value = null
@@ -166,6 +191,7 @@ export const useFeedbackStore = defineStore('feedback', () => {
     images,
     fileReferences,
     predefinedOptions,
+    optionDetails,
     selectedOptions,
     displayContent,
     isSubmitting,
@@ -180,6 +206,7 @@ export const useFeedbackStore = defineStore('feedback', () => {
     addFileReference,
     removeFileReference,
     setPredefinedOptions,
+    addPredefinedOptions,
     toggleOption,
     setDisplayContent,
     saveOriginalText,
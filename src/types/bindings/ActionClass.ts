@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Agent 请求的操作类别，决定弹窗提供的作答方式
+ */
+export type ActionClass = "general" | "review";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * AI 附带的附件，显示在问题下方（如改动的截图、diff）
+ */
+export type AgentAttachment = { "type": "image", data: string, mime_type: string, caption: string | null, } | { "type": "code", code: string, language: string | null, title: string | null, };
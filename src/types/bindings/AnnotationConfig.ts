@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 标注文字配置
+ */
+export type AnnotationConfig = { 
+/**
+ * 自定义字体文件路径（为空时自动查找系统 CJK 字体）
+ */
+fontPath: string | null, 
+/**
+ * 默认字号（像素）
+ */
+fontSize: number, 
+/**
+ * 默认文字颜色（#RRGGBB 或 #RRGGBBAA）
+ */
+color: string, };
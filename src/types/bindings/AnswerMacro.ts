@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MacroAction } from "./MacroAction";
+
+/**
+ * 单个回答宏
+ */
+export type AnswerMacro = { 
+/**
+ * 触发宏的选项文本
+ */
+option: string, 
+/**
+ * 追加到反馈中的文本
+ */
+appendText: string | null, actions: Array<MacroAction>, };
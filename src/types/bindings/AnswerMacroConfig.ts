@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AnswerMacro } from "./AnswerMacro";
+
+/**
+ * 回答宏配置
+ */
+export type AnswerMacroConfig = { enabled: boolean, 
+/**
+ * 允许宏读取的日志文件
+ */
+allowedLogFiles: Array<string>, macros: Array<AnswerMacro>, };
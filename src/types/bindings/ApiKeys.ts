@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AzureOpenAiConfig } from "./AzureOpenAiConfig";
+import type { CustomProvider } from "./CustomProvider";
+import type { OllamaConfig } from "./OllamaConfig";
+
+export type ApiKeys = { openai: string | null, gemini: string | null, deepseek: string | null, volcengine: string | null, anthropic: string | null, 
+/**
+ * Azure OpenAI 的终结点、部署和（混淆后的）API 密钥
+ */
+azure: AzureOpenAiConfig, 
+/**
+ * 本地模型（不需要 API 密钥）
+ */
+ollama: OllamaConfig, 
+/**
+ * 用户添加的 OpenAI 兼容提供商
+ */
+custom: Array<CustomProvider>, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * API 测试状态
+ */
+export type ApiTestStatus = { openai: boolean, gemini: boolean, deepseek: boolean, volcengine: boolean, anthropic: boolean, azure: boolean, ollama: boolean, 
+/**
+ * 自定义提供商（按名称）
+ */
+custom: { [key in string]?: boolean }, };
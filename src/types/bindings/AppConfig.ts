@@ -0,0 +1,202 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AnnotationConfig } from "./AnnotationConfig";
+import type { AnswerMacroConfig } from "./AnswerMacroConfig";
+import type { ApiKeys } from "./ApiKeys";
+import type { ApiTestStatus } from "./ApiTestStatus";
+import type { ArchiveConfig } from "./ArchiveConfig";
+import type { AuditLogConfig } from "./AuditLogConfig";
+import type { AvailabilityConfig } from "./AvailabilityConfig";
+import type { ColorManagementConfig } from "./ColorManagementConfig";
+import type { ContextCaptureConfig } from "./ContextCaptureConfig";
+import type { CustomOption } from "./CustomOption";
+import type { DaemonModeConfig } from "./DaemonModeConfig";
+import type { DisplayMode } from "./DisplayMode";
+import type { FloodProtectionConfig } from "./FloodProtectionConfig";
+import type { FontSizeConfig } from "./FontSizeConfig";
+import type { GuiLimitConfig } from "./GuiLimitConfig";
+import type { ImageEncoderConfig } from "./ImageEncoderConfig";
+import type { LaunchRetryConfig } from "./LaunchRetryConfig";
+import type { Layout } from "./Layout";
+import type { McpToolsConfig } from "./McpToolsConfig";
+import type { McpWindowBehavior } from "./McpWindowBehavior";
+import type { OptimizationTypeConfig } from "./OptimizationTypeConfig";
+import type { OptimizeRateLimitConfig } from "./OptimizeRateLimitConfig";
+import type { OptionPack } from "./OptionPack";
+import type { PiiScanConfig } from "./PiiScanConfig";
+import type { PrivacyBlurConfig } from "./PrivacyBlurConfig";
+import type { RepeatAnswerPolicy } from "./RepeatAnswerPolicy";
+import type { ResultLanguage } from "./ResultLanguage";
+import type { RetentionConfig } from "./RetentionConfig";
+import type { SamplingMode } from "./SamplingMode";
+import type { ScalePreset } from "./ScalePreset";
+import type { StorageEncryptionConfig } from "./StorageEncryptionConfig";
+import type { Theme } from "./Theme";
+import type { ThemeColor } from "./ThemeColor";
+import type { UserPreferencesConfig } from "./UserPreferencesConfig";
+import type { WindowGeometryConfig } from "./WindowGeometryConfig";
+import type { WorkspaceConfig } from "./WorkspaceConfig";
+
+/**
+ * 应用配置
+ */
+export type AppConfig = { theme: Theme, themeColor: ThemeColor, fontSize: FontSizeConfig, layout: Layout, displayMode: DisplayMode, audioEnabled: boolean, audioFile: string | null, windowPinned: boolean, autoMinimize: boolean, splitterPosition: number, apiKeys: ApiKeys, apiTestStatus: ApiTestStatus, 
+/**
+ * API 提供商优先级顺序（第一个优先级最高）
+ */
+providerOrder: Array<string>, selectedProvider: string, optimizePrompt: string, enhancePrompt: string, 
+/**
+ * 自定义选项功能
+ */
+customOptionsEnabled: boolean, customOptions: Array<CustomOption>, 
+/**
+ * 文本优化类型配置
+ */
+optimizationTypes: Array<OptimizationTypeConfig>, 
+/**
+ * 是否在下拉列表中显示系统预设优化类型
+ */
+showSystemOptimizationTypes: boolean, 
+/**
+ * 优化类型系统提示词的 token 预算（超出时保存会给出警告）
+ */
+promptTokenBudget: number, 
+/**
+ * 截图默认缩放预设（单次截图可覆盖）
+ */
+screenshotScale: ScalePreset, 
+/**
+ * 截图隐私模糊配置
+ */
+privacyBlur: PrivacyBlurConfig, 
+/**
+ * 附加文件内嵌到 MCP 结果中的最大字节数（0 表示不内嵌）
+ */
+embedFileMaxBytes: number, 
+/**
+ * 截图色彩管理（ICC 转换到 sRGB）
+ */
+colorManagement: ColorManagementConfig, 
+/**
+ * 截图标注文字设置
+ */
+annotation: AnnotationConfig, 
+/**
+ * MCP 反馈请求默认超时时间（秒，0 表示一直等待）
+ */
+feedbackTimeoutSecs: number, 
+/**
+ * 已完成请求的自动归档设置
+ */
+archive: ArchiveConfig, 
+/**
+ * 本地数据保留策略
+ */
+retention: RetentionConfig, 
+/**
+ * 本地数据库静态加密
+ */
+storageEncryption: StorageEncryptionConfig, 
+/**
+ * 按 Agent 区分的工作区设置
+ */
+workspaces: Array<WorkspaceConfig>, 
+/**
+ * 重复问题的处理策略
+ */
+repeatAnswerPolicy: RepeatAnswerPolicy, 
+/**
+ * 选中选项时自动执行的回答宏
+ */
+answerMacros: AnswerMacroConfig, 
+/**
+ * MCP 工具调用审计日志
+ */
+auditLog: AuditLogConfig, 
+/**
+ * 文本优化发送前的敏感信息扫描
+ */
+piiScan: PiiScanConfig, 
+/**
+ * 客户端支持 MCP elicitation 时优先由客户端收集输入（不支持时仍使用弹窗）
+ */
+preferElicitation: boolean, 
+/**
+ * MCP 工具前缀、重命名和启用设置
+ */
+mcpTools: McpToolsConfig, 
+/**
+ * 文本优化何时改用 MCP 客户端的模型（sampling）
+ */
+optimizeSampling: SamplingMode, 
+/**
+ * MCP 模式下弹窗的激活和置顶行为
+ */
+mcpWindowBehavior: McpWindowBehavior, 
+/**
+ * 弹窗位置、大小和首选显示器
+ */
+windowGeometry: WindowGeometryConfig, 
+/**
+ * MCP 工具结果标签和错误信息的默认语言（可被调用参数覆盖）
+ */
+resultLanguage: ResultLanguage, 
+/**
+ * 反馈结果的 Handlebars 模板（为空时使用默认的 Markdown 格式）
+ */
+feedbackResultTemplate: string | null, 
+/**
+ * 在反馈结果中回显原始问题，与回答配对
+ */
+echoRequestInResult: boolean, 
+/**
+ * 提交 MCP 回答前检查回答质量并提示警告
+ */
+responseLintEnabled: boolean, 
+/**
+ * Agent 请求时自动截取当前显示器作为上下文
+ */
+contextCapture: ContextCaptureConfig, 
+/**
+ * 工具调用限流和未回答请求熔断
+ */
+floodProtection: FloodProtectionConfig, 
+/**
+ * 文本优化工具的限流（避免 Agent 循环调用耗尽 API 配额）
+ */
+optimizeRateLimit: OptimizeRateLimitConfig, 
+/**
+ * 截图和图片压缩使用的编码器
+ */
+imageEncoder: ImageEncoderConfig, 
+/**
+ * 常驻 GUI 模式（请求交给后台常驻的窗口显示）
+ */
+daemonMode: DaemonModeConfig, 
+/**
+ * 同时运行的独立弹窗数量上限
+ */
+guiLimit: GuiLimitConfig, 
+/**
+ * GUI 启动失败或崩溃时的重试
+ */
+launchRetry: LaunchRetryConfig, 
+/**
+ * 残留的 MCP 请求/响应临时文件超过多少小时后清理（0 表示不清理）
+ */
+tempFileMaxAgeHours: number, 
+/**
+ * 请求/响应临时文件目录（为空时使用系统临时目录，环境变量 `WHALE_IPC_DIR` 优先）
+ */
+ipcDir: string | null, 
+/**
+ * 命名的预定义选项组，Agent 通过 `options_pack` 引用
+ */
+optionPacks: Array<OptionPack>, 
+/**
+ * 通过 `whale_get_user_preferences` 提供给 Agent 的偏好
+ */
+userPreferences: UserPreferencesConfig, 
+/**
+ * 工作时间和日历：不方便时延后弹窗或通过 Webhook 升级
+ */
+availability: AvailabilityConfig, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 归档配置
+ */
+export type ArchiveConfig = { 
+/**
+ * 是否启用自动归档
+ */
+enabled: boolean, 
+/**
+ * 归档目录
+ */
+directory: string | null, 
+/**
+ * 保留天数（0 表示永久保留）
+ */
+retentionDays: number, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 审计日志配置
+ */
+export type AuditLogConfig = { 
+/**
+ * 是否启用审计日志
+ */
+enabled: boolean, 
+/**
+ * 日志文件路径（为空时使用应用数据目录下的 audit.jsonl）
+ */
+path: string | null, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 自动提交设置：用户在指定时间内未响应时自动选择默认选项
+ */
+export type AutoSubmit = { 
+/**
+ * 默认选项的显示文本
+ */
+option: string, 
+/**
+ * 倒计时秒数
+ */
+after_secs: number, };
@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UnavailableAction } from "./UnavailableAction";
+import type { WorkingHours } from "./WorkingHours";
+
+/**
+ * 用户可用时间设置（见 [`crate::availability`]）
+ */
+export type AvailabilityConfig = { 
+/**
+ * 工作时间段，为空表示不限制
+ */
+workingHours: Array<WorkingHours>, 
+/**
+ * ICS 日历地址（http(s)://、webcal:// 或本地文件路径），其中的事件视为会议
+ */
+calendarUrl: string | null, 
+/**
+ * 日历刷新间隔（分钟）
+ */
+calendarPollMinutes: number, 
+/**
+ * 会议期间收到请求时的处理方式
+ */
+duringMeeting: UnavailableAction, 
+/**
+ * 工作时间以外收到请求时的处理方式
+ */
+outsideWorkingHours: UnavailableAction, 
+/**
+ * `webhook` 处理方式使用的地址（未设置时照常弹窗）
+ */
+webhookUrl: string | null, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Azure OpenAI：请求发送到 `{endpoint}/openai/deployments/{deployment}`，使用 `api-key` 请求头
+ */
+export type AzureOpenAiConfig = { 
+/**
+ * 资源终结点，如 `https://my-resource.openai.azure.com`
+ */
+endpoint: string, 
+/**
+ * 部署名称（Azure 按部署选择模型）
+ */
+deployment: string, 
+/**
+ * `api-version` 查询参数
+ */
+apiVersion: string, 
+/**
+ * 混淆后的 API 密钥
+ */
+apiKey: string | null, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangeSeverity } from "./ChangeSeverity";
+
+/**
+ * 审查请求中用户提出的一条修改意见
+ */
+export type ChangeRequest = { 
+/**
+ * 文件路径
+ */
+file: string, 
+/**
+ * 位置（行号、行范围或符号名），为空时针对整个文件
+ */
+location: string | null, comment: string, severity: ChangeSeverity, };
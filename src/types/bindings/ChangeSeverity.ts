@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 修改意见的严重程度
+ */
+export type ChangeSeverity = "blocker" | "major" | "minor" | "nit";
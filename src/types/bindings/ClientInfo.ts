@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 发起请求的 Agent（MCP 客户端）
+ */
+export type ClientInfo = { 
+/**
+ * 客户端名称（如 `claude-code`、`cursor`）
+ */
+name: string, version: string | null, };
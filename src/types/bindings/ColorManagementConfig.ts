@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 色彩管理配置
+ */
+export type ColorManagementConfig = { 
+/**
+ * 是否启用 ICC 转换
+ */
+enabled: boolean, 
+/**
+ * 显示器名称 -> ICC 文件路径
+ */
+profileOverrides: { [key in string]?: string }, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 自动截图设置：弹窗打开后倒计时截取当前显示器，作为预填的上下文图片
+ */
+export type ContextCapture = { 
+/**
+ * 截图前的倒计时秒数
+ */
+countdown_secs: number, };
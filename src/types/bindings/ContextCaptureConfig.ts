@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 自动截图上下文设置（默认关闭，需用户开启）
+ */
+export type ContextCaptureConfig = { 
+/**
+ * 是否允许 Agent 请求自动截图
+ */
+enabled: boolean, 
+/**
+ * 截图前的倒计时（秒），用户可在此期间取消
+ */
+countdownSecs: number, };
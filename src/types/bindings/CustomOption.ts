@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InsertPosition } from "./InsertPosition";
+
+/**
+ * 自定义快捷选项
+ *
+ * 兼容旧版配置：`customOptions` 为纯字符串数组时自动迁移为结构化条目
+ */
+export type CustomOption = { text: string, icon: string | null, color: string | null, insertPosition: InsertPosition, };
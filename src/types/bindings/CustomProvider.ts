@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 自定义的 OpenAI 兼容提供商（LiteLLM、one-api、自建网关等）
+ */
+export type CustomProvider = { 
+/**
+ * 名称，同时作为提供商 ID（不能与内置提供商重名）
+ */
+name: string, 
+/**
+ * API 基础 URL（请求发送到 `{base_url}/chat/completions`）
+ */
+baseUrl: string, 
+/**
+ * 模型名称
+ */
+model: string, 
+/**
+ * 额外的请求头
+ */
+headers: { [key in string]?: string }, 
+/**
+ * 混淆后的 API 密钥（网关不需要密钥时为空）
+ */
+apiKey: string | null, };
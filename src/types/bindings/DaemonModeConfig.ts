@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 常驻 GUI 模式设置
+ *
+ * 启用后 GUI 在后台常驻，MCP Server 通过本地 socket 发送请求，省去每次启动 webview 的等待
+ */
+export type DaemonModeConfig = { enabled: boolean, 
+/**
+ * 常驻进程未运行时，启动后等待其就绪的最长时间（秒）
+ */
+startTimeoutSecs: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 反馈内容
+ */
+export type FeedbackContent = { "type": "text", text: string, } | { "type": "image", data: string, mime_type: string, attachment_id: string | null, } | { "type": "file_reference", display_name: string, path: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeedbackContent } from "./FeedbackContent";
+
+/**
+ * 反馈数据
+ */
+export type FeedbackData = { content: Array<FeedbackContent>, };
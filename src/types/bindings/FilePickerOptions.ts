@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 文件选择器选项
+ */
+export type FilePickerOptions = { 
+/**
+ * 选择目录而不是文件
+ */
+directory: boolean, 
+/**
+ * 允许多选
+ */
+multiple: boolean, 
+/**
+ * 允许的文件扩展名（不含点号），为空表示不限制
+ */
+extensions: Array<string>, 
+/**
+ * 初始目录
+ */
+default_path: string | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * File reference data in response
+ */
+export type FileReferenceData = { display_name: string, path: string, is_directory: boolean, };
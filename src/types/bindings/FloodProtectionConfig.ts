@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 弹窗类工具的限流和熔断设置
+ */
+export type FloodProtectionConfig = { enabled: boolean, 
+/**
+ * 每个客户端每分钟最多发起的弹窗请求数
+ */
+maxCallsPerMinute: number, 
+/**
+ * 连续多少个请求未得到回答后熔断（0 表示不熔断）
+ */
+maxUnanswered: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 弹窗出现时如何引起注意
+ */
+export type FocusPolicy = "steal" | "flash";
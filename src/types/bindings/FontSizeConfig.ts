@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 字体大小配置
+ */
+export type FontSizeConfig = { display: number, options: number, input: number, };
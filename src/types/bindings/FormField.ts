@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FormFieldKind } from "./FormFieldKind";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * 表单字段
+ */
+export type FormField = { name: string, label: string | null, kind: FormFieldKind, required: boolean, options: Array<string>, default: JsonValue | null, description: string | null, };
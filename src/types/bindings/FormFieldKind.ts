@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 表单字段类型
+ */
+export type FormFieldKind = "text" | "multiline" | "number" | "select" | "checkbox";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 独立弹窗数量达到上限时的处理方式
+ */
+export type GuiLimitAction = "queue" | "reject";
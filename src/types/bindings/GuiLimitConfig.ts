@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GuiLimitAction } from "./GuiLimitAction";
+
+/**
+ * 同时运行的独立弹窗进程数量上限（所有 MCP Server 进程合计，常驻模式不受限制）
+ */
+export type GuiLimitConfig = { 
+/**
+ * 最多同时运行的弹窗数（0 表示不限制）
+ */
+maxConcurrent: number, whenFull: GuiLimitAction, 
+/**
+ * 拒绝时建议 Agent 等待的秒数
+ */
+retryAfterSecs: number, };
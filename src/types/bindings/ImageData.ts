@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Image data in response
+ */
+export type ImageData = { 
+/**
+ * Base64 数据，只引用附件时为空
+ */
+data: string, mime_type: string, 
+/**
+ * 附件存储中的 ID（见 [`crate::attachments`]），需要数据时再解析
+ */
+attachment_id: string | null, };
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AttachmentFormat } from "./AttachmentFormat";
+import type { JpegBackend } from "./JpegBackend";
+import type { PngCompression } from "./PngCompression";
+
+/**
+ * 图片编码器设置
+ */
+export type ImageEncoderConfig = { jpeg: JpegBackend, png: PngCompression, 
+/**
+ * 截图附件格式（可在工作区设置中为不支持的客户端改回 PNG）
+ */
+attachment: AttachmentFormat, };
@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 图片处理的度量信息
+ *
+ * 界面据此显示压缩效果（如 “8.2 MB → 240 KB”），也便于发现处理流程的性能回退
+ */
+export type ImageMetrics = { 
+/**
+ * 原始宽度
+ */
+original_width: number, 
+/**
+ * 原始高度
+ */
+original_height: number, 
+/**
+ * 原始字节数（截图为未压缩的 RGBA 像素数据）
+ */
+original_bytes: number, 
+/**
+ * 输出大小与原始大小之比
+ */
+compression_ratio: number, 
+/**
+ * 使用的 JPEG 质量（无损编码时为 None）
+ */
+quality: number | null, 
+/**
+ * 处理耗时（毫秒）
+ */
+elapsed_ms: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * JPEG 编码后端
+ */
+export type JpegBackend = "image" | "mozjpeg" | "turbojpeg";
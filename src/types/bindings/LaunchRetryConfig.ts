@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * GUI 启动失败或崩溃（没有响应且非正常退出）时的重试设置
+ */
+export type LaunchRetryConfig = { 
+/**
+ * 最多启动次数（包括第一次）
+ */
+maxAttempts: number, 
+/**
+ * 第一次重试前的等待时间（毫秒），之后每次翻倍
+ */
+initialBackoffMs: number, };
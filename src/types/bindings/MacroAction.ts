@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 宏动作
+ */
+export type MacroAction = { "type": "screenshot" } | { "type": "tailLog", path: string, lines: number, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * MCP 工具配置
+ */
+export type McpToolsConfig = { 
+/**
+ * 工具名前缀（替换默认的 `whale_`）
+ */
+prefix: string | null, 
+/**
+ * 单独重命名（key: 原始工具名），优先于前缀
+ */
+rename: { [key in string]?: string }, 
+/**
+ * 禁用的工具（原始工具名）
+ */
+disabled: Array<string>, 
+/**
+ * 未配置 API 密钥（且无法使用客户端 sampling）时隐藏文本优化工具
+ */
+hideOptimizeWithoutApiKeys: boolean, };
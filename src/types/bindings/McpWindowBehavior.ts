@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FocusPolicy } from "./FocusPolicy";
+
+/**
+ * MCP 模式下弹窗的激活和置顶行为
+ */
+export type McpWindowBehavior = { 
+/**
+ * 窗口创建后等待多久再激活（毫秒），较慢的机器上可适当调大
+ */
+activationDelayMs: number, 
+/**
+ * 是否抢占焦点（低优先级请求始终不抢占）
+ */
+focus: FocusPolicy, 
+/**
+ * 激活时是否置顶窗口（紧急请求始终保持置顶）
+ */
+alwaysOnTop: boolean, 
+/**
+ * 置顶多久后取消（毫秒，0 表示一直置顶）
+ */
+pinDurationMs: number, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 本地模型提供商（Ollama），使用其 OpenAI 兼容 API，不需要 API 密钥
+ */
+export type OllamaConfig = { 
+/**
+ * 是否启用
+ */
+enabled: boolean, 
+/**
+ * OpenAI 兼容 API 地址
+ */
+baseUrl: string, 
+/**
+ * 模型名称（本地已下载的模型见 `GET /api/tags`）
+ */
+model: string, };
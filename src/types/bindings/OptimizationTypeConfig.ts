@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 文本优化类型配置
+ */
+export type OptimizationTypeConfig = { id: string, label: string, icon: string, description: string, prompt: string, isSystem: boolean, enabled: boolean, 
+/**
+ * 是否置顶（置顶项始终显示在下拉列表最前面）
+ */
+pinned: boolean, 
+/**
+ * 排序权重（越小越靠前）
+ */
+order: number, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 文本优化工具的限流设置
+ */
+export type OptimizeRateLimitConfig = { 
+/**
+ * 每分钟最多调用次数（0 表示不限制）
+ */
+callsPerMinute: number, 
+/**
+ * 同时进行的最大 LLM 调用数（0 表示不限制）
+ */
+maxConcurrent: number, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 结构化预定义选项（与 `predefined_options` 按下标一一对应）
+ */
+export type OptionDetail = { 
+/**
+ * 机器可读的选项 ID，返回给 AI
+ */
+id: string, 
+/**
+ * 显示文本
+ */
+label: string, 
+/**
+ * 选项说明（鼠标悬停显示）
+ */
+description: string | null, 
+/**
+ * 互斥选项：选中后取消其他选项
+ */
+exclusive: boolean, };
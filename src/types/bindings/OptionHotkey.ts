@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 选项的数字快捷键：按下后直接以该选项作答
+ *
+ * 由服务端按请求生成，所有前端使用同一映射
+ */
+export type OptionHotkey = { 
+/**
+ * 按键（"1".."9"）
+ */
+key: string, 
+/**
+ * 对应的选项文本
+ */
+option: string, };
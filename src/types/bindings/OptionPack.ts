@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PredefinedOptionParam } from "./PredefinedOptionParam";
+
+/**
+ * 命名的选项组
+ */
+export type OptionPack = { 
+/**
+ * 名称（Agent 引用时不区分大小写）
+ */
+name: string, 
+/**
+ * 选项组用途说明
+ */
+description: string | null, 
+/**
+ * 纯文本选项或结构化选项 `{id, label, description?, exclusive?}`
+ */
+options: Array<PredefinedOptionParam>, };
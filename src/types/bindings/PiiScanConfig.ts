@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PiiAction } from "./PiiAction";
+
+/**
+ * 敏感信息扫描配置
+ */
+export type PiiScanConfig = { action: PiiAction, };
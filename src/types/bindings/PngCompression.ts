@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * PNG 压缩级别
+ */
+export type PngCompression = "fast" | "default" | "best";
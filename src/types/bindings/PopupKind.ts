@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Popup 类型
+ */
+export type PopupKind = "feedback" | "confirm" | "select_file" | "form";
@@ -0,0 +1,137 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ActionClass } from "./ActionClass";
+import type { AgentAttachment } from "./AgentAttachment";
+import type { AttachmentFormat } from "./AttachmentFormat";
+import type { AutoSubmit } from "./AutoSubmit";
+import type { ClientInfo } from "./ClientInfo";
+import type { ContextCapture } from "./ContextCapture";
+import type { CustomOption } from "./CustomOption";
+import type { FilePickerOptions } from "./FilePickerOptions";
+import type { FormField } from "./FormField";
+import type { OptionDetail } from "./OptionDetail";
+import type { OptionHotkey } from "./OptionHotkey";
+import type { PopupKind } from "./PopupKind";
+import type { PopupStrings } from "./PopupStrings";
+import type { Priority } from "./Priority";
+import type { SessionSummary } from "./SessionSummary";
+
+/**
+ * Popup request sent to the GUI
+ */
+export type PopupRequest = { id: string, 
+/**
+ * 格式版本（见 [`POPUP_SCHEMA_VERSION`]）
+ */
+schema_version: number, 
+/**
+ * 发送方能兼容的最低格式版本（旧版本不发送，视为 0）
+ */
+min_schema_version: number, 
+/**
+ * 发送方的程序版本
+ */
+app_version: string | null, kind: PopupKind, 
+/**
+ * 窗口标题（确认对话框使用）
+ */
+title: string | null, 
+/**
+ * 是否显示“记住我的选择”（确认对话框使用）
+ */
+allow_remember: boolean, 
+/**
+ * 文件选择器选项（文件选择模式使用）
+ */
+file_picker: FilePickerOptions | null, message: string | null, full_response: string | null, predefined_options: Array<string> | null, 
+/**
+ * 预定义选项的结构化信息（ID、说明、互斥）
+ */
+option_details: Array<OptionDetail>, 
+/**
+ * 用户自定义的快捷选项（已与 AI 提供的选项合并去重）
+ */
+quick_options: Array<CustomOption>, 
+/**
+ * 超时自动提交默认选项
+ */
+auto_submit: AutoSubmit | null, 
+/**
+ * 优先级
+ */
+priority: Priority, 
+/**
+ * 发起请求的项目名称（显示在窗口标题中）
+ */
+project_name: string | null, 
+/**
+ * 发起请求的工作目录
+ */
+working_dir: string | null, 
+/**
+ * 期望的响应截止时间（RFC 3339）
+ */
+respond_by: string | null, 
+/**
+ * 相同问题的上次回答（供用户一键复用）
+ */
+previous_answer: SessionSummary | null, 
+/**
+ * 所属会话线程（Agent 指定，关联同一话题的多次提问）
+ */
+thread_id: string | null, 
+/**
+ * 同一线程中之前的问答（按时间顺序，显示在问题上方）
+ */
+thread_history: Array<SessionSummary>, 
+/**
+ * AI 附带的图片和代码片段
+ */
+agent_attachments: Array<AgentAttachment>, 
+/**
+ * Agent 请求的自动截图（用户在设置中开启后才会携带）
+ */
+capture_context: ContextCapture | null, 
+/**
+ * Agent 提示的语言（BCP 47，如 `en-US`）
+ */
+locale: string | null, 
+/**
+ * 按 `locale` 本地化的弹窗文本
+ */
+strings: PopupStrings | null, 
+/**
+ * 表单字段（表单模式使用）
+ */
+form_fields: Array<FormField>, 
+/**
+ * 多轮对话会话 ID：同一会话的连续请求复用同一个弹窗
+ */
+session_id: string | null, 
+/**
+ * 操作类别（审查请求显示修改意见编辑器）
+ */
+action_class: ActionClass, 
+/**
+ * 预定义选项的数字快捷键（见 [`PopupRequest::assign_hotkeys`]）
+ */
+hotkeys: Array<OptionHotkey>, 
+/**
+ * 发起请求的 MCP 客户端（弹窗显示“某某正在询问”）
+ */
+client_info: ClientInfo | null, 
+/**
+ * 发起请求的工具调用 ID（JSON-RPC 请求 ID），用于在日志中关联弹窗和 Agent 会话
+ */
+tool_call_id: string | null, 
+/**
+ * 接收响应的本地 socket（Unix 域套接字路径或 Windows 命名管道名），为空时写入响应文件
+ */
+response_endpoint: string | null, 
+/**
+ * 截图附件的编码格式（GUI 提交时将 PNG 截图转换为该格式）
+ */
+attachment_format: AttachmentFormat, 
+/**
+ * 临时文件密钥（Base64），只通过 stdin 管道/本地 socket 传递或在 GUI 读取请求文件后填入，不写入请求文件
+ */
+file_key: string | null, created_at: string, };
@@ -0,0 +1,58 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangeRequest } from "./ChangeRequest";
+import type { FileReferenceData } from "./FileReferenceData";
+import type { ImageData } from "./ImageData";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * Response from the popup GUI
+ */
+export type PopupResponse = { request_id: string, 
+/**
+ * 格式版本（见 [`POPUP_SCHEMA_VERSION`]）
+ */
+schema_version: number, 
+/**
+ * 发送方能兼容的最低格式版本（旧版本不发送，视为 0）
+ */
+min_schema_version: number, 
+/**
+ * 发送方的程序版本
+ */
+app_version: string | null, user_input: string | null, selected_options: Array<string>, images: Array<ImageData>, file_references: Array<FileReferenceData>, cancelled: boolean, 
+/**
+ * 确认对话框结果
+ */
+confirmed: boolean | null, 
+/**
+ * 用户是否勾选了“记住我的选择”
+ */
+remember_choice: boolean, 
+/**
+ * 是否因超时而自动取消
+ */
+timed_out: boolean, 
+/**
+ * 是否因用户未响应而自动选择了默认选项
+ */
+auto_selected: boolean, 
+/**
+ * 表单填写结果（表单模式使用）
+ */
+form_values: { [key in string]?: JsonValue } | null, 
+/**
+ * 是否因 MCP 客户端断开连接而关闭了弹窗
+ */
+client_disconnected: boolean, 
+/**
+ * 用户作答时看到的请求修订号（请求未更新过或旧版 GUI 时为 None）
+ */
+revision: number | null, 
+/**
+ * 审查请求中用户逐条填写的修改意见
+ */
+change_requests: Array<ChangeRequest>, 
+/**
+ * 用户作答时按下的选项快捷键
+ */
+hotkey: string | null, };
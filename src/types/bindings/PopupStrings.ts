@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 弹窗中按请求语言显示的文本（未提供时 GUI 使用默认的中文文本）
+ */
+export type PopupStrings = { input_placeholder: string, submit: string, cancel: string, previous_answer: string, thread_history: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 预定义选项：纯文本或结构化选项
+ */
+export type PredefinedOptionParam = string | { id: string, label: string, description: string | null, exclusive: boolean | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 请求优先级，决定窗口激活方式和提示音
+ */
+export type Priority = "low" | "normal" | "critical";
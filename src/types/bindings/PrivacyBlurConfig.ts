@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ScreenRegion } from "./ScreenRegion";
+
+/**
+ * 截图隐私模糊配置
+ *
+ * 在截图离开后端之前对指定屏幕区域和窗口进行像素化处理
+ */
+export type PrivacyBlurConfig = { 
+/**
+ * 始终模糊的屏幕区域（全局屏幕坐标）
+ */
+regions: Array<ScreenRegion>, 
+/**
+ * 窗口标题包含这些关键字时模糊整个窗口（不区分大小写）
+ */
+windowTitles: Array<string>, 
+/**
+ * 像素化块大小（像素）
+ */
+blockSize: number, };
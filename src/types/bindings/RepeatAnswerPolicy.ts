@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RepeatAnswerMode } from "./RepeatAnswerMode";
+
+/**
+ * 重复问题策略
+ */
+export type RepeatAnswerPolicy = { mode: RepeatAnswerMode, 
+/**
+ * 只匹配最近多少分钟内的回答
+ */
+windowMinutes: number, 
+/**
+ * 相似度阈值（0~1，1 表示完全相同）
+ */
+minSimilarity: number, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 数据保留配置（各项为 0 表示不限制）
+ */
+export type RetentionConfig = { 
+/**
+ * 保留天数
+ */
+maxAgeDays: number, 
+/**
+ * 每类数据最多保留的条数
+ */
+maxEntries: number, 
+/**
+ * 每类数据最多占用的空间（MB）
+ */
+maxDiskMb: number, };
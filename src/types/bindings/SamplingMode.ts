@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 文本优化使用 MCP sampling 的时机
+ */
+export type SamplingMode = "off" | "fallback" | "always";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 截图缩放预设
+ */
+export type ScalePreset = { "preset": "original" } | { "preset": "half" } | { "preset": "fit1280" } | { "preset": "fit-token-budget", tokens: number, };
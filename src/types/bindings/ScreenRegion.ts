@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 截图区域
+ */
+export type ScreenRegion = { x: number, y: number, width: number, height: number, };
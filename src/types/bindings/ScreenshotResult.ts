@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ImageMetrics } from "./ImageMetrics";
+
+/**
+ * 截图结果
+ */
+export type ScreenshotResult = { data: string, mime_type: string, width: number, height: number, size: number, 
+/**
+ * 处理度量（原始分辨率、压缩比、耗时等）
+ */
+metrics: ImageMetrics | null, };
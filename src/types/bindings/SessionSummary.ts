@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 会话摘要（历史列表和搜索结果使用，不含图片数据）
+ */
+export type SessionSummary = { id: string, kind: string, message: string | null, userInput: string | null, selectedOptions: Array<string>, cancelled: boolean, createdAt: string, respondedAt: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KeySource } from "./KeySource";
+
+/**
+ * 存储加密配置
+ */
+export type StorageEncryptionConfig = { enabled: boolean, keySource: KeySource, };
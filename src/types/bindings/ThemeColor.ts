@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 主题色
+ */
+export type ThemeColor = "blue" | "mint" | "rose" | "peach" | "lavender" | "slate";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 用户不方便时收到反馈请求的处理方式
+ */
+export type UnavailableAction = "popup" | "defer" | "webhook";
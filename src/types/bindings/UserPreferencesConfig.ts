@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 提供给 Agent 的用户偏好，Agent 可据此调整提问方式而不需要用户在每个会话中重复说明
+ */
+export type UserPreferencesConfig = { 
+/**
+ * 希望 Agent 使用的语言（BCP 47，如 `zh-CN`），为空时按结果语言
+ */
+preferredLanguage: string | null, 
+/**
+ * 回答风格说明（如“尽量给出选项，少问开放式问题”）
+ */
+styleNotes: string | null, 
+/**
+ * 工作时间说明（如“工作日 09:00-18:00，UTC+8”）
+ */
+workingHours: string | null, };
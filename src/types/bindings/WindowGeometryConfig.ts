@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 弹窗位置和大小（关闭弹窗时自动记录，见 [`crate::window_geometry`]）
+ */
+export type WindowGeometryConfig = { 
+/**
+ * 是否记录并恢复窗口位置和大小
+ */
+remember: boolean, 
+/**
+ * 首选显示器名称：设置后弹窗总是出现在该显示器上
+ */
+preferredMonitor: string | null, 
+/**
+ * 上次所在的显示器名称
+ */
+monitor: string | null, 
+/**
+ * 相对显示器左上角的位置（逻辑像素）
+ */
+x: number | null, y: number | null, 
+/**
+ * 窗口大小（逻辑像素）
+ */
+width: number | null, height: number | null, };
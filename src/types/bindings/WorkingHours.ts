@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 工作时间段（本地时间）
+ */
+export type WorkingHours = { 
+/**
+ * 适用的星期（`mon`、`tue` ... `sun`）
+ */
+days: Array<string>, 
+/**
+ * 开始时间（`HH:MM`）
+ */
+start: string, 
+/**
+ * 结束时间（`HH:MM`，早于开始时间表示跨过午夜）
+ */
+end: string, };
@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AttachmentFormat } from "./AttachmentFormat";
+import type { CustomOption } from "./CustomOption";
+import type { Priority } from "./Priority";
+
+/**
+ * 单个工作区的设置（未设置的项沿用全局配置）
+ */
+export type WorkspaceConfig = { 
+/**
+ * 匹配的 Agent 名称（MCP 客户端名称，不区分大小写，支持 `*` 结尾的前缀匹配）
+ */
+agent: string, customOptionsEnabled: boolean | null, customOptions: Array<CustomOption> | null, feedbackTimeoutSecs: number | null, 
+/**
+ * 默认优先级（请求未指定时使用）
+ */
+priority: Priority | null, 
+/**
+ * 自动回复：设置后不再弹窗，直接以该文本作为用户反馈返回
+ */
+autoReply: string | null, 
+/**
+ * 截图附件格式（客户端无法显示 WebP/AVIF 时设为 PNG）
+ */
+attachmentFormat: AttachmentFormat | null, };
@@ -1,63 +1,63 @@
-// 主题色类型
-export type ThemeColor = 'blue' | 'mint' | 'rose' | 'peach' | 'lavender' | 'slate'
+// 与 Rust 类型对应的定义由 ts-rs 生成到 ./bindings（修改 Rust 类型后运行 `pnpm bindings`，CI 会检查是否与提交的文件一致），
+// 这里只做重新导出，不要手写重复的定义
 
-// 字体大小配置
-export interface FontSizeConfig {
-  display: number    // 提示区文字大小
-  options: number    // 选项区文字大小
-  input: number      // 输入框文字大小
-}
-
-// 应用配置类型
-export interface AppConfig {
-  theme: 'dark' | 'light'
-  themeColor: ThemeColor
-  fontSize: FontSizeConfig
-  layout: 'vertical' | 'horizontal'
-  displayMode: 'simple' | 'full'
-  audioEnabled: boolean
-  audioFile?: string
-  windowPinned: boolean
-  autoMinimize: boolean
-  splitterPosition: number
-  apiKeys: Record<string, string>
-  apiTestStatus: Record<string, boolean>
-  providerOrder: string[]  // API 提供商优先级顺序
-  selectedProvider: string
-  optimizePrompt: string
-  enhancePrompt: string
-  // 自定义选项功能
-  customOptionsEnabled: boolean
-  customOptions: string[]
-  // 文本优化类型配置
-  optimizationTypes: OptimizationTypeConfig[]
-}
-
-// 反馈内容类型
-export type FeedbackContentType = 'text' | 'image' | 'file_reference'
-
-export interface TextContent {
-  type: 'text'
-  text: string
-}
-
-export interface ImageContent {
-  type: 'image'
-  data: string // Base64 encoded
-  mimeType: string
-}
+export type { ActionClass } from './bindings/ActionClass'
+export type { AgentAttachment } from './bindings/AgentAttachment'
+export type { AnswerMacro } from './bindings/AnswerMacro'
+export type { AnswerMacroConfig } from './bindings/AnswerMacroConfig'
+export type { ApiKeys } from './bindings/ApiKeys'
+export type { ApiTestStatus } from './bindings/ApiTestStatus'
+export type { AppConfig } from './bindings/AppConfig'
+export type { AttachmentFormat } from './bindings/AttachmentFormat'
+export type { AutoSubmit } from './bindings/AutoSubmit'
+export type { AvailabilityConfig } from './bindings/AvailabilityConfig'
+export type { AzureOpenAiConfig } from './bindings/AzureOpenAiConfig'
+export type { ChangeRequest } from './bindings/ChangeRequest'
+export type { ChangeSeverity } from './bindings/ChangeSeverity'
+export type { ClientInfo } from './bindings/ClientInfo'
+export type { CustomOption } from './bindings/CustomOption'
+export type { CustomProvider } from './bindings/CustomProvider'
+export type { FeedbackContent } from './bindings/FeedbackContent'
+export type { FeedbackData } from './bindings/FeedbackData'
+export type { FileReferenceData } from './bindings/FileReferenceData'
+export type { FontSizeConfig } from './bindings/FontSizeConfig'
+export type { FormField } from './bindings/FormField'
+export type { FormFieldKind } from './bindings/FormFieldKind'
+export type { ImageData } from './bindings/ImageData'
+export type { ImageEncoderConfig } from './bindings/ImageEncoderConfig'
+export type { ImageMetrics } from './bindings/ImageMetrics'
+export type { InsertPosition } from './bindings/InsertPosition'
+export type { MacroAction } from './bindings/MacroAction'
+export type { OllamaConfig } from './bindings/OllamaConfig'
+export type { OptimizationTypeConfig } from './bindings/OptimizationTypeConfig'
+export type { OptionDetail } from './bindings/OptionDetail'
+export type { OptionHotkey } from './bindings/OptionHotkey'
+export type { OptionPack } from './bindings/OptionPack'
+export type { PopupKind } from './bindings/PopupKind'
+export type { PopupRequest } from './bindings/PopupRequest'
+export type { PopupResponse } from './bindings/PopupResponse'
+export type { PopupStrings } from './bindings/PopupStrings'
+export type { Priority } from './bindings/Priority'
+export type { PrivacyBlurConfig } from './bindings/PrivacyBlurConfig'
+export type { ScalePreset } from './bindings/ScalePreset'
+export type { ScreenshotResult } from './bindings/ScreenshotResult'
+export type { SessionSummary } from './bindings/SessionSummary'
+export type { ThemeColor } from './bindings/ThemeColor'
+export type { UnavailableAction } from './bindings/UnavailableAction'
+export type { UserPreferencesConfig } from './bindings/UserPreferencesConfig'
+export type { WindowGeometryConfig } from './bindings/WindowGeometryConfig'
+export type { WorkingHours } from './bindings/WorkingHours'
+export type { WorkspaceConfig } from './bindings/WorkspaceConfig'
+export type { JsonValue } from './bindings/serde_json/JsonValue'
 
-export interface FileReferenceContent {
-  type: 'file_reference'
-  display_name: string
-  path: string
-}
+import type { FeedbackContent } from './bindings/FeedbackContent'
+import type { ImageMetrics } from './bindings/ImageMetrics'
 
-export type FeedbackContent = TextContent | ImageContent | FileReferenceContent
-
-export interface FeedbackData {
-  content: FeedbackContent[]
-}
+// 反馈内容的各个变体
+export type FeedbackContentType = FeedbackContent['type']
+export type TextContent = Extract<FeedbackContent, { type: 'text' }>
+export type ImageContent = Extract<FeedbackContent, { type: 'image' }>
+export type FileReferenceContent = Extract<FeedbackContent, { type: 'file_reference' }>
 
 // MCP 工具调用类型
 export interface McpToolCall {
@@ -85,6 +85,8 @@ export interface ImagePreviewData {
   width: number
   height: number
   size: number
+  metrics?: ImageMetrics | null  // 后端处理的度量信息
+  attachmentId?: string | null  // 附件存储中的 ID（内容哈希）
 }
 
 // 文件引用类型
@@ -99,16 +101,27 @@ export interface FileReference {
   isDirectory?: boolean   // 是否为文件夹
 }
 
-// 文本优化类型配置
-export interface OptimizationTypeConfig {
-  id: string                // 唯一标识
-  label: string             // 显示名称
-  icon: string              // 图标类名
-  description: string       // 简短描述
-  prompt: string            // 提示词模板
-  isSystem: boolean         // 是否系统预设（不可删除）
-  enabled: boolean          // 是否启用
+// 优化类型保存校验结果
+export interface OptimizationTypeValidation {
+  estimatedTokens: number
+  tokenBudget: number
+  exceedsBudget: boolean
+  warnings: string[]
 }
 
 // 系统预设的优化类型 ID
 export type SystemOptimizationType = 'improve' | 'formal' | 'casual' | 'concise' | 'expand'
+
+// 提交前的回答质量警告（与后端 response_lint 模块一致）
+export interface LintWarning {
+  kind: 'emptyAnswer' | 'unansweredQuestions' | 'todoMarker'
+  message: string
+  details: string[]
+}
+
+// 用户主动留给 Agent 的消息（与后端 storage::UserNote 一致）
+export interface UserNote {
+  id: number
+  message: string
+  createdAt: string
+}